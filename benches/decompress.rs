@@ -0,0 +1,83 @@
+//! Dump decompression throughput by read buffer size.
+//!
+//! Streams a fixed, generated bz2 sample dump through
+//! `decompressing_reader` into quick-xml, as extraction does, for several
+//! sizes of the buffer between the decompressor and the parser.
+//!
+//! Run with `cargo bench --bench decompress`.
+
+use std::io::{Cursor, Write};
+
+use bzip2::write::BzEncoder;
+use bzip2::Compression;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use rustipedia::downloader::{decompressing_reader, DumpCompression};
+
+/// Pages in the sample dump (about 20 MB of XML)
+const PAGES: usize = 4000;
+
+const WORDS: &[&str] = &[
+    "the", "river", "empire", "[[Rust (programming language)|Rust]]", "was", "founded", "in",
+    "{{convert|5|km}}", "population", "'''city'''", "north", "of", "and", "a", "century",
+    "<ref>{{cite web|url=http://example.com}}</ref>", "language", "station", "1848", "==History==",
+];
+
+/// Deterministic MediaWiki export with varied article text
+fn sample_dump() -> Vec<u8> {
+    let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+
+    let mut xml = String::from("<mediawiki><siteinfo><sitename>Bench</sitename></siteinfo>");
+    for id in 0..PAGES {
+        let words = 300 + (next() % 1500) as usize;
+        let text: Vec<&str> = (0..words).map(|_| WORDS[(next() % WORDS.len() as u64) as usize]).collect();
+        xml.push_str(&format!(
+            "<page><title>Article {}</title><ns>0</ns><id>{}</id><revision><text>{}</text></revision></page>",
+            id, id + 1, quick_xml::escape::escape(text.join(" ").as_str())
+        ));
+    }
+    xml.push_str("</mediawiki>");
+    xml.into_bytes()
+}
+
+fn bench_buffer_sizes(c: &mut Criterion) {
+    let xml = sample_dump();
+    let mut encoder = BzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&xml).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let mut group = c.benchmark_group("bz2_read_buffer");
+    group.throughput(Throughput::Bytes(xml.len() as u64));
+    group.sample_size(10);
+    for size in [8 * 1024, 64 * 1024, 256 * 1024, 1024 * 1024] {
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            b.iter(|| {
+                let reader = decompressing_reader(Cursor::new(compressed.clone()), DumpCompression::Bzip2, size);
+                let mut xml_reader = Reader::from_reader(reader);
+                let mut buf = Vec::new();
+                let mut pages = 0;
+                loop {
+                    match xml_reader.read_event_into(&mut buf).unwrap() {
+                        Event::Start(e) if e.name().as_ref() == b"page" => pages += 1,
+                        Event::Eof => break,
+                        _ => {}
+                    }
+                    buf.clear();
+                }
+                assert_eq!(pages, PAGES);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_buffer_sizes);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+//! Search index build time by number of indexing threads.
+//!
+//! Writes a fixed, generated `articles.jsonl` and builds a fresh index from
+//! it with `build_from_jsonl_parallel` for several thread counts, as
+//! `rustipedia-download index --index-threads N` does.
+//!
+//! Run with `cargo bench --bench index`.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use rustipedia::{Article, SearchIndex};
+
+/// Articles in the sample file (about 27 MiB of JSONL)
+const ARTICLES: u64 = 10_000;
+
+const WORDS: &[&str] = &[
+    "the", "river", "empire", "was", "founded", "in", "population", "city", "north", "of",
+    "and", "a", "century", "language", "station", "1848", "railway", "parliament", "mountain",
+    "<a href=\"/wiki/Rust\">rust</a>", "treaty", "cathedral", "harbour", "election",
+];
+
+/// Deterministic articles with varied text
+fn write_sample(path: &Path) -> u64 {
+    let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+
+    let mut file = BufWriter::new(File::create(path).unwrap());
+    for id in 1..=ARTICLES {
+        let words = 100 + (next() % 500) as usize;
+        let content: Vec<&str> = (0..words).map(|_| WORDS[(next() % WORDS.len() as u64) as usize]).collect();
+        let article = Article::new(id, format!("Article {}", id), content.join(" "));
+        writeln!(file, "{}", serde_json::to_string(&article).unwrap()).unwrap();
+    }
+    file.flush().unwrap();
+    std::fs::metadata(path).unwrap().len()
+}
+
+fn bench_index_threads(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let jsonl = dir.path().join("articles.jsonl");
+    let bytes = write_sample(&jsonl);
+
+    let mut group = c.benchmark_group("index_threads");
+    group.throughput(Throughput::Bytes(bytes));
+    group.sample_size(10);
+    for threads in [1, 2, 4, 8] {
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, &threads| {
+            b.iter_with_setup(
+                || tempfile::tempdir_in(dir.path()).unwrap(),
+                |index_dir| {
+                    let index = SearchIndex::create(index_dir.path().join("search_index")).unwrap();
+                    assert_eq!(index.build_from_jsonl_parallel(&jsonl, threads).unwrap(), ARTICLES);
+                },
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_index_threads);
+criterion_main!(benches);
@@ -0,0 +1,165 @@
+//! Request logging for the server
+//!
+//! Logs method, path, status and latency of every request and tags each one
+//! with a short random ID, returned in the `X-Request-Id` header and added
+//! to error responses so users can quote it in bug reports.
+
+use std::time::Instant;
+
+use axum::body::{to_bytes, Body};
+use axum::extract::Request;
+use axum::http::{header, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// Response header carrying the request ID
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Paths logged at debug instead of info level (assets, scrapes)
+const QUIET_PATHS: &[&str] = &["/logo", "/metrics", "/favicon.ico"];
+
+/// Path prefix of the bundled scripts, also logged at debug level
+const STATIC_PREFIX: &str = "/static/";
+
+/// Error bodies larger than this are passed through without the request ID
+const MAX_ERROR_BODY: usize = 64 * 1024;
+
+/// ID of the current request, available as a request extension
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// Generate a short random ID (8 hex characters)
+    pub fn generate() -> Self {
+        RequestId(format!("{:08x}", rand::random::<u32>()))
+    }
+}
+
+/// Middleware assigning a request ID and logging the finished request
+pub async fn log_requests(mut request: Request, next: Next) -> Response {
+    let id = RequestId::generate();
+    request.extensions_mut().insert(id.clone());
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let status = response.status().as_u16();
+
+    if is_quiet(&path) {
+        tracing::debug!(request_id = %id.0, %method, %path, status, latency_ms, "request");
+    } else {
+        tracing::info!(request_id = %id.0, %method, %path, status, latency_ms, "request");
+    }
+
+    let mut response = if response.status().is_client_error() || response.status().is_server_error() {
+        add_id_to_error_body(response, &id).await
+    } else {
+        response
+    };
+    if let Ok(value) = HeaderValue::from_str(&id.0) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Whether requests for `path` are logged at debug level, at the root or
+/// under a language prefix (`/de/logo`)
+fn is_quiet(path: &str) -> bool {
+    let unprefixed = match path.match_indices('/').nth(1) {
+        Some((index, _)) => &path[index..],
+        None => path,
+    };
+    QUIET_PATHS.contains(&path) || QUIET_PATHS.contains(&unprefixed) || path.starts_with(STATIC_PREFIX)
+}
+
+/// Mention the request ID in an HTML, JSON or plain-text error body
+async fn add_id_to_error_body(response: Response, id: &RequestId) -> Response {
+    let content_type = response.headers().get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_ERROR_BODY).await {
+        Ok(bytes) => bytes,
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+    let Ok(text) = std::str::from_utf8(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let body = if content_type.starts_with("text/html") {
+        let note = format!("<p class=\"request-id\">Request ID: {}</p>", id.0);
+        match text.rfind("</main>").or_else(|| text.rfind("</body>")) {
+            Some(pos) => format!("{}{}{}", &text[..pos], note, &text[pos..]),
+            None => format!("{}{}", text, note),
+        }
+    } else if content_type.starts_with("application/json") {
+        match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(serde_json::Value::Object(mut object)) => {
+                object.insert("request_id".to_string(), serde_json::Value::String(id.0.clone()));
+                serde_json::Value::Object(object).to_string()
+            }
+            _ => text.to_string(),
+        }
+    } else if content_type.starts_with("text/plain") {
+        format!("{} (request id: {})", text.trim_end(), id.0)
+    } else {
+        text.to_string()
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::{Html, IntoResponse};
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_request_id_header_and_error_body() {
+        let app = Router::new()
+            .route("/ok", get(|| async { "fine" }))
+            .route("/missing", get(|| async {
+                (StatusCode::NOT_FOUND, Html("<html><body><main><p>Not found</p></main></body></html>")).into_response()
+            }))
+            .layer(axum::middleware::from_fn(log_requests));
+
+        let ok = app.clone()
+            .oneshot(Request::builder().uri("/ok").body(Body::empty()).unwrap())
+            .await.unwrap();
+        let ok_id = ok.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        assert_eq!(ok_id.len(), 8);
+        assert_eq!(to_bytes(ok.into_body(), 1024).await.unwrap(), "fine");
+
+        let missing = app
+            .oneshot(Request::builder().uri("/missing").body(Body::empty()).unwrap())
+            .await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+        let id = missing.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        assert_ne!(id, ok_id);
+        let body = to_bytes(missing.into_body(), 4096).await.unwrap();
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains(&format!("Request ID: {}</p></main>", id)), "{}", body);
+    }
+
+    #[test]
+    fn test_quiet_paths() {
+        assert!(is_quiet("/metrics"));
+        assert!(is_quiet("/favicon.ico"));
+        assert!(is_quiet("/static/keyboard.js"));
+        assert!(is_quiet("/static/settings.js"));
+        assert!(is_quiet("/de/logo"));
+        assert!(is_quiet("/simple/favicon.ico"));
+        assert!(!is_quiet("/de/wiki/Logo"));
+        assert!(!is_quiet("/de/a/logo"));
+        assert!(!is_quiet("/wiki/Rust"));
+        assert!(!is_quiet("/api/v1/search"));
+    }
+}
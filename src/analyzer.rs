@@ -0,0 +1,180 @@
+//! Language-specific text analysis for the search index
+//!
+//! Each Wikipedia edition gets a named Tantivy tokenizer. The name is stored
+//! in the index schema, so an index opened later uses the same analysis it
+//! was built with.
+
+use tantivy::tokenizer::{
+    Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer, TextAnalyzer, Token,
+    TokenStream, Tokenizer,
+};
+use tantivy::Index;
+
+use crate::WikiLanguage;
+
+/// Tokens longer than this (in bytes) are dropped
+const MAX_TOKEN_LENGTH: usize = 40;
+
+/// Tokenizer for Chinese and Japanese text
+const CJK_TOKENIZER: &str = "lang_cjk";
+
+/// Stemming tokenizers: (name, stemmer language)
+const STEMMED_TOKENIZERS: &[(&str, Language)] = &[
+    ("lang_en", Language::English),
+    ("lang_de", Language::German),
+    ("lang_fr", Language::French),
+    ("lang_es", Language::Spanish),
+    ("lang_it", Language::Italian),
+    ("lang_pt", Language::Portuguese),
+    ("lang_ru", Language::Russian),
+];
+
+/// Name of the tokenizer used for a Wikipedia edition
+pub fn tokenizer_name(language: WikiLanguage) -> &'static str {
+    match language {
+        WikiLanguage::Simple | WikiLanguage::English => "lang_en",
+        WikiLanguage::German => "lang_de",
+        WikiLanguage::French => "lang_fr",
+        WikiLanguage::Spanish => "lang_es",
+        WikiLanguage::Italian => "lang_it",
+        WikiLanguage::Portuguese => "lang_pt",
+        WikiLanguage::Russian => "lang_ru",
+        WikiLanguage::Japanese | WikiLanguage::Chinese => CJK_TOKENIZER,
+    }
+}
+
+/// Register every language tokenizer with an index.
+///
+/// Must be called before building query parsers or writers for the index.
+pub fn register_analyzers(index: &Index) {
+    let tokenizers = index.tokenizers();
+    for (name, language) in STEMMED_TOKENIZERS {
+        let analyzer = TextAnalyzer::builder(SimpleTokenizer::default())
+            .filter(RemoveLongFilter::limit(MAX_TOKEN_LENGTH))
+            .filter(LowerCaser)
+            .filter(Stemmer::new(*language))
+            .build();
+        tokenizers.register(name, analyzer);
+    }
+
+    let cjk = TextAnalyzer::builder(CjkBigramTokenizer)
+        .filter(RemoveLongFilter::limit(MAX_TOKEN_LENGTH))
+        .filter(LowerCaser)
+        .build();
+    tokenizers.register(CJK_TOKENIZER, cjk);
+}
+
+/// Check if a character belongs to a script written without spaces
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'     // Hiragana, Katakana
+        | '\u{31F0}'..='\u{31FF}'   // Katakana phonetic extensions
+        | '\u{3400}'..='\u{4DBF}'   // CJK extension A
+        | '\u{4E00}'..='\u{9FFF}'   // CJK unified ideographs
+        | '\u{F900}'..='\u{FAFF}'   // CJK compatibility ideographs
+        | '\u{FF66}'..='\u{FF9F}'   // Half-width Katakana
+        | '\u{20000}'..='\u{2A6DF}' // CJK extension B
+    )
+}
+
+/// Tokenizer emitting overlapping character bigrams for CJK runs and whole
+/// words for everything else.
+///
+/// "東京都 Tokyo" becomes `東京`, `京都`, `Tokyo`. A CJK run of a single
+/// character is emitted as-is.
+#[derive(Clone, Default)]
+pub struct CjkBigramTokenizer;
+
+/// Token stream over pre-computed tokens
+pub struct CjkBigramTokenStream {
+    tokens: Vec<Token>,
+    cursor: usize,
+}
+
+impl Tokenizer for CjkBigramTokenizer {
+    type TokenStream<'a> = CjkBigramTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> CjkBigramTokenStream {
+        let mut tokens = Vec::new();
+        let mut push = |from: usize, to: usize| {
+            tokens.push(Token {
+                offset_from: from,
+                offset_to: to,
+                position: tokens.len(),
+                text: text[from..to].to_string(),
+                position_length: 1,
+            });
+        };
+
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let end_of = |i: usize| chars.get(i).map(|(offset, _)| *offset).unwrap_or(text.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i].1;
+            if is_cjk(c) {
+                let start = i;
+                while i < chars.len() && is_cjk(chars[i].1) {
+                    i += 1;
+                }
+                if i - start == 1 {
+                    push(chars[start].0, end_of(i));
+                } else {
+                    for (j, (offset, _)) in chars.iter().enumerate().take(i - 1).skip(start) {
+                        push(*offset, end_of(j + 2));
+                    }
+                }
+            } else if c.is_alphanumeric() {
+                let start = i;
+                while i < chars.len() && chars[i].1.is_alphanumeric() && !is_cjk(chars[i].1) {
+                    i += 1;
+                }
+                push(chars[start].0, end_of(i));
+            } else {
+                i += 1;
+            }
+        }
+
+        CjkBigramTokenStream { tokens, cursor: 0 }
+    }
+}
+
+impl TokenStream for CjkBigramTokenStream {
+    fn advance(&mut self) -> bool {
+        if self.cursor < self.tokens.len() {
+            self.cursor += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.cursor - 1]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.cursor - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &str) -> Vec<String> {
+        let mut tokenizer = CjkBigramTokenizer;
+        let mut stream = tokenizer.token_stream(text);
+        let mut out = Vec::new();
+        while let Some(token) = stream.next() {
+            out.push(token.text.clone());
+        }
+        out
+    }
+
+    #[test]
+    fn test_cjk_bigrams() {
+        assert_eq!(tokens("東京都 Tokyo"), vec!["東京", "京都", "Tokyo"]);
+        assert_eq!(tokens("日本の首都"), vec!["日本", "本の", "の首", "首都"]);
+        assert_eq!(tokens("猫、Rust2024"), vec!["猫", "Rust2024"]);
+    }
+}
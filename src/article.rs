@@ -1,7 +1,31 @@
 //! Article data structures
 
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use crate::parser::WikiParser;
+
+/// Section headings shown in the table of contents
+static HEADING_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<h([23])>(.*?)</h[23]>").unwrap());
+static TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+static ENTITY_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap());
+
+/// Title prefixes of list and outline articles
+const LIST_TITLE_PREFIXES: &[&str] = &["List of ", "Lists of ", "Outline of ", "Index of "];
+
+/// Links per word from which an article is rendered as a list
+const LIST_LINK_DENSITY: f64 = 0.2;
+
+/// Links an article needs before its link density counts
+const MIN_LIST_LINKS: usize = 20;
+
+/// High bit set on fallback IDs so they never collide with real page IDs
+const FALLBACK_ID_FLAG: u64 = 1 << 63;
 
 /// A Wikipedia article
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +48,14 @@ pub struct Article {
     /// Extraction timestamp
     #[serde(default = "Utc::now")]
     pub extracted_at: DateTime<Utc>,
+    /// Hash of `content`, for detecting changed articles between extractions
+    /// (0 for articles extracted before hashes were recorded)
+    #[serde(default)]
+    pub content_hash: u64,
+    /// When the article was last edited on Wikipedia: the timestamp of its
+    /// latest revision in the dump (not when it was extracted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_edited: Option<DateTime<Utc>>,
 }
 
 impl Article {
@@ -32,14 +64,43 @@ impl Article {
         Self {
             id,
             title,
+            content_hash: Self::hash_content(&content),
             content,
             raw_markup: None,
             categories: Vec::new(),
             redirect_to: None,
             extracted_at: Utc::now(),
+            last_edited: None,
         }
     }
 
+    /// Derive a stable ID from a title, for dumps that don't provide page IDs.
+    ///
+    /// The ID is taken from a SHA-256 of the title with the high bit set, so it
+    /// is the same across runs and stays clear of real Wikipedia page IDs.
+    pub fn fallback_id(title: &str) -> u64 {
+        let digest = Sha256::digest(title.as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(bytes) | FALLBACK_ID_FLAG
+    }
+
+    /// Hash article content (first 8 bytes of its SHA-256).
+    ///
+    /// Stable across runs and platforms, so hashes from different
+    /// extractions can be compared.
+    pub fn hash_content(content: &str) -> u64 {
+        let digest = Sha256::digest(content.as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(bytes)
+    }
+
+    /// Recompute `content_hash` after changing `content`
+    pub fn update_content_hash(&mut self) {
+        self.content_hash = Self::hash_content(&self.content);
+    }
+
     /// Check if this is a redirect page
     pub fn is_redirect(&self) -> bool {
         self.redirect_to.is_some()
@@ -55,23 +116,187 @@ impl Article {
         self.content.split_whitespace().count()
     }
 
+    /// Whether this is a list or outline rather than prose: its title starts
+    /// with "List of", "Outline of" and the like, or it is mostly links.
+    ///
+    /// Such articles are rendered with their lines as list items.
+    pub fn is_list_article(&self) -> bool {
+        if LIST_TITLE_PREFIXES.iter().any(|prefix| self.title.starts_with(prefix)) {
+            return true;
+        }
+        let links = crate::prune::count_wiki_links(&self.content);
+        links >= MIN_LIST_LINKS && links as f64 >= self.word_count() as f64 * LIST_LINK_DENSITY
+    }
+
     /// Get a preview/summary of the article (first N characters)
     pub fn preview(&self, max_chars: usize) -> &str {
-        if self.content.len() <= max_chars {
-            &self.content
-        } else {
-            // Find a good break point (end of word)
-            let mut end = max_chars;
-            while end > 0 && !self.content.is_char_boundary(end) {
-                end -= 1;
-            }
-            // Try to end at a space
-            if let Some(space_pos) = self.content[..end].rfind(' ') {
-                &self.content[..space_pos]
-            } else {
-                &self.content[..end]
-            }
+        truncate_at_word(&self.content, max_chars)
+    }
+
+    /// Render the content as escaped HTML paragraphs.
+    ///
+    /// Only the link, heading, list and `<mark>` tags the parser emits are
+    /// kept, so markup that slips past the parser is shown as text.
+    ///
+    /// `<h2>` and `<h3>` headings get the `id` anchors of [`Self::headings`].
+    /// In list articles (see [`Self::is_list_article`]), paragraphs of
+    /// several lines become `<ul>` lists with one item per line.
+    pub fn content_html(&self) -> String {
+        self.content_html_blocks().join("\n")
+    }
+
+    /// Render like [`Self::content_html`], split after the paragraph that
+    /// reaches `max_chars` of HTML.
+    ///
+    /// Returns the leading part and the rest, if any. At least one
+    /// paragraph is always in the leading part; `max_chars` 0 never splits.
+    pub fn content_html_split(&self, max_chars: usize) -> (String, Option<String>) {
+        let blocks = self.content_html_blocks();
+        if max_chars == 0 {
+            return (blocks.join("\n"), None);
         }
+        let mut length = 0;
+        let split = blocks.iter()
+            .position(|block| {
+                length += block.chars().count();
+                length >= max_chars
+            })
+            .map_or(blocks.len(), |last| last + 1);
+        if split >= blocks.len() {
+            return (blocks.join("\n"), None);
+        }
+        (blocks[..split].join("\n"), Some(blocks[split..].join("\n")))
+    }
+
+    /// Rendered paragraphs and headings, in order
+    fn content_html_blocks(&self) -> Vec<String> {
+        let headings = self.headings();
+        let mut next_heading = headings.iter();
+        let html = WikiParser::escape_content(&self.content);
+        let html = HEADING_RE.replace_all(&html, |caps: &regex::Captures| {
+            match next_heading.next() {
+                Some(heading) => format!(r#"<h{0} id="{1}">{2}</h{0}>"#, &caps[1], heading.anchor, &caps[2]),
+                None => caps[0].to_string(),
+            }
+        });
+        let list = self.is_list_article();
+        html.split("\n\n")
+            .map(|paragraph| {
+                if paragraph.starts_with("<h") && paragraph.ends_with('>') {
+                    paragraph.to_string()
+                } else if list && paragraph.trim().contains('\n') {
+                    let items: String = paragraph.lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .map(|line| format!("<li>{}</li>", line))
+                        .collect();
+                    format!("<ul>{}</ul>", items)
+                } else {
+                    format!("<p>{}</p>", paragraph)
+                }
+            })
+            .collect()
+    }
+
+    /// The content as plain text: the parser's link and heading tags are
+    /// removed and entity references decoded
+    pub fn to_plain_text(&self) -> String {
+        let text = TAG_RE.replace_all(&self.content, "");
+        ENTITY_RE.replace_all(&text, |caps: &regex::Captures| {
+            let entity = &caps[1];
+            let decoded = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{A0}'),
+                _ => {
+                    let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                    };
+                    code.and_then(char::from_u32)
+                }
+            };
+            decoded.map(String::from).unwrap_or_else(|| caps[0].to_string())
+        }).into_owned()
+    }
+
+    /// `<h2>` and `<h3>` headings in the content, in order.
+    ///
+    /// Anchors are slugs of the heading text, suffixed with `-2`, `-3`, ...
+    /// when several headings share the same text. Content extracted without
+    /// headings has none.
+    pub fn headings(&self) -> Vec<Heading> {
+        let mut used = HashSet::new();
+        HEADING_RE.captures_iter(&self.content)
+            .map(|caps| {
+                let text = TAG_RE.replace_all(&caps[2], "").trim().to_string();
+                let slug = slugify(&text);
+                let mut anchor = slug.clone();
+                let mut n = 2;
+                while !used.insert(anchor.clone()) {
+                    anchor = format!("{}-{}", slug, n);
+                    n += 1;
+                }
+                Heading { level: if &caps[1] == "2" { 2 } else { 3 }, text, anchor }
+            })
+            .collect()
+    }
+}
+
+/// A section heading in an article
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// Heading level (2 or 3)
+    pub level: u8,
+    /// Heading text, without tags
+    pub text: String,
+    /// Unique `id` of the heading within the article
+    pub anchor: String,
+}
+
+/// Lowercase `text`, joining runs of letters and digits with `-`
+/// ("History & Origins" -> "history-origins")
+pub fn slugify(text: &str) -> String {
+    let slug = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() { "section".to_string() } else { slug }
+}
+
+/// Normalize a page title the way MediaWiki does: underscores become
+/// spaces, runs of spaces collapse, the ends are trimmed and the first
+/// letter is capitalized. The rest stays case-sensitive, so "iPhone" and
+/// "IPhone" are the same page but "IPhone" and "Iphone" are not.
+pub fn normalize_title(title: &str) -> String {
+    let title = title.split([' ', '_']).filter(|word| !word.is_empty()).collect::<Vec<_>>().join(" ");
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => title,
+    }
+}
+
+/// Truncate text to at most `max_chars` characters, ending at a word
+/// boundary where possible. Never splits a multibyte character.
+pub fn truncate_at_word(text: &str, max_chars: usize) -> &str {
+    let end = match text.char_indices().nth(max_chars) {
+        Some((end, _)) => end,
+        None => return text,
+    };
+    // Keep the last word if the cut falls right after it
+    if text[end..].starts_with(char::is_whitespace) {
+        return &text[..end];
+    }
+    // Try to end at a space
+    match text[..end].rfind(char::is_whitespace) {
+        Some(space_pos) => &text[..space_pos],
+        None => &text[..end],
     }
 }
 
@@ -86,9 +311,31 @@ pub struct ExtractionStats {
     pub redirects: u64,
     /// Special pages skipped
     pub special_pages: u64,
+    /// Articles skipped because of an excluded category, or because they
+    /// matched none of the included categories
+    #[serde(default)]
+    pub skipped_by_category: u64,
+    /// Articles skipped for being outside the configured word count range
+    #[serde(default)]
+    pub skipped_by_word_count: u64,
+    /// Articles skipped because their latest revision predates `--since`
+    #[serde(default)]
+    pub skipped_by_date: u64,
+    /// Articles skipped for having fewer links than `min_links`
+    #[serde(default)]
+    pub skipped_by_links: u64,
+    /// Malformed pages skipped by resynchronizing on the next `<page>`
+    #[serde(default)]
+    pub xml_errors_recovered: u64,
     /// Total bytes of content
     pub total_bytes: u64,
-    /// Minimum article length requirement
+    /// Article limit used for extraction (0 = unlimited)
+    #[serde(default)]
+    pub max_articles: usize,
+    /// Extraction stopped at `max_articles` before the end of the dump
+    #[serde(default)]
+    pub truncated: bool,
+    /// Minimum article length requirement, in characters
     pub min_length: usize,
     /// Source Wikipedia edition
     pub language: String,
@@ -121,14 +368,140 @@ impl ExtractionStats {
         self.completed_at = Some(now);
     }
 
-    /// Articles processed per second
+    /// Articles processed per second (so far, while extraction is running)
     pub fn articles_per_second(&self) -> f64 {
-        if let Some(duration) = self.duration_secs {
-            if duration > 0.0 {
-                return self.articles_extracted as f64 / duration;
-            }
+        let duration = self.duration_secs
+            .unwrap_or_else(|| (Utc::now() - self.started_at).num_milliseconds() as f64 / 1000.0);
+        if duration > 0.0 {
+            return self.articles_extracted as f64 / duration;
         }
         0.0
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash_tracks_content() {
+        let a = Article::new(1, "A".to_string(), "Same body".to_string());
+        let b = Article::new(2, "B".to_string(), "Same body".to_string());
+        let c = Article::new(1, "A".to_string(), "Edited body".to_string());
+
+        assert_eq!(a.content_hash, b.content_hash);
+        assert_ne!(a.content_hash, c.content_hash);
+
+        // Survives a JSON round trip; missing in old data means 0
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(serde_json::from_str::<Article>(&json).unwrap().content_hash, a.content_hash);
+        let old: Article = serde_json::from_str(r#"{"id":1,"title":"A","content":"Same body"}"#).unwrap();
+        assert_eq!(old.content_hash, 0);
+    }
+
+    #[test]
+    fn test_content_html_escapes_stray_markup() {
+        let article = Article::new(1, "A".to_string(), concat!(
+            "Intro <script>alert(\"x\")</script> & more\n\n",
+            "See <a href=\"/wiki/Rust\">Rust &amp; Cargo</a>."
+        ).to_string());
+
+        assert_eq!(article.content_html(), concat!(
+            "<p>Intro &lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt; &amp; more</p>\n",
+            "<p>See <a href=\"/wiki/Rust\">Rust &amp; Cargo</a>.</p>"
+        ));
+    }
+
+    #[test]
+    fn test_headings_get_unique_anchors() {
+        let article = Article::new(1, "A".to_string(), concat!(
+            "Intro\n\n<h2>History</h2>\n\nText\n\n<h3>Early <a href=\"/wiki/Life\">life</a></h3>\n\n",
+            "<h2>History</h2>\n\n<h2>!!</h2>"
+        ).to_string());
+
+        let anchors: Vec<_> = article.headings().into_iter().map(|h| (h.level, h.text, h.anchor)).collect();
+        assert_eq!(anchors, vec![
+            (2, "History".to_string(), "history".to_string()),
+            (3, "Early life".to_string(), "early-life".to_string()),
+            (2, "History".to_string(), "history-2".to_string()),
+            (2, "!!".to_string(), "section".to_string()),
+        ]);
+        let html = article.content_html();
+        assert!(html.contains("<h2 id=\"history-2\">History</h2>\n<h2 id=\"section\">"), "{}", html);
+        assert!(html.starts_with("<p>Intro</p>\n<h2 id=\"history\">"), "{}", html);
+
+        // No headings, no anchors
+        assert!(Article::new(2, "B".to_string(), "Plain text".to_string()).headings().is_empty());
+    }
+
+    #[test]
+    fn test_content_html_split_keeps_short_articles_whole() {
+        let short = Article::new(1, "A".to_string(), "One.\n\nTwo.".to_string());
+        assert_eq!(short.content_html_split(1000), (short.content_html(), None));
+
+        let long = Article::new(2, "B".to_string(), "First paragraph.\n\nSecond one.\n\nThird.".to_string());
+        let (head, rest) = long.content_html_split(30);
+        assert_eq!(head, "<p>First paragraph.</p>\n<p>Second one.</p>");
+        assert_eq!(rest.as_deref(), Some("<p>Third.</p>"));
+        assert_eq!(long.content_html_split(0).1, None);
+    }
+
+    #[test]
+    fn test_list_articles_render_lines_as_items() {
+        let content = "Rivers by length:\n\n<h2>Africa</h2>\n\n<a href=\"/wiki/Nile\">Nile</a>\n<a href=\"/wiki/Congo\">Congo</a> – 4,700 km";
+        let list = Article::new(1, "List of rivers".to_string(), content.to_string());
+        assert!(list.is_list_article());
+        assert_eq!(list.content_html(), concat!(
+            "<p>Rivers by length:</p>\n<h2 id=\"africa\">Africa</h2>\n",
+            "<ul><li><a href=\"/wiki/Nile\">Nile</a></li><li><a href=\"/wiki/Congo\">Congo</a> – 4,700 km</li></ul>"
+        ));
+
+        // The same content under a prose title stays in paragraphs
+        let prose = Article::new(2, "Rivers".to_string(), content.to_string());
+        assert!(!prose.is_list_article());
+        assert!(prose.content_html().ends_with("<p><a href=\"/wiki/Nile\">Nile</a>\n<a href=\"/wiki/Congo\">Congo</a> – 4,700 km</p>"));
+
+        // Mostly links, whatever the title
+        let links: Vec<String> = (0..30).map(|i| format!("<a href=\"/wiki/P{0}\">P{0}</a>", i)).collect();
+        assert!(Article::new(3, "Rivers".to_string(), links.join("\n")).is_list_article());
+    }
+
+    #[test]
+    fn test_plain_text_strips_tags() {
+        let article = Article::new(1, "A".to_string(), concat!(
+            "<h2>History</h2>\n\nSee <a href=\"/wiki/Rust_%26_Cargo\">Rust &amp; Cargo</a> ",
+            "and <a>Iron</a>.\n\n<h3>Caf&#233;s &#x2014; &unknown;</h3>"
+        ).to_string());
+
+        let text = article.to_plain_text();
+        assert!(!text.contains('<') && !text.contains('>'), "{}", text);
+        assert_eq!(text, "History\n\nSee Rust & Cargo and Iron.\n\nCafés — &unknown;");
+    }
+
+    #[test]
+    fn test_normalize_title_like_mediawiki() {
+        // Only the first letter is case-insensitive
+        assert_eq!(normalize_title("iPhone"), "IPhone");
+        assert_eq!(normalize_title("IPhone"), "IPhone");
+        assert_ne!(normalize_title("IPhone"), normalize_title("Iphone"));
+        assert_ne!(normalize_title("United States"), normalize_title("united states"));
+
+        // Underscores and spaces are interchangeable, runs collapse, ends are trimmed
+        assert_eq!(normalize_title("United_States"), "United States");
+        assert_eq!(normalize_title("  _United__ _States_ "), "United States");
+        assert_eq!(normalize_title("éclair"), "Éclair");
+        assert_eq!(normalize_title("1984 (novel)"), "1984 (novel)");
+        assert_eq!(normalize_title(" _ "), "");
+    }
+
+    #[test]
+    fn test_truncate_at_word_utf8() {
+        let text = "Zürich ist die größte Stadt der Schweiz";
+
+        assert_eq!(truncate_at_word(text, 15), "Zürich ist die");
+        assert_eq!(truncate_at_word(text, 14), "Zürich ist die");
+        assert_eq!(truncate_at_word(text, 19), "Zürich ist die");
+        assert_eq!(truncate_at_word(text, 100), text);
+        assert_eq!(truncate_at_word("日本語のテキスト", 3), "日本語");
+    }
+}
@@ -0,0 +1,113 @@
+//! In-memory cache of recently viewed articles
+//!
+//! Fetching an article from the search index runs a Tantivy query and
+//! deserializes the stored document. Popular articles are kept in a bounded
+//! LRU cache so repeated views skip that work.
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use lru::LruCache;
+
+use crate::article::Article;
+
+/// Default number of cached articles
+pub const DEFAULT_ARTICLE_CACHE_SIZE: usize = 1000;
+
+/// Bounded LRU cache of articles keyed by ID
+pub struct ArticleCache {
+    /// `None` when caching is disabled (capacity 0)
+    cache: Option<Mutex<LruCache<u64, Article>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ArticleCache {
+    /// Create a cache holding up to `capacity` articles (0 disables caching)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            cache: NonZeroUsize::new(capacity).map(|capacity| Mutex::new(LruCache::new(capacity))),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Return the cached article, or call `load` and cache its result.
+    ///
+    /// Missing articles (`None`) are not cached. The lock is not held while
+    /// `load` runs, so concurrent misses for the same ID may both load it.
+    pub fn get_or_load(&self, id: u64, load: impl FnOnce() -> Option<Article>) -> Option<Article> {
+        let Some(ref cache) = self.cache else {
+            return load();
+        };
+
+        if let Some(article) = cache.lock().unwrap().get(&id) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Some(article.clone());
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let article = load()?;
+        cache.lock().unwrap().put(id, article.clone());
+        Some(article)
+    }
+
+    /// Maximum number of cached articles
+    pub fn capacity(&self) -> usize {
+        self.cache.as_ref().map_or(0, |cache| cache.lock().unwrap().cap().get())
+    }
+
+    /// Number of lookups served from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of lookups that had to load the article
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for ArticleCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_ARTICLE_CACHE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_second_fetch_served_from_cache() {
+        let cache = ArticleCache::new(2);
+        let loads = Cell::new(0);
+        let load = |id: u64| {
+            loads.set(loads.get() + 1);
+            Some(Article::new(id, format!("Article {}", id), "Some content".to_string()))
+        };
+
+        let first = cache.get_or_load(1, || load(1)).unwrap();
+        let second = cache.get_or_load(1, || load(1)).unwrap();
+        assert_eq!(first.title, second.title);
+        assert_eq!(loads.get(), 1);
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        // Filling the cache evicts the least recently used article
+        cache.get_or_load(2, || load(2));
+        cache.get_or_load(3, || load(3));
+        cache.get_or_load(1, || load(1));
+        assert_eq!(loads.get(), 4);
+
+        // Missing articles aren't cached, and capacity 0 disables caching
+        assert!(cache.get_or_load(9, || None).is_none());
+        assert_eq!(cache.get_or_load(9, || load(9)).unwrap().id, 9);
+        let disabled = ArticleCache::new(0);
+        disabled.get_or_load(1, || load(1));
+        disabled.get_or_load(1, || load(1));
+        assert_eq!(disabled.hits(), 0);
+        assert_eq!(disabled.capacity(), 0);
+    }
+}
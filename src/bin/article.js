@@ -0,0 +1,121 @@
+// Article page helpers:
+// - "Copy as text" fetches /article/:id/text and writes it to the clipboard.
+// - "Show full article" loads the content past the server's render limit,
+//   also triggered when following a contents link into the missing part.
+// - Reader settings save the content width and text size to localStorage
+//   and apply them as classes on <html> (medium is the default, no class).
+(function () {
+    'use strict';
+
+    const READER_SETTINGS = {
+        readerWidth: { key: 'rustipedia.readerWidth', prefix: 'reader-width-', attribute: 'data-reader-width' },
+        readerFont: { key: 'rustipedia.readerFont', prefix: 'reader-font-', attribute: 'data-reader-font' },
+    };
+
+    function readSetting(setting) {
+        try {
+            return localStorage.getItem(setting.key) || 'medium';
+        } catch (e) {
+            return 'medium';
+        }
+    }
+
+    function applySetting(name, value) {
+        const setting = READER_SETTINGS[name];
+        const root = document.documentElement;
+        Array.from(root.classList)
+            .filter(function (c) { return c.startsWith(setting.prefix); })
+            .forEach(function (c) { root.classList.remove(c); });
+        if (value !== 'medium') root.classList.add(setting.prefix + value);
+        document.querySelectorAll('[' + setting.attribute + ']').forEach(function (button) {
+            button.setAttribute('aria-pressed', button.dataset[name] === value ? 'true' : 'false');
+        });
+    }
+
+    function saveSetting(name, value) {
+        try {
+            if (value === 'medium') {
+                localStorage.removeItem(READER_SETTINGS[name].key);
+            } else {
+                localStorage.setItem(READER_SETTINGS[name].key, value);
+            }
+        } catch (e) {
+            console.error('Failed to save reader settings:', e);
+        }
+        applySetting(name, value);
+    }
+
+    async function copyText(button) {
+        const label = button.textContent;
+        try {
+            const res = await fetch(button.dataset.copyText);
+            if (!res.ok) throw new Error(res.status + ' ' + res.statusText);
+            await navigator.clipboard.writeText(await res.text());
+            button.textContent = 'Copied!';
+        } catch (e) {
+            button.textContent = 'Copy failed';
+            console.error('Failed to copy article text:', e);
+        }
+        setTimeout(function () { button.textContent = label; }, 2000);
+    }
+
+    async function loadRest() {
+        const button = document.querySelector('[data-load-rest]');
+        if (!button || button.disabled) return;
+        button.disabled = true;
+        button.textContent = 'Loading...';
+        try {
+            const res = await fetch(button.dataset.loadRest);
+            const data = await res.json();
+            if (!res.ok) throw new Error(data.error);
+            document.querySelector('.article-content').insertAdjacentHTML('beforeend', data.html);
+            button.parentElement.remove();
+        } catch (e) {
+            button.disabled = false;
+            button.textContent = 'Show full article';
+            console.error('Failed to load the rest of the article:', e);
+        }
+    }
+
+    async function showAnchor(hash) {
+        if (!hash || hash.length < 2) return;
+        const id = decodeURIComponent(hash.slice(1));
+        if (!document.getElementById(id)) {
+            await loadRest();
+        }
+        const target = document.getElementById(id);
+        if (target) target.scrollIntoView();
+    }
+
+    document.addEventListener('click', function (event) {
+        const copy = event.target.closest('[data-copy-text]');
+        if (copy) {
+            copyText(copy);
+            return;
+        }
+
+        if (event.target.closest('[data-load-rest]')) {
+            loadRest();
+            return;
+        }
+
+        const reader = event.target.closest('[data-reader-width], [data-reader-font]');
+        if (reader) {
+            const name = reader.dataset.readerWidth ? 'readerWidth' : 'readerFont';
+            saveSetting(name, reader.dataset[name]);
+            return;
+        }
+
+        const link = event.target.closest('a[href^="#"]');
+        if (link && !document.getElementById(decodeURIComponent(link.hash.slice(1)))) {
+            event.preventDefault();
+            history.pushState(null, '', link.hash);
+            showAnchor(link.hash);
+        }
+    });
+
+    Object.keys(READER_SETTINGS).forEach(function (name) {
+        applySetting(name, readSetting(READER_SETTINGS[name]));
+    });
+    showAnchor(window.location.hash);
+})();
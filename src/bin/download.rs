@@ -19,13 +19,21 @@
 //! rustipedia-download --lang de --output ./german-wiki --max-articles 10000
 //! ```
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand, ValueEnum};
 use tracing_subscriber::EnvFilter;
 
-use rustipedia::{Config, WikiDownloader, WikiLanguage, SearchIndex};
+use rustipedia::{Config, DataDirConfig, DownloadPlan, ExtractionState, WikiDownloader, WikiLanguage, SearchIndex};
+use rustipedia::downloader::{format_bytes, DEFAULT_DECOMPRESS_BUFFER_SIZE, DEFAULT_EXTRACT_SPACE_MULTIPLIER};
+use rustipedia::graph::GraphFormat;
+use rustipedia::jsonl;
+use rustipedia::lock::DataDirLock;
+use rustipedia::progress::{self, Progress, ProgressUnit};
+use rustipedia::prune::ValidTitles;
+use rustipedia::search::index_count_matches;
 
 #[derive(Parser)]
 #[command(name = "rustipedia-download")]
@@ -46,6 +54,9 @@ EXAMPLES:
   Download full English Wikipedia (~22GB):
     rustipedia-download --lang en
 
+  Download, index and start serving in one go:
+    rustipedia-download quickstart --lang simple --port 8080
+
   Download German Wikipedia to custom directory:
     rustipedia-download --lang de --output ./german-wiki
 
@@ -57,6 +68,33 @@ EXAMPLES:
 
   Resume extraction from existing dump:
     rustipedia-download --lang simple --skip-download
+
+  Drop articles in certain categories:
+    rustipedia-download --lang simple --exclude-category "Living people"
+
+  Build a themed wiki from selected categories only:
+    rustipedia-download --lang en --include-category Physics --include-category Chemistry
+
+  Check URL, paths and disk space without downloading:
+    rustipedia-download --lang en --dry-run
+
+  Check that the search index matches the extracted articles:
+    rustipedia-download validate ./wikipedia
+
+  Import articles cleaned by another tool, then index them:
+    rustipedia-download import cleaned.jsonl ./wikipedia --language en
+
+  Import a Kiwix ZIM file instead of downloading a dump:
+    rustipedia-download import-zim wikipedia_en_simple_all.zim ./wikipedia
+
+  See what changed between an old and a new extraction:
+    rustipedia-download diff ./wikipedia-old ./wikipedia
+
+  Export articles for pandas/Polars:
+    rustipedia-download export-parquet ./wikipedia --out articles.parquet
+
+  Export the link graph for NetworkX or Gephi:
+    rustipedia-download export-graph ./wikipedia --format graphml
 "#)]
 struct Cli {
     #[command(subcommand)]
@@ -74,9 +112,22 @@ struct Cli {
     #[arg(short, long, default_value = "0")]
     max_articles: usize,
 
-    /// Minimum article length in characters
-    #[arg(long, default_value = "200")]
-    min_length: usize,
+    /// Minimum article length in characters [default: 200; 100 for Simple English, 60 for Japanese and Chinese]
+    #[arg(long)]
+    min_length: Option<usize>,
+
+    /// Minimum article length in words, after cleaning (0 = no minimum)
+    #[arg(long, default_value = "0")]
+    min_words: usize,
+
+    /// Maximum article length in words, after cleaning (0 = unlimited)
+    #[arg(long, default_value = "0")]
+    max_words: usize,
+
+    /// Skip articles with fewer links to other articles (0 = no minimum). Counted before
+    /// --prune-links, so links to articles missing from the dump count too.
+    #[arg(long, value_name = "N", default_value = "0")]
+    min_links: usize,
 
     /// Skip download if dump already exists
     #[arg(long)]
@@ -94,13 +145,94 @@ struct Cli {
     #[arg(long)]
     keep_dump: bool,
 
+    /// Download the .xml.xz dump instead of .xml.bz2 (faster to decompress)
+    #[arg(long)]
+    prefer_xz: bool,
+
+    /// Download from a mirror of dumps.wikimedia.org instead, e.g.
+    /// https://mirror.example.edu/wikimedia/dumps ({lang}wiki/latest/... is appended)
+    #[arg(long, value_name = "URL")]
+    dump_base_url: Option<String>,
+
+    /// Store the original wiki markup with each article (roughly doubles articles.jsonl).
+    /// Lets `prune` re-render links from the source instead of patching HTML.
+    #[arg(long)]
+    keep_raw_markup: bool,
+
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
 
+    /// Print plain progress lines instead of progress bars (the default when
+    /// stdout is not a terminal)
+    #[arg(long, visible_alias = "quiet", global = true)]
+    no_progress: bool,
+
+    /// Fail on malformed lines in articles.jsonl instead of skipping them
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Don't lock the data directory (for filesystems without lock support)
+    #[arg(long, global = true)]
+    no_lock: bool,
+
+    /// Print what would be downloaded and where, check disk space, then exit
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Number of search indexing threads (0 = number of CPUs, max 8)
+    #[arg(long, default_value = "0")]
+    index_threads: usize,
+
+    /// Don't checkpoint search indexing progress; an interrupted build
+    /// starts over instead of resuming
+    #[arg(long, global = true)]
+    no_index_checkpoint: bool,
+
     /// Prune broken links (remove links to articles that don't exist in the dump)
     #[arg(long)]
     prune_links: bool,
+
+    /// Prune using a bloom filter instead of an exact title set. Uses far less
+    /// memory but may keep about 1% of broken links.
+    #[arg(long)]
+    low_memory: bool,
+
+    /// Skip articles in this category (repeatable, case-insensitive)
+    #[arg(long = "exclude-category", value_name = "CATEGORY")]
+    exclude_categories: Vec<String>,
+
+    /// Only keep articles in at least one of these categories (repeatable).
+    /// --exclude-category wins when an article matches both.
+    #[arg(long = "include-category", value_name = "CATEGORY")]
+    include_categories: Vec<String>,
+
+    /// Read buffer between the dump decompressor and the XML parser, in bytes
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_DECOMPRESS_BUFFER_SIZE)]
+    decompress_buffer_size: usize,
+
+    /// Only keep articles whose latest revision is on or after this date (YYYY-MM-DD, UTC)
+    #[arg(long, value_name = "DATE")]
+    since: Option<NaiveDate>,
+
+    /// Write articles.jsonl.gz (gzip-compressed, roughly a third of the size) instead of
+    /// articles.jsonl. Served from the search index.
+    #[arg(long)]
+    compress_output: bool,
+
+    /// Split the articles into articles.000.jsonl, articles.001.jsonl, ... of N articles each
+    /// (0 = a single articles.jsonl). Needs the search index to serve.
+    #[arg(long, value_name = "N", default_value = "0")]
+    shard_size: usize,
+
+    /// Write the in-progress download and pruned articles to this directory (e.g. a fast
+    /// scratch disk), moving them to the data directory once finished [default: the data directory]
+    #[arg(long, value_name = "DIR", global = true)]
+    temp_dir: Option<PathBuf>,
+
+    /// Free space required before extracting, as a multiple of the dump size
+    #[arg(long, value_name = "X", default_value_t = DEFAULT_EXTRACT_SPACE_MULTIPLIER)]
+    extract_space_multiplier: f64,
 }
 
 #[derive(Subcommand)]
@@ -114,9 +246,24 @@ enum Commands {
         lang: Option<String>,
     },
     
+    /// Download, extract and index a Wikipedia, then serve it with rustipedia-serve
+    Quickstart {
+        /// Wikipedia language code (e.g., simple, en, de, fr) [default: --lang before the subcommand]
+        #[arg(short, long)]
+        lang: Option<String>,
+        
+        /// Port to serve on
+        #[arg(short, long, default_value = "8080")]
+        port: u16,
+        
+        /// Host address to bind to
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+    },
+    
     /// Extract articles from an existing dump
     Extract {
-        /// Path to the dump file
+        /// Path to the dump file (.xml.bz2, .xml.xz or plain .xml)
         dump: PathBuf,
         
         /// Output directory
@@ -131,16 +278,164 @@ enum Commands {
         data_dir: PathBuf,
     },
     
+    /// Rebuild the search index in place, swapping it in only once complete
+    Reindex {
+        /// Directory containing articles.jsonl
+        #[arg(default_value = "wikipedia")]
+        data_dir: PathBuf,
+    },
+    
     /// Prune broken links from extracted articles
     Prune {
         /// Directory containing articles.jsonl
         #[arg(default_value = "wikipedia")]
         data_dir: PathBuf,
     },
+    
+    /// Check that articles, search index and config in a data directory are consistent
+    Validate {
+        /// Directory containing articles.jsonl
+        #[arg(default_value = "wikipedia")]
+        data_dir: PathBuf,
+    },
+    
+    /// Import articles from a JSON Lines file cleaned by another tool
+    Import {
+        /// JSON Lines file with one article (id, title, content) per line
+        file: PathBuf,
+        
+        /// Data directory to write articles.jsonl to
+        #[arg(default_value = "wikipedia")]
+        data_dir: PathBuf,
+        
+        /// Language of the articles, recorded in config.json for search and display
+        #[arg(long)]
+        language: Option<String>,
+        
+        /// Don't build the search index after importing
+        #[arg(long)]
+        no_index: bool,
+    },
+    
+    /// Import articles and redirects from a Kiwix ZIM file
+    ImportZim {
+        /// ZIM file to import
+        file: PathBuf,
+        
+        /// Data directory to write articles.jsonl to
+        #[arg(default_value = "wikipedia")]
+        data_dir: PathBuf,
+        
+        /// Language of the articles [default: from the ZIM metadata]
+        #[arg(long)]
+        language: Option<String>,
+        
+        /// Don't build the search index after importing
+        #[arg(long)]
+        no_index: bool,
+    },
+    
+    /// Show articles added, removed and modified between two data directories
+    Diff {
+        /// Data directory of the older extraction
+        old_dir: PathBuf,
+        
+        /// Data directory of the newer extraction
+        new_dir: PathBuf,
+        
+        /// Maximum titles to list per category
+        #[arg(long, default_value = "20")]
+        limit: usize,
+        
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+    },
+    
+    /// Report corpus statistics: token counts, frequent words and categories
+    CorpusStats {
+        /// Directory containing articles.jsonl
+        #[arg(default_value = "wikipedia")]
+        data_dir: PathBuf,
+        
+        /// Number of most frequent words and categories to list
+        #[arg(long, default_value = "20")]
+        top: usize,
+        
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+    },
+    
+    /// Export the links between articles as a graph (edge list of article IDs)
+    ExportGraph {
+        /// Directory containing articles.jsonl
+        #[arg(default_value = "wikipedia")]
+        data_dir: PathBuf,
+        
+        /// File to write [default: links.csv, links.graphml or links.dot]
+        #[arg(long)]
+        out: Option<PathBuf>,
+        
+        /// Graph format
+        #[arg(long, value_enum, default_value = "csv")]
+        format: GraphFileFormat,
+        
+        /// Include links to missing articles, flagged as broken
+        #[arg(long)]
+        include_broken: bool,
+    },
+    
+    /// Export extracted articles to a Parquet file
+    #[cfg(feature = "parquet")]
+    ExportParquet {
+        /// Directory containing articles.jsonl
+        #[arg(default_value = "wikipedia")]
+        data_dir: PathBuf,
+        
+        /// Parquet file to write
+        #[arg(long, default_value = "articles.parquet")]
+        out: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable report
+    Text,
+    /// Single JSON object on stdout
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum GraphFileFormat {
+    /// Edge list: source_id,target_id
+    Csv,
+    /// GraphML with article titles
+    Graphml,
+    /// Graphviz DOT with article titles
+    Dot,
+}
+
+impl From<GraphFileFormat> for GraphFormat {
+    fn from(format: GraphFileFormat) -> Self {
+        match format {
+            GraphFileFormat::Csv => GraphFormat::Csv,
+            GraphFileFormat::Graphml => GraphFormat::GraphMl,
+            GraphFileFormat::Dot => GraphFormat::Dot,
+        }
+    }
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    if cli.no_progress {
+        progress::set_progress_bars(false);
+    }
+    rustipedia::jsonl::set_strict(cli.strict);
+    if cli.no_lock {
+        rustipedia::lock::set_locking(false);
+    }
 
     // Initialize logging
     let filter = if cli.verbose {
@@ -165,16 +460,57 @@ fn main() -> Result<()> {
             download_wikipedia(&lang_code, &cli)
         }
         
+        Some(Commands::Quickstart { lang, port, host }) => {
+            let lang_code = lang.clone().unwrap_or_else(|| cli.lang.clone());
+            quickstart(&lang_code, *port, host, &cli)
+        }
+        
         Some(Commands::Extract { dump, output }) => {
             extract_dump(dump, output, &cli)
         }
         
         Some(Commands::Index { data_dir }) => {
-            build_index(data_dir)
+            build_index(data_dir, index_threads(&cli), !cli.no_index_checkpoint)
+        }
+        
+        Some(Commands::Reindex { data_dir }) => {
+            reindex(data_dir, index_threads(&cli))
         }
         
         Some(Commands::Prune { data_dir }) => {
-            prune_articles(data_dir)
+            prune_articles(data_dir, cli.low_memory, cli.temp_dir.as_deref())
+        }
+        
+        Some(Commands::Validate { data_dir }) => {
+            validate(data_dir)
+        }
+        
+        Some(Commands::Import { file, data_dir, language, no_index }) => {
+            import(file, data_dir, language.as_deref(), *no_index, index_threads(&cli), !cli.no_index_checkpoint)
+        }
+        
+        Some(Commands::ImportZim { file, data_dir, language, no_index }) => {
+            import_zim(file, data_dir, language.as_deref(), *no_index, index_threads(&cli), !cli.no_index_checkpoint)
+        }
+        
+        Some(Commands::Diff { old_dir, new_dir, limit, format }) => {
+            diff(old_dir, new_dir, *limit, *format)
+        }
+        
+        Some(Commands::CorpusStats { data_dir, top, format }) => {
+            corpus_stats(data_dir, *top, *format)
+        }
+        
+        Some(Commands::ExportGraph { data_dir, out, format, include_broken }) => {
+            export_graph(data_dir, out.as_deref(), (*format).into(), *include_broken)
+        }
+        
+        #[cfg(feature = "parquet")]
+        Some(Commands::ExportParquet { data_dir, out }) => {
+            println!("📦 Exporting {:?} to {:?}...", data_dir, out);
+            let count = rustipedia::export::export_parquet(data_dir, out)?;
+            println!("✅ Exported {} articles", count);
+            Ok(())
         }
         
         None => {
@@ -215,31 +551,73 @@ fn download_wikipedia(lang: &str, cli: &Cli) -> Result<()> {
 
     print_banner(&language);
 
+    warn_raw_markup_size(cli);
+
     // Create config
     let config = Config {
         language: language.code().to_string(),
         output_dir: cli.output.clone(),
         max_articles: cli.max_articles,
         min_length: cli.min_length,
+        min_words: cli.min_words,
+        max_words: cli.max_words,
+        min_links: cli.min_links,
         skip_download: cli.skip_download,
         build_index: cli.build_index,
         keep_dump: cli.keep_dump,
+        prefer_xz: cli.prefer_xz,
+        dump_file: None,
+        dump_base_url: cli.dump_base_url.clone(),
+        keep_raw_markup: cli.keep_raw_markup,
+        exclude_categories: cli.exclude_categories.clone(),
+        include_categories: cli.include_categories.clone(),
+        decompress_buffer_size: cli.decompress_buffer_size,
+        since: cli.since,
+        compress_output: cli.compress_output,
+        shard_size: cli.shard_size,
+        temp_dir: cli.temp_dir.clone(),
+        extract_space_multiplier: cli.extract_space_multiplier,
     };
 
+    config.check_dump_base_url()?;
+
     // Create downloader
     let downloader = WikiDownloader::with_config(config.clone());
 
+    if cli.dry_run {
+        return print_plan(&downloader.plan(), cli);
+    }
+
     // Download
     if !cli.download_only {
         // Download and extract
         let stats = downloader.run()?;
+        let numbers = language.number_format();
         
         println!("\n╔══════════════════════════════════════════════════════════════════╗");
         println!("║                     ✅ Extraction Complete!                       ║");
         println!("╠══════════════════════════════════════════════════════════════════╣");
-        println!("║  Articles extracted: {:>10}                                  ║", stats.articles_extracted);
-        println!("║  Articles skipped:   {:>10}                                  ║", stats.articles_skipped);
-        println!("║  Redirects:          {:>10}                                  ║", stats.redirects);
+        println!("║  Articles extracted: {:>10}                                  ║", numbers.format_number(stats.articles_extracted));
+        println!("║  Articles skipped:   {:>10}                                  ║", numbers.format_number(stats.articles_skipped));
+        println!("║  Redirects:          {:>10}                                  ║", numbers.format_number(stats.redirects));
+        if stats.skipped_by_category > 0 {
+            println!("║  Skipped (category): {:>10}                                  ║", numbers.format_number(stats.skipped_by_category));
+        }
+        if stats.skipped_by_word_count > 0 {
+            println!("║  Skipped (words):    {:>10}                                  ║", numbers.format_number(stats.skipped_by_word_count));
+        }
+        if stats.skipped_by_date > 0 {
+            println!("║  Skipped (--since):  {:>10}                                  ║", numbers.format_number(stats.skipped_by_date));
+        }
+        if stats.skipped_by_links > 0 {
+            println!("║  Skipped (links):    {:>10}                                  ║", numbers.format_number(stats.skipped_by_links));
+        }
+        if stats.xml_errors_recovered > 0 {
+            println!("║  XML errors skipped: {:>10}                                  ║", numbers.format_number(stats.xml_errors_recovered));
+        }
+        if stats.truncated {
+            println!("║  Sample dataset:     stopped at --max-articles {:<10}        ║", stats.max_articles);
+        }
         if let Some(duration) = stats.duration_secs {
             println!("║  Duration:           {:>10.1}s                                 ║", duration);
         }
@@ -247,18 +625,19 @@ fn download_wikipedia(lang: &str, cli: &Cli) -> Result<()> {
 
         // Prune links if requested
         if cli.prune_links {
-            prune_articles(&cli.output)?;
+            prune_articles(&cli.output, cli.low_memory, cli.temp_dir.as_deref())?;
         }
 
         // Build search index if enabled
         if cli.build_index {
             println!("\n📇 Building search index...");
             let index_path = config.index_path();
-            let data_path = config.data_path();
+            let articles_files = jsonl::articles_files(&cli.output);
             
-            let index = SearchIndex::create(&index_path)?;
-            let indexed = index.build_from_jsonl(&data_path)?;
-            println!("✅ Indexed {} articles", indexed);
+            let _lock = DataDirLock::exclusive(&cli.output, "indexing")?;
+            let index = SearchIndex::create_resumable(&index_path, language, !cli.no_index_checkpoint)?;
+            let indexed = index.build_from_jsonl_files(&articles_files, index_threads(cli))?;
+            println!("✅ Indexed {} articles", numbers.format_number(indexed));
         }
     } else {
         // Download only
@@ -273,160 +652,570 @@ fn download_wikipedia(lang: &str, cli: &Cli) -> Result<()> {
     Ok(())
 }
 
-fn extract_dump(dump: &PathBuf, output: &PathBuf, cli: &Cli) -> Result<()> {
+/// Download, extract and index into `--output` unless that was already
+/// done, then replace this process with `rustipedia-serve` on it
+fn quickstart(lang: &str, port: u16, host: &str, cli: &Cli) -> Result<()> {
+    let index_path = cli.output.join("search_index");
+    if ExtractionState::of(&cli.output) == ExtractionState::Complete && index_path.exists() {
+        // Don't serve (or overwrite) another language's data
+        let existing = DataDirConfig::load(&cli.output.join("config.json"), false)?.language;
+        if let Some(existing) = existing.filter(|existing| existing != lang) {
+            anyhow::bail!(
+                "{:?} already holds the '{}' Wikipedia, not '{}'. Use --output to pick another directory, or --lang {} to serve it.",
+                cli.output, existing, lang, existing
+            );
+        }
+        println!("📂 {:?} already has articles and a search index, skipping the download", cli.output);
+    } else {
+        download_wikipedia(lang, cli)?;
+        if cli.dry_run || cli.download_only {
+            return Ok(());
+        }
+    }
+
+    // The server lives in its own binary, installed next to this one
+    let serve_path = std::env::current_exe()?
+        .with_file_name(format!("rustipedia-serve{}", std::env::consts::EXE_SUFFIX));
+    if !serve_path.exists() {
+        anyhow::bail!("{:?} not found; install rustipedia-serve next to rustipedia-download", serve_path);
+    }
+    println!("\n🌐 Starting rustipedia-serve on http://{}:{}/ (Ctrl+C to stop)\n", host, port);
+
+    let mut command = std::process::Command::new(&serve_path);
+    command.arg("--data").arg(&cli.output)
+        .arg("--port").arg(port.to_string())
+        .arg("--host").arg(host);
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let err = command.exec();
+        Err(anyhow::anyhow!("Failed to start {:?}: {}", serve_path, err))
+    }
+    #[cfg(not(unix))]
+    {
+        let status = command.status()?;
+        if !status.success() {
+            anyhow::bail!("rustipedia-serve exited with {}", status);
+        }
+        Ok(())
+    }
+}
+
+fn extract_dump(dump: &Path, output: &Path, cli: &Cli) -> Result<()> {
     println!("📦 Extracting from {:?}...", dump);
+    warn_raw_markup_size(cli);
     
     let config = Config {
         language: "custom".to_string(),
-        output_dir: output.clone(),
+        output_dir: output.to_path_buf(),
         max_articles: cli.max_articles,
         min_length: cli.min_length,
+        min_words: cli.min_words,
+        max_words: cli.max_words,
+        min_links: cli.min_links,
         skip_download: true,
         build_index: cli.build_index,
         keep_dump: true,
+        prefer_xz: cli.prefer_xz,
+        dump_file: Some(dump.to_path_buf()),
+        dump_base_url: None,
+        keep_raw_markup: cli.keep_raw_markup,
+        exclude_categories: cli.exclude_categories.clone(),
+        include_categories: cli.include_categories.clone(),
+        decompress_buffer_size: cli.decompress_buffer_size,
+        since: cli.since,
+        compress_output: cli.compress_output,
+        shard_size: cli.shard_size,
+        temp_dir: cli.temp_dir.clone(),
+        extract_space_multiplier: cli.extract_space_multiplier,
     };
 
+    std::fs::create_dir_all(output)?;
     let downloader = WikiDownloader::with_config(config);
     let stats = downloader.extract()?;
     
     println!("✅ Extracted {} articles", stats.articles_extracted);
+    if stats.skipped_by_date > 0 {
+        println!("   ({} skipped as last edited before --since)", stats.skipped_by_date);
+    }
+    if stats.skipped_by_links > 0 {
+        println!("   ({} skipped for having fewer than --min-links links)", stats.skipped_by_links);
+    }
+    if stats.truncated {
+        println!("   (sample dataset: stopped at --max-articles {})", stats.max_articles);
+    }
     
     if cli.prune_links {
-        prune_articles(output)?;
+        prune_articles(output, cli.low_memory, cli.temp_dir.as_deref())?;
     }
     
     Ok(())
 }
 
-fn build_index(data_dir: &PathBuf) -> Result<()> {
+fn warn_raw_markup_size(cli: &Cli) {
+    if cli.keep_raw_markup {
+        println!("⚠️  --keep-raw-markup stores the wiki source with every article; expect articles.jsonl to roughly double in size.\n");
+    }
+}
+
+/// Resolve the --index-threads flag (0 = auto)
+fn index_threads(cli: &Cli) -> usize {
+    if cli.index_threads == 0 {
+        SearchIndex::default_threads()
+    } else {
+        cli.index_threads
+    }
+}
+
+/// Language recorded in the data directory's config.json (default if missing)
+fn data_dir_language(data_dir: &Path) -> WikiLanguage {
+    DataDirConfig::load(&data_dir.join("config.json"), false)
+        .ok()
+        .and_then(|config| config.language)
+        .and_then(|code| WikiLanguage::from_code(&code))
+        .unwrap_or_default()
+}
+
+/// Build the search index, resuming an interrupted build if `resume`
+fn build_index(data_dir: &Path, threads: usize, resume: bool) -> Result<()> {
     let index_path = data_dir.join("search_index");
-    let data_path = data_dir.join("articles.jsonl");
+    let articles_files = jsonl::articles_files(data_dir);
     
-    if !data_path.exists() {
-        anyhow::bail!("Articles file not found: {:?}. Run download first.", data_path);
+    if articles_files.is_empty() {
+        anyhow::bail!("Articles file not found: {:?}. Run download first.", jsonl::articles_path(data_dir));
     }
     
-    println!("📇 Building search index...");
-    let index = SearchIndex::create(&index_path)?;
-    let indexed = index.build_from_jsonl(&data_path)?;
+    let _lock = DataDirLock::exclusive(data_dir, "indexing")?;
+    let language = data_dir_language(data_dir);
+    println!("📇 Building {} search index ({} threads)...", language, threads);
+    let index = SearchIndex::create_resumable(&index_path, language, resume)?;
+    let indexed = index.build_from_jsonl_files(&articles_files, threads)?;
     println!("✅ Indexed {} articles to {:?}", indexed, index_path);
     
     Ok(())
 }
 
-fn prune_articles(data_dir: &PathBuf) -> Result<()> {
-    use std::collections::HashSet;
-    use std::fs::File;
-    use std::io::{BufRead, BufReader, Write, BufWriter};
-    use rustipedia::Article;
-    use indicatif::{ProgressBar, ProgressStyle};
-
-    let articles_path = data_dir.join("articles.jsonl");
-    let temp_path = data_dir.join("articles_pruned.jsonl");
+fn reindex(data_dir: &Path, threads: usize) -> Result<()> {
+    let index_path = data_dir.join("search_index");
+    let articles_files = jsonl::articles_files(data_dir);
     
-    if !articles_path.exists() {
-        anyhow::bail!("Articles file not found: {:?}", articles_path);
+    if articles_files.is_empty() {
+        anyhow::bail!("Articles file not found: {:?}. Run download first.", jsonl::articles_path(data_dir));
     }
-
-    println!("\n✂️  Pruning broken links...");
     
-    // Pass 1: Collect titles
-    println!("   Scanning articles to build title index...");
-    let mut title_index: HashSet<String> = HashSet::new();
-    let file = File::open(&articles_path)?;
-    let reader = BufReader::new(file);
+    let _lock = DataDirLock::exclusive(data_dir, "indexing")?;
+    let language = data_dir_language(data_dir);
+    println!("📇 Rebuilding {} search index ({} threads)...", language, threads);
+    let indexed = SearchIndex::rebuild(&index_path, &articles_files, language, threads)?;
+    println!("✅ Reindexed {} articles to {:?}", indexed, index_path);
+    println!("   Restart rustipedia-serve to use the new index.");
+    
+    Ok(())
+}
+
+/// Result of scanning articles.jsonl for titles
+struct TitleScan {
+    /// Number of non-empty lines
+    lines: u64,
+    /// Number of titles found
+    titles: u64,
+    /// Line numbers (1-based) that are not valid article JSON
+    invalid_lines: Vec<u64>,
+}
+
+/// Scan the articles files, adding normalized titles to `valid_titles` (if
+/// given) and recording any unparseable lines (numbered across all files)
+fn scan_titles(articles_files: &[PathBuf], mut valid_titles: Option<&mut ValidTitles>) -> Result<TitleScan> {
+    use std::io::BufRead;
+    use indicatif::ProgressStyle;
+
+    #[derive(serde::Deserialize)]
+    struct TitleOnly {
+        title: String,
+    }
+
+    let reader = jsonl::open_all(articles_files)?;
+    let mut scan = TitleScan { lines: 0, titles: 0, invalid_lines: Vec::new() };
     
-    let pb = ProgressBar::new_spinner();
+    let pb = Progress::new(None, ProgressUnit::Items, "Scanning titles");
     pb.set_style(ProgressStyle::default_spinner()
         .template("{spinner:.green} {msg}")
         .unwrap());
     
-    for line in reader.lines() {
+    for (line_number, line) in reader.lines().enumerate() {
         let line = line?;
         if line.is_empty() { continue; }
+        scan.lines += 1;
         
-        // Fast parse just for title to avoid full deserialization overhead if possible
-        // But we need to handle JSON correctly.
-        // Let's just use serde_json::from_str::<Article> for safety, or a minimal struct
-        #[derive(serde::Deserialize)]
-        struct TitleOnly {
-            title: String,
-        }
-        if let Ok(article) = serde_json::from_str::<TitleOnly>(&line) {
-            title_index.insert(article.title.to_lowercase().replace('_', " "));
+        // Only deserialize the title to avoid full parsing overhead
+        match serde_json::from_str::<TitleOnly>(&line) {
+            Ok(article) => {
+                if let Some(titles) = valid_titles.as_deref_mut() {
+                    titles.insert(&article.title);
+                }
+                scan.titles += 1;
+            }
+            Err(_) => scan.invalid_lines.push(line_number as u64 + 1),
         }
         
-        if title_index.len() % 1000 == 0 {
-            pb.set_message(format!("Found {} articles...", title_index.len()));
+        if scan.lines.is_multiple_of(1000) {
+            pb.set_message(format!("Found {} articles...", scan.titles));
+            pb.set_position(scan.lines);
         }
     }
-    pb.finish_with_message(format!("✅ Found {} valid titles", title_index.len()));
+    pb.finish_with_message(format!("✅ Found {} valid titles", scan.titles));
     
-    // Pass 2: Prune links
-    println!("   Rewriting articles with valid links only...");
-    let file = File::open(&articles_path)?;
-    let reader = BufReader::new(file);
-    let out_file = File::create(&temp_path)?;
-    let mut writer = BufWriter::new(out_file);
+    Ok(scan)
+}
+
+/// Count non-empty lines without parsing them
+fn count_lines(paths: &[PathBuf]) -> Result<usize> {
+    use std::io::BufRead;
+
+    let reader = jsonl::open_all(paths)?;
+    let mut count = 0;
+    for line in reader.split(b'\n') {
+        if !line?.is_empty() {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+fn import(file: &Path, data_dir: &Path, language: Option<&str>, no_index: bool, threads: usize, resume: bool) -> Result<()> {
+    use rustipedia::import::import_jsonl;
+
+    warn_unknown_language(language);
+    println!("📥 Importing {:?} into {:?}...", file, data_dir);
+    std::fs::create_dir_all(data_dir)?;
+    let report = {
+        let _lock = DataDirLock::exclusive(data_dir, "importing")?;
+        import_jsonl(file, data_dir, language)?
+    };
+    print_import_report(&report, "line");
+    finish_import(file, data_dir, &report, no_index, threads, resume)
+}
+
+fn import_zim(file: &Path, data_dir: &Path, language: Option<&str>, no_index: bool, threads: usize, resume: bool) -> Result<()> {
+    warn_unknown_language(language);
+    println!("📥 Importing ZIM file {:?} into {:?}...", file, data_dir);
+    std::fs::create_dir_all(data_dir)?;
+    let report = {
+        let _lock = DataDirLock::exclusive(data_dir, "importing")?;
+        rustipedia::zim::import_zim(file, data_dir, language)?
+    };
+    print_import_report(&report, "entry");
+    if report.redirects > 0 {
+        println!("↪️  Saved {} redirects", report.redirects);
+    }
+    finish_import(file, data_dir, &report, no_index, threads, resume)
+}
+
+fn warn_unknown_language(language: Option<&str>) {
+    if let Some(code) = language {
+        if WikiLanguage::from_code(code).is_none() {
+            println!("⚠️  Unknown language {:?}; search will use the default analyzer", code);
+        }
+    }
+}
+
+/// Print accepted and rejected counts; `record` names what a rejection's
+/// position refers to ("line" or "entry")
+fn print_import_report(report: &rustipedia::import::ImportReport, record: &str) {
+    println!("✅ Accepted {} articles", report.accepted);
+    if report.rejected > 0 {
+        println!("❌ Rejected {} records:", report.rejected);
+        for (reason, count) in &report.reasons {
+            println!("   {}: {}", reason, count);
+        }
+        for rejection in &report.rejections {
+            println!("   {} {}: {}", record, rejection.record, rejection.reason);
+        }
+        if report.rejected > report.rejections.len() as u64 {
+            println!("   ... and {} more", report.rejected - report.rejections.len() as u64);
+        }
+    }
+}
+
+/// Build or rebuild the search index after an import, unless `no_index`
+fn finish_import(file: &Path, data_dir: &Path, report: &rustipedia::import::ImportReport, no_index: bool, threads: usize, resume: bool) -> Result<()> {
+    if report.accepted == 0 {
+        anyhow::bail!("No valid articles in {:?}", file);
+    }
+
+    let has_index = data_dir.join("search_index").exists();
+    if no_index {
+        if has_index {
+            println!("⚠️  The existing search index is out of date. Rebuild it: rustipedia-download reindex {:?}", data_dir);
+        }
+        Ok(())
+    } else if has_index {
+        reindex(data_dir, threads)
+    } else {
+        build_index(data_dir, threads, resume)
+    }
+}
+
+fn diff(old_dir: &Path, new_dir: &Path, limit: usize, format: OutputFormat) -> Result<()> {
+    use rustipedia::diff::diff_snapshots;
+
+    let diff = diff_snapshots(&jsonl::articles_files(old_dir), &jsonl::articles_files(new_dir), limit)?;
+    if format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+
+    println!("📊 Changes from {:?} to {:?}", old_dir, new_dir);
+    println!("   Added:     {}", diff.added);
+    println!("   Removed:   {}", diff.removed);
+    println!("   Modified:  {}", diff.modified);
+    println!("   Unchanged: {}", diff.unchanged);
+
+    let sections = [
+        ("➕ Added", &diff.added_titles, diff.added),
+        ("➖ Removed", &diff.removed_titles, diff.removed),
+        ("✏️  Modified", &diff.modified_titles, diff.modified),
+    ];
+    for (heading, titles, total) in sections {
+        if titles.is_empty() {
+            continue;
+        }
+        println!("\n{}:", heading);
+        for title in titles {
+            println!("   {}", title);
+        }
+        if total > titles.len() as u64 {
+            println!("   ... and {} more", total - titles.len() as u64);
+        }
+    }
+
+    Ok(())
+}
+
+fn corpus_stats(data_dir: &Path, top: usize, format: OutputFormat) -> Result<()> {
+    let articles_files = jsonl::articles_files(data_dir);
+    if articles_files.is_empty() {
+        anyhow::bail!("Articles file not found: {:?}. Run download first.", jsonl::articles_path(data_dir));
+    }
+    if format == OutputFormat::Text {
+        println!("📊 Scanning {:?}...", data_dir);
+    }
+    let stats = rustipedia::corpus::corpus_stats(&articles_files, top)?;
+    if format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    println!("   Articles:        {}", stats.articles);
+    println!("   Tokens:          {}", stats.total_tokens);
+    println!("   Unique tokens:   {}{}", if stats.unique_tokens_approximate { "~" } else { "" }, stats.unique_tokens);
+    println!("   Average length:  {:.1} tokens", stats.average_tokens);
+    println!("   Categories:      {} ({} articles uncategorized)", stats.categories, stats.uncategorized_articles);
+
+    let sections = [("🔤 Top words", &stats.top_words), ("🏷️  Top categories", &stats.top_categories)];
+    for (heading, terms) in sections {
+        if terms.is_empty() {
+            continue;
+        }
+        println!("\n{}:", heading);
+        for term in terms {
+            println!("   {:>10}  {}", term.count, term.term);
+        }
+    }
+
+    Ok(())
+}
+
+fn export_graph(data_dir: &Path, out: Option<&Path>, format: GraphFormat, include_broken: bool) -> Result<()> {
+    let out = out.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(format!("links.{}", format.extension())));
+    println!("🕸️  Exporting the link graph of {:?} to {:?}...", data_dir, out);
+    let mut file = std::io::BufWriter::new(std::fs::File::create(&out)?);
+    let report = rustipedia::graph::export_graph(data_dir, &mut file, format, include_broken)?;
+
+    println!("✅ Exported the link graph");
+    println!("   Nodes:         {}", report.nodes);
+    println!("   Edges:         {}", report.edges);
+    println!("   Broken links:  {} ({})", report.broken_links, if include_broken { "included, flagged" } else { "skipped" });
+    Ok(())
+}
+
+fn validate(data_dir: &Path) -> Result<()> {
+    let articles_files = jsonl::articles_files(data_dir);
+    let index_path = data_dir.join("search_index");
+    let config_path = data_dir.join("config.json");
+    let mut problems = 0;
+
+    println!("🔍 Validating {:?}...", data_dir);
+
+    // Articles
+    let article_count = if !articles_files.is_empty() {
+        let scan = scan_titles(&articles_files, None)?;
+        if scan.invalid_lines.is_empty() {
+            println!("✅ articles.jsonl: {} articles", scan.lines);
+        } else {
+            problems += 1;
+            let shown: Vec<String> = scan.invalid_lines.iter().take(5).map(|n| n.to_string()).collect();
+            println!("❌ articles.jsonl: {} invalid line(s) (e.g. line {})", scan.invalid_lines.len(), shown.join(", "));
+            println!("   Fix: re-run extraction: rustipedia-download --skip-download --output {:?}", data_dir);
+        }
+        Some(scan.lines - scan.invalid_lines.len() as u64)
+    } else {
+        problems += 1;
+        println!("❌ articles.jsonl not found");
+        println!("   Fix: rustipedia-download --output {:?}", data_dir);
+        None
+    };
+
+    // Extraction completeness
+    match ExtractionState::of(data_dir) {
+        ExtractionState::Complete => println!("✅ extraction: complete"),
+        ExtractionState::Interrupted => {
+            problems += 1;
+            println!("❌ extraction: interrupted (articles.jsonl.tmp left behind); articles.jsonl is from an earlier run, if any");
+            println!("   Fix: re-run extraction: rustipedia-download --skip-download --output {:?}", data_dir);
+        }
+        ExtractionState::Unknown => println!("⚠️  extraction: no completion marker (extracted by an older version?)"),
+        ExtractionState::Missing => {}
+    }
+
+    // Search index
+    if index_path.exists() {
+        match SearchIndex::open(&index_path).and_then(|index| index.num_docs()) {
+            Ok(indexed) => match article_count {
+                Some(articles) if !index_count_matches(indexed, articles) => {
+                    problems += 1;
+                    println!("❌ search_index: {} documents, but articles.jsonl has {} articles", indexed, articles);
+                    println!("   Fix: rustipedia-download reindex {:?}", data_dir);
+                }
+                Some(articles) if articles != indexed => {
+                    println!("✅ search_index: {} documents (articles.jsonl has {})", indexed, articles);
+                }
+                _ => println!("✅ search_index: {} documents", indexed),
+            },
+            Err(e) => {
+                problems += 1;
+                println!("❌ search_index could not be opened: {:#}", e);
+                println!("   Fix: rustipedia-download reindex {:?}", data_dir);
+            }
+        }
+    } else {
+        println!("⚠️  search_index not found (search will be disabled)");
+        println!("   Fix: rustipedia-download index {:?}", data_dir);
+    }
+
+    // Config
+    if config_path.exists() {
+        let result = std::fs::read_to_string(&config_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| DataDirConfig::parse(&content));
+        match result {
+            Ok((_, issues)) if issues.is_empty() => println!("✅ config.json"),
+            Ok((_, issues)) => {
+                problems += 1;
+                println!("❌ config.json: {}", issues.join("; "));
+                println!("   Fix: correct or remove these keys in {:?}", config_path);
+            }
+            Err(e) => {
+                problems += 1;
+                println!("❌ config.json: {:#}", e);
+                println!("   Fix: correct the JSON syntax or delete {:?} to use defaults", config_path);
+            }
+        }
+    } else {
+        println!("⚠️  config.json not found (defaults will be used)");
+    }
+
+    if problems > 0 {
+        anyhow::bail!("Validation found {} problem(s)", problems);
+    }
+    println!("\n✅ Data directory is valid");
+    Ok(())
+}
+
+fn prune_articles(data_dir: &Path, low_memory: bool, temp_dir: Option<&Path>) -> Result<()> {
+    use rustipedia::prune::{prune_articles_files, prune_temp_dir, TitlesWithRedirects};
+    use rustipedia::RedirectMap;
+    use indicatif::ProgressStyle;
+
+    let articles_files = jsonl::articles_files(data_dir);
+    
+    if articles_files.is_empty() {
+        anyhow::bail!("Articles file not found: {:?}", jsonl::articles_path(data_dir));
+    }
+    let _lock = DataDirLock::exclusive(data_dir, "pruning")?;
+    let temp_dir = prune_temp_dir(data_dir, temp_dir, &articles_files)?;
+
+    println!("\n✂️  Pruning broken links...");
+    
+    // Pass 1: Collect titles
+    let mut title_index = if low_memory {
+        let expected = count_lines(&articles_files)?;
+        let titles = ValidTitles::low_memory(expected);
+        if let ValidTitles::Bloom(ref filter) = titles {
+            println!("   Low-memory mode: bloom filter of {} for {} titles (a few broken links may be kept)",
+                format_bytes(filter.size_bytes() as u64), expected);
+        }
+        titles
+    } else {
+        ValidTitles::exact()
+    };
+    println!("   Scanning articles to build title index...");
+    let scan = scan_titles(&articles_files, Some(&mut title_index))?;
+    // Links to redirects stay; the server follows them
+    let redirects = RedirectMap::load_from_dir(data_dir)?;
+    let title_index = TitlesWithRedirects { titles: &title_index, redirects: &redirects };
     
-    let pb = ProgressBar::new(title_index.len() as u64);
+    // Pass 2: Prune links, one file (shard) at a time
+    println!("   Rewriting articles with valid links only...");
+    let pb = Progress::new(Some(scan.lines), ProgressUnit::Items, "Pruning");
     pb.set_style(ProgressStyle::default_bar()
         .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
         .unwrap()
         .progress_chars("#>-"));
-        
-    for line in reader.lines() {
-        let line = line?;
-        if line.is_empty() { continue; }
-        
-        let mut article: Article = serde_json::from_str(&line)?;
-        
-        // If we have raw markup, we should re-process it.
-        // But currently Article struct stores `content` (HTML) and `raw_markup` (WikiText).
-        // If we only have HTML in `content`, we can't easily "un-link" without parsing HTML.
-        // However, `WikiParser::clean_wiki_markup` produced the HTML.
-        // If we saved `raw_markup`, we can re-generate `content`.
-        // If we didn't save `raw_markup`, we are in trouble unless we parse HTML.
-        
-        // The default `WikiParser` config has `keep_raw: false`.
-        // So `article.raw_markup` is likely None.
-        // This means we need to process the HTML in `article.content`.
-        // But `clean_wiki_markup` produced HTML like `<a href="/wiki/Target">Text</a>`.
-        // We can use Regex to replace these in the HTML!
-        
-        // Regex for HTML links: <a href="/wiki/([^"]+)">([^<]+)</a>
-        let link_re = regex::Regex::new(r#"<a href="/wiki/([^"]+)">([^<]+)</a>"#).unwrap();
-        
-        let new_content = link_re.replace_all(&article.content, |caps: &regex::Captures| {
-            let target = &caps[1];
-            let text = &caps[2];
-            let normalized = target.to_lowercase().replace('_', " ");
-            
-            if title_index.contains(&normalized) {
-                // Keep link
-                caps[0].to_string()
-            } else {
-                // Remove link, keep text
-                text.to_string()
-            }
-        }).to_string();
-        
-        article.content = new_content;
-        
-        serde_json::to_writer(&mut writer, &article)?;
-        writer.write_all(b"\n")?;
-        pb.inc(1);
-    }
+
+    let report = prune_articles_files(&temp_dir, &articles_files, &title_index, || pb.inc(1))?;
     pb.finish_with_message("✅ Pruning complete");
+    println!("   Removed {} broken link(s) from {} articles", report.links_removed, report.articles);
+    if report.dropped_lines > 0 {
+        println!("⚠️  Dropped {} malformed line(s) from the articles", report.dropped_lines);
+    }
     
-    // Replace original file
-    std::fs::rename(&temp_path, &articles_path)?;
-    
-    println!("✅ Replaced articles.jsonl with pruned version");
+    println!("✅ Replaced the articles with the pruned version");
     
     Ok(())
 }
 
+fn print_plan(plan: &DownloadPlan, cli: &Cli) -> Result<()> {
+    let available = plan.available_space
+        .map(format_bytes)
+        .unwrap_or_else(|| "unknown".to_string());
+    let dump_action = if plan.dump_exists && cli.skip_download {
+        "reuse existing dump"
+    } else {
+        "download"
+    };
+
+    println!("📋 Dry run - nothing will be downloaded or written\n");
+    println!("   Dump URL:        {}", plan.url);
+    println!("   Checksum URL:    {}", plan.checksum_url);
+    println!("   Dump file:       {:?} ({})", plan.dump_path, dump_action);
+    if !cli.download_only {
+        println!("   Articles:        {:?}", plan.data_path);
+        if cli.build_index {
+            println!("   Search index:    {:?}", plan.index_path);
+        }
+    }
+    println!("   Estimated size:  {}", format_bytes(plan.estimated_size));
+    println!("   Required space:  {}", format_bytes(plan.required_space));
+    println!("   Available space: {}", available);
+
+    if !plan.has_enough_space() {
+        anyhow::bail!("Insufficient disk space. Available: {}, Required: {}", available, format_bytes(plan.required_space));
+    }
+    println!("\n✅ Pre-flight checks passed");
+    Ok(())
+}
+
 fn print_banner(lang: &WikiLanguage) {
     println!();
     println!("╔══════════════════════════════════════════════════════════════════╗");
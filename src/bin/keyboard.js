@@ -0,0 +1,63 @@
+// Keyboard shortcuts: "/" focuses the header search box, and on result lists
+// marked with data-keyboard-nav the arrow keys move a highlight between
+// results, with Enter opening the highlighted one.
+(function () {
+    'use strict';
+
+    function isTyping(target) {
+        if (!target) return false;
+        const tag = target.tagName;
+        return tag === 'INPUT' || tag === 'TEXTAREA' || tag === 'SELECT' || target.isContentEditable;
+    }
+
+    let selected = -1;
+
+    function resultItems() {
+        const list = document.querySelector('[data-keyboard-nav]');
+        return list ? Array.from(list.querySelectorAll('li')) : [];
+    }
+
+    function select(items, index) {
+        if (selected >= 0 && items[selected]) {
+            items[selected].classList.remove('selected');
+        }
+        selected = index;
+        const item = items[selected];
+        item.classList.add('selected');
+        item.scrollIntoView({ block: 'nearest' });
+    }
+
+    document.addEventListener('keydown', function (event) {
+        if (event.ctrlKey || event.metaKey || event.altKey || isTyping(event.target)) {
+            return;
+        }
+
+        if (event.key === '/') {
+            const search = document.getElementById('header-search');
+            if (search) {
+                event.preventDefault();
+                search.focus();
+                search.select();
+            }
+            return;
+        }
+
+        const items = resultItems();
+        if (items.length === 0) return;
+
+        if (event.key === 'ArrowDown' || event.key === 'ArrowUp') {
+            event.preventDefault();
+            const step = event.key === 'ArrowDown' ? 1 : -1;
+            const next = selected < 0
+                ? (step > 0 ? 0 : items.length - 1)
+                : Math.min(Math.max(selected + step, 0), items.length - 1);
+            select(items, next);
+        } else if (event.key === 'Enter' && selected >= 0) {
+            const link = items[selected].querySelector('a');
+            if (link) {
+                event.preventDefault();
+                window.location.href = link.href;
+            }
+        }
+    });
+})();
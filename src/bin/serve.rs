@@ -15,31 +15,53 @@
 //! ```
 
 use std::collections::HashMap;
-use std::fs::{self, File, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
+use std::fs;
+#[cfg(windows)]
+use std::fs::OpenOptions;
+use std::io::BufRead;
+#[cfg(windows)]
+use std::io::Write;
 use std::path::PathBuf;
 use std::sync::Arc;
+#[cfg(windows)]
 use std::ffi::OsString;
 
 use anyhow::Result;
 use axum::{
-    extract::{Path, Query, State, Form, Json, Multipart},
+    extract::{ConnectInfo, DefaultBodyLimit, Path, Query, State, Form, Json, Multipart},
     http::StatusCode,
     response::{Html, IntoResponse, Response},
     routing::{get, post},
     Router,
-    http::{HeaderName, HeaderValue, header},
+    http::{HeaderMap, HeaderName, HeaderValue, header},
 };
 use clap::Parser;
 use tokio::sync::RwLock;
 use tracing_subscriber::EnvFilter;
 use tower_http::set_header::SetResponseHeaderLayer;
 use tower_http::timeout::TimeoutLayer;
-use tower_http::cors::{CorsLayer, Any};
+use tower::limit::GlobalConcurrencyLimitLayer;
 use tower_governor::{governor::GovernorConfigBuilder, GovernorLayer};
+use rand::seq::IndexedRandom;
 use rand::Rng;
 
-use rustipedia::{Article, SearchIndex, WikiLanguage, UpdateConfig, UpdateSchedule, Weekday, UpdateManager};
+use rustipedia::article::{normalize_title, truncate_at_word, ExtractionStats};
+use rustipedia::article_cache::{ArticleCache, DEFAULT_ARTICLE_CACHE_SIZE};
+use rustipedia::search::{category_facets, closest_title, similar_titles, sort_results, SearchResult, SearchSort, MAX_FACETS};
+use rustipedia::access_log;
+use rustipedia::disk_usage::{DiskUsageCache, DISK_USAGE_TTL};
+use rustipedia::downloader::format_bytes;
+use rustipedia::favicon;
+use rustipedia::feed::{self, FeedEntry, RecentArticles, FEED_SIZE};
+use rustipedia::metrics::{self, Metrics};
+use rustipedia::print;
+use rustipedia::prune::{self as link_prune, PruneState, PruneStatus};
+use rustipedia::jsonl::{self, LineParser};
+use rustipedia::lock::DataDirLock;
+use rustipedia::offsets::ArticleOffsets;
+use rustipedia::onboarding;
+use rustipedia::server::{self, page_size, page_slice, DEFAULT_BROWSE_PER_PAGE, DEFAULT_MAX_RENDER_CHARS, DEFAULT_MAX_SEARCH_RESULTS, DEFAULT_SEARCH_PER_PAGE};
+use rustipedia::{parse_pause_until, Article, DataDirConfig, NumberFormat, ExtractionState, RedirectMap, SearchIndex, WikiLanguage, UpdateConfig, UpdateSchedule, Weekday, UpdateManager};
 
 // Windows service support
 #[cfg(windows)]
@@ -59,6 +81,18 @@ use windows_service::{
 
 const DEFAULT_LOGO: &[u8] = include_bytes!("Logo.png");
 
+/// `DEFAULT_LOGO` scaled to 32x32
+const DEFAULT_FAVICON: &[u8] = include_bytes!("favicon.png");
+
+/// Keyboard shortcuts ("/" to search, arrow keys in result lists)
+const KEYBOARD_JS: &str = include_str!("keyboard.js");
+
+/// "Copy as text" button on article pages
+const ARTICLE_JS: &str = include_str!("article.js");
+
+/// Update status, history and controls on the settings page
+const SETTINGS_JS: &str = include_str!("settings.js");
+
 // Global shutdown flag for Windows service
 #[cfg(windows)]
 static SHUTDOWN_FLAG: Mutex<bool> = Mutex::new(false);
@@ -91,11 +125,14 @@ EXAMPLES:
 
   Bind to all interfaces (for network access):
     rustipedia-serve --host 0.0.0.0
+
+  Serve several languages (at /en/... and /simple/...):
+    rustipedia-serve --data ./wiki-en --data ./wiki-simple
 "#)]
 struct Cli {
-    /// Directory containing Wikipedia data
+    /// Directory containing Wikipedia data; repeat to serve several languages
     #[arg(short, long, default_value = "wikipedia")]
-    data: PathBuf,
+    data: Vec<PathBuf>,
 
     /// Port to listen on
     #[arg(short, long)]
@@ -108,14 +145,82 @@ struct Cli {
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
+
+    /// Refuse to start if config.json has unknown keys or invalid values
+    #[arg(long)]
+    strict_config: bool,
+
+    /// Refuse to start if articles.jsonl has malformed lines instead of skipping them
+    #[arg(long)]
+    strict: bool,
+
+    /// Tokio worker threads [default: one per CPU core]
+    #[arg(long)]
+    worker_threads: Option<usize>,
+
+    /// Maximum requests handled at once; further requests wait [default: unlimited]
+    #[arg(long)]
+    max_connections: Option<usize>,
+
+    /// TCP listen backlog [default: 1024]
+    #[arg(long)]
+    listen_backlog: Option<u32>,
+
+    /// Articles kept in the in-memory cache, 0 to disable [default: 1000]
+    #[arg(long)]
+    article_cache_size: Option<usize>,
+
+    /// Characters of article HTML shown before a "Show full article" button, 0 to disable [default: 200000]
+    #[arg(long)]
+    max_render_chars: Option<usize>,
+
+    /// Results fetched from the index per search [default: 100]
+    #[arg(long)]
+    max_search_results: Option<usize>,
+
+    /// Origin allowed to call the API from a browser, e.g. https://wiki.example.org;
+    /// repeat for several [default: any origin]
+    #[arg(long = "cors-origin")]
+    cors_origins: Vec<String>,
+
+    /// Bearer token required by admin endpoints such as /api/v1/reload [default: none, local clients only]
+    #[arg(long)]
+    admin_token: Option<String>,
+
+    /// Largest request body accepted, in bytes; logo uploads allow up to 5 MB regardless [default: 4194304]
+    #[arg(long)]
+    max_body_bytes: Option<usize>,
+
+    /// Offer a link to the article on the live Wikipedia when it isn't in the
+    /// data directory [default: off]
+    #[arg(long)]
+    fallback_to_wikipedia: bool,
+
+    /// Render links to missing articles like working ones instead of in red
+    #[arg(long)]
+    no_broken_link_marks: bool,
+}
+
+/// Command-line settings applied whenever a data directory is (re)loaded
+#[derive(Clone)]
+struct LoadOptions {
+    strict_config: bool,
+    article_cache_size: Option<usize>,
+    max_render_chars: Option<usize>,
+    max_search_results: Option<usize>,
+    admin_token: Option<String>,
+    fallback_to_wikipedia: bool,
+    no_broken_link_marks: bool,
 }
 
 /// Application state shared across handlers
 struct AppState {
-    /// Articles indexed by ID (fallback if search index is missing)
-    articles: HashMap<u64, Article>,
-    /// Articles indexed by title (lowercase)
+    /// Byte offsets of articles in articles.jsonl (fallback if search index is missing)
+    offsets: Option<ArticleOffsets>,
+    /// Articles indexed by title (see `normalize_title`)
     by_title: HashMap<String, u64>,
+    /// Redirect aliases (alias -> target title)
+    redirects: RedirectMap,
     /// Search index (optional)
     search_index: Option<SearchIndex>,
     /// List of all titles for browsing
@@ -124,22 +229,76 @@ struct AppState {
     language: String,
     /// Total article count
     article_count: usize,
+    /// Stats from the last extraction (stats.json), if available
+    extraction_stats: Option<ExtractionStats>,
     /// Data directory
     data_dir: PathBuf,
     /// Auto-update configuration
     update_config: UpdateConfig,
-    /// Configured port (from config.json)
-    config_port: Option<u16>,
-    /// Configured host (from config.json)
-    config_host: Option<String>,
+    /// Recently viewed articles fetched from the search index
+    article_cache: ArticleCache,
+    /// Space used by the data directory, for the settings page
+    disk_usage: DiskUsageCache,
+    /// Most recently extracted articles, newest first (for /feed.xml)
+    recent_articles: Vec<FeedEntry>,
+    /// Default articles per page on /browse
+    browse_per_page: usize,
+    /// Default results per page on /search
+    search_per_page: usize,
+    /// Characters of article HTML rendered up front (0 renders everything)
+    max_render_chars: usize,
+    /// Cap on results fetched per search
+    max_search_results: usize,
+    /// URL prefix of this language's routes, e.g. `/simple`; empty when
+    /// serving a single data directory
+    base_path: String,
+    /// Language codes served by this instance, for the header switcher
+    /// (empty when serving a single data directory)
+    languages: Vec<String>,
+    /// Token required by admin endpoints (`None` allows local clients only)
+    admin_token: Option<String>,
+    /// Link missing articles to the live Wikipedia of `language`
+    fallback_to_wikipedia: bool,
+    /// Give links to missing articles the broken-link class
+    mark_broken_links: bool,
+    /// Progress of a prune started with /api/v1/prune, kept across reloads
+    prune_status: Arc<std::sync::Mutex<PruneStatus>>,
+    /// Settings to load the data directory with again on reload
+    options: LoadOptions,
 }
 
 impl AppState {
-    fn load(data_dir: &PathBuf) -> Result<Self> {
-        let articles_path = data_dir.join("articles.jsonl");
-        if !articles_path.exists() {
-            anyhow::bail!("Articles file not found: {:?}. Run rustipedia-download first.", articles_path);
+    fn load(data_dir: &PathBuf, options: &LoadOptions) -> Result<Self> {
+        let strict_config = options.strict_config;
+        let articles_files = jsonl::articles_files(data_dir);
+        let interrupted = ExtractionState::of(data_dir) == ExtractionState::Interrupted;
+        if articles_files.is_empty() {
+            if interrupted {
+                anyhow::bail!("Extraction into {:?} did not finish (only articles.jsonl.tmp exists). Re-run rustipedia-download.", data_dir);
+            }
+            anyhow::bail!("Articles file not found: {:?}. Run rustipedia-download first.", jsonl::articles_path(data_dir));
         }
+        if interrupted {
+            tracing::warn!("An extraction into {:?} was interrupted or is still running; serving the previous articles.jsonl", data_dir);
+        }
+
+        // Load language and server settings from config
+        let config = DataDirConfig::load(&data_dir.join("config.json"), strict_config)?;
+        let language = config.language.unwrap_or_else(|| "unknown".to_string());
+        let article_cache = ArticleCache::new(options.article_cache_size.or(config.article_cache_size).unwrap_or(DEFAULT_ARTICLE_CACHE_SIZE));
+        let browse_per_page = page_size(config.browse_per_page, DEFAULT_BROWSE_PER_PAGE);
+        let search_per_page = page_size(config.search_per_page, DEFAULT_SEARCH_PER_PAGE);
+        let max_render_chars = options.max_render_chars.or(config.max_render_chars).unwrap_or(DEFAULT_MAX_RENDER_CHARS);
+        let max_search_results = options.max_search_results.or(config.max_search_results).unwrap_or(DEFAULT_MAX_SEARCH_RESULTS);
+        let admin_token = options.admin_token.clone().or(config.admin_token);
+        let fallback_to_wikipedia = options.fallback_to_wikipedia || config.fallback_to_wikipedia.unwrap_or(false);
+        let mark_broken_links = !options.no_broken_link_marks && config.mark_broken_links.unwrap_or(true);
+
+        // Offsets only work within a single uncompressed file
+        let articles_path = match articles_files.as_slice() {
+            [path] if !jsonl::is_compressed(path) => Some(path.clone()),
+            _ => None,
+        };
 
         // Try to load search index
         let index_path = data_dir.join("search_index");
@@ -155,116 +314,257 @@ impl AppState {
                     None
                 }
             }
+        } else if articles_path.is_none() {
+            tracing::warn!("No search index found, and the articles are compressed or sharded so they can only be served from one. Building it now; this can take a while.");
+            Some(build_search_index(data_dir, &index_path, &articles_files, &language)?)
         } else {
             tracing::warn!("No search index found. Search disabled. Run: rustipedia-download index {:?}", data_dir);
             None
         };
+        if search_index.is_none() && articles_path.is_none() {
+            anyhow::bail!(
+                "The articles in {:?} are compressed or sharded, so they can only be served from the search index, which failed to load. Run: rustipedia-download reindex {:?}",
+                data_dir, data_dir
+            );
+        }
 
-        tracing::info!("Loading articles from {:?}...", articles_path);
+        tracing::info!("Loading articles from {:?}...", data_dir);
         
-        let file = File::open(&articles_path)?;
-        let reader = BufReader::new(file);
-        
-        let mut articles = HashMap::new();
         let mut by_title = HashMap::new();
         let mut all_titles = Vec::new();
+        let mut recent_articles = RecentArticles::new(FEED_SIZE);
         
-        for line in reader.lines() {
-            let line = line?;
-            if line.is_empty() {
-                continue;
-            }
-            
-            // Optimization: If we have a search index, we don't need to load the full article content into RAM.
-            // We just need the ID and Title for routing/listing.
-            // However, we currently parse the whole line anyway. 
-            // To truly optimize, we would need a lighter parsing or separate index file.
-            // For now, we just avoid storing the heavy content in the HashMap.
-            
-            let article: Article = serde_json::from_str(&line)?;
-            let id = article.id;
-            let title = article.title.clone();
-            
-            by_title.insert(title.to_lowercase(), id);
-            all_titles.push((id, title));
-            
-            if search_index.is_none() {
-                articles.insert(id, article);
+        // Only ID and title are kept in memory; content comes from the search
+        // index, or is read from articles.jsonl by offset
+        #[derive(serde::Deserialize)]
+        struct TitleEntry {
+            id: u64,
+            title: String,
+            #[serde(default)]
+            extracted_at: Option<chrono::DateTime<chrono::Utc>>,
+        }
+
+        for path in &articles_files {
+            let mut parser = LineParser::new(path);
+            for (line_number, line) in jsonl::open(path)?.lines().enumerate() {
+                let line = line?;
+                if line.is_empty() {
+                    continue;
+                }
+
+                let Some(entry) = parser.parse::<TitleEntry>(&line, line_number as u64 + 1)? else {
+                    continue;
+                };
+                if let Some(extracted_at) = entry.extracted_at {
+                    recent_articles.push(entry.id, &entry.title, extracted_at);
+                }
+                by_title.insert(normalize_title(&entry.title), entry.id);
+                all_titles.push((entry.id, entry.title));
             }
+            parser.warn_if_skipped();
         }
         
         all_titles.sort_by(|a, b| a.1.cmp(&b.1));
         let article_count = all_titles.len();
-        
-        tracing::info!("Loaded {} articles (Content loaded: {})", article_count, search_index.is_none());
 
-        // Try to load config for language info
-        let config_path = data_dir.join("config.json");
-        let language = if config_path.exists() {
-            let content = fs::read_to_string(&config_path).unwrap_or_default();
-            serde_json::from_str::<serde_json::Value>(&content)
-                .ok()
-                .and_then(|v| v["language"].as_str().map(String::from))
-                .unwrap_or_else(|| "unknown".to_string())
-        } else {
-            "unknown".to_string()
+        let offsets = match articles_path {
+            Some(path) if search_index.is_none() => Some(ArticleOffsets::build(path)?),
+            _ => None,
         };
-
-        // Try to load port/host from config
-        let (config_port, config_host) = if config_path.exists() {
-            let content = fs::read_to_string(&config_path).unwrap_or_default();
-            let v: serde_json::Value = serde_json::from_str(&content).unwrap_or(serde_json::json!({}));
-            (
-                v["port"].as_u64().map(|p| p as u16),
-                v["host"].as_str().map(String::from)
-            )
+        
+        tracing::info!("Loaded {} articles (reading content from {})", article_count,
+            if search_index.is_some() { "search index" } else { "articles.jsonl" });
+
+        // Load redirect aliases
+        let redirects_path = data_dir.join("redirects.json");
+        let redirects = if redirects_path.exists() {
+            match RedirectMap::load(&redirects_path) {
+                Ok(redirects) => {
+                    tracing::info!("Loaded {} redirects", redirects.len());
+                    redirects
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to load redirects: {}. Aliases disabled.", e);
+                    RedirectMap::new()
+                }
+            }
         } else {
-            (None, None)
+            RedirectMap::new()
         };
 
+        // Load extraction stats to detect sample datasets
+        let extraction_stats = fs::read_to_string(data_dir.join("stats.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<ExtractionStats>(&content).ok());
+
         // Load update config
         let update_config = UpdateConfig::load(UpdateConfig::config_path(data_dir)).unwrap_or_default();
 
         Ok(Self {
-            articles,
+            offsets,
             by_title,
+            redirects,
             search_index,
             all_titles,
             language,
             article_count,
+            extraction_stats,
             data_dir: data_dir.clone(),
             update_config,
-            config_port,
-            config_host,
+            article_cache,
+            disk_usage: DiskUsageCache::new(DISK_USAGE_TTL),
+            recent_articles: recent_articles.into_entries(),
+            browse_per_page,
+            search_per_page,
+            max_render_chars,
+            max_search_results,
+            base_path: String::new(),
+            languages: Vec::new(),
+            admin_token,
+            fallback_to_wikipedia,
+            mark_broken_links,
+            prune_status: Arc::default(),
+            options: options.clone(),
         })
     }
 
+    /// Load this state's data directory again, keeping its place in the router
+    fn reload(&self) -> impl FnOnce() -> Result<Self> + Send + 'static {
+        let data_dir = self.data_dir.clone();
+        let options = self.options.clone();
+        let base_path = self.base_path.clone();
+        let languages = self.languages.clone();
+        let prune_status = self.prune_status.clone();
+        move || {
+            let mut state = Self::load(&data_dir, &options)?;
+            state.base_path = base_path;
+            state.languages = languages;
+            state.prune_status = prune_status;
+            Ok(state)
+        }
+    }
+
+    /// Link to `path` (e.g. `/browse`) within this language
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_path, path)
+    }
+
+    /// Absolute URL of this language's root, using the host the client
+    /// connected to (for feeds and printed links)
+    fn absolute_url(&self, headers: &HeaderMap) -> String {
+        let host = headers.get(header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .unwrap_or("localhost");
+        format!("http://{}{}", host, self.base_path)
+    }
+
+    /// Link to this language's home page
+    fn home_url(&self) -> String {
+        if self.base_path.is_empty() { "/".to_string() } else { self.base_path.clone() }
+    }
+
     /// Get an article by ID from either the search index or in-memory storage
     fn get_article_by_id(&self, id: u64) -> Option<Article> {
         if let Some(ref index) = self.search_index {
-            index.get_article(id).ok().flatten()
+            self.article_cache.get_or_load(id, || index.get_article(id).ok().flatten())
         } else {
-            self.articles.get(&id).cloned()
+            let offsets = self.offsets.as_ref()?;
+            offsets.get(id).unwrap_or_else(|e| {
+                tracing::warn!("Failed to read article {}: {:#}", id, e);
+                None
+            })
         }
     }
 
-    /// Get an article by title
+    /// Get an article by title, following redirects if needed
     fn get_article_by_title(&self, title: &str) -> Option<Article> {
-        let title_lower = title.to_lowercase().replace('_', " ");
-        let id = self.by_title.get(&title_lower)?;
-        self.get_article_by_id(*id)
+        let id = match self.by_title.get(&normalize_title(title)) {
+            Some(id) => *id,
+            None => {
+                let target = self.redirect_target(title)?;
+                let page = target.split('#').next().unwrap_or(&target);
+                *self.by_title.get(&normalize_title(page))?
+            }
+        };
+        self.get_article_by_id(id)
+    }
+
+    /// Resolve a redirect alias to the title of an existing article.
+    ///
+    /// Returns `None` if `title` is itself an article, isn't a redirect, or
+    /// the redirect chain ends at a page that wasn't extracted.
+    fn redirect_target(&self, title: &str) -> Option<String> {
+        if self.by_title.contains_key(&normalize_title(title)) {
+            return None;
+        }
+        let target = self.redirects.resolve(title)?;
+        let page = target.split('#').next().unwrap_or(&target);
+        if self.by_title.contains_key(&normalize_title(page)) {
+            Some(target)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a link to `raw_target` leads to an article, directly or
+    /// through redirects (see `prune::title_exists`), without loading it
+    fn title_exists(&self, raw_target: &str) -> bool {
+        link_prune::title_exists(raw_target, &self.by_title, &self.redirects)
+    }
+
+    /// Point the links of rendered article HTML at this language's routes,
+    /// marking links to missing articles unless that's turned off
+    fn article_links_html(&self, html: &str) -> String {
+        if !self.mark_broken_links {
+            return server::prefix_wiki_links(html, &self.base_path);
+        }
+        let marked = server::mark_broken_links(html, |title| self.title_exists(title));
+        server::prefix_wiki_links(&marked, &self.base_path)
+    }
+
+    /// Suggest the closest article title for a query that found nothing
+    fn suggest_title(&self, query: &str) -> Option<(u64, String)> {
+        if let Some(ref index) = self.search_index {
+            index.suggest(query).ok().flatten().map(|r| (r.id, r.title))
+        } else {
+            let title = closest_title(query, self.all_titles.iter().map(|(_, t)| t.as_str()))?;
+            let id = *self.by_title.get(&normalize_title(title))?;
+            Some((id, title.to_string()))
+        }
+    }
+
+    /// Titles similar to a missing title, for "did you mean" links
+    fn similar_titles(&self, title: &str, limit: usize) -> Vec<String> {
+        let query = title.replace('_', " ");
+        if let Some(ref index) = self.search_index {
+            index.similar_titles(&query, limit)
+                .map(|results| results.into_iter().map(|r| r.title).collect())
+                .unwrap_or_default()
+        } else {
+            similar_titles(&query, self.all_titles.iter().map(|(_, t)| t.as_str()), limit)
+                .into_iter()
+                .map(|t| t.to_string())
+                .collect()
+        }
     }
 
-    /// Get article preview by ID
+    /// Number formatting conventions of the wiki's language
+    fn number_format(&self) -> NumberFormat {
+        NumberFormat::for_code(&self.language)
+    }
+
+    /// Get article preview by ID, cut to `length` characters at a word boundary.
+    ///
+    /// With a search index, previews are limited to `MAX_PREVIEW_LENGTH` characters.
     fn get_article_preview(&self, id: u64, length: usize) -> String {
         if let Some(ref index) = self.search_index {
             index.get_by_id(id)
                 .ok()
                 .flatten()
-                .map(|r| r.preview)
+                .map(|r| truncate_at_word(&r.preview, length).to_string())
                 .unwrap_or_default()
         } else {
-            self.articles.get(&id)
+            self.get_article_by_id(id)
                 .map(|a| a.preview(length).to_string())
                 .unwrap_or_default()
         }
@@ -274,6 +574,47 @@ impl AppState {
 
 type SharedState = Arc<RwLock<AppState>>;
 
+/// Routes served for each language, at the root or under `/{code}`
+fn language_routes() -> Router<SharedState> {
+    Router::new()
+        .route("/", get(home))
+        .route("/article/:id", get(article_by_id))
+        .route("/article/:id/text", get(article_text))
+        .route("/article/:id/print", get(article_print))
+        .route("/wiki/:title", get(article_by_title))
+        .route("/search", get(search))
+        .route("/browse", get(browse))
+        .route("/random", get(random_article))
+        .route("/feed.xml", get(atom_feed))
+        .route("/settings", get(settings_page).post(update_settings))
+        .route("/api/version", get(api_version))
+        .merge(server::versioned_api(api_routes()))
+        .route("/logo", get(logo_handler))
+        .route("/settings/logo/reset", post(reset_logo))
+        .route("/favicon.ico", get(favicon_handler))
+        // Raised above the server-wide body limit for the largest accepted logo
+        .route("/settings/logo", post(upload_logo).layer(DefaultBodyLimit::max(favicon::MAX_LOGO_UPLOAD_BYTES)))
+}
+
+/// JSON API routes, served under `/api/v1` and (deprecated) `/api`
+fn api_routes() -> Router<SharedState> {
+    Router::new()
+        .route("/articles", get(api_articles))
+        .route("/articles/batch", post(api_articles_batch))
+        .route("/search", get(api_search))
+        .route("/article/:id", get(api_article))
+        .route("/article/:id/rest", get(api_article_rest))
+        .route("/update/status", get(api_update_status))
+        .route("/update/trigger", post(api_trigger_update))
+        .route("/update/cancel", post(api_cancel_update))
+        .route("/update/pause", post(api_pause_updates))
+        .route("/update/resume", post(api_resume_updates))
+        .route("/update/history", get(api_update_history))
+        .route("/reload", post(api_reload))
+        .route("/prune", post(api_prune))
+        .route("/prune/status", get(api_prune_status))
+}
+
 // Main entry point - detects if running as service or CLI
 fn main() -> Result<()> {
     #[cfg(windows)]
@@ -293,9 +634,19 @@ fn main() -> Result<()> {
     }
 }
 
+/// Worker thread count from the CLI, falling back to config.json
+fn configured_worker_threads() -> Result<Option<usize>> {
+    let cli = Cli::parse();
+    if cli.worker_threads.is_some() {
+        return Ok(cli.worker_threads);
+    }
+    let config = DataDirConfig::load(&cli.data[0].join("config.json"), cli.strict_config)?;
+    Ok(config.worker_threads)
+}
+
 // CLI mode entry point
 fn run_cli_mode() -> Result<()> {
-    let rt = tokio::runtime::Runtime::new()?;
+    let rt = server::build_runtime(configured_worker_threads()?)?;
     rt.block_on(async {
         run_server(None).await
     })
@@ -355,8 +706,9 @@ fn run_service(_arguments: Vec<OsString>) -> Result<()> {
     })?;
     
     // Start the server in a separate thread
-    let server_handle = std::thread::spawn(|| {
-        let rt = tokio::runtime::Runtime::new().unwrap();
+    let worker_threads = configured_worker_threads()?;
+    let server_handle = std::thread::spawn(move || {
+        let rt = server::build_runtime(worker_threads).unwrap();
         rt.block_on(async {
             run_server(Some("Service mode")).await
         })
@@ -407,6 +759,35 @@ fn run_service(_arguments: Vec<OsString>) -> Result<()> {
     Ok(())
 }
 
+/// Build the router for loaded data directories: one is served at the root,
+/// several under /{language}, with / going to the first. `missing_languages`
+/// are the prefixes kept for directories without articles yet; with any, even
+/// a single loaded directory is served under its prefix.
+fn data_routes(mut states: Vec<AppState>, missing_languages: &[String]) -> Result<Router> {
+    if states.len() == 1 && missing_languages.is_empty() {
+        let state = states.pop().unwrap();
+        return Ok(language_routes().with_state(Arc::new(RwLock::new(state))));
+    }
+
+    let languages: Vec<String> = states.iter().map(|state| state.language.clone()).collect();
+    server::check_language_codes(&languages.iter().chain(missing_languages).map(String::as_str).collect::<Vec<_>>())?;
+    let home = format!("/{}", languages[0]);
+    let favicon = format!("{}/favicon.ico", home);
+    let mut app = Router::new()
+        .route("/", get(move || async move { axum::response::Redirect::to(&home) }))
+        .route("/favicon.ico", get(move || async move { axum::response::Redirect::permanent(&favicon) }));
+    for mut state in states {
+        state.base_path = format!("/{}", state.language);
+        state.languages = languages.clone();
+        let base_path = state.base_path.clone();
+        let home = base_path.clone();
+        app = app
+            .route(&format!("{}/", base_path), get(move || async move { axum::response::Redirect::to(&home) }))
+            .nest(&base_path, language_routes().with_state(Arc::new(RwLock::new(state))));
+    }
+    Ok(app)
+}
+
 // Server logic - can be called from either service or CLI mode
 async fn run_server(mode: Option<&str>) -> Result<()> {
     let cli = Cli::parse();
@@ -440,7 +821,7 @@ async fn run_server(mode: Option<&str>) -> Result<()> {
                     .unwrap()
             });
         
-        let filter = EnvFilter::new("rustipedia_serve=info,warn");
+        let filter = EnvFilter::new("rustipedia_serve=info,rustipedia::access_log=info,warn");
         tracing_subscriber::fmt()
             .with_env_filter(filter)
             .with_target(false)
@@ -450,10 +831,11 @@ async fn run_server(mode: Option<&str>) -> Result<()> {
         tracing::info!("Starting in {}", mode.unwrap_or("unknown mode"));
     } else {
         // CLI mode - log to stdout
+        // Requests are logged at info; asset and /metrics requests only with --verbose
         let filter = if cli.verbose {
-            EnvFilter::new("rustipedia_serve=debug,tower_http=debug,info")
+            EnvFilter::new("rustipedia_serve=debug,rustipedia::access_log=debug,tower_http=debug,info")
         } else {
-            EnvFilter::new("rustipedia_serve=info,warn")
+            EnvFilter::new("rustipedia_serve=info,rustipedia::access_log=info,warn")
         };
         
         tracing_subscriber::fmt()
@@ -463,28 +845,55 @@ async fn run_server(mode: Option<&str>) -> Result<()> {
     }
 
     // Load data
-    let state = AppState::load(&cli.data)?;
-    let config_port = state.config_port;
-    let config_host = state.config_host.clone();
-    let shared_state: SharedState = Arc::new(RwLock::new(state));
+    jsonl::set_strict(cli.strict);
+    // Held while serving so updates can tell the directories are in use
+    let _reader_locks: Vec<DataDirLock> = cli.data.iter()
+        .filter(|data_dir| data_dir.exists())
+        .filter_map(|data_dir| DataDirLock::shared(data_dir)
+            .map_err(|e| tracing::warn!("Could not take a reader lock on {:?}: {:#}", data_dir, e))
+            .ok())
+        .collect();
+    // Without data yet, explain how to download it instead of exiting
+    for data_dir in onboarding::missing_data_dirs(&cli.data) {
+        tracing::warn!(
+            "No articles in {:?}. Showing download instructions; run rustipedia-download and restart to serve them.",
+            data_dir
+        );
+    }
+    let app = onboarding::app(&cli.data, |data_dirs, missing_languages| {
+        let options = LoadOptions {
+            strict_config: cli.strict_config,
+            article_cache_size: cli.article_cache_size,
+            max_render_chars: cli.max_render_chars,
+            max_search_results: cli.max_search_results,
+            admin_token: cli.admin_token.clone(),
+            fallback_to_wikipedia: cli.fallback_to_wikipedia,
+            no_broken_link_marks: cli.no_broken_link_marks,
+        };
+        let states = data_dirs.iter()
+            .map(|data_dir| AppState::load(data_dir, &options))
+            .collect::<Result<Vec<_>>>()?;
+        data_routes(states, missing_languages)
+    })?;
 
-    // Build router
-    let app = Router::new()
-        .route("/", get(home))
-        .route("/article/:id", get(article_by_id))
-        .route("/wiki/:title", get(article_by_title))
-        .route("/search", get(search))
-        .route("/browse", get(browse))
-        .route("/random", get(random_article))
-        .route("/api/articles", get(api_articles))
-        .route("/api/search", get(api_search))
-        .route("/settings", get(settings_page).post(update_settings))
-        .route("/api/update/status", get(api_update_status))
-        .route("/api/update/trigger", post(api_trigger_update))
-        .route("/api/update/history", get(api_update_history))
-        .route("/logo", get(logo_handler))
-        .route("/settings/logo", post(upload_logo))
-        .with_state(shared_state);
+    // Server settings come from the first data directory
+    let server_config = DataDirConfig::load(&cli.data[0].join("config.json"), cli.strict_config)?;
+    let max_connections = cli.max_connections.or(server_config.max_connections);
+    let listen_backlog = cli.listen_backlog.or(server_config.listen_backlog);
+    let max_body_bytes = cli.max_body_bytes.or(server_config.max_body_bytes).unwrap_or(server::DEFAULT_MAX_BODY_BYTES);
+    let cors_origins = if cli.cors_origins.is_empty() {
+        server_config.cors_origins.unwrap_or_default()
+    } else {
+        cli.cors_origins.clone()
+    };
+    let cors = server::cors_layer(&cors_origins)?;
+
+    let app = app
+        .route("/static/keyboard.js", get(keyboard_js))
+        .route("/static/article.js", get(article_js))
+        .route("/static/settings.js", get(settings_js))
+        // Routes that need more (logo uploads) raise it for themselves
+        .layer(DefaultBodyLimit::max(max_body_bytes));
 
     // Rate Limiting Configuration
     let governor_conf = Arc::new(
@@ -519,16 +928,25 @@ async fn run_server(mode: Option<&str>) -> Result<()> {
             HeaderValue::from_static("1; mode=block"),
         ))
         .layer(TimeoutLayer::new(std::time::Duration::from_secs(30)))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any) // For a local tool, Any is acceptable, but in prod we'd restrict.
-                .allow_methods(Any)
-                .allow_headers(Any)
-        );
+        .layer(cors);
+
+    // Shared limit across all routes; excess requests queue rather than fail
+    let app = match max_connections {
+        Some(limit) => app.layer(GlobalConcurrencyLimitLayer::new(limit)),
+        None => app,
+    };
+
+    // Metrics wrap everything above (so rate-limited requests are counted);
+    // /metrics is merged last so it isn't rate limited itself
+    let metrics = Arc::new(Metrics::new());
+    let app = app
+        .layer(axum::middleware::from_fn_with_state(metrics.clone(), metrics::track_requests))
+        .merge(Router::new().route("/metrics", get(metrics::metrics_handler)).with_state(metrics))
+        .layer(axum::middleware::from_fn(access_log::log_requests));
 
     // Determine port and host: CLI > Config > Default
-    let port = cli.port.or(config_port).unwrap_or(8080);
-    let host = cli.host.or(config_host).unwrap_or_else(|| "127.0.0.1".to_string());
+    let port = cli.port.or(server_config.port).unwrap_or(8080);
+    let host = cli.host.or(server_config.host).unwrap_or_else(|| "127.0.0.1".to_string());
     let addr = format!("{}:{}", host, port);
     
     if !is_service {
@@ -537,16 +955,18 @@ async fn run_server(mode: Option<&str>) -> Result<()> {
         println!("║                     RUSTIPEDIA                                    ║");
         println!("╠══════════════════════════════════════════════════════════════════╣");
         println!("║  Server running at: http://{}                          ", addr);
-        println!("║  Data directory:    {:?}                                ", cli.data);
+        for data_dir in &cli.data {
+            println!("║  Data directory:    {:?}                                ", data_dir);
+        }
         println!("╚══════════════════════════════════════════════════════════════════╝");
         println!();
         println!("Press Ctrl+C to stop the server");
     } else {
         tracing::info!("Server starting at http://{}", addr);
-        tracing::info!("Data directory: {:?}", cli.data);
+        tracing::info!("Data directories: {:?}", cli.data);
     }
 
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    let listener = server::bind_listener(&addr, listen_backlog).await?;
     
     // Run server with graceful shutdown for service mode
     #[cfg(windows)]
@@ -587,9 +1007,13 @@ fn base_html(title: &str, content: &str, state: &AppState) -> String {
 <head>
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{} - Rustipedia</title>
+    <title>{title} - Rustipedia</title>
+    <link rel="icon" href="{favicon_url}" sizes="any">
+    <link rel="apple-touch-icon" href="{logo_url}">
     <link rel="preconnect" href="https://fonts.googleapis.com">
     <link rel="preconnect" href="https://fonts.gstatic.com" crossorigin>
+    <link rel="alternate" type="application/atom+xml" title="Recently added articles" href="{feed_url}">
+    <script src="/static/keyboard.js" defer></script>
     <link href="https://fonts.googleapis.com/css2?family=Outfit:wght@300;400;500;600;700&family=Crimson+Pro:ital,wght@0,400;0,600;1,400&display=swap" rel="stylesheet">
     <style>
         :root {{
@@ -605,8 +1029,16 @@ fn base_html(title: &str, content: &str, state: &AppState) -> String {
             --shadow: 0 4px 6px -1px rgb(0 0 0 / 0.1), 0 2px 4px -2px rgb(0 0 0 / 0.1);
             --shadow-lg: 0 10px 15px -3px rgb(0 0 0 / 0.1), 0 4px 6px -4px rgb(0 0 0 / 0.1);
             --radius: 12px;
+            --reader-width: 70ch;
+            --reader-font-size: 1.25rem;
         }}
 
+        /* Reader preferences, set on <html> from localStorage by article pages */
+        html.reader-width-narrow {{ --reader-width: 56ch; }}
+        html.reader-width-wide {{ --reader-width: 90ch; }}
+        html.reader-font-small {{ --reader-font-size: 1.1rem; }}
+        html.reader-font-large {{ --reader-font-size: 1.45rem; }}
+
         @media (prefers-color-scheme: dark) {{
             :root {{
                 --bg-primary: #0f172a;
@@ -726,6 +1158,21 @@ fn base_html(title: &str, content: &str, state: &AppState) -> String {
             background: var(--bg-secondary);
             color: var(--accent);
         }}
+
+        .language-switcher {{
+            gap: 2px;
+            text-transform: uppercase;
+            font-size: 0.85rem;
+        }}
+
+        .language-switcher a, .language-switcher .current {{
+            padding: 6px 10px;
+        }}
+
+        .language-switcher .current {{
+            font-weight: 600;
+            color: var(--accent);
+        }}
         
         main {{
             padding: 40px 0;
@@ -762,12 +1209,107 @@ fn base_html(title: &str, content: &str, state: &AppState) -> String {
             align-items: center;
         }}
         
+        .copy-text, .print-link {{
+            background: var(--bg-primary);
+            color: var(--text-secondary);
+            border: 1px solid var(--border);
+            padding: 6px 16px;
+            border-radius: 99px;
+            font-size: 0.85rem;
+            cursor: pointer;
+            text-decoration: none;
+        }}
+
+        .copy-text {{
+            margin-left: auto;
+        }}
+
+        .show-full {{
+            text-align: center;
+            margin-top: 24px;
+        }}
+
+        .show-full button {{
+            background: var(--accent);
+            color: white;
+            border: none;
+            padding: 12px 24px;
+            border-radius: 99px;
+            font-size: 1rem;
+            font-weight: 600;
+            cursor: pointer;
+        }}
+
+        .copy-text:hover, .print-link:hover {{
+            border-color: var(--accent);
+            color: var(--accent);
+        }}
+
+        .reader-settings {{
+            position: relative;
+        }}
+
+        .reader-settings summary {{
+            list-style: none;
+            background: var(--bg-primary);
+            color: var(--text-secondary);
+            border: 1px solid var(--border);
+            padding: 6px 16px;
+            border-radius: 99px;
+            font-size: 0.85rem;
+            cursor: pointer;
+        }}
+
+        .reader-settings summary::-webkit-details-marker {{
+            display: none;
+        }}
+
+        .reader-settings-panel {{
+            position: absolute;
+            right: 0;
+            top: calc(100% + 8px);
+            z-index: 10;
+            display: grid;
+            gap: 12px;
+            padding: 16px;
+            background: var(--bg-secondary);
+            border: 1px solid var(--border);
+            border-radius: var(--radius);
+            box-shadow: var(--shadow-lg);
+            white-space: nowrap;
+        }}
+
+        .reader-setting {{
+            display: flex;
+            gap: 6px;
+            align-items: center;
+        }}
+
+        .reader-setting span {{
+            min-width: 5em;
+        }}
+
+        .reader-setting button {{
+            background: var(--bg-primary);
+            color: var(--text-secondary);
+            border: 1px solid var(--border);
+            padding: 4px 12px;
+            border-radius: 99px;
+            cursor: pointer;
+        }}
+
+        .reader-setting button[aria-pressed="true"] {{
+            border-color: var(--accent);
+            color: var(--accent);
+            font-weight: 600;
+        }}
+
         .article-content {{
             font-family: 'Crimson Pro', serif;
-            font-size: 1.25rem;
+            font-size: var(--reader-font-size);
             line-height: 1.8;
             color: var(--text-primary);
-            max-width: 70ch;
+            max-width: var(--reader-width);
             margin-left: auto;
             margin-right: auto;
         }}
@@ -775,6 +1317,70 @@ fn base_html(title: &str, content: &str, state: &AppState) -> String {
         .article-content p {{
             margin-bottom: 1.5em;
         }}
+
+        /* Links to articles missing from this copy */
+        .article-content a.broken-link {{
+            color: #d9534f;
+            text-decoration: underline dashed;
+        }}
+
+        .article-content h2, .article-content h3 {{
+            font-family: 'Outfit', sans-serif;
+            margin: 1.5em 0 0.5em;
+            scroll-margin-top: 96px;
+        }}
+
+        .article-content.list-article {{
+            font-family: 'Outfit', sans-serif;
+            font-size: 1rem;
+            line-height: 1.5;
+        }}
+
+        .list-article p {{
+            margin-bottom: 1em;
+        }}
+
+        .list-article ul {{
+            columns: 2 18em;
+            column-gap: 32px;
+            margin: 0 0 1.5em 1.2em;
+        }}
+
+        .list-article li {{
+            padding: 2px 0;
+            break-inside: avoid;
+        }}
+
+        .toc {{
+            max-width: var(--reader-width);
+            margin: 0 auto 32px;
+            padding: 16px 24px;
+            background: var(--bg-primary);
+            border: 1px solid var(--border);
+            border-radius: var(--radius);
+        }}
+
+        .toc-title {{
+            font-weight: 600;
+            margin-bottom: 8px;
+        }}
+
+        .toc ul {{
+            list-style: none;
+        }}
+
+        .toc li {{
+            padding: 2px 0;
+        }}
+
+        .toc .toc-h3 {{
+            padding-left: 20px;
+            font-size: 0.95em;
+        }}
+
+        .toc.list-toc ul {{
+            columns: 3 12em;
+        }}
         
         .categories {{
             display: flex;
@@ -814,7 +1420,7 @@ fn base_html(title: &str, content: &str, state: &AppState) -> String {
             transition: all 0.2s;
         }}
 
-        .article-list li:hover {{
+        .article-list li:hover, .article-list li.selected {{
             transform: translateY(-2px);
             box-shadow: var(--shadow);
             border-color: var(--accent);
@@ -847,10 +1453,50 @@ fn base_html(title: &str, content: &str, state: &AppState) -> String {
             font-size: 1.1rem;
         }}
         
-        .pagination {{
-            display: flex;
-            justify-content: center;
-            gap: 8px;
+        .search-suggestion {{
+            margin-bottom: 24px;
+            font-size: 1.1rem;
+        }}
+        
+        .search-tips {{
+            margin-bottom: 24px;
+            color: var(--text-secondary);
+            font-size: 0.9rem;
+        }}
+
+        .search-tips summary {{
+            cursor: pointer;
+            color: var(--text-muted);
+        }}
+
+        .search-tips ul {{
+            margin: 8px 0 0 20px;
+        }}
+
+        .search-tips code {{
+            background: var(--bg-secondary);
+            padding: 1px 6px;
+            border-radius: 4px;
+        }}
+
+        .search-sort {{
+            margin-bottom: 24px;
+            color: var(--text-muted);
+        }}
+        
+        .search-sort .current {{
+            font-weight: 600;
+            color: var(--text-primary);
+        }}
+        
+        .search-sort .random-result {{
+            margin-left: 16px;
+        }}
+        
+        .pagination {{
+            display: flex;
+            justify-content: center;
+            gap: 8px;
             margin-top: 40px;
         }}
         
@@ -914,6 +1560,15 @@ fn base_html(title: &str, content: &str, state: &AppState) -> String {
             letter-spacing: 0.05em;
         }}
         
+        .notice {{
+            margin-top: 24px;
+            padding: 12px 16px;
+            border: 1px solid var(--border);
+            border-left: 4px solid var(--accent);
+            border-radius: 8px;
+            background: var(--bg-secondary);
+        }}
+        
         .hero {{
             text-align: center;
             padding: 80px 0;
@@ -1000,30 +1655,63 @@ fn base_html(title: &str, content: &str, state: &AppState) -> String {
 <body>
     <header>
         <div class="container header-inner">
-            <a href="/" class="logo">
-                <img src="/logo" alt="Logo" style="height: 32px; width: auto;">
+            <a href="{home_url}" class="logo">
+                <img src="{logo_url}" alt="Logo" style="height: 32px; width: auto;">
                 <span>Rustipedia</span>
             </a>
-            <form action="/search" method="GET" class="search-form">
-                <input type="search" name="q" placeholder="Search articles..." class="search-input">
+            <form action="{search_url}" method="GET" class="search-form">
+                <input type="search" name="q" id="header-search" placeholder="Search articles... (press /)" class="search-input">
             </form>
             <nav>
-                <a href="/browse">Browse</a>
-                <a href="/random">Random</a>
-                <a href="/settings">Settings</a>
-            </nav>
+                <a href="{browse_url}">Browse</a>
+                <a href="{random_url}">Random</a>
+                <a href="{settings_url}">Settings</a>
+            </nav>{language_switcher}
         </div>
     </header>
     
     <main class="container">
-        {}
+        {content}
     </main>
     
     <footer class="container">
-        <p>Rustipedia • {} articles • Powered by rustipedia-download</p>
+        <p>Rustipedia • {article_count} articles • Powered by rustipedia-download</p>
     </footer>
 </body>
-</html>"#, title, content, state.article_count)
+</html>"#,
+        title = title,
+        content = content,
+        article_count = state.article_count,
+        feed_url = state.url("/feed.xml"),
+        home_url = state.home_url(),
+        logo_url = state.url("/logo"),
+        favicon_url = state.url("/favicon.ico"),
+        search_url = state.url("/search"),
+        browse_url = state.url("/browse"),
+        random_url = state.url("/random"),
+        settings_url = state.url("/settings"),
+        language_switcher = language_switcher_html(state),
+    )
+}
+
+/// Links to the other languages served, or empty for a single language
+fn language_switcher_html(state: &AppState) -> String {
+    if state.languages.len() < 2 {
+        return String::new();
+    }
+    let links = state.languages.iter()
+        .map(|code| {
+            let name = WikiLanguage::from_code(code).map(|l| l.display_name()).unwrap_or(code);
+            if *code == state.language {
+                format!(r#"<span class="current" title="{}">{}</span>"#, html_escape(name), html_escape(code))
+            } else {
+                format!(r#"<a href="/{}" title="{}">{}</a>"#, urlencoding::encode(code), html_escape(name), html_escape(code))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("");
+    format!(r#"
+            <nav class="language-switcher" aria-label="Language">{}</nav>"#, links)
 }
 
 // ============================================================================
@@ -1033,15 +1721,30 @@ fn base_html(title: &str, content: &str, state: &AppState) -> String {
 async fn home(State(state): State<SharedState>) -> impl IntoResponse {
     let state = state.read().await;
     
-    let lang = WikiLanguage::from_code(&state.language)
+    let wiki_language = WikiLanguage::from_code(&state.language);
+    let lang = wiki_language
         .map(|l| l.display_name())
         .unwrap_or("Wikipedia");
     
+    let numbers = state.number_format();
+
+    // Warn when extraction stopped at --max-articles, so a small dataset isn't mistaken for broken search
+    let sample_notice = match state.extraction_stats {
+        Some(ref stats) if stats.truncated => format!(
+            r#"<div class="notice">⚠️ Sample dataset ({} of {} articles). Extraction stopped at the --max-articles limit of {}, so search only covers this subset.</div>"#,
+            numbers.format_number(state.article_count as u64),
+            wiki_language.map(|l| l.estimated_articles()).unwrap_or("?"),
+            numbers.format_number(stats.max_articles as u64),
+        ),
+        _ => String::new(),
+    };
+    
     let content = format!(r#"
+        {}
         <div class="hero">
             <h1>📚 Your Local {}</h1>
             <p>Browse and search {} articles offline</p>
-            <form action="/search" method="GET" class="hero-search">
+            <form action="{}" method="GET" class="hero-search">
                 <input type="search" name="q" placeholder="Search for any article..." class="search-input" autofocus>
             </form>
         </div>
@@ -1066,15 +1769,17 @@ async fn home(State(state): State<SharedState>) -> impl IntoResponse {
             {}
         </ul>
     "#, 
+        sample_notice,
         lang,
-        state.article_count,
-        format_number(state.article_count),
+        numbers.format_number(state.article_count as u64),
+        state.url("/search"),
+        numbers.format_number(state.article_count as u64),
         lang,
         if state.search_index.is_some() { "✅ Enabled" } else { "❌ Disabled" },
         state.all_titles.iter().take(10).map(|(id, title)| {
             let preview = state.get_article_preview(*id, 150);
-            format!(r#"<li><a href="/article/{}"><div class="title">{}</div><div class="preview">{}</div></a></li>"#, 
-                id, html_escape(title), html_escape(&preview))
+            format!(r#"<li><a href="{}"><div class="title">{}</div><div class="preview">{}</div></a></li>"#, 
+                state.url(&format!("/article/{}", id)), html_escape(title), html_escape(&preview))
         }).collect::<Vec<_>>().join("\n")
     );
     
@@ -1088,24 +1793,155 @@ async fn article_by_id(
     let state = state.read().await;
     
     if let Some(article) = state.get_article_by_id(id) {
-        let content = render_article_html(&article);
+        let content = render_article_html(&article, &state);
         Html(base_html(&article.title, &content, &state)).into_response()
     } else {
-        (StatusCode::NOT_FOUND, Html(base_html("Not Found", "<p>Article not found</p>", &state))).into_response()
+        let content = format!(
+            r#"<h1>Article not found</h1>
+<p>There is no article with ID {}.</p>
+<p><a href="{}">Browse all articles</a> or read a <a href="{}">random article</a>.</p>"#,
+            id, state.url("/browse"), state.url("/random")
+        );
+        (StatusCode::NOT_FOUND, Html(base_html("Not Found", &content, &state))).into_response()
+    }
+}
+
+/// The article as plain text, e.g. for copying
+async fn article_text(
+    Path(id): Path<u64>,
+    State(state): State<SharedState>,
+) -> Response {
+    let state = state.read().await;
+    match state.get_article_by_id(id) {
+        Some(article) => (
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            article.to_plain_text(),
+        ).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("There is no article with ID {}.", id)).into_response(),
     }
 }
 
+/// The article on its own, styled for printing or saving as PDF
+async fn article_print(
+    Path(id): Path<u64>,
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+) -> Response {
+    let state = state.read().await;
+    match state.get_article_by_id(id) {
+        Some(article) => Html(print::print_html(&article, &state.absolute_url(&headers))).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("There is no article with ID {}.", id)).into_response(),
+    }
+}
+
+/// An article as JSON; `last_edited` is null without revision timestamps
+async fn api_article(
+    Path(id): Path<u64>,
+    State(state): State<SharedState>,
+) -> Response {
+    let state = state.read().await;
+    match state.get_article_by_id(id) {
+        Some(article) => Json(server::article_json(&article)).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("There is no article with ID {}", id) })),
+        ).into_response(),
+    }
+}
+
+/// Several articles in one request: a JSON array of IDs in, the articles
+/// (or `null` for missing IDs) out, in the same order
+async fn api_articles_batch(
+    State(state): State<SharedState>,
+    Json(ids): Json<Vec<u64>>,
+) -> Response {
+    let state = state.read().await;
+    match server::batch_articles(&ids, |id| state.get_article_by_id(id)) {
+        Ok(articles) => Json(serde_json::json!({ "articles": articles })).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        ).into_response(),
+    }
+}
+
+/// Article HTML left out of the page by `max_render_chars`
+async fn api_article_rest(
+    Path(id): Path<u64>,
+    State(state): State<SharedState>,
+) -> Response {
+    let state = state.read().await;
+    match state.get_article_by_id(id) {
+        Some(article) => {
+            let (_, rest) = article.content_html_split(state.max_render_chars);
+            let html = state.article_links_html(&rest.unwrap_or_default());
+            Json(serde_json::json!({ "id": id, "html": html })).into_response()
+        }
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("There is no article with ID {}", id) })),
+        ).into_response(),
+    }
+}
+
+/// Maximum "did you mean" links on the missing-title page
+const NOT_FOUND_SUGGESTIONS: usize = 5;
+
+/// 404 page for a missing title, with links to similar titles
+fn title_not_found_html(title: &str, state: &AppState) -> String {
+    let display_title = title.replace('_', " ");
+    let suggestions = state.similar_titles(title, NOT_FOUND_SUGGESTIONS);
+    let mut content = format!(
+        "<h1>Article not found</h1>\n<p>There is no article titled \"{}\".</p>\n",
+        html_escape(&display_title)
+    );
+    if suggestions.is_empty() {
+        content.push_str(&format!(
+            r#"<p><a href="{}?q={}">Search for "{}"</a> or <a href="{}">browse all articles</a>.</p>"#,
+            state.url("/search"), urlencoding::encode(&display_title), html_escape(&display_title), state.url("/browse")
+        ));
+    } else {
+        content.push_str("<p>Did you mean:</p>\n<ul class=\"not-found-suggestions\">\n");
+        for suggestion in &suggestions {
+            content.push_str(&format!(
+                "<li><a href=\"{}/wiki/{}\">{}</a></li>\n",
+                state.base_path, urlencoding::encode(suggestion), html_escape(suggestion)
+            ));
+        }
+        content.push_str("</ul>");
+    }
+    if state.fallback_to_wikipedia {
+        if let Some(url) = server::wikipedia_url(&state.language, title) {
+            content.push_str(&format!(
+                r#"<p class="wikipedia-fallback">Not in this copy. <a href="{}" rel="noopener noreferrer">Read "{}" on Wikipedia</a> (needs an internet connection).</p>"#,
+                html_escape(&url), html_escape(&display_title)
+            ));
+        }
+    }
+    base_html("Not Found", &content, state)
+}
+
 async fn article_by_title(
     Path(title): Path<String>,
     State(state): State<SharedState>,
 ) -> Response {
     let state = state.read().await;
+
+    // Send aliases to the canonical URL so the address bar shows the real title
+    if let Some(target) = state.redirect_target(&title) {
+        let (page, fragment) = match target.split_once('#') {
+            Some((page, fragment)) => (page, format!("#{}", urlencoding::encode(fragment))),
+            None => (target.as_str(), String::new()),
+        };
+        let location = format!("{}/wiki/{}{}", state.base_path, urlencoding::encode(page), fragment);
+        return axum::response::Redirect::to(&location).into_response();
+    }
     
     if let Some(article) = state.get_article_by_title(&title) {
-        let content = render_article_html(&article);
+        let content = render_article_html(&article, &state);
         Html(base_html(&article.title, &content, &state)).into_response()
     } else {
-        (StatusCode::NOT_FOUND, Html(base_html("Not Found", "<p>Article not found</p>", &state))).into_response()
+        (StatusCode::NOT_FOUND, Html(title_not_found_html(&title, &state))).into_response()
     }
 }
 
@@ -1114,10 +1950,22 @@ struct SearchQuery {
     q: String,
     #[serde(default = "default_page")]
     page: usize,
+    #[serde(default)]
+    sort: SearchSort,
+    #[serde(default)]
+    per_page: Option<usize>,
+    /// Include category counts (`/api/v1/search` only)
+    #[serde(default)]
+    facets: bool,
 }
 
 fn default_page() -> usize { 1 }
 
+/// `&per_page=N` for pagination links, if the request chose a page size
+fn per_page_param(requested: Option<usize>, per_page: usize) -> String {
+    requested.map(|_| format!("&per_page={}", per_page)).unwrap_or_default()
+}
+
 async fn search(
     Query(params): Query<SearchQuery>,
     State(state): State<SharedState>,
@@ -1125,10 +1973,10 @@ async fn search(
     let state = state.read().await;
     let query = params.q.trim();
     let page = params.page.max(1);
-    let per_page = 20;
+    let per_page = page_size(params.per_page, state.search_per_page);
     
     if query.is_empty() {
-        return Html(base_html("Search", "<p>Enter a search query</p>", &state));
+        return Html(base_html("Search", &format!("<p>Enter a search query</p>{}", SEARCH_TIPS_HTML), &state));
     }
 
     // Security: Validate search query length
@@ -1136,44 +1984,87 @@ async fn search(
         return Html(base_html("Search", "<p>Search query too long (max 200 characters)</p>", &state));
     }
     
-    let results = if let Some(ref index) = state.search_index {
+    let limit = server::search_limit(DEFAULT_MAX_SEARCH_RESULTS, state.max_search_results);
+    let mut results = if let Some(ref index) = state.search_index {
         // Use full-text search
-        match index.search(query, 100) {
-            Ok(results) => results.into_iter()
-                .map(|r| (r.id, r.title, r.preview))
-                .collect(),
-            Err(_) => Vec::new(),
-        }
+        index.search(query, limit).unwrap_or_default()
     } else {
         // Fallback to simple title search
         let query_lower = query.to_lowercase();
         state.all_titles.iter()
             .filter(|(_, title)| title.to_lowercase().contains(&query_lower))
-            .take(100)
+            .take(limit)
             .filter_map(|(id, title)| {
-                state.articles.get(id).map(|a| (*id, title.clone(), a.preview(150).to_string()))
+                state.get_article_by_id(*id).map(|a| SearchResult {
+                    id: *id,
+                    title: title.clone(),
+                    preview: a.preview(150).to_string(),
+                    score: 0.0,
+                    word_count: Some(a.word_count() as u64),
+                    categories: a.categories.clone(),
+                })
             })
             .collect()
     };
+    sort_results(&mut results, params.sort);
     
     let total = results.len();
-    let start = (page - 1) * per_page;
-    let page_results: Vec<_> = results.into_iter().skip(start).take(per_page).collect();
-    let total_pages = (total + per_page - 1) / per_page;
+    let random_result = results.choose(&mut rand::rng()).map(|r| r.id);
+    let page_results = page_slice(&results, page, per_page);
+    let total_pages = total.div_ceil(per_page);
+    let per_page_param = per_page_param(params.per_page, per_page);
+    let search_url = |sort: SearchSort, page: usize| {
+        format!("{}/search?q={}&sort={}&page={}{}", state.base_path, urlencoding::encode(query), sort.as_str(), page, per_page_param)
+    };
+    
+    let sort_links = if total > 1 {
+        let links = [(SearchSort::Relevance, "Relevance"), (SearchSort::Title, "Title"), (SearchSort::Length, "Length")]
+            .iter()
+            .map(|(sort, label)| {
+                if *sort == params.sort {
+                    format!(r#"<span class="current">{}</span>"#, label)
+                } else {
+                    format!(r#"<a href="{}">{}</a>"#, html_escape(&search_url(*sort, page)), label)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" · ");
+        let random = random_result
+            .map(|id| format!(r#" <a class="random-result" href="{}/article/{}">🎲 Random result</a>"#, state.base_path, id))
+            .unwrap_or_default();
+        format!(r#"<p class="search-sort">Sort by: {}{}</p>"#, links, random)
+    } else {
+        String::new()
+    };
+    
+    let suggestion = if total == 0 {
+        state.suggest_title(query)
+            .map(|(id, title)| format!(r#"<p class="search-suggestion">Did you mean: <a href="{}/article/{}">{}</a>?</p>"#,
+                state.base_path, id, html_escape(&title)))
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
     
     let content = format!(r#"
         <h1>Search: "{}"</h1>
         <p class="search-results-count">{} results found</p>
-        <ul class="article-list">
+        {}
+        {}
+        {}
+        <ul class="article-list" data-keyboard-nav>
             {}
         </ul>
         {}
     "#,
         html_escape(query),
         total,
-        page_results.iter().map(|(id, title, preview)| {
-            format!(r#"<li><a href="/article/{}"><div class="title">{}</div><div class="preview">{}</div></a></li>"#,
-                id, html_escape(title), html_escape(preview))
+        suggestion,
+        SEARCH_TIPS_HTML,
+        sort_links,
+        page_results.iter().map(|r| {
+            format!(r#"<li><a href="{}/article/{}"><div class="title">{}</div><div class="preview">{}</div></a></li>"#,
+                state.base_path, r.id, html_escape(&r.title), html_escape(truncate_at_word(&r.preview, 150)))
         }).collect::<Vec<_>>().join("\n"),
         if total_pages > 1 {
             format!(r#"<div class="pagination">{}</div>"#,
@@ -1181,7 +2072,7 @@ async fn search(
                     if p == page {
                         format!(r#"<span class="current">{}</span>"#, p)
                     } else {
-                        format!(r#"<a href="/search?q={}&page={}">{}</a>"#, urlencoding::encode(query), p, p)
+                        format!(r#"<a href="{}">{}</a>"#, html_escape(&search_url(params.sort, p)), p)
                     }
                 }).collect::<Vec<_>>().join("")
             )
@@ -1193,12 +2084,45 @@ async fn search(
     Html(base_html(&format!("Search: {}", query), &content, &state))
 }
 
+/// Collapsible help for the search query syntax
+const SEARCH_TIPS_HTML: &str = r#"<details class="search-tips">
+    <summary>Search tips</summary>
+    <ul>
+        <li><code>einstein relativity</code> finds articles containing every word</li>
+        <li><code>"theory of relativity"</code> finds the exact phrase</li>
+        <li><code>title:Einstein</code> matches titles only; <code>content:</code> matches article text only</li>
+        <li><code>category:Physics</code> matches articles in a category</li>
+        <li><code>einstein -relativity</code> excludes a word; <code>cats OR dogs</code> finds either</li>
+    </ul>
+</details>"#;
+
+/// Width and text size controls for article pages, applied by article.js
+const READER_SETTINGS_HTML: &str = r#"<details class="reader-settings">
+    <summary title="Reader settings">Aa</summary>
+    <div class="reader-settings-panel">
+        <div class="reader-setting">
+            <span>Width</span>
+            <button type="button" data-reader-width="narrow">Narrow</button>
+            <button type="button" data-reader-width="medium">Medium</button>
+            <button type="button" data-reader-width="wide">Wide</button>
+        </div>
+        <div class="reader-setting">
+            <span>Text size</span>
+            <button type="button" data-reader-font="small">Small</button>
+            <button type="button" data-reader-font="medium">Medium</button>
+            <button type="button" data-reader-font="large">Large</button>
+        </div>
+    </div>
+</details>"#;
+
 #[derive(serde::Deserialize)]
 struct BrowseQuery {
     #[serde(default = "default_page")]
     page: usize,
     #[serde(default)]
     letter: Option<char>,
+    #[serde(default)]
+    per_page: Option<usize>,
 }
 
 async fn browse(
@@ -1207,7 +2131,7 @@ async fn browse(
 ) -> impl IntoResponse {
     let state = state.read().await;
     let page = params.page.max(1);
-    let per_page = 50;
+    let per_page = page_size(params.per_page, state.browse_per_page);
     
     let filtered: Vec<_> = if let Some(letter) = params.letter {
         state.all_titles.iter()
@@ -1219,44 +2143,45 @@ async fn browse(
     };
     
     let total = filtered.len();
-    let start = (page - 1) * per_page;
-    let page_titles: Vec<_> = filtered.into_iter().skip(start).take(per_page).collect();
-    let total_pages = (total + per_page - 1) / per_page;
+    let page_titles = page_slice(&filtered, page, per_page);
+    let total_pages = total.div_ceil(per_page);
     
     // Letter navigation
     let letters: Vec<char> = ('A'..='Z').collect();
     let letter_nav = letters.iter().map(|l| {
         let class = if params.letter == Some(*l) { "current" } else { "" };
-        format!(r#"<a href="/browse?letter={}" class="{}">{}</a>"#, l, class, l)
+        format!(r#"<a href="{}/browse?letter={}" class="{}">{}</a>"#, state.base_path, l, class, l)
     }).collect::<Vec<_>>().join(" ");
     
     let content = format!(r#"
         <h1>Browse Articles</h1>
         <p class="search-results-count">{} articles{}</p>
         <div class="pagination" style="margin-bottom: 24px;">
-            <a href="/browse">All</a> {}
+            <a href="{}">All</a> {}
         </div>
-        <ul class="article-list">
+        <ul class="article-list" data-keyboard-nav>
             {}
         </ul>
         {}
     "#,
         total,
         params.letter.map(|l| format!(" starting with '{}'", l)).unwrap_or_default(),
+        state.url("/browse"),
         letter_nav,
         page_titles.iter().map(|(id, title)| {
             let preview = state.get_article_preview(*id, 100);
-            format!(r#"<li><a href="/article/{}"><div class="title">{}</div><div class="preview">{}</div></a></li>"#,
-                id, html_escape(title), html_escape(&preview))
+            format!(r#"<li><a href="{}/article/{}"><div class="title">{}</div><div class="preview">{}</div></a></li>"#,
+                state.base_path, id, html_escape(title), html_escape(&preview))
         }).collect::<Vec<_>>().join("\n"),
         if total_pages > 1 {
             let letter_param = params.letter.map(|l| format!("&letter={}", l)).unwrap_or_default();
+            let per_page_param = per_page_param(params.per_page, per_page);
             format!(r#"<div class="pagination">{}</div>"#,
                 (1..=total_pages.min(20)).map(|p| {
                     if p == page {
                         format!(r#"<span class="current">{}</span>"#, p)
                     } else {
-                        format!(r#"<a href="/browse?page={}{}">{}</a>"#, p, letter_param, p)
+                        format!(r#"<a href="{}/browse?page={}{}{}">{}</a>"#, state.base_path, p, letter_param, per_page_param, p)
                     }
                 }).collect::<Vec<_>>().join("")
             )
@@ -1280,7 +2205,21 @@ async fn random_article(State(state): State<SharedState>) -> Response {
     let idx = rng.random_range(0..state.all_titles.len());
     let (id, _) = &state.all_titles[idx];
     
-    axum::response::Redirect::to(&format!("/article/{}", id)).into_response()
+    axum::response::Redirect::to(&state.url(&format!("/article/{}", id))).into_response()
+}
+
+/// Atom feed of the most recently extracted articles
+async fn atom_feed(State(state): State<SharedState>, headers: HeaderMap) -> impl IntoResponse {
+    let state = state.read().await;
+    let title = match WikiLanguage::from_code(&state.language) {
+        Some(language) => format!("Rustipedia ({}): recently added articles", language.display_name()),
+        None => "Rustipedia: recently added articles".to_string(),
+    };
+    (
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        // Atom needs absolute links
+        feed::atom_feed(&title, &state.absolute_url(&headers), &state.recent_articles),
+    )
 }
 
 // ============================================================================
@@ -1293,16 +2232,13 @@ async fn api_articles(
 ) -> impl IntoResponse {
     let state = state.read().await;
     let page = params.page.max(1);
-    let per_page = 50;
-    let start = (page - 1) * per_page;
+    let per_page = page_size(params.per_page, state.browse_per_page);
     
-    let articles: Vec<_> = state.all_titles.iter()
-        .skip(start)
-        .take(per_page)
+    let articles: Vec<_> = page_slice(&state.all_titles, page, per_page).iter()
         .map(|(id, title)| {
             let preview = state.get_article_preview(*id, 200);
             let word_count = if state.search_index.is_none() {
-                state.articles.get(id).map(|a| a.word_count()).unwrap_or(0)
+                state.get_article_by_id(*id).map(|a| a.word_count()).unwrap_or(0)
             } else {
                 0 // Approximate or fetch full article if needed
             };
@@ -1319,6 +2255,7 @@ async fn api_articles(
     axum::Json(serde_json::json!({
         "articles": articles,
         "page": page,
+        "per_page": per_page,
         "total": state.article_count
     }))
 }
@@ -1329,36 +2266,75 @@ async fn api_search(
 ) -> impl IntoResponse {
     let state = state.read().await;
     
-    let results = if let Some(ref index) = state.search_index {
-        match index.search(&params.q, 50) {
-            Ok(results) => results.into_iter()
-                .map(|r| {
-                    serde_json::json!({
-                        "id": r.id,
-                        "title": r.title,
-                        "preview": r.preview,
-                        "score": r.score
-                    })
-                })
-                .collect(),
-            Err(_) => Vec::new(),
-        }
+    let matches = match state.search_index {
+        Some(ref index) => index
+            .search(&params.q, server::search_limit(server::API_SEARCH_RESULTS, state.max_search_results))
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let facets = params.facets.then(|| {
+        category_facets(&matches, MAX_FACETS).into_iter()
+            .map(|(category, count)| (category, serde_json::Value::from(count)))
+            .collect::<serde_json::Map<_, _>>()
+    });
+    let results: Vec<serde_json::Value> = matches.into_iter()
+        .map(|r| {
+            serde_json::json!({
+                "id": r.id,
+                "title": r.title,
+                "preview": truncate_at_word(&r.preview, 200),
+                "score": r.score
+            })
+        })
+        .collect();
+    
+    let suggestion = if results.is_empty() && !params.q.trim().is_empty() {
+        state.suggest_title(params.q.trim()).map(|(_, title)| title)
     } else {
-        Vec::new()
+        None
     };
     
-    axum::Json(serde_json::json!({
+    let mut response = serde_json::json!({
         "query": params.q,
-        "results": results
-    }))
+        "results": results,
+        "suggestion": suggestion
+    });
+    if let Some(facets) = facets {
+        response["facets"] = facets.into();
+    }
+    axum::Json(response)
 }
 
 // ============================================================================
 // Utilities
 // ============================================================================
 
-/// Render an article as HTML
-fn render_article_html(article: &Article) -> String {
+/// Articles with fewer headings are shown without a table of contents
+const MIN_TOC_HEADINGS: usize = 3;
+
+/// List articles with fewer headings are shown without a table of contents
+const MIN_LIST_TOC_HEADINGS: usize = 2;
+
+/// Table of contents linking to the article's headings, or empty for short
+/// articles. List articles get it with fewer headings, laid out in columns.
+fn toc_html(article: &Article) -> String {
+    let headings = article.headings();
+    let list = article.is_list_article();
+    if headings.len() < if list { MIN_LIST_TOC_HEADINGS } else { MIN_TOC_HEADINGS } {
+        return String::new();
+    }
+    format!(r#"<nav class="toc{}"><div class="toc-title">Contents</div><ul>{}</ul></nav>"#,
+        if list { " list-toc" } else { "" },
+        headings.iter()
+            .map(|h| format!(r##"<li class="toc-h{}"><a href="#{}">{}</a></li>"##, h.level, h.anchor, html_escape(&h.text)))
+            .collect::<Vec<_>>()
+            .join("")
+    )
+}
+
+/// Render an article as HTML, leaving content past `max_render_chars` to be
+/// loaded by the "Show full article" button
+fn render_article_html(article: &Article, state: &AppState) -> String {
     let categories_html = if !article.categories.is_empty() {
         format!(r#"<div class="categories">{}</div>"#,
             article.categories.iter()
@@ -1370,22 +2346,49 @@ fn render_article_html(article: &Article) -> String {
         String::new()
     };
 
+    let (content_html, rest) = article.content_html_split(state.max_render_chars);
+    let content_html = state.article_links_html(&content_html);
+    let show_full = match rest {
+        Some(_) => format!(
+            r#"<div class="show-full"><button type="button" data-load-rest="{}/api/v1/article/{}/rest">Show full article</button></div>"#,
+            state.base_path, article.id
+        ),
+        None => String::new(),
+    };
+
     format!(r#"
         <article class="article">
             <h1>{}</h1>
             <div class="article-meta">
-                Article ID: {} • {} words
+                Article ID: {} • {} words{}
+                <button type="button" class="copy-text" data-copy-text="{}/article/{}/text">Copy as text</button>
+                <a class="print-link" href="{}/article/{}/print">Print / PDF</a>
+                {}
             </div>
-            <div class="article-content">
+            {}
+            <div class="article-content{}">
                 {}
             </div>
             {}
+            {}
         </article>
+        <script src="/static/article.js" defer></script>
     "#, 
         html_escape(&article.title),
         article.id,
         article.word_count(),
-        article.content.split("\n\n").map(|p| format!("<p>{}</p>", p)).collect::<Vec<_>>().join("\n"),
+        article.last_edited
+            .map(|last_edited| format!(" • Last edited {} on Wikipedia", last_edited.format("%Y-%m-%d")))
+            .unwrap_or_default(),
+        state.base_path,
+        article.id,
+        state.base_path,
+        article.id,
+        READER_SETTINGS_HTML,
+        toc_html(article),
+        if article.is_list_article() { " list-article" } else { "" },
+        content_html,
+        show_full,
         categories_html
     )
 }
@@ -1398,17 +2401,6 @@ fn html_escape(s: &str) -> String {
         .replace('\'', "&#x27;")
 }
 
-fn format_number(n: usize) -> String {
-    let s = n.to_string();
-    let mut result = String::new();
-    for (i, c) in s.chars().rev().enumerate() {
-        if i > 0 && i % 3 == 0 {
-            result.insert(0, ',');
-        }
-        result.insert(0, c);
-    }
-    result
-}
 
 
 #[derive(serde::Deserialize)]
@@ -1473,12 +2465,12 @@ async fn update_settings(
     }
 
     // Redirect back to settings
-    (StatusCode::SEE_OTHER, [("Location", "/settings")])
+    (StatusCode::SEE_OTHER, [("Location", state.url("/settings"))])
 }
 
 async fn api_update_status(State(state): State<SharedState>) -> impl IntoResponse {
     let state = state.read().await;
-    let manager = UpdateManager::load(&state.data_dir).unwrap_or_else(|_| {
+    let manager = UpdateManager::load_async(&state.data_dir).await.unwrap_or_else(|_| {
         UpdateManager::new(UpdateConfig::default())
     });
     let status = manager.get_status().await;
@@ -1486,23 +2478,211 @@ async fn api_update_status(State(state): State<SharedState>) -> impl IntoRespons
 }
 
 async fn api_trigger_update(State(state): State<SharedState>) -> impl IntoResponse {
-    let state = state.read().await;
-    
-    let data_dir = state.data_dir.clone();
+    let data_dir = state.read().await.data_dir.clone();
     
     tokio::spawn(async move {
-        let manager = UpdateManager::load(&data_dir).unwrap_or_else(|_| {
+        let manager = UpdateManager::load_async(&data_dir).await.unwrap_or_else(|_| {
             UpdateManager::new(UpdateConfig::default())
         });
-        let _ = manager.perform_update().await;
+        if manager.perform_update().await.is_ok() {
+            // Serve the new data right away
+            if let Err(e) = reload_data(&state).await {
+                tracing::error!("Failed to load the updated data, still serving the previous data: {:#}", e);
+            }
+        }
     });
 
     Json(serde_json::json!({ "status": "started" }))
 }
 
+/// Load the data directory again and swap it in, keeping the current data
+/// if that fails; returns the new article count
+async fn reload_data(state: &SharedState) -> Result<usize> {
+    let reload = state.read().await.reload();
+    server::reload_state(state, reload).await?;
+    let article_count = state.read().await.article_count;
+    tracing::info!("Reloaded data: {} articles", article_count);
+    Ok(article_count)
+}
+
+/// Index articles that can't be read by offset, as `rustipedia-download index` does
+fn build_search_index(data_dir: &std::path::Path, index_path: &std::path::Path, articles_files: &[PathBuf], language: &str) -> Result<SearchIndex> {
+    let _lock = DataDirLock::exclusive(data_dir, "indexing")?;
+    let language = WikiLanguage::from_code(language).unwrap_or_default();
+    let indexed = SearchIndex::create_resumable(index_path, language, true)?
+        .build_from_jsonl_files(articles_files, SearchIndex::default_threads())?;
+    tracing::info!("Indexed {} articles to {:?}", indexed, index_path);
+    SearchIndex::open(index_path)
+}
+
+/// A 403 response unless the request may run admin actions; `action` names
+/// the action for the error message, e.g. "Reloading"
+async fn require_admin(state: &SharedState, headers: &HeaderMap, peer: std::net::SocketAddr, action: &str) -> Result<(), Response> {
+    let admin_token = state.read().await.admin_token.clone();
+    if server::admin_authorized(headers, Some(peer), admin_token.as_deref()) {
+        return Ok(());
+    }
+    let error = match admin_token {
+        Some(_) => "Missing or wrong admin token".to_string(),
+        None => format!("{} is only allowed from this machine unless admin_token is set", action),
+    };
+    Err((StatusCode::FORBIDDEN, Json(serde_json::json!({ "error": error }))).into_response())
+}
+
+async fn api_reload(
+    State(state): State<SharedState>,
+    ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(response) = require_admin(&state, &headers, peer, "Reloading").await {
+        return response;
+    }
+
+    match reload_data(&state).await {
+        Ok(articles) => Json(serde_json::json!({ "status": "reloaded", "articles": articles })).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Reload failed, still serving the previous data: {:#}", e) })),
+        ).into_response(),
+    }
+}
+
+/// Prune broken links from the served data directory in the background,
+/// then reload it; progress is at /api/v1/prune/status
+async fn api_prune(
+    State(state): State<SharedState>,
+    ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(response) = require_admin(&state, &headers, peer, "Pruning").await {
+        return response;
+    }
+    let conflict = |error: String| (StatusCode::CONFLICT, Json(serde_json::json!({ "error": error }))).into_response();
+    let (data_dir, prune_status) = {
+        let state = state.read().await;
+        (state.data_dir.clone(), state.prune_status.clone())
+    };
+
+    if prune_status.lock().unwrap_or_else(|e| e.into_inner()).state == PruneState::Running {
+        return conflict("A prune is already running".to_string());
+    }
+    let update_status = match UpdateManager::load_async(&data_dir).await {
+        Ok(manager) => manager.get_status().await,
+        Err(_) => Default::default(),
+    };
+    if update_status.current_status.is_in_progress() {
+        return conflict("An update is in progress; prune once it has finished".to_string());
+    }
+    // Held until the articles and index are rewritten, so an update can't start meanwhile
+    let lock = match DataDirLock::exclusive(&data_dir, "pruning") {
+        Ok(lock) => lock,
+        Err(e) => return conflict(format!("{:#}", e)),
+    };
+    prune_status.lock().unwrap_or_else(|e| e.into_inner()).start();
+
+    tokio::spawn(async move {
+        let status = prune_status.clone();
+        let pruned = tokio::task::spawn_blocking(move || {
+            let _lock = lock;
+            link_prune::prune_data_dir(&data_dir, &status)
+        }).await;
+        let mut result = pruned.unwrap_or_else(|e| Err(e.into()));
+        if result.is_ok() {
+            prune_status.lock().unwrap_or_else(|e| e.into_inner()).set_phase("Reloading");
+            // Serve the pruned articles right away
+            if let Err(e) = reload_data(&state).await {
+                result = Err(e.context("Pruned, but failed to load the pruned data; still serving the previous data"));
+            }
+        }
+        match result {
+            Ok(ref report) => tracing::info!("Pruned {} broken links from {} articles", report.links_removed, report.articles),
+            Err(ref e) => tracing::error!("Pruning failed: {:#}", e),
+        }
+        prune_status.lock().unwrap_or_else(|e| e.into_inner()).finish(&result);
+    });
+
+    (StatusCode::ACCEPTED, Json(serde_json::json!({ "status": "started" }))).into_response()
+}
+
+async fn api_prune_status(State(state): State<SharedState>) -> impl IntoResponse {
+    let prune_status = state.read().await.prune_status.clone();
+    let status = prune_status.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    Json(status)
+}
+
+async fn api_cancel_update(
+    State(state): State<SharedState>,
+    ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(response) = require_admin(&state, &headers, peer, "Cancelling updates").await {
+        return response;
+    }
+    let state = state.read().await;
+    let manager = UpdateManager::load_async(&state.data_dir).await.unwrap_or_else(|_| {
+        UpdateManager::new(UpdateConfig::default())
+    });
+
+    match manager.cancel_update().await {
+        Ok(()) => Json(manager.get_status().await).into_response(),
+        Err(e) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": format!("{}. Nothing to cancel.", e) })),
+        ).into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct PauseParams {
+    until: String,
+}
+
+async fn api_pause_updates(
+    State(state): State<SharedState>,
+    ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+    headers: HeaderMap,
+    Query(params): Query<PauseParams>,
+) -> Response {
+    if let Err(response) = require_admin(&state, &headers, peer, "Pausing updates").await {
+        return response;
+    }
+    match parse_pause_until(&params.until) {
+        Ok(until) => set_paused_until(&state, Some(until)).await,
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        ).into_response(),
+    }
+}
+
+async fn api_resume_updates(
+    State(state): State<SharedState>,
+    ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(response) = require_admin(&state, &headers, peer, "Resuming updates").await {
+        return response;
+    }
+    set_paused_until(&state, None).await
+}
+
+/// Save a new pause end (`None` resumes updates) and return it as JSON
+async fn set_paused_until(state: &SharedState, paused_until: Option<chrono::DateTime<chrono::Utc>>) -> Response {
+    let mut state = state.write().await;
+    state.update_config.paused_until = paused_until;
+    if let Err(e) = state.update_config.save(UpdateConfig::config_path(&state.data_dir)) {
+        tracing::error!("Failed to save update config: {}", e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Failed to save update config: {}", e) })),
+        ).into_response();
+    }
+    Json(serde_json::json!({ "paused_until": paused_until })).into_response()
+}
+
 async fn api_update_history(State(state): State<SharedState>) -> impl IntoResponse {
     let state = state.read().await;
-    let manager = UpdateManager::load(&state.data_dir).unwrap_or_else(|_| {
+    let manager = UpdateManager::load_async(&state.data_dir).await.unwrap_or_else(|_| {
         UpdateManager::new(UpdateConfig::default())
     });
     
@@ -1510,54 +2690,122 @@ async fn api_update_history(State(state): State<SharedState>) -> impl IntoRespon
     Json(history)
 }
 
+async fn api_version() -> impl IntoResponse {
+    Json(server::api_version_json())
+}
+
+async fn keyboard_js() -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "application/javascript; charset=utf-8"),
+            (header::CACHE_CONTROL, "public, max-age=86400"),
+        ],
+        KEYBOARD_JS,
+    )
+}
+
+async fn article_js() -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "application/javascript; charset=utf-8"),
+            (header::CACHE_CONTROL, "public, max-age=86400"),
+        ],
+        ARTICLE_JS,
+    )
+}
+
+async fn settings_js() -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "application/javascript; charset=utf-8"),
+            (header::CACHE_CONTROL, "public, max-age=86400"),
+        ],
+        SETTINGS_JS,
+    )
+}
+
 async fn logo_handler(State(state): State<SharedState>) -> impl IntoResponse {
     let state = state.read().await;
-    let custom_logo_path = state.data_dir.join("custom_logo.png");
-    
-    if custom_logo_path.exists() {
-        match fs::read(&custom_logo_path) {
-            Ok(bytes) => return (
-                [(header::CONTENT_TYPE, "image/png")],
-                bytes
-            ).into_response(),
-            Err(e) => tracing::error!("Failed to read custom logo: {}", e),
-        }
+    favicon::logo_response(&state.data_dir, DEFAULT_LOGO)
+}
+
+/// The logo as a 32x32 favicon; the bundled one has a pre-rendered version
+async fn favicon_handler(State(state): State<SharedState>) -> Response {
+    let state = state.read().await;
+    let icon = favicon::read_custom_favicon(&state.data_dir).unwrap_or_else(|| DEFAULT_FAVICON.to_vec());
+    favicon::favicon_response(icon)
+}
+
+/// Go back to the bundled logo
+async fn reset_logo(
+    State(state): State<SharedState>,
+    ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(response) = require_admin(&state, &headers, peer, "Changing the logo").await {
+        return response;
     }
-    
-    (
-        [(header::CONTENT_TYPE, "image/png")],
-        DEFAULT_LOGO.to_vec()
-    ).into_response()
+    let state = state.read().await;
+    if let Err(e) = favicon::reset_logo(&state.data_dir) {
+        return (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response();
+    }
+    (StatusCode::SEE_OTHER, [("Location", state.url("/settings"))]).into_response()
 }
 
 async fn upload_logo(
     State(state): State<SharedState>,
+    ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+    headers: HeaderMap,
     mut multipart: Multipart,
-) -> impl IntoResponse {
-    while let Some(field) = multipart.next_field().await.unwrap_or(None) {
-        let name = field.name().unwrap_or("").to_string();
-        if name == "logo" {
-            let data = match field.bytes().await {
-                Ok(data) => data,
-                Err(e) => return (StatusCode::BAD_REQUEST, format!("Failed to read upload: {}", e)).into_response(),
-            };
-            
-            if data.is_empty() {
-                continue;
-            }
+) -> Response {
+    if let Err(response) = require_admin(&state, &headers, peer, "Changing the logo").await {
+        return response;
+    }
+    let data = match favicon::read_logo_upload(&mut multipart).await {
+        Ok(Some(data)) => data,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "No logo file provided").into_response(),
+        Err(e) => return e.into_response(),
+    };
+    if let Err(e) = favicon::check_logo(&data) {
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
 
-            let state = state.read().await;
-            let custom_logo_path = state.data_dir.join("custom_logo.png");
-            
-            if let Err(e) = fs::write(&custom_logo_path, data) {
-                return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to save logo: {}", e)).into_response();
-            }
-            
-            return (StatusCode::SEE_OTHER, [("Location", "/settings")]).into_response();
-        }
+    let icon = match favicon::favicon_png(&data) {
+        Ok(icon) => icon,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("{:#}", e)).into_response(),
+    };
+
+    let state = state.read().await;
+    if let Err(e) = favicon::save_logo(&state.data_dir, &data, &icon) {
+        return (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response();
     }
-    
-    (StatusCode::BAD_REQUEST, "No logo file provided").into_response()
+
+    (StatusCode::SEE_OTHER, [("Location", state.url("/settings"))]).into_response()
+}
+
+/// Read-only data directory location and disk usage
+fn storage_html(state: &AppState) -> String {
+    let usage = state.disk_usage.get(&state.data_dir);
+    let data_dir = state.data_dir.canonicalize().unwrap_or_else(|_| state.data_dir.clone());
+    let disk = match (usage.used_bytes(), usage.available_bytes, usage.total_bytes) {
+        (Some(used), Some(available), Some(total)) => format!(
+            "{} free of {} ({} used)", format_bytes(available), format_bytes(total), format_bytes(used)
+        ),
+        _ => "unknown".to_string(),
+    };
+    format!(
+        r#"<div style="margin-bottom: 48px; padding: 24px; background: var(--bg-primary); border-radius: var(--radius); border: 1px solid var(--border);">
+                <h2 style="margin-bottom: 16px; font-size: 1.25rem;">Storage</h2>
+                <p style="margin-bottom: 8px;">Data directory: <code>{}</code></p>
+                <p style="margin-bottom: 8px;">Articles: {} • Search index: {}</p>
+                <p style="margin-bottom: 8px;">Disk: {}</p>
+                <p style="font-size: 0.85rem; color: var(--text-muted);">The data directory is set with <code>--data</code> when the server starts. Sizes are refreshed every minute.</p>
+            </div>"#,
+        html_escape(&data_dir.to_string_lossy()),
+        format_bytes(usage.articles_bytes),
+        format_bytes(usage.index_bytes),
+        disk,
+    )
 }
 
 fn settings_html(state: &AppState) -> String {
@@ -1583,6 +2831,17 @@ fn settings_html(state: &AppState) -> String {
         _ => (3, 0, ""),
     };
 
+    let now = chrono::Utc::now();
+    let pause_status = match config.paused_until {
+        Some(until) if config.is_paused_at(&now) => format!(
+            r#"<p style="margin-bottom: 16px;">Scheduled updates are paused until <strong>{}</strong>.</p>
+                <button type="button" id="resume-updates" data-url="{}" style="background: var(--accent); color: white; border: none; padding: 12px 24px; border-radius: 99px; font-size: 0.95rem; font-weight: 600; cursor: pointer;">Resume now</button>"#,
+            until.format("%Y-%m-%d %H:%M UTC"),
+            state.url("/api/v1/update/resume"),
+        ),
+        _ => r#"<p style="color: var(--text-muted);">Scheduled updates are not paused.</p>"#.to_string(),
+    };
+
     format!(r#"
         <div class="article">
             <h1>⚙️ Settings</h1>
@@ -1592,20 +2851,23 @@ fn settings_html(state: &AppState) -> String {
                 <div style="display: flex; gap: 24px; align-items: center; flex-wrap: wrap;">
                     <div style="text-align: center;">
                         <div style="margin-bottom: 8px; font-weight: 500; font-size: 0.9rem; color: var(--text-muted);">Current Logo</div>
-                        <img src="/logo" alt="Current Logo" style="height: 64px; width: auto; border: 1px solid var(--border); border-radius: 8px; padding: 8px; background: white;">
+                        <img src="{base_path}/logo" alt="Current Logo" style="height: 64px; width: auto; border: 1px solid var(--border); border-radius: 8px; padding: 8px; background: white;">
                     </div>
-                    <form action="/settings/logo" method="POST" enctype="multipart/form-data" style="flex: 1; min-width: 300px;">
+                    <form action="{base_path}/settings/logo" method="POST" enctype="multipart/form-data" style="flex: 1; min-width: 300px;">
                         <label style="display: block; margin-bottom: 8px; font-weight: 500;">Upload Custom Logo</label>
                         <div style="display: flex; gap: 12px; flex-wrap: wrap;">
                             <input type="file" name="logo" accept="image/png,image/jpeg" class="search-input" style="padding: 8px; flex: 1;">
                             <button type="submit" style="background: var(--accent); color: white; border: none; padding: 12px 24px; border-radius: 99px; font-size: 0.95rem; font-weight: 600; cursor: pointer;">Upload</button>
                         </div>
-                        <p style="margin-top: 8px; font-size: 0.85rem; color: var(--text-muted);">Recommended: PNG or JPG, square aspect ratio.</p>
+                        <p style="margin-top: 8px; font-size: 0.85rem; color: var(--text-muted);">PNG or JPG up to 5 MB and 4096 pixels per side; square works best.</p>
                     </form>
+                    {logo_reset}
                 </div>
             </div>
 
-            <form action="/settings" method="POST" style="max-width: 600px;">
+            {storage}
+
+            <form action="{base_path}/settings" method="POST" style="max-width: 600px;">
                 <div style="margin-bottom: 24px;">
                     <label style="display: flex; align-items: center; gap: 12px; font-size: 1.1rem; font-weight: 500;">
                         <input type="checkbox" name="enabled" {} style="width: 20px; height: 20px;">
@@ -1620,7 +2882,7 @@ fn settings_html(state: &AppState) -> String {
 
                 <div style="margin-bottom: 24px;">
                     <label style="display: block; margin-bottom: 8px; font-weight: 500;">Update Frequency</label>
-                    <select name="frequency" class="search-input" style="width: 100%;" onchange="toggleDay(this.value)">
+                    <select name="frequency" id="update-frequency" class="search-input" style="width: 100%;">
                         <option value="Daily" {}>Daily</option>
                         <option value="Weekly" {}>Weekly</option>
                         <option value="Monthly" {}>Monthly</option>
@@ -1658,93 +2920,39 @@ fn settings_html(state: &AppState) -> String {
 
             <hr style="margin: 48px 0; border: none; border-top: 1px solid var(--border);">
 
+            <h2>Pause Updates</h2>
+            <div style="margin-top: 16px; max-width: 600px;">
+                {}
+                <label style="display: block; margin: 16px 0 8px; font-weight: 500;">Pause until (UTC)</label>
+                <div style="display: flex; gap: 12px; flex-wrap: wrap;">
+                    <input type="datetime-local" id="pause-until" class="search-input" style="flex: 1;">
+                    <button type="button" id="pause-updates" data-url="{base_path}/api/v1/update/pause" style="background: var(--bg-secondary); color: var(--text-primary); border: 1px solid var(--border); padding: 12px 24px; border-radius: 99px; font-size: 0.95rem; font-weight: 600; cursor: pointer;">Pause</button>
+                </div>
+                <p style="margin-top: 8px; font-size: 0.85rem; color: var(--text-muted);">The schedule is kept and applies again once the pause ends. Manual updates still run.</p>
+            </div>
+
+            <hr style="margin: 48px 0; border: none; border-top: 1px solid var(--border);">
+
             <h2>Update Status</h2>
-            <div id="update-status" style="margin-top: 16px; padding: 24px; background: var(--bg-primary); border-radius: var(--radius); border: 1px solid var(--border);">
+            <div id="update-status" data-url="{base_path}/api/v1/update/status" style="margin-top: 16px; padding: 24px; background: var(--bg-primary); border-radius: var(--radius); border: 1px solid var(--border);">
                 Loading status...
             </div>
             
-            <button onclick="triggerUpdate()" style="margin-top: 16px; background: var(--bg-secondary); color: var(--text-primary); border: 1px solid var(--border); padding: 12px 24px; border-radius: 99px; font-size: 1rem; font-weight: 600; cursor: pointer;">
+            <button type="button" id="trigger-update" data-url="{base_path}/api/v1/update/trigger" style="margin-top: 16px; background: var(--bg-secondary); color: var(--text-primary); border: 1px solid var(--border); padding: 12px 24px; border-radius: 99px; font-size: 1rem; font-weight: 600; cursor: pointer;">
                 Check for Updates Now
             </button>
+            <button type="button" id="cancel-update" data-url="{base_path}/api/v1/update/cancel" style="display: none; margin-top: 16px; margin-left: 8px; background: #ef4444; color: white; border: none; padding: 12px 24px; border-radius: 99px; font-size: 1rem; font-weight: 600; cursor: pointer;">
+                Cancel Update
+            </button>
 
             <hr style="margin: 48px 0; border: none; border-top: 1px solid var(--border);">
 
             <h2>Update History</h2>
-            <div id="update-history" style="margin-top: 16px; padding: 24px; background: var(--bg-primary); border-radius: var(--radius); border: 1px solid var(--border); max-height: 300px; overflow-y: auto; font-family: monospace; font-size: 0.9rem;">
+            <div id="update-history" data-url="{base_path}/api/v1/update/history" style="margin-top: 16px; padding: 24px; background: var(--bg-primary); border-radius: var(--radius); border: 1px solid var(--border); max-height: 300px; overflow-y: auto; font-size: 0.9rem;">
                 Loading history...
             </div>
 
-            <script>
-                function toggleDay(freq) {{
-                    const daySelect = document.getElementById('day-select');
-                    daySelect.style.display = freq === 'Weekly' ? 'block' : 'none';
-                }}
-
-                async function loadStatus() {{
-                    const res = await fetch('/api/update/status');
-                    const status = await res.json();
-                    const el = document.getElementById('update-status');
-                    
-                    let html = `
-                        <div style="display: grid; gap: 8px;">
-                            <div><strong>Status:</strong> ${{status.current_status}}</div>
-                            <div><strong>Last Check:</strong> ${{status.last_check || 'Never'}}</div>
-                            <div><strong>Last Update:</strong> ${{status.last_update || 'Never'}}</div>
-                        </div>
-                    `;
-                    
-                    if (status.error_message) {{
-                        html += `<div style="color: #ef4444; margin-top: 8px;">Error: ${{status.error_message}}</div>`;
-                    }}
-                    
-                    if (status.progress > 0 && status.progress < 100) {{
-                        html += `
-                            <div style="margin-top: 12px; height: 8px; background: var(--border); border-radius: 4px; overflow: hidden;">
-                                <div style="height: 100%; width: ${{status.progress}}%; background: var(--accent);"></div>
-                            </div>
-                            <div style="text-align: right; font-size: 0.9rem; margin-top: 4px;">${{status.progress.toFixed(1)}}%</div>
-                        `;
-                    }}
-                    
-                    el.innerHTML = html;
-                }}
-
-                async function loadHistory() {{
-                    try {{
-                        const res = await fetch('/api/update/history');
-                        const history = await res.json();
-                        const el = document.getElementById('update-history');
-                        
-                        if (history.length === 0) {{
-                            el.innerHTML = '<div style="color: var(--text-muted);">No update history found.</div>';
-                            return;
-                        }}
-                        
-                        el.innerHTML = history.map(line => `<div>${{line}}</div>`).join('');
-                    }} catch (e) {{
-                        console.error('Failed to load history:', e);
-                    }}
-                }}
-
-                async function triggerUpdate() {{
-                    if (!confirm('Are you sure you want to start an update check?')) return;
-                    
-                    try {{
-                        const res = await fetch('/api/update/trigger', {{ method: 'POST' }});
-                        const data = await res.json();
-                        alert('Update started!');
-                        loadStatus();
-                    }} catch (e) {{
-                        alert('Failed to trigger update: ' + e);
-                    }}
-                }}
-
-                // Initial load
-                loadStatus();
-                loadHistory();
-                // Poll every 5 seconds
-                setInterval(loadStatus, 5000);
-            </script>
+            <script src="/static/settings.js" defer></script>
         </div>
     "#,
         if config.enabled { "checked" } else { "" },
@@ -1761,6 +2969,19 @@ fn settings_html(state: &AppState) -> String {
         if day_str == "Friday" { "selected" } else { "" },
         if day_str == "Saturday" { "selected" } else { "" },
         hour,
-        minute
+        minute,
+        pause_status,
+        base_path = state.base_path,
+        storage = storage_html(state),
+        logo_reset = if state.data_dir.join(favicon::CUSTOM_LOGO_FILE).exists() {
+            format!(
+                r#"<form action="{}" method="POST">
+                        <button type="submit" style="background: transparent; color: var(--text-muted); border: 1px solid var(--border); padding: 10px 20px; border-radius: 99px; font-size: 0.9rem; cursor: pointer;">Reset to default logo</button>
+                    </form>"#,
+                state.url("/settings/logo/reset")
+            )
+        } else {
+            String::new()
+        },
     )
 }
@@ -0,0 +1,173 @@
+// Settings page helpers:
+// - The day of week picker is only shown for weekly updates.
+// - Update status is polled every 5 seconds and the history loaded once.
+// - Buttons start, cancel, pause and resume updates through the API; their
+//   endpoints come from data-url attributes so the base path is respected.
+(function () {
+    'use strict';
+
+    const escape = function (s) {
+        return String(s ?? '').replace(/[&<>"']/g, function (c) {
+            return { '&': '&amp;', '<': '&lt;', '>': '&gt;', '"': '&quot;', "'": '&#x27;' }[c];
+        });
+    };
+    const formatBytes = function (b) {
+        if (b == null) return '';
+        if (b >= 1073741824) return (b / 1073741824).toFixed(2) + ' GB';
+        if (b >= 1048576) return (b / 1048576).toFixed(2) + ' MB';
+        return (b / 1024).toFixed(2) + ' KB';
+    };
+    const formatDuration = function (s) {
+        if (s == null) return '';
+        if (s >= 3600) return Math.floor(s / 3600) + 'h ' + Math.floor(s % 3600 / 60) + 'm';
+        if (s >= 60) return Math.floor(s / 60) + 'm ' + Math.floor(s % 60) + 's';
+        return s.toFixed(1) + 's';
+    };
+
+    async function loadStatus() {
+        const el = document.getElementById('update-status');
+        const res = await fetch(el.dataset.url);
+        const status = await res.json();
+
+        let html = `
+            <div style="display: grid; gap: 8px;">
+                <div><strong>Status:</strong> ${escape(status.current_status)}</div>
+                <div><strong>Last Check:</strong> ${escape(status.last_check || 'Never')}</div>
+                <div><strong>Last Update:</strong> ${escape(status.last_update || 'Never')}</div>
+            </div>
+        `;
+
+        if (status.error_message) {
+            html += `<div style="color: #ef4444; margin-top: 8px;">Error: ${escape(status.error_message)}</div>`;
+        }
+
+        if (status.progress > 0 && status.progress < 100) {
+            html += `
+                <div style="margin-top: 12px; height: 8px; background: var(--border); border-radius: 4px; overflow: hidden;">
+                    <div style="height: 100%; width: ${status.progress}%; background: var(--accent);"></div>
+                </div>
+                <div style="text-align: right; font-size: 0.9rem; margin-top: 4px;">${status.progress.toFixed(1)}%</div>
+            `;
+        }
+
+        el.innerHTML = html;
+
+        const running = ['Checking', 'Downloading', 'Extracting', 'Indexing'].includes(status.current_status);
+        document.getElementById('cancel-update').style.display = running ? 'inline-block' : 'none';
+    }
+
+    async function loadHistory() {
+        const el = document.getElementById('update-history');
+        try {
+            const res = await fetch(el.dataset.url);
+            const history = await res.json();
+
+            if (history.length === 0) {
+                el.innerHTML = '<div style="color: var(--text-muted);">No update history found.</div>';
+                return;
+            }
+
+            const rows = history.map(entry => `
+                <tr style="border-top: 1px solid var(--border);">
+                    <td style="padding: 6px 8px;">${escape(new Date(entry.timestamp).toLocaleString())}</td>
+                    <td style="padding: 6px 8px; color: ${entry.status === 'Success' ? '#22c55e' : '#ef4444'};">${escape(entry.status)}</td>
+                    <td style="padding: 6px 8px;">${escape(entry.language)}</td>
+                    <td style="padding: 6px 8px;">${escape(formatDuration(entry.duration_secs))}</td>
+                    <td style="padding: 6px 8px;">${escape(formatBytes(entry.bytes))}</td>
+                    <td style="padding: 6px 8px;">${escape(entry.error)}</td>
+                </tr>
+            `).join('');
+
+            el.innerHTML = `
+                <table style="width: 100%; border-collapse: collapse; text-align: left;">
+                    <thead>
+                        <tr style="color: var(--text-muted);">
+                            <th style="padding: 6px 8px;">Finished</th>
+                            <th style="padding: 6px 8px;">Status</th>
+                            <th style="padding: 6px 8px;">Language</th>
+                            <th style="padding: 6px 8px;">Duration</th>
+                            <th style="padding: 6px 8px;">Size</th>
+                            <th style="padding: 6px 8px;">Error</th>
+                        </tr>
+                    </thead>
+                    <tbody>${rows}</tbody>
+                </table>
+            `;
+        } catch (e) {
+            console.error('Failed to load history:', e);
+        }
+    }
+
+    // POST to a button's endpoint, showing the API's error message on failure
+    async function post(url) {
+        const res = await fetch(url, { method: 'POST' });
+        if (!res.ok) {
+            let message = res.statusText;
+            try {
+                message = (await res.json()).error || message;
+            } catch (e) {
+                // Not JSON; keep the status text
+            }
+            alert(message);
+        }
+        return res.ok;
+    }
+
+    async function triggerUpdate(button) {
+        if (!confirm('Are you sure you want to start an update check?')) return;
+        try {
+            if (await post(button.dataset.url)) alert('Update started!');
+            loadStatus();
+        } catch (e) {
+            alert('Failed to trigger update: ' + e);
+        }
+    }
+
+    async function cancelUpdate(button) {
+        if (!confirm('Are you sure you want to cancel the running update?')) return;
+        try {
+            await post(button.dataset.url);
+            loadStatus();
+        } catch (e) {
+            alert('Failed to cancel update: ' + e);
+        }
+    }
+
+    async function pauseUpdates(button) {
+        const until = document.getElementById('pause-until').value;
+        if (!until) {
+            alert('Choose when updates should resume.');
+            return;
+        }
+        if (await post(button.dataset.url + '?until=' + encodeURIComponent(until))) location.reload();
+    }
+
+    async function resumeUpdates(button) {
+        if (await post(button.dataset.url)) location.reload();
+    }
+
+    const actions = {
+        'trigger-update': triggerUpdate,
+        'cancel-update': cancelUpdate,
+        'pause-updates': pauseUpdates,
+        'resume-updates': resumeUpdates,
+    };
+
+    document.addEventListener('click', function (event) {
+        const button = event.target.closest('button[id]');
+        if (button && actions[button.id]) actions[button.id](button);
+    });
+
+    const frequency = document.getElementById('update-frequency');
+    if (frequency) {
+        frequency.addEventListener('change', function () {
+            document.getElementById('day-select').style.display = frequency.value === 'Weekly' ? 'block' : 'none';
+        });
+    }
+
+    if (document.getElementById('update-status')) {
+        loadStatus();
+        loadHistory();
+        setInterval(loadStatus, 5000);
+    }
+})();
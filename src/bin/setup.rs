@@ -505,12 +505,14 @@ fn setup_auto_update(
     println!("\n⏰ Setting up Auto-Update...");
     
     // 1. Create and save update config
-    let mut config = UpdateConfig::default();
-    config.enabled = true;
-    config.schedule = schedule;
-    config.language = lang.to_string();
-    config.data_dir = data_dir.to_path_buf();
-    config.max_bandwidth = max_bandwidth;
+    let mut config = UpdateConfig {
+        enabled: true,
+        schedule,
+        language: lang.to_string(),
+        data_dir: data_dir.to_path_buf(),
+        max_bandwidth,
+        ..Default::default()
+    };
     config.retry_config.max_retries = retry_count;
     
     config.save(UpdateConfig::config_path(data_dir))?;
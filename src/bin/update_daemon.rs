@@ -9,6 +9,7 @@ use clap::Parser;
 use chrono::Utc;
 use tokio::time::sleep;
 
+use rustipedia::update_manager::request_reload;
 use rustipedia::{UpdateManager, UpdateConfig, Status};
 
 #[derive(Parser)]
@@ -19,7 +20,8 @@ struct Cli {
     #[arg(short, long, default_value = "wikipedia")]
     data_dir: PathBuf,
 
-    /// Check interval in minutes
+    /// Check interval in minutes; a scheduled time is still caught this long
+    /// after it passes (set it to the cron interval when using --once)
     #[arg(short, long, default_value = "60")]
     interval: u64,
 
@@ -30,6 +32,14 @@ struct Cli {
     /// Force update immediately (ignore schedule)
     #[arg(long)]
     force: bool,
+
+    /// Server reload URL to call after a successful update, e.g. http://127.0.0.1:8080/api/v1/reload
+    #[arg(long)]
+    reload_url: Option<String>,
+
+    /// Admin token for --reload-url, if the server has one
+    #[arg(long)]
+    reload_token: Option<String>,
 }
 
 #[tokio::main]
@@ -48,7 +58,7 @@ async fn main() -> Result<()> {
     tracing::info!("Data directory: {}", cli.data_dir.display());
 
     // Load the update manager
-    let manager = UpdateManager::load(&cli.data_dir)
+    let manager = UpdateManager::load_async(&cli.data_dir).await
         .context("Failed to load update manager")?;
 
     // Get the config to check if updates are enabled
@@ -68,13 +78,13 @@ async fn main() -> Result<()> {
 
     if cli.force {
         tracing::info!("Force update requested, ignoring schedule...");
-        perform_update(&manager).await?;
+        perform_update(&manager, &cli).await?;
         return Ok(());
     }
 
     if cli.once {
         tracing::info!("Running in single-check mode...");
-        check_and_update(&manager, &config).await?;
+        check_and_update(&manager, &config, &cli).await?;
         return Ok(());
     }
 
@@ -82,8 +92,15 @@ async fn main() -> Result<()> {
     tracing::info!("Entering daemon mode. Checking every {} minutes.", cli.interval);
     tracing::info!("Update schedule: {}", config.schedule.to_human_string());
 
+    let mut config = config;
     loop {
-        match check_and_update(&manager, &config).await {
+        // Pick up changes made from the settings page, e.g. a pause
+        match UpdateConfig::load(&config_path) {
+            Ok(latest) => config = latest,
+            Err(e) => tracing::warn!("Failed to reload update configuration: {}", e),
+        }
+
+        match check_and_update(&manager, &config, &cli).await {
             Ok(_) => {}
             Err(e) => {
                 tracing::error!("Error during update check: {}", e);
@@ -96,7 +113,7 @@ async fn main() -> Result<()> {
 }
 
 /// Check if an update should run and execute it if needed
-async fn check_and_update(manager: &UpdateManager, config: &UpdateConfig) -> Result<()> {
+async fn check_and_update(manager: &UpdateManager, config: &UpdateConfig, cli: &Cli) -> Result<()> {
     // Check current status
     let status = manager.get_status().await;
 
@@ -119,7 +136,7 @@ async fn check_and_update(manager: &UpdateManager, config: &UpdateConfig) -> Res
     }
 
     // Check if it's time to update based on the schedule
-    if !should_update_now(config, &status) {
+    if !should_update_now(config, &status, cli.interval) {
         tracing::debug!("Not time to update yet");
         return Ok(());
     }
@@ -130,7 +147,7 @@ async fn check_and_update(manager: &UpdateManager, config: &UpdateConfig) -> Res
     match manager.check_for_updates().await {
         Ok(true) => {
             tracing::info!("Updates available, starting update process...");
-            perform_update(manager).await?;
+            perform_update(manager, cli).await?;
         }
         Ok(false) => {
             tracing::info!("No updates needed");
@@ -144,13 +161,19 @@ async fn check_and_update(manager: &UpdateManager, config: &UpdateConfig) -> Res
     Ok(())
 }
 
-/// Perform the actual update
-async fn perform_update(manager: &UpdateManager) -> Result<()> {
+/// Perform the actual update, then have the server load the new data
+async fn perform_update(manager: &UpdateManager, cli: &Cli) -> Result<()> {
     tracing::info!("Starting Wikipedia update...");
 
     match manager.perform_update().await {
         Ok(_) => {
             tracing::info!("✅ Update completed successfully!");
+            if let Some(ref url) = cli.reload_url {
+                match request_reload(url, cli.reload_token.as_deref()).await {
+                    Ok(()) => tracing::info!("Server reloaded the updated data"),
+                    Err(e) => tracing::warn!("Server reload failed; restart it to serve the new data: {:#}", e),
+                }
+            }
             Ok(())
         }
         Err(e) => {
@@ -165,64 +188,11 @@ async fn perform_update(manager: &UpdateManager) -> Result<()> {
 }
 
 /// Determine if an update should run now based on the schedule
-fn should_update_now(config: &UpdateConfig, status: &rustipedia::UpdateStatus) -> bool {
-    use rustipedia::UpdateSchedule;
-    use chrono::Timelike;
-
+fn should_update_now(config: &UpdateConfig, status: &rustipedia::UpdateStatus, interval: u64) -> bool {
     let now = Utc::now();
-    let current_hour = now.hour() as u8;
-    let current_minute = now.minute() as u8;
-
-    // Check if we've already updated recently
-    if let Some(last_success) = status.last_success {
-        let hours_since_update = (now - last_success).num_hours();
-        
-        // Don't update more than once per day
-        if hours_since_update < 23 {
-            return false;
-        }
-    }
-
-    // Check the schedule
-    match &config.schedule {
-        UpdateSchedule::Daily { hour, minute } => {
-            // Update if we're within 5 minutes of the scheduled time
-            current_hour == *hour && current_minute >= *minute && current_minute < minute + 5
-        }
-        UpdateSchedule::Weekly { day, hour, minute } => {
-            use chrono::Datelike;
-            let current_day = now.weekday();
-            
-            // Convert our Weekday to chrono's Weekday
-            let scheduled_day = match day {
-                rustipedia::Weekday::Sunday => chrono::Weekday::Sun,
-                rustipedia::Weekday::Monday => chrono::Weekday::Mon,
-                rustipedia::Weekday::Tuesday => chrono::Weekday::Tue,
-                rustipedia::Weekday::Wednesday => chrono::Weekday::Wed,
-                rustipedia::Weekday::Thursday => chrono::Weekday::Thu,
-                rustipedia::Weekday::Friday => chrono::Weekday::Fri,
-                rustipedia::Weekday::Saturday => chrono::Weekday::Sat,
-            };
-
-            current_day == scheduled_day 
-                && current_hour == *hour 
-                && current_minute >= *minute 
-                && current_minute < minute + 5
-        }
-        UpdateSchedule::Monthly { day, hour, minute } => {
-            use chrono::Datelike;
-            let current_day = now.day() as u8;
-            
-            current_day == *day 
-                && current_hour == *hour 
-                && current_minute >= *minute 
-                && current_minute < minute + 5
-        }
-        #[cfg(unix)]
-        UpdateSchedule::Custom { cron_expression: _ } => {
-            // TODO: Implement cron expression parsing
-            // For now, just update once per day
-            current_hour == 3 && current_minute < 5
-        }
+    if let Some(until) = config.paused_until.filter(|_| config.is_paused_at(&now)) {
+        tracing::debug!("Updates are paused until {}", until);
+        return false;
     }
+    config.should_update_at(&now, status.last_check, status.last_success, chrono::Duration::minutes(interval as i64))
 }
@@ -3,16 +3,19 @@
 //! Checks for broken internal links in the downloaded articles.
 
 use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::BufRead;
 use std::path::PathBuf;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use regex::Regex;
 use once_cell::sync::Lazy;
+use serde::Serialize;
 
-use rustipedia::Article;
+use rustipedia::article::normalize_title;
+use rustipedia::jsonl;
+use rustipedia::prune::{classify_link, LinkStatus};
+use rustipedia::{Article, RedirectMap};
 
 static LINK_PIPE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[\[([^|\]]+)\|([^\]]+)\]\]").unwrap());
 
@@ -31,23 +34,73 @@ struct Cli {
     /// Maximum number of broken links to display
     #[arg(short, long, default_value = "20")]
     limit: usize,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+
+    /// Exit with an error if more than this fraction of links is broken (0.0-1.0)
+    #[arg(long)]
+    max_broken_ratio: Option<f64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable report
+    Text,
+    /// Single JSON object on stdout
+    Json,
+}
+
+/// Structured link report (the `--format json` output)
+#[derive(Serialize)]
+struct LinkReport {
+    total_articles: usize,
+    articles_with_links: usize,
+    total_links: usize,
+    valid_links: usize,
+    /// Links to redirects whose target is an extracted article
+    redirect_links: usize,
+    broken_links: usize,
+    broken_ratio: f64,
+    unique_broken_targets: usize,
+    /// Most common broken targets, by occurrence
+    top_broken_targets: Vec<BrokenTarget>,
+    examples: Vec<BrokenLinkExample>,
+}
+
+#[derive(Serialize)]
+struct BrokenTarget {
+    target: String,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct BrokenLinkExample {
+    article: String,
+    target: String,
+    text: String,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let text = cli.format == OutputFormat::Text;
     
-    println!("🔍 Loading articles and analyzing links...\n");
+    if text {
+        println!("🔍 Loading articles and analyzing links...\n");
+    }
     
-    let articles_path = cli.data.join("articles.jsonl");
-    if !articles_path.exists() {
-        anyhow::bail!("Articles file not found: {:?}", articles_path);
+    let articles_files = jsonl::articles_files(&cli.data);
+    if articles_files.is_empty() {
+        anyhow::bail!("Articles file not found: {:?}", jsonl::articles_path(&cli.data));
     }
     
-    // First pass: build title index (case-insensitive)
-    println!("📚 Building article index...");
+    // First pass: build title index
+    if text {
+        println!("📚 Building article index...");
+    }
     let mut title_index: HashSet<String> = HashSet::new();
-    let file = File::open(&articles_path)?;
-    let reader = BufReader::new(file);
+    let reader = jsonl::open_all(&articles_files)?;
     
     for line in reader.lines() {
         let line = line?;
@@ -55,20 +108,24 @@ fn main() -> Result<()> {
             continue;
         }
         let article: Article = serde_json::from_str(&line)?;
-        title_index.insert(article.title.to_lowercase().replace('_', " "));
+        title_index.insert(normalize_title(&article.title));
     }
     
-    println!("   Found {} articles\n", title_index.len());
+    let redirects = RedirectMap::load_from_dir(&cli.data)?;
     
-    // Second pass: check all links
-    println!("🔗 Scanning links in articles...");
-    let file = File::open(&articles_path)?;
-    let reader = BufReader::new(file);
+    if text {
+        println!("   Found {} articles", title_index.len());
+        println!("   Found {} redirects\n", redirects.len());
+        // Second pass: check all links
+        println!("🔗 Scanning links in articles...");
+    }
+    let reader = jsonl::open_all(&articles_files)?;
     
     let mut total_articles = 0;
     let mut articles_with_links = 0;
     let mut total_links = 0;
     let mut valid_links = 0;
+    let mut redirect_links = 0;
     let mut broken_links: HashMap<String, usize> = HashMap::new();
     let mut broken_link_examples: Vec<(String, String, String)> = Vec::new(); // (article, link_target, link_text)
     
@@ -90,19 +147,19 @@ fn main() -> Result<()> {
             
             let target = cap.get(1).unwrap().as_str();
             let link_text = cap.get(2).unwrap().as_str();
-            let normalized_target = target.to_lowercase().replace('_', " ");
-            
-            if title_index.contains(&normalized_target) {
-                valid_links += 1;
-            } else {
-                *broken_links.entry(target.to_string()).or_insert(0) += 1;
-                
-                if broken_link_examples.len() < cli.limit {
-                    broken_link_examples.push((
-                        article.title.clone(),
-                        target.to_string(),
-                        link_text.to_string(),
-                    ));
+            match classify_link(target, &title_index, &redirects) {
+                LinkStatus::Valid => valid_links += 1,
+                LinkStatus::Redirect => redirect_links += 1,
+                LinkStatus::Broken => {
+                    *broken_links.entry(target.to_string()).or_insert(0) += 1;
+                    
+                    if broken_link_examples.len() < cli.limit {
+                        broken_link_examples.push((
+                            article.title.clone(),
+                            target.to_string(),
+                            link_text.to_string(),
+                        ));
+                    }
                 }
             }
         }
@@ -112,38 +169,81 @@ fn main() -> Result<()> {
         }
     }
     
-    // Print statistics
+    let mut broken_vec: Vec<_> = broken_links.iter().collect();
+    broken_vec.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    
+    let report = LinkReport {
+        total_articles,
+        articles_with_links,
+        total_links,
+        valid_links,
+        redirect_links,
+        broken_links: total_links - valid_links - redirect_links,
+        broken_ratio: if total_links > 0 {
+            (total_links - valid_links - redirect_links) as f64 / total_links as f64
+        } else {
+            0.0
+        },
+        unique_broken_targets: broken_links.len(),
+        top_broken_targets: broken_vec.iter().take(20)
+            .map(|(target, count)| BrokenTarget { target: target.to_string(), count: **count })
+            .collect(),
+        examples: broken_link_examples.into_iter()
+            .map(|(article, target, text)| BrokenLinkExample { article, target, text })
+            .collect(),
+    };
+    
+    match cli.format {
+        OutputFormat::Text => print_report(&report, cli.verbose),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    
+    if let Some(max_ratio) = cli.max_broken_ratio {
+        if report.broken_ratio > max_ratio {
+            anyhow::bail!(
+                "Broken link ratio {:.3} exceeds --max-broken-ratio {:.3}",
+                report.broken_ratio, max_ratio
+            );
+        }
+    }
+    
+    Ok(())
+}
+
+fn print_report(report: &LinkReport, verbose: bool) {
+    let percent = |n: usize| {
+        if report.total_links > 0 { (n as f64 / report.total_links as f64) * 100.0 } else { 0.0 }
+    };
+    
     println!("\n╔══════════════════════════════════════════════════════════════════╗");
     println!("║                      📊 LINK ANALYSIS RESULTS                     ║");
     println!("╠══════════════════════════════════════════════════════════════════╣");
-    println!("║  Total articles:           {:>8}                              ║", total_articles);
-    println!("║  Articles with links:      {:>8}                              ║", articles_with_links);
-    println!("║  Total internal links:     {:>8}                              ║", total_links);
+    println!("║  Total articles:           {:>8}                              ║", report.total_articles);
+    println!("║  Articles with links:      {:>8}                              ║", report.articles_with_links);
+    println!("║  Total internal links:     {:>8}                              ║", report.total_links);
     println!("║  Valid links:              {:>8} ({:>5.1}%)                   ║", 
-        valid_links, 
-        if total_links > 0 { (valid_links as f64 / total_links as f64) * 100.0 } else { 0.0 }
+        report.valid_links, percent(report.valid_links)
+    );
+    println!("║  Resolved via redirect:    {:>8} ({:>5.1}%)                   ║", 
+        report.redirect_links, percent(report.redirect_links)
     );
     println!("║  Broken links:             {:>8} ({:>5.1}%)                   ║", 
-        total_links - valid_links,
-        if total_links > 0 { ((total_links - valid_links) as f64 / total_links as f64) * 100.0 } else { 0.0 }
+        report.broken_links, percent(report.broken_links)
     );
-    println!("║  Unique broken targets:    {:>8}                              ║", broken_links.len());
+    println!("║  Unique broken targets:    {:>8}                              ║", report.unique_broken_targets);
     println!("╚══════════════════════════════════════════════════════════════════╝");
     
-    if !broken_links.is_empty() {
+    if !report.top_broken_targets.is_empty() {
         println!("\n🔴 Most Common Broken Link Targets:");
-        let mut broken_vec: Vec<_> = broken_links.iter().collect();
-        broken_vec.sort_by(|a, b| b.1.cmp(a.1));
-        
-        for (target, count) in broken_vec.iter().take(20) {
-            println!("   /wiki/{:<40} ({} occurrences)", target, count);
+        for broken in &report.top_broken_targets {
+            println!("   /wiki/{:<40} ({} occurrences)", broken.target, broken.count);
         }
         
-        if cli.verbose && !broken_link_examples.is_empty() {
+        if verbose && !report.examples.is_empty() {
             println!("\n📝 Example Broken Links:");
-            for (article_title, target, link_text) in broken_link_examples.iter() {
-                println!("   Article: \"{}\"", article_title);
-                println!("   Link: [{}] -> /wiki/{}", link_text, target);
+            for example in &report.examples {
+                println!("   Article: \"{}\"", example.article);
+                println!("   Link: [{}] -> /wiki/{}", example.text, example.target);
                 println!();
             }
         }
@@ -159,6 +259,4 @@ fn main() -> Result<()> {
     } else {
         println!("\n✅ All links are valid!");
     }
-    
-    Ok(())
 }
@@ -1,8 +1,60 @@
 //! Rustipedia Config
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use crate::WikiLanguage;
+use crate::{WikiLanguage, DEFAULT_DUMP_BASE_URL, DEFAULT_MIN_LENGTH};
+use crate::downloader::{
+    COMPLETE_MARKER_FILE, DEFAULT_DECOMPRESS_BUFFER_SIZE, DEFAULT_EXTRACT_SPACE_MULTIPLIER, PARTIAL_ARTICLES_FILE,
+};
+
+/// Keys that may appear in a data directory's `config.json`.
+///
+/// The file is written by both `rustipedia-download` (a full [`Config`]) and
+/// `rustipedia-setup`, and read by `rustipedia-serve`.
+const KNOWN_DATA_CONFIG_KEYS: &[&str] = &[
+    "language",
+    "output_dir",
+    "max_articles",
+    "min_length",
+    "min_words",
+    "min_links",
+    "max_words",
+    "skip_download",
+    "build_index",
+    "keep_dump",
+    "keep_raw_markup",
+    "prefer_xz",
+    "dump_file",
+    "dump_base_url",
+    "exclude_categories",
+    "include_categories",
+    "decompress_buffer_size",
+    "since",
+    "compress_output",
+    "shard_size",
+    "temp_dir",
+    "extract_space_multiplier",
+    "port",
+    "host",
+    "worker_threads",
+    "max_connections",
+    "listen_backlog",
+    "article_cache_size",
+    "browse_per_page",
+    "search_per_page",
+    "max_render_chars",
+    "max_search_results",
+    "cors_origins",
+    "admin_token",
+    "max_body_bytes",
+    "fallback_to_wikipedia",
+    "mark_broken_links",
+    "prune",
+    "auto_update",
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -12,14 +64,76 @@ pub struct Config {
     pub output_dir: PathBuf,
     /// Maximum articles to extract (0 = unlimited)
     pub max_articles: usize,
-    /// Minimum article length in characters
-    pub min_length: usize,
+    /// Minimum article length in characters; `None` uses the language's
+    /// default (see [`WikiLanguage::default_min_length`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    /// Minimum article length in words (0 = no minimum)
+    #[serde(default)]
+    pub min_words: usize,
+    /// Maximum article length in words (0 = unlimited)
+    #[serde(default)]
+    pub max_words: usize,
+    /// Minimum number of links to other articles (0 = no minimum). Counted
+    /// on the cleaned HTML before any pruning, so links to articles missing
+    /// from the dump count too.
+    #[serde(default)]
+    pub min_links: usize,
     /// Skip download if dump already exists
     pub skip_download: bool,
     /// Build search index after extraction
     pub build_index: bool,
     /// Keep the raw bz2 dump file after extraction
     pub keep_dump: bool,
+    /// Download the XZ-compressed dump instead of bz2
+    #[serde(default)]
+    pub prefer_xz: bool,
+    /// Extract from this dump file instead of the default download location
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dump_file: Option<PathBuf>,
+    /// Download from this server instead of [`DEFAULT_DUMP_BASE_URL`]; it
+    /// must have the same `{lang}wiki/latest/...` layout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dump_base_url: Option<String>,
+    /// Store the original wiki markup with each article (roughly doubles articles.jsonl)
+    #[serde(default)]
+    pub keep_raw_markup: bool,
+    /// Skip articles belonging to any of these categories (case-insensitive)
+    #[serde(default)]
+    pub exclude_categories: Vec<String>,
+    /// When non-empty, only keep articles in at least one of these categories.
+    /// Exclusion wins if an article matches both lists.
+    #[serde(default)]
+    pub include_categories: Vec<String>,
+    /// Read buffer between the dump decompressor and the XML parser, in bytes
+    #[serde(default = "default_decompress_buffer_size")]
+    pub decompress_buffer_size: usize,
+    /// Skip articles whose latest revision is older than this date (UTC)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<NaiveDate>,
+    /// Write `articles.jsonl.gz` (gzip) instead of `articles.jsonl`
+    #[serde(default)]
+    pub compress_output: bool,
+    /// Split the articles into `articles.000.jsonl`, `articles.001.jsonl`, ...
+    /// of this many articles each (0 = a single file)
+    #[serde(default)]
+    pub shard_size: usize,
+    /// Write the in-progress download and pruned articles here instead of
+    /// the output directory, moving them there once finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp_dir: Option<PathBuf>,
+    /// Extraction needs this many times the dump size free in the output
+    /// directory, or it fails before writing anything
+    #[serde(default = "default_extract_space_multiplier")]
+    pub extract_space_multiplier: f64,
+}
+
+fn default_decompress_buffer_size() -> usize {
+    DEFAULT_DECOMPRESS_BUFFER_SIZE
+}
+
+fn default_extract_space_multiplier() -> f64 {
+    DEFAULT_EXTRACT_SPACE_MULTIPLIER
 }
 
 impl Default for Config {
@@ -28,10 +142,25 @@ impl Default for Config {
             language: "simple".to_string(),
             output_dir: PathBuf::from("wikipedia"),
             max_articles: 0,
-            min_length: 200,
+            min_length: None,
+            min_words: 0,
+            max_words: 0,
+            min_links: 0,
             skip_download: false,
             build_index: true,
             keep_dump: false,
+            prefer_xz: false,
+            dump_file: None,
+            dump_base_url: None,
+            keep_raw_markup: false,
+            exclude_categories: Vec::new(),
+            include_categories: Vec::new(),
+            decompress_buffer_size: DEFAULT_DECOMPRESS_BUFFER_SIZE,
+            since: None,
+            compress_output: false,
+            shard_size: 0,
+            temp_dir: None,
+            extract_space_multiplier: DEFAULT_EXTRACT_SPACE_MULTIPLIER,
         }
     }
 }
@@ -60,21 +189,173 @@ impl Config {
         self
     }
 
-    /// Set minimum article length
+    /// Set minimum article length, in characters
     pub fn with_min_length(mut self, min: usize) -> Self {
-        self.min_length = min;
+        self.min_length = Some(min);
+        self
+    }
+
+    /// Minimum article length in characters: the configured one, else the
+    /// language's default ([`DEFAULT_MIN_LENGTH`] for custom dumps)
+    pub fn min_length(&self) -> usize {
+        self.min_length.unwrap_or_else(|| {
+            WikiLanguage::from_code(&self.language).map_or(DEFAULT_MIN_LENGTH, |lang| lang.default_min_length())
+        })
+    }
+
+    /// Only extract articles with at least `min` and at most `max` words
+    /// (0 disables either bound)
+    pub fn with_word_range(mut self, min: usize, max: usize) -> Self {
+        self.min_words = min;
+        self.max_words = max;
+        self
+    }
+
+    /// Check if a word count is within `min_words..=max_words`
+    pub fn accepts_word_count(&self, words: usize) -> bool {
+        words >= self.min_words && (self.max_words == 0 || words <= self.max_words)
+    }
+
+    /// Only extract articles with at least `min` links to other articles
+    pub fn with_min_links(mut self, min: usize) -> Self {
+        self.min_links = min;
+        self
+    }
+
+    /// Check if a link count meets `min_links`
+    pub fn accepts_link_count(&self, links: usize) -> bool {
+        links >= self.min_links
+    }
+
+    /// Only keep articles edited on or after `date`
+    pub fn with_since(mut self, date: NaiveDate) -> Self {
+        self.since = Some(date);
+        self
+    }
+
+    /// Whether an article last edited at `edited_at` passes the `since`
+    /// cutoff. Articles without a revision timestamp are kept.
+    pub fn accepts_edit_time(&self, edited_at: Option<DateTime<Utc>>) -> bool {
+        match (self.since, edited_at) {
+            (Some(since), Some(edited_at)) => edited_at.date_naive() >= since,
+            _ => true,
+        }
+    }
+
+    /// Write `articles.jsonl.gz` instead of `articles.jsonl`
+    pub fn with_compress_output(mut self, compress: bool) -> Self {
+        self.compress_output = compress;
+        self
+    }
+
+    /// Write the articles in shards of `size` articles (0 = a single file)
+    pub fn with_shard_size(mut self, size: usize) -> Self {
+        self.shard_size = size;
+        self
+    }
+
+    /// Download the XZ-compressed dump instead of bz2
+    pub fn with_prefer_xz(mut self, prefer: bool) -> Self {
+        self.prefer_xz = prefer;
+        self
+    }
+
+    /// Extract from an existing dump file (bz2, xz or plain XML)
+    pub fn with_dump_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.dump_file = Some(path.into());
+        self
+    }
+
+    /// Download dumps from a mirror at `url` (see [`Self::dump_base_url`])
+    pub fn with_dump_base_url(mut self, url: impl Into<String>) -> Self {
+        self.dump_base_url = Some(url.into());
+        self
+    }
+
+    /// Keep the original wiki markup in extracted articles
+    pub fn with_raw_markup(mut self, keep: bool) -> Self {
+        self.keep_raw_markup = keep;
+        self
+    }
+
+    /// Set categories whose articles are skipped during extraction
+    pub fn with_exclude_categories(mut self, categories: Vec<String>) -> Self {
+        self.exclude_categories = categories;
+        self
+    }
+
+    /// Set categories an article must belong to (at least one) to be extracted
+    pub fn with_include_categories(mut self, categories: Vec<String>) -> Self {
+        self.include_categories = categories;
+        self
+    }
+
+    /// Set the read buffer size between the decompressor and the XML parser
+    pub fn with_decompress_buffer_size(mut self, bytes: usize) -> Self {
+        self.decompress_buffer_size = bytes;
+        self
+    }
+
+    /// Write temporary files to `path` (e.g. a fast scratch disk) instead of the output directory
+    pub fn with_temp_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.temp_dir = Some(path.into());
         self
     }
 
+    /// Require `multiplier` times the dump size free before extracting
+    pub fn with_extract_space_multiplier(mut self, multiplier: f64) -> Self {
+        self.extract_space_multiplier = multiplier;
+        self
+    }
+
+    /// Directory for temporary files: [`Self::temp_dir`], or the output directory
+    pub fn temp_dir(&self) -> &Path {
+        self.temp_dir.as_deref().unwrap_or(&self.output_dir)
+    }
+
     /// Get the wiki language enum
     pub fn wiki_language(&self) -> WikiLanguage {
         WikiLanguage::from_code(&self.language).unwrap_or_default()
     }
 
+    /// Get the URL of the dump to download (XZ with [`Self::prefer_xz`])
+    pub fn dump_url(&self) -> String {
+        let base_url = self.dump_base_url.as_deref().unwrap_or(DEFAULT_DUMP_BASE_URL);
+        self.wiki_language().dump_url_at(base_url, self.prefer_xz)
+    }
+
+    /// Check that [`Self::dump_base_url`], if set, is an http(s) URL with
+    /// a host and no query or fragment
+    pub fn check_dump_base_url(&self) -> anyhow::Result<()> {
+        let Some(ref base_url) = self.dump_base_url else {
+            return Ok(());
+        };
+        let url = reqwest::Url::parse(base_url)
+            .with_context(|| format!("Invalid dump base URL {:?}", base_url))?;
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            anyhow::bail!("Dump base URL {:?} must be an http:// or https:// URL with a host", base_url);
+        }
+        if url.query().is_some() || url.fragment().is_some() {
+            anyhow::bail!("Dump base URL {:?} can't have a query or fragment", base_url);
+        }
+        Ok(())
+    }
+
     /// Get the path to the dump file
     pub fn dump_path(&self) -> PathBuf {
+        if let Some(ref dump_file) = self.dump_file {
+            return dump_file.clone();
+        }
         let lang = self.wiki_language();
-        self.output_dir.join(format!("{}wiki-latest-pages-articles.xml.bz2", lang.code()))
+        let extension = if self.prefer_xz { "xz" } else { "bz2" };
+        self.output_dir.join(format!("{}wiki-latest-pages-articles.xml.{}", lang.code(), extension))
+    }
+
+    /// Path the dump is downloaded to before it is moved to [`Self::dump_path`]
+    pub fn partial_dump_path(&self) -> PathBuf {
+        let mut name = self.dump_path().file_name().map(|name| name.to_os_string()).unwrap_or_default();
+        name.push(".tmp");
+        self.temp_dir().join(name)
     }
 
     /// Get the path to the articles directory
@@ -82,9 +363,33 @@ impl Config {
         self.output_dir.join("articles")
     }
 
-    /// Get the path to the JSONL data file
+    /// Get the path to the JSONL data file (`articles.jsonl.gz` with
+    /// [`Self::compress_output`], the first shard with [`Self::shard_size`])
     pub fn data_path(&self) -> PathBuf {
-        self.output_dir.join("articles.jsonl")
+        if self.shard_size > 0 {
+            return crate::jsonl::shard_path(&self.output_dir, 0, self.compress_output);
+        }
+        let path = self.output_dir.join("articles.jsonl");
+        if self.compress_output {
+            crate::jsonl::compressed_path(&path)
+        } else {
+            path
+        }
+    }
+
+    /// Get the path articles are written to until extraction completes
+    pub fn partial_data_path(&self) -> PathBuf {
+        self.output_dir.join(PARTIAL_ARTICLES_FILE)
+    }
+
+    /// Get the path to the marker written when extraction completes
+    pub fn complete_marker_path(&self) -> PathBuf {
+        self.output_dir.join(COMPLETE_MARKER_FILE)
+    }
+
+    /// Get the path to the redirect alias map
+    pub fn redirects_path(&self) -> PathBuf {
+        self.output_dir.join("redirects.json")
     }
 
     /// Get the path to the search index
@@ -118,6 +423,132 @@ impl Config {
     }
 }
 
+/// Server settings read from a data directory's `config.json`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DataDirConfig {
+    /// Wikipedia language code
+    pub language: Option<String>,
+    /// Port to listen on
+    pub port: Option<u16>,
+    /// Host to bind to
+    pub host: Option<String>,
+    /// Tokio worker threads (default: one per CPU core)
+    pub worker_threads: Option<usize>,
+    /// Maximum requests handled at once; others wait (default: unlimited)
+    pub max_connections: Option<usize>,
+    /// TCP listen backlog (default: 1024)
+    pub listen_backlog: Option<u32>,
+    /// Articles kept in the in-memory cache (default: 1000, 0 disables)
+    pub article_cache_size: Option<usize>,
+    /// Articles per page on `/browse` (default: 50)
+    pub browse_per_page: Option<usize>,
+    /// Results per page on `/search` (default: 20)
+    pub search_per_page: Option<usize>,
+    /// Rendered article HTML shown before a "Show full article" button
+    /// (default: 200000, 0 disables)
+    pub max_render_chars: Option<usize>,
+    /// Results fetched from the index per search (default: 100)
+    pub max_search_results: Option<usize>,
+    /// Origins allowed to call the API from a browser (default: any)
+    pub cors_origins: Option<Vec<String>>,
+    /// Bearer token for admin endpoints such as `/api/v1/reload`
+    /// (default: none, only local clients allowed)
+    pub admin_token: Option<String>,
+    /// Largest request body accepted, in bytes (default: 4 MB)
+    pub max_body_bytes: Option<usize>,
+    /// Directory for pruning's temporary output (default: the data directory)
+    pub temp_dir: Option<PathBuf>,
+    /// Link missing articles to the live Wikipedia (default: off)
+    pub fallback_to_wikipedia: Option<bool>,
+    /// Style links to missing articles as broken (default: on)
+    pub mark_broken_links: Option<bool>,
+}
+
+impl DataDirConfig {
+    /// Parse `config.json` content.
+    ///
+    /// Returns the recognized settings along with a list of problems
+    /// (unknown keys and type mismatches). Fields with problems are left unset.
+    pub fn parse(content: &str) -> anyhow::Result<(Self, Vec<String>)> {
+        let value: serde_json::Value = serde_json::from_str(content)
+            .context("config.json is not valid JSON")?;
+        let object = value.as_object()
+            .ok_or_else(|| anyhow::anyhow!("config.json must contain a JSON object"))?;
+
+        let mut problems: Vec<String> = object.keys()
+            .filter(|key| !KNOWN_DATA_CONFIG_KEYS.contains(&key.as_str()))
+            .map(|key| format!("unknown key \"{}\"", key))
+            .collect();
+
+        let config = Self {
+            language: typed_field(object, "language", &mut problems),
+            port: typed_field(object, "port", &mut problems),
+            host: typed_field(object, "host", &mut problems),
+            worker_threads: typed_field(object, "worker_threads", &mut problems),
+            max_connections: typed_field(object, "max_connections", &mut problems),
+            listen_backlog: typed_field(object, "listen_backlog", &mut problems),
+            article_cache_size: typed_field(object, "article_cache_size", &mut problems),
+            browse_per_page: typed_field(object, "browse_per_page", &mut problems),
+            search_per_page: typed_field(object, "search_per_page", &mut problems),
+            max_render_chars: typed_field(object, "max_render_chars", &mut problems),
+            max_search_results: typed_field(object, "max_search_results", &mut problems),
+            cors_origins: typed_field(object, "cors_origins", &mut problems),
+            admin_token: typed_field(object, "admin_token", &mut problems),
+            max_body_bytes: typed_field(object, "max_body_bytes", &mut problems),
+            temp_dir: typed_field(object, "temp_dir", &mut problems),
+            fallback_to_wikipedia: typed_field(object, "fallback_to_wikipedia", &mut problems),
+            mark_broken_links: typed_field(object, "mark_broken_links", &mut problems),
+        };
+        Ok((config, problems))
+    }
+
+    /// Load `config.json`, falling back to defaults if it is missing.
+    ///
+    /// Problems are logged as warnings and the affected settings use their
+    /// defaults. With `strict`, any problem (including malformed JSON) is an error.
+    pub fn load(path: &Path, strict: bool) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {:?}", path))?;
+
+        let (config, problems) = match Self::parse(&content) {
+            Ok(parsed) => parsed,
+            Err(e) if strict => return Err(e),
+            Err(e) => {
+                tracing::warn!("{:#}. Using defaults.", e);
+                return Ok(Self::default());
+            }
+        };
+
+        if !problems.is_empty() {
+            let list = problems.join("; ");
+            if strict {
+                anyhow::bail!("Invalid config {:?}: {}", path, list);
+            }
+            tracing::warn!("Ignoring invalid settings in {:?}: {}", path, list);
+        }
+        Ok(config)
+    }
+}
+
+/// Deserialize `key` from `object`, recording a problem on type mismatch
+fn typed_field<T: DeserializeOwned>(
+    object: &serde_json::Map<String, serde_json::Value>,
+    key: &str,
+    problems: &mut Vec<String>,
+) -> Option<T> {
+    let value = object.get(key).filter(|v| !v.is_null())?;
+    match serde_json::from_value(value.clone()) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            problems.push(format!("\"{}\": {}", key, e));
+            None
+        }
+    }
+}
+
 /// Configuration for the web server
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
@@ -131,6 +562,8 @@ pub struct ServerConfig {
     pub enable_search: bool,
     /// Maximum search results
     pub max_search_results: usize,
+    /// Origins allowed to make cross-origin requests; empty allows any
+    pub cors_origins: Vec<String>,
 }
 
 impl Default for ServerConfig {
@@ -141,6 +574,7 @@ impl Default for ServerConfig {
             data_dir: PathBuf::from("wikipedia"),
             enable_search: true,
             max_search_results: 50,
+            cors_origins: Vec::new(),
         }
     }
 }
@@ -165,8 +599,92 @@ impl ServerConfig {
         self
     }
 
+    pub fn with_cors_origins(mut self, origins: Vec<String>) -> Self {
+        self.cors_origins = origins;
+        self
+    }
+
     pub fn bind_address(&self) -> String {
         format!("{}:{}", self.host, self.port)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_length_defaults_per_language() {
+        assert_eq!(Config::new().min_length(), 100);
+        assert_eq!(Config::new().with_language(WikiLanguage::English).min_length(), 200);
+        assert_eq!(Config::new().with_language(WikiLanguage::Chinese).min_length(), 60);
+        assert_eq!(Config::new().with_language(WikiLanguage::Chinese).with_min_length(10).min_length(), 10);
+
+        // Saved configs keep their explicit minimum
+        let config: Config = serde_json::from_str(&serde_json::to_string(&Config::new().with_min_length(200)).unwrap()).unwrap();
+        assert_eq!(config.min_length(), 200);
+        let custom = Config { language: "custom".to_string(), ..Config::new() };
+        assert_eq!(custom.min_length(), DEFAULT_MIN_LENGTH);
+    }
+
+    #[test]
+    fn test_dump_base_url_points_at_a_mirror() {
+        let config = Config::new().with_language(WikiLanguage::German);
+        assert_eq!(config.dump_url(), "https://dumps.wikimedia.org/dewiki/latest/dewiki-latest-pages-articles.xml.bz2");
+
+        let mirror = config.with_dump_base_url("https://mirror.example.edu/wikimedia/dumps/").with_prefer_xz(true);
+        assert!(mirror.check_dump_base_url().is_ok());
+        assert_eq!(mirror.dump_url(), "https://mirror.example.edu/wikimedia/dumps/dewiki/latest/dewiki-latest-pages-articles.xml.xz");
+
+        for bad in ["mirror.example.edu/dumps", "ftp://mirror.example.edu/dumps", "https://mirror.example.edu/?x=1", "not a url"] {
+            assert!(Config::new().with_dump_base_url(bad).check_dump_base_url().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_data_dir_config_parses_known_keys() {
+        let (config, problems) = DataDirConfig::parse(
+            r#"{"language": "de", "port": 3000, "prune": true, "max_articles": 0}"#
+        ).unwrap();
+
+        assert!(problems.is_empty());
+        assert_eq!(config.language.as_deref(), Some("de"));
+        assert_eq!(config.port, Some(3000));
+        assert_eq!(config.host, None);
+    }
+
+    #[test]
+    fn test_data_dir_config_reports_unknown_key_and_type_mismatch() {
+        let (config, problems) = DataDirConfig::parse(
+            r#"{"language": "en", "prt": 3000, "host": 42}"#
+        ).unwrap();
+
+        assert_eq!(config.language.as_deref(), Some("en"));
+        assert_eq!(config.port, None);
+        assert_eq!(config.host, None);
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().any(|p| p.contains("\"prt\"")));
+        assert!(problems.iter().any(|p| p.contains("\"host\"")));
+    }
+
+    #[test]
+    fn test_data_dir_config_load_malformed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, "{ \"port\": 3000,").unwrap();
+
+        assert_eq!(DataDirConfig::load(&path, false).unwrap(), DataDirConfig::default());
+        assert!(DataDirConfig::load(&path, true).is_err());
+    }
+
+    #[test]
+    fn test_data_dir_config_load_strict_rejects_unknown_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, r#"{"prt": 3000}"#).unwrap();
+
+        assert_eq!(DataDirConfig::load(&path, false).unwrap(), DataDirConfig::default());
+        let err = DataDirConfig::load(&path, true).unwrap_err();
+        assert!(err.to_string().contains("prt"));
+    }
+}
@@ -0,0 +1,265 @@
+//! Corpus statistics
+//!
+//! Streams `articles.jsonl` once and reports token counts, the most frequent
+//! words (without stopwords), average article length and how articles are
+//! spread over categories. Word and category counts are capped: past
+//! [`DEFAULT_MAX_TRACKED_TERMS`] distinct terms the rarest are dropped, so
+//! memory stays bounded on a full dump while the top of the list stays
+//! accurate, and unique counts become bloom filter estimates.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::article::Article;
+use crate::jsonl::{self, LineParser};
+use crate::prune::BloomFilter;
+
+/// Distinct words (and categories) counted exactly before the rarest are dropped
+pub const DEFAULT_MAX_TRACKED_TERMS: usize = 1_000_000;
+
+/// Distinct terms the unique-count bloom filter is sized for once counts are capped
+const BLOOM_EXPECTED_TERMS: usize = 20_000_000;
+
+/// Common English words left out of the top words
+const STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been",
+    "but", "by", "can", "could", "did", "do", "for", "from", "had", "has", "have", "he", "her",
+    "him", "his", "i", "if", "in", "into", "is", "it", "its", "may", "more", "most", "no", "not",
+    "of", "on", "one", "or", "other", "our", "she", "so", "some", "such", "than", "that", "the",
+    "their", "them", "then", "there", "these", "they", "this", "those", "to", "two", "up", "was",
+    "we", "were", "what", "when", "where", "which", "while", "who", "will", "with", "would", "you",
+];
+
+/// A word or category and how often it occurred
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TermCount {
+    pub term: String,
+    pub count: u64,
+}
+
+/// Statistics over all articles of a data directory
+#[derive(Debug, Clone, Serialize)]
+pub struct CorpusStats {
+    pub articles: u64,
+    /// Words (runs of letters and digits) in the plain text of all articles
+    pub total_tokens: u64,
+    /// Distinct lowercased words
+    pub unique_tokens: u64,
+    /// `unique_tokens` is an estimate because word counts were capped
+    pub unique_tokens_approximate: bool,
+    /// Average tokens per article
+    pub average_tokens: f64,
+    /// Most frequent words, stopwords and numbers excluded
+    pub top_words: Vec<TermCount>,
+    /// Distinct categories
+    pub categories: u64,
+    /// Articles without any category
+    pub uncategorized_articles: u64,
+    /// Categories with the most articles
+    pub top_categories: Vec<TermCount>,
+}
+
+/// Term counts that keep at most `max_terms` entries.
+///
+/// When full, only the most frequent half is kept. Counts of the kept
+/// terms are exact if they were never dropped, which holds for anything
+/// frequent enough to make a top list.
+struct CappedCounter {
+    counts: HashMap<String, u64>,
+    max_terms: usize,
+    /// Set on the first pruning; every term seen since is in the filter
+    seen: Option<BloomFilter>,
+    unique: u64,
+}
+
+impl CappedCounter {
+    fn new(max_terms: usize) -> Self {
+        Self { counts: HashMap::new(), max_terms: max_terms.max(2), seen: None, unique: 0 }
+    }
+
+    fn add(&mut self, term: &str) {
+        if let Some(seen) = self.seen.as_mut() {
+            if !seen.contains(term) {
+                seen.insert(term);
+                self.unique += 1;
+            }
+        }
+        match self.counts.get_mut(term) {
+            Some(count) => *count += 1,
+            None => {
+                self.counts.insert(term.to_string(), 1);
+                if self.seen.is_none() {
+                    self.unique += 1;
+                }
+                if self.counts.len() > self.max_terms {
+                    self.prune();
+                }
+            }
+        }
+    }
+
+    /// Drop all but the most frequent half of the terms
+    fn prune(&mut self) {
+        if self.seen.is_none() {
+            // Everything seen so far is still counted, so the filter starts complete
+            let mut seen = BloomFilter::new(BLOOM_EXPECTED_TERMS, 0.01);
+            for term in self.counts.keys() {
+                seen.insert(term);
+            }
+            self.seen = Some(seen);
+        }
+        let keep = self.max_terms / 2;
+        let mut counts: Vec<u64> = self.counts.values().copied().collect();
+        let (_, threshold, _) = counts.select_nth_unstable_by(keep, |a, b| b.cmp(a));
+        let threshold = *threshold;
+        self.counts.retain(|_, count| *count > threshold);
+    }
+
+    fn is_approximate(&self) -> bool {
+        self.seen.is_some()
+    }
+
+    /// The `n` most frequent terms passing `keep`, ties broken alphabetically
+    fn top(&self, n: usize, keep: impl Fn(&str) -> bool) -> Vec<TermCount> {
+        let mut terms: Vec<(&String, &u64)> = self.counts.iter().filter(|(term, _)| keep(term)).collect();
+        terms.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        terms.into_iter()
+            .take(n)
+            .map(|(term, count)| TermCount { term: term.clone(), count: *count })
+            .collect()
+    }
+}
+
+/// Accumulates [`CorpusStats`] one article at a time
+pub struct CorpusScanner {
+    words: CappedCounter,
+    categories: CappedCounter,
+    articles: u64,
+    total_tokens: u64,
+    uncategorized_articles: u64,
+}
+
+impl CorpusScanner {
+    /// Count at most `max_terms` distinct words and categories exactly
+    pub fn new(max_terms: usize) -> Self {
+        Self {
+            words: CappedCounter::new(max_terms),
+            categories: CappedCounter::new(max_terms),
+            articles: 0,
+            total_tokens: 0,
+            uncategorized_articles: 0,
+        }
+    }
+
+    pub fn add(&mut self, article: &Article) {
+        self.articles += 1;
+        for token in article.to_plain_text().split(|c: char| !c.is_alphanumeric()).filter(|t| !t.is_empty()) {
+            self.total_tokens += 1;
+            self.words.add(&token.to_lowercase());
+        }
+        if article.categories.is_empty() {
+            self.uncategorized_articles += 1;
+        }
+        for category in &article.categories {
+            self.categories.add(category);
+        }
+    }
+
+    /// Finish with the `top` most frequent words and categories
+    pub fn finish(self, top: usize) -> CorpusStats {
+        let is_word = |term: &str| !STOPWORDS.contains(&term) && !term.chars().all(|c| c.is_numeric());
+        CorpusStats {
+            articles: self.articles,
+            total_tokens: self.total_tokens,
+            unique_tokens: self.words.unique,
+            unique_tokens_approximate: self.words.is_approximate(),
+            average_tokens: if self.articles > 0 { self.total_tokens as f64 / self.articles as f64 } else { 0.0 },
+            top_words: self.words.top(top, is_word),
+            categories: self.categories.unique,
+            uncategorized_articles: self.uncategorized_articles,
+            top_categories: self.categories.top(top, |_| true),
+        }
+    }
+}
+
+/// Compute [`CorpusStats`] over articles files (plain or compressed, e.g.
+/// the shards of [`jsonl::articles_files`]), listing the `top` most
+/// frequent words and categories.
+///
+/// Malformed lines are skipped (see [`LineParser`]).
+pub fn corpus_stats(articles_files: &[PathBuf], top: usize) -> Result<CorpusStats> {
+    let mut scanner = CorpusScanner::new(DEFAULT_MAX_TRACKED_TERMS);
+    for articles_path in articles_files {
+        let mut parser = LineParser::new(articles_path);
+        for (line_number, line) in jsonl::open(articles_path)?.lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(article) = parser.parse::<Article>(&line, line_number as u64 + 1)? {
+                scanner.add(&article);
+            }
+        }
+        parser.warn_if_skipped();
+    }
+    Ok(scanner.finish(top))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn article(id: u64, content: &str, categories: &[&str]) -> Article {
+        let mut article = Article::new(id, format!("Article {}", id), content.to_string());
+        article.categories = categories.iter().map(|c| c.to_string()).collect();
+        article
+    }
+
+    #[test]
+    fn test_corpus_stats_over_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("articles.jsonl");
+        let mut file = std::fs::File::create(&path).unwrap();
+        for article in [
+            article(1, "The river flows into the <a href=\"/wiki/Sea\">sea</a>. The river is long.", &["Rivers", "Geography"]),
+            article(2, "A river and a lake, mapped in 1848.", &["Geography"]),
+            article(3, "Lakes &amp; rivers", &[]),
+        ] {
+            writeln!(file, "{}", serde_json::to_string(&article).unwrap()).unwrap();
+        }
+        writeln!(file, "not json").unwrap();
+
+        let stats = corpus_stats(&[path], 3).unwrap();
+        assert_eq!(stats.articles, 3);
+        assert_eq!(stats.total_tokens, 10 + 8 + 2);
+        assert_eq!(stats.unique_tokens, 15);
+        assert!(!stats.unique_tokens_approximate);
+        assert!((stats.average_tokens - 20.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.top_words, vec![
+            TermCount { term: "river".to_string(), count: 3 },
+            TermCount { term: "flows".to_string(), count: 1 },
+            TermCount { term: "lake".to_string(), count: 1 },
+        ]);
+        assert_eq!(stats.categories, 2);
+        assert_eq!(stats.uncategorized_articles, 1);
+        assert_eq!(stats.top_categories[0], TermCount { term: "Geography".to_string(), count: 2 });
+    }
+
+    #[test]
+    fn test_capped_counts_keep_frequent_words() {
+        let mut scanner = CorpusScanner::new(10);
+        for i in 0..100 {
+            scanner.add(&article(i, &format!("common word{}", i), &[]));
+        }
+        let stats = scanner.finish(1);
+
+        assert_eq!(stats.top_words, vec![TermCount { term: "common".to_string(), count: 100 }]);
+        assert!(stats.unique_tokens_approximate);
+        assert!((95..=101).contains(&stats.unique_tokens), "{}", stats.unique_tokens);
+    }
+}
@@ -0,0 +1,326 @@
+//! Comparing extraction snapshots
+//!
+//! Reports which articles were added, removed or modified between two
+//! `articles.jsonl` files. Neither snapshot is held in memory: the title and
+//! content fingerprint of each article are sorted in bounded runs spilled to
+//! a temporary directory, and the runs of both sides are merged while
+//! comparing.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tempfile::TempDir;
+
+use crate::jsonl::{self, LineParser};
+
+/// Fingerprints sorted in memory before they are written out as a run
+/// (roughly 100 MB for typical titles)
+pub const DEFAULT_RUN_SIZE: usize = 1_000_000;
+
+/// Differences between two extraction snapshots
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SnapshotDiff {
+    pub added: u64,
+    pub removed: u64,
+    pub modified: u64,
+    pub unchanged: u64,
+    /// First added titles, alphabetically
+    pub added_titles: Vec<String>,
+    /// First removed titles, alphabetically
+    pub removed_titles: Vec<String>,
+    /// First modified titles, alphabetically
+    pub modified_titles: Vec<String>,
+}
+
+/// Content fingerprint of one article
+#[derive(Serialize, Deserialize)]
+struct Fingerprint {
+    title: String,
+    content_hash: u64,
+    length: usize,
+    /// Position in the snapshot, so later duplicates sort after earlier ones
+    position: u64,
+}
+
+impl Fingerprint {
+    /// Compare by content hash, or by length when either side predates hashes
+    fn same_content(&self, other: &Fingerprint) -> bool {
+        if self.content_hash != 0 && other.content_hash != 0 {
+            self.content_hash == other.content_hash
+        } else {
+            self.length == other.length
+        }
+    }
+}
+
+// Ordered by title, then position
+impl Ord for Fingerprint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.title.cmp(&other.title).then(self.position.cmp(&other.position))
+    }
+}
+
+impl PartialOrd for Fingerprint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Fingerprint {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Fingerprint {}
+
+/// Compare two snapshots, each one `articles.jsonl` or its shards (see
+/// [`jsonl::articles_files`]), keeping at most `limit` titles per category
+pub fn diff_snapshots(old_files: &[PathBuf], new_files: &[PathBuf], limit: usize) -> Result<SnapshotDiff> {
+    diff_snapshots_with_run_size(old_files, new_files, limit, DEFAULT_RUN_SIZE)
+}
+
+/// [`diff_snapshots`], sorting at most `run_size` fingerprints in memory at a time
+pub fn diff_snapshots_with_run_size(old_files: &[PathBuf], new_files: &[PathBuf], limit: usize, run_size: usize) -> Result<SnapshotDiff> {
+    let mut old = SortedFingerprints::new(old_files, run_size)?;
+    let mut new = SortedFingerprints::new(new_files, run_size)?;
+
+    let mut diff = SnapshotDiff::default();
+    let push = |titles: &mut Vec<String>, title: &str| {
+        if titles.len() < limit {
+            titles.push(title.to_string());
+        }
+    };
+
+    // Merge the two title-sorted streams
+    let mut old_next = old.next()?;
+    let mut new_next = new.next()?;
+    loop {
+        let order = match (&old_next, &new_next) {
+            (Some(old), Some(new)) => old.title.cmp(&new.title),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+        match order {
+            Ordering::Less => {
+                let old_fingerprint = old_next.take().unwrap();
+                diff.removed += 1;
+                push(&mut diff.removed_titles, &old_fingerprint.title);
+                old_next = old.next()?;
+            }
+            Ordering::Greater => {
+                let new_fingerprint = new_next.take().unwrap();
+                diff.added += 1;
+                push(&mut diff.added_titles, &new_fingerprint.title);
+                new_next = new.next()?;
+            }
+            Ordering::Equal => {
+                let old_fingerprint = old_next.take().unwrap();
+                let new_fingerprint = new_next.take().unwrap();
+                if old_fingerprint.same_content(&new_fingerprint) {
+                    diff.unchanged += 1;
+                } else {
+                    diff.modified += 1;
+                    push(&mut diff.modified_titles, &new_fingerprint.title);
+                }
+                old_next = old.next()?;
+                new_next = new.next()?;
+            }
+        }
+    }
+
+    Ok(diff)
+}
+
+/// A sorted run: spilled to a file, or the last one still in memory
+type Run = Box<dyn Iterator<Item = Result<Fingerprint>>>;
+
+/// The fingerprints of one snapshot in title order, merged from sorted runs.
+///
+/// Duplicate titles keep the last occurrence, matching how the index
+/// treats re-added articles.
+struct SortedFingerprints {
+    runs: Vec<Run>,
+    /// Next fingerprint of each run that has one, with the run's index
+    heads: BinaryHeap<Reverse<(Fingerprint, usize)>>,
+    /// Holds the spilled runs until the comparison is done
+    _spill_dir: TempDir,
+}
+
+impl SortedFingerprints {
+    /// Read every article in `paths`, writing a sorted run each time
+    /// `run_size` fingerprints have been collected. Malformed lines are
+    /// skipped (see [`LineParser`]).
+    fn new(paths: &[PathBuf], run_size: usize) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct ArticleFingerprint {
+            title: String,
+            content: String,
+            #[serde(default)]
+            content_hash: u64,
+        }
+
+        let spill_dir = tempfile::Builder::new().prefix("rustipedia-diff").tempdir()
+            .context("Failed to create a temporary directory for sorting titles")?;
+        let run_size = run_size.max(1);
+        let mut runs: Vec<Run> = Vec::new();
+        let mut buffer = Vec::with_capacity(run_size.min(DEFAULT_RUN_SIZE));
+        let mut position = 0;
+        for path in paths {
+            let mut parser = LineParser::new(path);
+            for (line_number, line) in jsonl::open(path)?.lines().enumerate() {
+                let line = line?;
+                if line.is_empty() {
+                    continue;
+                }
+                let Some(article) = parser.parse::<ArticleFingerprint>(&line, line_number as u64 + 1)? else {
+                    continue;
+                };
+                buffer.push(Fingerprint {
+                    title: article.title,
+                    content_hash: article.content_hash,
+                    length: article.content.len(),
+                    position,
+                });
+                position += 1;
+                if buffer.len() >= run_size {
+                    let run_path = spill_dir.path().join(format!("run-{}.jsonl", runs.len()));
+                    runs.push(spill_run(std::mem::take(&mut buffer), run_path)?);
+                }
+            }
+            parser.warn_if_skipped();
+        }
+        if !buffer.is_empty() {
+            buffer.sort_unstable();
+            runs.push(Box::new(buffer.into_iter().map(Ok)));
+        }
+
+        let mut heads = BinaryHeap::with_capacity(runs.len());
+        for (index, run) in runs.iter_mut().enumerate() {
+            if let Some(fingerprint) = run.next() {
+                heads.push(Reverse((fingerprint?, index)));
+            }
+        }
+        Ok(Self { runs, heads, _spill_dir: spill_dir })
+    }
+
+    /// The next fingerprint in title order, duplicates included
+    fn next_merged(&mut self) -> Result<Option<Fingerprint>> {
+        let Some(Reverse((fingerprint, index))) = self.heads.pop() else {
+            return Ok(None);
+        };
+        if let Some(next) = self.runs[index].next() {
+            self.heads.push(Reverse((next?, index)));
+        }
+        Ok(Some(fingerprint))
+    }
+
+    /// The next title's fingerprint, the last of its duplicates
+    fn next(&mut self) -> Result<Option<Fingerprint>> {
+        let Some(mut fingerprint) = self.next_merged()? else {
+            return Ok(None);
+        };
+        while self.heads.peek().is_some_and(|Reverse((next, _))| next.title == fingerprint.title) {
+            fingerprint = self.next_merged()?.unwrap();
+        }
+        Ok(Some(fingerprint))
+    }
+}
+
+/// Sort `fingerprints`, write them to `path` and return a reader over them
+fn spill_run(mut fingerprints: Vec<Fingerprint>, path: PathBuf) -> Result<Run> {
+    fingerprints.sort_unstable();
+    let mut writer = BufWriter::new(File::create(&path).with_context(|| format!("Failed to create {:?}", path))?);
+    for fingerprint in &fingerprints {
+        serde_json::to_writer(&mut writer, fingerprint)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    drop(fingerprints);
+
+    let reader = BufReader::new(File::open(&path).with_context(|| format!("Failed to open {:?}", path))?);
+    Ok(Box::new(reader.lines().map(move |line| {
+        let line = line.with_context(|| format!("Failed to read {:?}", path))?;
+        Ok(serde_json::from_str(&line)?)
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Article;
+    use std::io::Write;
+    use std::path::Path;
+
+    fn write_jsonl(path: &Path, articles: &[Article]) {
+        let mut file = std::fs::File::create(path).unwrap();
+        for article in articles {
+            writeln!(file, "{}", serde_json::to_string(article).unwrap()).unwrap();
+        }
+    }
+
+    fn article(title: &str, content: &str) -> Article {
+        Article::new(1, title.to_string(), content.to_string())
+    }
+
+    #[test]
+    fn test_diff_added_removed_modified() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old.jsonl");
+        let new = dir.path().join("new.jsonl");
+        write_jsonl(&old, &[article("Zebra", "Stripes"), article("Apple", "Fruit"), article("Moon", "Rock")]);
+        write_jsonl(&new, &[article("Moon", "Rock, orbiting"), article("Apple", "Fruit"), article("Kiwi", "Bird")]);
+
+        let diff = diff_snapshots(&[old], &[new], 10).unwrap();
+        assert_eq!((diff.added, diff.removed, diff.modified, diff.unchanged), (1, 1, 1, 1));
+        assert_eq!(diff.added_titles, vec!["Kiwi"]);
+        assert_eq!(diff.removed_titles, vec!["Zebra"]);
+        assert_eq!(diff.modified_titles, vec!["Moon"]);
+    }
+
+    #[test]
+    fn test_diff_without_hashes_compares_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old.jsonl");
+        let new = dir.path().join("new.jsonl");
+        // The truncated middle line is skipped (see LineParser)
+        std::fs::write(&old, "{\"id\":1,\"title\":\"A\",\"content\":\"abc\"}\n{\"id\":3,\"tit\n{\"id\":2,\"title\":\"B\",\"content\":\"abc\"}\n").unwrap();
+        write_jsonl(&new, &[article("A", "xyz"), article("B", "abcd")]);
+
+        let diff = diff_snapshots(&[old], &[new], 0).unwrap();
+        assert_eq!((diff.modified, diff.unchanged), (1, 1));
+        assert!(diff.modified_titles.is_empty());
+    }
+
+    #[test]
+    fn test_diff_merges_sorted_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old.jsonl");
+        let new = dir.path().join("new.jsonl");
+        let old_articles: Vec<Article> = (0..25).map(|i| article(&format!("Article {:02}", i), "Old text")).collect();
+        let mut new_articles: Vec<Article> = (5..30).rev()
+            .map(|i| article(&format!("Article {:02}", i), if i % 2 == 0 { "Old text" } else { "New text" }))
+            .collect();
+        // A duplicate in a later run replaces the earlier occurrence
+        new_articles.push(article("Article 10", "Rewritten"));
+        write_jsonl(&old, &old_articles);
+        write_jsonl(&new, &new_articles);
+
+        // Runs of 3 spill every side to many files
+        let diff = diff_snapshots_with_run_size(std::slice::from_ref(&old), std::slice::from_ref(&new), 3, 3).unwrap();
+        assert_eq!((diff.added, diff.removed, diff.modified, diff.unchanged), (5, 5, 11, 9));
+        assert_eq!(diff.added_titles, vec!["Article 25", "Article 26", "Article 27"]);
+        assert_eq!(diff.removed_titles, vec!["Article 00", "Article 01", "Article 02"]);
+        assert_eq!(diff.modified_titles, vec!["Article 05", "Article 07", "Article 09"]);
+
+        let in_memory = diff_snapshots(&[old], &[new], 100).unwrap();
+        assert_eq!((in_memory.added, in_memory.removed, in_memory.modified, in_memory.unchanged), (5, 5, 11, 9));
+        assert!(in_memory.modified_titles.contains(&"Article 10".to_string()));
+    }
+}
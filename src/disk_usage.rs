@@ -0,0 +1,115 @@
+//! Disk usage of a data directory
+//!
+//! The settings page shows how much space the articles and the search index
+//! take and how much is left on the disk. Walking the index directory on
+//! every page load would be wasteful, so measurements are cached briefly.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use fs2::{available_space, total_space};
+
+use crate::jsonl;
+
+/// How long a measurement is reused
+pub const DISK_USAGE_TTL: Duration = Duration::from_secs(60);
+
+/// Space used by a data directory and left on its filesystem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskUsage {
+    /// Size of articles.jsonl (or articles.jsonl.gz, or all shards)
+    pub articles_bytes: u64,
+    /// Size of the search_index directory
+    pub index_bytes: u64,
+    /// Free space on the filesystem, if it could be determined
+    pub available_bytes: Option<u64>,
+    /// Size of the filesystem, if it could be determined
+    pub total_bytes: Option<u64>,
+}
+
+impl DiskUsage {
+    /// Measure `data_dir` now
+    pub fn measure(data_dir: &Path) -> Self {
+        Self {
+            articles_bytes: jsonl::articles_files(data_dir).iter()
+                .map(|path| fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+                .sum(),
+            index_bytes: dir_size(&data_dir.join("search_index")),
+            available_bytes: available_space(data_dir).ok(),
+            total_bytes: total_space(data_dir).ok(),
+        }
+    }
+
+    /// Space used on the filesystem by everything, not just this data directory
+    pub fn used_bytes(&self) -> Option<u64> {
+        Some(self.total_bytes?.saturating_sub(self.available_bytes?))
+    }
+}
+
+/// Total size of the files under `path`, 0 if it doesn't exist
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// A [`DiskUsage`] measured at most once per `ttl`
+pub struct DiskUsageCache {
+    ttl: Duration,
+    cached: Mutex<Option<(Instant, DiskUsage)>>,
+}
+
+impl DiskUsageCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, cached: Mutex::new(None) }
+    }
+
+    /// The usage of `data_dir`, measured again if the last measurement is
+    /// older than the TTL
+    pub fn get(&self, data_dir: &Path) -> DiskUsage {
+        let mut cached = self.cached.lock().unwrap_or_else(|e| e.into_inner());
+        match *cached {
+            Some((measured_at, usage)) if measured_at.elapsed() < self.ttl => usage,
+            _ => {
+                let usage = DiskUsage::measure(data_dir);
+                *cached = Some((Instant::now(), usage));
+                usage
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_usage_is_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("articles.jsonl"), vec![b'x'; 100]).unwrap();
+        fs::create_dir_all(dir.path().join("search_index/segments")).unwrap();
+        fs::write(dir.path().join("search_index/meta.json"), vec![b'x'; 30]).unwrap();
+        fs::write(dir.path().join("search_index/segments/a.idx"), vec![b'x'; 20]).unwrap();
+
+        let cache = DiskUsageCache::new(Duration::from_secs(3600));
+        let usage = cache.get(dir.path());
+        assert_eq!(usage.articles_bytes, 100);
+        assert_eq!(usage.index_bytes, 50);
+        assert!(usage.available_bytes.is_some());
+        assert!(usage.used_bytes().unwrap() <= usage.total_bytes.unwrap());
+
+        fs::write(dir.path().join("articles.jsonl"), vec![b'x'; 200]).unwrap();
+        assert_eq!(cache.get(dir.path()).articles_bytes, 100);
+        assert_eq!(DiskUsageCache::new(Duration::ZERO).get(dir.path()).articles_bytes, 200);
+    }
+}
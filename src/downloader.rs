@@ -1,26 +1,204 @@
 //! Wikipedia dump downloader
 
+use std::collections::{HashSet, VecDeque};
 use std::fs::{self, File};
-use std::io::{BufReader, BufWriter, Read, Write};
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use bzip2::read::BzDecoder;
-use indicatif::{ProgressBar, ProgressStyle};
+use chrono::{DateTime, Utc};
+use xz2::read::XzDecoder;
+use indicatif::ProgressStyle;
 use quick_xml::events::Event;
 use quick_xml::Reader;
 use sha2::{Sha256, Digest};
 use fs2::available_space;
-#[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
 
 use crate::article::{Article, ExtractionStats};
 use crate::config::Config;
+use crate::error::{DownloadError, Error, ExtractError};
+use crate::jsonl::{self, ArticlesWriter};
+use crate::lock::DataDirLock;
+use crate::locale::NumberFormat;
 use crate::parser::{ParsedArticle, WikiParser};
+use crate::progress::{Progress, ProgressUnit};
+use crate::prune::count_wiki_links;
+use crate::redirects::RedirectMap;
+use crate::WikiLanguage;
+
+/// Minimum number of bytes between two progress callback invocations
+const PROGRESS_REPORT_INTERVAL: u64 = 1024 * 1024;
+
+/// Window over which the extraction rate is measured for the ETA
+const RATE_WINDOW: Duration = Duration::from_secs(30);
+
+/// Largest dump [`WikiDownloader::download`] accepts
+pub const MAX_DOWNLOAD_SIZE: u64 = 100 * 1024 * 1024 * 1024;
+
+/// Default size of the buffer between the decompressor and the XML parser.
+///
+/// `benches/decompress.rs` showed no gain from larger buffers: bz2
+/// decompression is CPU-bound, so this stays at `BufReader`'s 8 KiB.
+pub const DEFAULT_DECOMPRESS_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Default estimate of extracted output (articles and search index) per byte of dump
+pub const DEFAULT_EXTRACT_SPACE_MULTIPLIER: f64 = 4.0;
+
+/// Articles file written during extraction, renamed to `articles.jsonl` when done
+pub const PARTIAL_ARTICLES_FILE: &str = "articles.jsonl.tmp";
+
+/// Marker written after a successful extraction, holding the article count
+pub const COMPLETE_MARKER_FILE: &str = ".complete";
+
+/// Whether a data directory holds a finished extraction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtractionState {
+    /// `articles.jsonl` was written by a finished extraction
+    Complete,
+    /// An extraction was interrupted (or is still running); any
+    /// `articles.jsonl` is from an earlier run
+    Interrupted,
+    /// `articles.jsonl` predates completion markers
+    Unknown,
+    /// No articles have been extracted
+    Missing,
+}
+
+impl ExtractionState {
+    /// Inspect the extraction files in `data_dir`
+    pub fn of(data_dir: &Path) -> Self {
+        if jsonl::has_partial_files(data_dir) {
+            ExtractionState::Interrupted
+        } else if jsonl::articles_files(data_dir).is_empty() {
+            ExtractionState::Missing
+        } else if data_dir.join(COMPLETE_MARKER_FILE).exists() {
+            ExtractionState::Complete
+        } else {
+            ExtractionState::Unknown
+        }
+    }
+}
+
+/// Phase of a download/extraction run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadPhase {
+    /// Downloading the dump file
+    Downloading,
+    /// Extracting articles from the dump
+    Extracting,
+}
+
+/// Progress report passed to a [`WikiDownloader`] progress callback
+#[derive(Debug, Clone)]
+pub struct DownloadProgress {
+    /// Bytes processed so far in the current phase
+    pub bytes_done: u64,
+    /// Total bytes for the current phase (if known)
+    pub total: Option<u64>,
+    /// Current phase
+    pub phase: DownloadPhase,
+}
+
+/// Callback receiving progress reports
+pub type ProgressCallback = Box<dyn Fn(DownloadProgress) + Send + Sync>;
+
+/// What a download run would do, computed without touching the network
+#[derive(Debug, Clone)]
+pub struct DownloadPlan {
+    /// Dump URL
+    pub url: String,
+    /// Checksum URL
+    pub checksum_url: String,
+    /// Where the dump will be written
+    pub dump_path: PathBuf,
+    /// Whether the dump already exists (and would be reused with `skip_download`)
+    pub dump_exists: bool,
+    /// Where extracted articles will be written
+    pub data_path: PathBuf,
+    /// Where the search index will be built
+    pub index_path: PathBuf,
+    /// Estimated dump size in bytes
+    pub estimated_size: u64,
+    /// Disk space required for the estimated dump size
+    pub required_space: u64,
+    /// Disk space available in the output directory (if it could be determined)
+    pub available_space: Option<u64>,
+}
+
+impl DownloadPlan {
+    /// Whether the available space covers the required space.
+    ///
+    /// Returns `true` when the available space is unknown, matching the
+    /// behaviour of the check during a real download.
+    pub fn has_enough_space(&self) -> bool {
+        self.available_space.is_none_or(|available| available >= self.required_space)
+    }
+}
+
+/// Compression format of a dump file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpCompression {
+    /// `.xml.bz2`
+    Bzip2,
+    /// `.xml.xz`
+    Xz,
+    /// Plain `.xml`
+    Uncompressed,
+}
+
+impl DumpCompression {
+    /// Detect the compression of a dump from its magic bytes, falling back to
+    /// the file extension. Files with neither are read as plain XML.
+    pub fn detect(path: &Path) -> Result<Self> {
+        let mut magic = [0u8; 6];
+        let mut file = File::open(path)?;
+        let mut len = 0;
+        while len < magic.len() {
+            match file.read(&mut magic[len..])? {
+                0 => break,
+                n => len += n,
+            }
+        }
+        let magic = &magic[..len];
+
+        if magic.starts_with(b"BZh") {
+            Ok(DumpCompression::Bzip2)
+        } else if magic.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+            Ok(DumpCompression::Xz)
+        } else {
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("bz2") => Ok(DumpCompression::Bzip2),
+                Some("xz") => Ok(DumpCompression::Xz),
+                _ => Ok(DumpCompression::Uncompressed),
+            }
+        }
+    }
+}
+
+/// Decompress `reader` according to `compression`, buffering the
+/// decompressed stream with `buffer_size` bytes for the XML parser
+pub fn decompressing_reader<R: Read + 'static>(
+    reader: R,
+    compression: DumpCompression,
+    buffer_size: usize,
+) -> BufReader<Box<dyn Read>> {
+    let decompressor: Box<dyn Read> = match compression {
+        DumpCompression::Bzip2 => Box::new(BzDecoder::new(reader)),
+        DumpCompression::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+        DumpCompression::Uncompressed => Box::new(reader),
+    };
+    BufReader::with_capacity(buffer_size.max(1), decompressor)
+}
 
 /// Wikipedia downloader and extractor
 pub struct WikiDownloader {
     config: Config,
     parser: WikiParser,
+    progress_callback: Option<ProgressCallback>,
 }
 
 impl WikiDownloader {
@@ -29,13 +207,26 @@ impl WikiDownloader {
         Self {
             config: Config::default(),
             parser: WikiParser::new(),
+            progress_callback: None,
         }
     }
 
     /// Create a downloader with custom config
     pub fn with_config(config: Config) -> Self {
-        let parser = WikiParser::new().with_min_length(config.min_length);
-        Self { config, parser }
+        let parser = WikiParser::new()
+            .with_language(config.wiki_language())
+            .with_min_length(config.min_length())
+            .with_raw_markup(config.keep_raw_markup);
+        Self { config, parser, progress_callback: None }
+    }
+
+    /// Report progress to a callback instead of drawing progress bars.
+    ///
+    /// The callback is invoked periodically during both download and
+    /// extraction, and once more when each phase completes.
+    pub fn with_progress_callback(mut self, callback: ProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
     }
 
     /// Get the config
@@ -43,8 +234,51 @@ impl WikiDownloader {
         &self.config
     }
 
+    /// Create a progress bar, hidden when progress goes to a callback instead
+    fn progress_bar(&self, len: Option<u64>, label: &str) -> Progress {
+        if self.progress_callback.is_some() {
+            return Progress::hidden();
+        }
+        Progress::new(len, ProgressUnit::Bytes, label)
+    }
+
+    /// Send a progress report to the callback, if any
+    fn report_progress(&self, phase: DownloadPhase, bytes_done: u64, total: Option<u64>) {
+        if let Some(ref callback) = self.progress_callback {
+            callback(DownloadProgress { bytes_done, total, phase });
+        }
+    }
+
+    /// URL of the dump to download (XZ when `prefer_xz` is set, on the
+    /// mirror when `dump_base_url` is)
+    fn dump_url(&self) -> String {
+        self.config.dump_url()
+    }
+
+    /// Compute the download plan for the current config.
+    ///
+    /// Does not make any network requests or create any files.
+    pub fn plan(&self) -> DownloadPlan {
+        let lang = self.config.wiki_language();
+        let url = self.dump_url();
+        let dump_path = self.config.dump_path();
+        let estimated_size = lang.estimated_size_bytes();
+
+        DownloadPlan {
+            checksum_url: format!("{}.sha256", url),
+            url,
+            dump_exists: dump_path.exists(),
+            dump_path,
+            data_path: self.config.data_path(),
+            index_path: self.config.index_path(),
+            estimated_size,
+            required_space: required_space(estimated_size),
+            available_space: available_space_for(&self.config.output_dir),
+        }
+    }
+
     /// Download the Wikipedia dump file
-    pub fn download(&self) -> Result<()> {
+    pub fn download(&self) -> Result<(), DownloadError> {
         let lang = self.config.wiki_language();
         let dump_path = self.config.dump_path();
 
@@ -53,12 +287,18 @@ impl WikiDownloader {
             tracing::info!("Dump file already exists, skipping download: {:?}", dump_path);
             return Ok(());
         }
+        self.config.check_dump_base_url()
+            .map_err(|e| DownloadError::InvalidDumpBaseUrl(format!("{:#}", e)))?;
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| DownloadError::Io { path, source }
+        };
 
         // Create output directory
         fs::create_dir_all(&self.config.output_dir)
-            .context("Failed to create output directory")?;
+            .map_err(io_error(&self.config.output_dir))?;
 
-        let url = lang.dump_url();
+        let url = self.dump_url();
         tracing::info!("Downloading {} Wikipedia dump...", lang.display_name());
         tracing::info!("URL: {}", url);
         tracing::info!("Estimated size: {}", lang.estimated_size());
@@ -89,72 +329,76 @@ impl WikiDownloader {
             }
         };
 
-        let response = client.get(&url).send()
-            .context("Failed to start download")?;
+        let response = client.get(&url).send()?;
 
-        if !response.status().is_success() {
-            anyhow::bail!("Download failed with status: {}", response.status());
+        match response.status() {
+            status if status.is_success() => {}
+            reqwest::StatusCode::NOT_FOUND => return Err(DownloadError::DumpNotFound { url }),
+            status => return Err(DownloadError::HttpStatus { url, status: status.as_u16() }),
         }
 
         let total_size = response.content_length().unwrap_or(0);
         
         // Security: Check available disk space
-        let required_space = if total_size > 0 { total_size * 2 } else { 1024 * 1024 * 1024 }; // Default 1GB
-        if let Ok(available) = available_space(&self.config.output_dir) {
-             if available < required_space {
-                 anyhow::bail!("Insufficient disk space. Available: {}, Required: {}", format_bytes(available), format_bytes(required_space));
-             }
+        check_disk_space(&self.config.output_dir, total_size)?;
+        if let Some(ref temp_dir) = self.config.temp_dir {
+            check_temp_dir(temp_dir, total_size)?;
         }
 
-        // Security: Enforce maximum download size (e.g., 100GB)
-        const MAX_DOWNLOAD_SIZE: u64 = 100 * 1024 * 1024 * 1024;
-        if total_size > MAX_DOWNLOAD_SIZE {
-            anyhow::bail!("Download size {} exceeds limit of {}", format_bytes(total_size), format_bytes(MAX_DOWNLOAD_SIZE));
-        }
+        // Security: Enforce maximum download size
+        check_download_size(total_size)?;
         
         // Create progress bar
         let pb = if total_size > 0 {
-            let pb = ProgressBar::new(total_size);
+            let pb = self.progress_bar(Some(total_size), "Downloading");
             pb.set_style(ProgressStyle::default_bar()
                 .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, ETA: {eta})")
                 .unwrap()
                 .progress_chars("█▉▊▋▌▍▎▏  "));
             pb
         } else {
-            let pb = ProgressBar::new_spinner();
+            let pb = self.progress_bar(None, "Downloading");
             pb.set_style(ProgressStyle::default_spinner()
                 .template("{spinner:.green} [{elapsed_precise}] Downloaded: {bytes}")
                 .unwrap());
             pb
         };
 
-        // Stream to file
-        let mut file = File::create(&dump_path)
-            .context("Failed to create dump file")?;
+        // Stream to a temporary file, moved into place once complete and verified
+        let partial_path = self.config.partial_dump_path();
+        let mut file = File::create(&partial_path)
+            .map_err(io_error(&partial_path))?;
         let mut response = response;
         let mut buffer = [0u8; 65536]; // 64KB buffer
         let mut downloaded = 0u64;
+        let mut last_reported = 0u64;
+        let total = if total_size > 0 { Some(total_size) } else { None };
 
         loop {
-            let bytes_read = response.read(&mut buffer)?;
+            let bytes_read = response.read(&mut buffer).map_err(DownloadError::Interrupted)?;
             if bytes_read == 0 {
                 break;
             }
-            file.write_all(&buffer[..bytes_read])?;
+            file.write_all(&buffer[..bytes_read]).map_err(io_error(&partial_path))?;
             downloaded += bytes_read as u64;
             pb.set_position(downloaded);
+
+            if downloaded - last_reported >= PROGRESS_REPORT_INTERVAL {
+                self.report_progress(DownloadPhase::Downloading, downloaded, total);
+                last_reported = downloaded;
+            }
         }
 
-        pb.finish_with_message("Download complete!");
+        self.report_progress(DownloadPhase::Downloading, downloaded, total);
         pb.finish_with_message("Download complete!");
         tracing::info!("Downloaded {} to {:?}", format_bytes(downloaded), dump_path);
 
         // Security: Verify checksum
         if let Some(expected) = expected_checksum {
             tracing::info!("Verifying checksum...");
-            let mut file = File::open(&dump_path)?;
+            let mut file = File::open(&partial_path).map_err(io_error(&partial_path))?;
             let mut hasher = Sha256::new();
-            std::io::copy(&mut file, &mut hasher)?;
+            std::io::copy(&mut file, &mut hasher).map_err(io_error(&partial_path))?;
             let result = hasher.finalize();
             let calculated = hex::encode(result);
             
@@ -164,23 +408,33 @@ impl WikiDownloader {
             let expected_hash = expected.split_whitespace().next().unwrap_or(&expected);
             
             if calculated != expected_hash {
-                anyhow::bail!("Checksum mismatch! Expected: {}, Calculated: {}", expected_hash, calculated);
+                return Err(DownloadError::ChecksumMismatch { expected: expected_hash.to_string(), calculated });
             }
             tracing::info!("Checksum verified!");
         }
 
+        move_file(&partial_path, &dump_path).map_err(io_error(&dump_path))?;
         Ok(())
     }
 
     /// Extract articles from the downloaded dump
-    pub fn extract(&self) -> Result<ExtractionStats> {
+    pub fn extract(&self) -> Result<ExtractionStats, ExtractError> {
         let lang = self.config.wiki_language();
         let dump_path = self.config.dump_path();
 
         if !dump_path.exists() {
-            anyhow::bail!("Dump file not found: {:?}. Run download first.", dump_path);
+            return Err(ExtractError::DumpNotFound(dump_path));
         }
 
+        fs::create_dir_all(&self.config.output_dir)?;
+        let _lock = DataDirLock::exclusive(&self.config.output_dir, "extraction")
+            .map_err(|e| ExtractError::Locked(format!("{:#}", e)))?;
+        check_extraction_space(
+            available_space_for(&self.config.output_dir),
+            fs::metadata(&dump_path)?.len(),
+            self.config.extract_space_multiplier,
+        )?;
+
         // Security: Path Traversal Prevention
         // Canonicalize output directory and ensure it's safe
         let output_dir = self.config.output_dir.canonicalize().unwrap_or(self.config.output_dir.clone());
@@ -196,32 +450,39 @@ impl WikiDownloader {
         let dump_filename = dump_path.file_name()
             .map(|s| s.to_string_lossy().to_string())
             .unwrap_or_default();
-        let mut stats = ExtractionStats::new(lang.code(), &dump_filename, self.config.min_length);
+        let mut stats = ExtractionStats::new(lang.code(), &dump_filename, self.config.min_length());
+        stats.max_articles = self.config.max_articles;
+
+        // Expected article count for the ETA; unknown for custom dumps
+        let estimated = WikiLanguage::from_code(&self.config.language)
+            .map(|lang| lang.estimated_article_count());
+        let expected_articles = match (estimated, self.config.max_articles as u64) {
+            (_, 0) => estimated,
+            (Some(estimated), max) => Some(estimated.min(max)),
+            (None, max) => Some(max),
+        };
+        let mut rate = ArticleRate::new();
 
-        // Open dump file
+        // Open dump file. Progress is measured in (compressed) bytes read
+        // from the file, which is what the file size refers to.
         let file = File::open(&dump_path)?;
         let file_size = file.metadata()?.len();
+        let compressed_read = Arc::new(AtomicU64::new(0));
+        let file = CountingReader::new(file, compressed_read.clone());
         let reader = BufReader::with_capacity(1024 * 1024, file); // 1MB buffer
 
-        // Decompress bz2
-        let decompressor = BzDecoder::new(reader);
+        // Decompress
+        let compression = DumpCompression::detect(&dump_path)?;
+        tracing::debug!("Dump compression: {:?}, read buffer {} bytes", compression, self.config.decompress_buffer_size);
+        let decompressed = decompressing_reader(reader, compression, self.config.decompress_buffer_size);
 
-        // Create output file
-        let output_path = self.config.data_path();
-        
-        // Security: Set restrictive permissions on output file (Unix only)
-        let file = File::create(&output_path)?;
-        #[cfg(unix)]
-        {
-            let mut perms = file.metadata()?.permissions();
-            perms.set_mode(0o644);
-            file.set_permissions(perms)?;
-        }
-        
-        let mut writer = BufWriter::new(file);
+        // Articles go to temporary files, renamed over articles.jsonl (or
+        // its shards) only once extraction finishes, so an interrupted run
+        // leaves the previous dataset intact
+        let mut writer = ArticlesWriter::create(&self.config.output_dir, self.config.compress_output, self.config.shard_size)?;
 
-        // Progress bar (estimated based on file size)
-        let pb = ProgressBar::new(file_size);
+        // Progress bar over compressed bytes consumed (accurate to the 1MB read buffer)
+        let pb = self.progress_bar(Some(file_size), "Extracting");
         pb.set_style(ProgressStyle::default_bar()
             .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} compressed bytes ({msg})")
             .unwrap()
@@ -229,7 +490,7 @@ impl WikiDownloader {
         pb.set_message("0 articles");
 
         // Parse XML
-        let mut xml_reader = Reader::from_reader(BufReader::new(decompressor));
+        let mut xml_reader = Reader::from_reader(decompressed);
         xml_reader.config_mut().trim_text(true);
         // Security: Disable entity expansion to prevent XXE
         // quick-xml doesn't expand by default, but we can be explicit if the API supports it.
@@ -238,40 +499,98 @@ impl WikiDownloader {
         // Actually, for XXE, we just need to ensure we don't resolve external entities.
         // quick-xml doesn't resolve external entities automatically.
 
+        // Category filters, normalized for comparison. Only direct category
+        // membership is checked: the dump carries no category graph, so
+        // subcategories have to be listed explicitly.
+        let excluded_categories: HashSet<String> = self.config.exclude_categories
+            .iter()
+            .map(|c| WikiParser::normalize_category(c))
+            .collect();
+        let included_categories: HashSet<String> = self.config.include_categories
+            .iter()
+            .map(|c| WikiParser::normalize_category(c))
+            .collect();
+
+        // Redirect pages are kept as aliases rather than articles
+        let mut redirects = RedirectMap::new();
+
         let mut buf = Vec::with_capacity(1024 * 1024);
         let mut current_title = String::new();
         let mut current_text = String::new();
         let mut current_id: u64 = 0;
+        // Latest `revision/timestamp` of the page (dumps with history have several)
+        let mut current_edited_at: Option<DateTime<Utc>> = None;
+        // Target of the page's `<redirect title="..."/>` element
+        let mut current_redirect: Option<String> = None;
         let mut in_title = false;
         let mut in_text = false;
         let mut in_id = false;
-        let mut first_id = true;
+        let mut in_timestamp = false;
+        // Names of the currently open elements. Used to tell the page id
+        // (`page/id`) apart from `revision/id` and `contributor/id`.
+        let mut path: Vec<Vec<u8>> = Vec::new();
+        let mut last_reported = 0u64;
+        // After an XML error, events are ignored until the next `<page>` so
+        // content from a broken page can't leak into the following one
+        let mut resyncing = false;
+        let mut xml_error: Option<String> = None;
+        let mut last_error_position: Option<u64> = None;
 
         loop {
-            match xml_reader.read_event_into(&mut buf) {
+            let event = xml_reader.read_event_into(&mut buf);
+            if resyncing {
+                match &event {
+                    Ok(Event::Start(e)) if e.name().as_ref() == b"page" => {
+                        resyncing = false;
+                        if let Some(page_depth) = path.iter().position(|name| name == b"page") {
+                            path.truncate(page_depth);
+                        }
+                    }
+                    Ok(Event::Eof) => {}
+                    Err(_) if last_error_position == Some(xml_reader.buffer_position()) => {
+                        tracing::warn!("XML reader made no progress after an error, stopping extraction");
+                        break;
+                    }
+                    _ => {
+                        last_error_position = Some(xml_reader.buffer_position());
+                        buf.clear();
+                        continue;
+                    }
+                }
+            }
+
+            match event {
                 Ok(Event::Start(e)) => {
+                    let is_page_child = path.last().map(|p| p.as_slice()) == Some(b"page".as_slice());
                     match e.name().as_ref() {
+                        b"page" => {
+                            current_redirect = None;
+                            current_edited_at = None;
+                        }
                         b"title" => in_title = true,
                         b"text" => in_text = true,
-                        b"id" => {
-                            if first_id {
-                                in_id = true;
-                            }
-                        },
-                        b"page" => first_id = true,
+                        b"id" if is_page_child => in_id = true,
+                        b"timestamp" if path.last().map(|p| p.as_slice()) == Some(b"revision".as_slice()) => in_timestamp = true,
                         _ => {}
                     }
+                    path.push(e.name().as_ref().to_vec());
+                }
+                Ok(Event::Empty(e)) => {
+                    let is_page_child = path.last().map(|p| p.as_slice()) == Some(b"page".as_slice());
+                    if is_page_child && e.name().as_ref() == b"redirect" {
+                        current_redirect = e.try_get_attribute("title").ok().flatten()
+                            .and_then(|attr| attr.unescape_value().ok())
+                            .map(|target| target.into_owned());
+                    }
                 }
                 Ok(Event::End(e)) => {
+                    path.pop();
                     match e.name().as_ref() {
                         b"title" => in_title = false,
-                        b"id" => {
-                            in_id = false;
-                            first_id = false;
-                        },
-                        b"text" => {
-                            in_text = false;
-
+                        b"id" => in_id = false,
+                        b"text" => in_text = false,
+                        b"timestamp" => in_timestamp = false,
+                        b"page" => {
                             // Security: Max article size check
                             const MAX_ARTICLE_SIZE: usize = 10_000_000; // 10MB
                             if current_text.len() > MAX_ARTICLE_SIZE {
@@ -280,6 +599,7 @@ impl WikiDownloader {
                                 current_title.clear();
                                 current_text.clear();
                                 current_id = 0;
+                                current_edited_at = None;
                                 continue;
                             }
 
@@ -296,31 +616,75 @@ impl WikiDownloader {
                                 current_title.clear();
                                 current_text.clear();
                                 current_id = 0;
+                                current_edited_at = None;
                                 continue;
                             }
 
                             // Process the article
-                            match self.parser.parse_article(&sanitized_title, &current_text) {
+                            let redirect = current_redirect.take();
+                            match self.parser.parse_page(&sanitized_title, &current_text, redirect.as_deref()) {
+                                Some(ParsedArticle::Article { categories, .. })
+                                    if has_category_in(&categories, &excluded_categories)
+                                        || (!included_categories.is_empty()
+                                            && !has_category_in(&categories, &included_categories)) =>
+                                {
+                                    stats.skipped_by_category += 1;
+                                    stats.articles_skipped += 1;
+                                }
+                                Some(ParsedArticle::Article { .. }) if !self.config.accepts_edit_time(current_edited_at) => {
+                                    stats.skipped_by_date += 1;
+                                    stats.articles_skipped += 1;
+                                }
+                                Some(ParsedArticle::Article { ref content, .. })
+                                    if !self.config.accepts_word_count(content.split_whitespace().count()) =>
+                                {
+                                    stats.skipped_by_word_count += 1;
+                                    stats.articles_skipped += 1;
+                                }
+                                // Before pruning, so links to missing articles still count
+                                Some(ParsedArticle::Article { ref content, .. })
+                                    if !self.config.accepts_link_count(count_wiki_links(content)) =>
+                                {
+                                    stats.skipped_by_links += 1;
+                                    stats.articles_skipped += 1;
+                                }
                                 Some(ParsedArticle::Article { title, content, categories, raw_markup }) => {
+                                    let id = if current_id == 0 {
+                                        let id = Article::fallback_id(&title);
+                                        tracing::warn!("Article '{}' has no page id, using fallback id {}", title, id);
+                                        id
+                                    } else {
+                                        current_id
+                                    };
+
                                     let article = Article {
-                                        id: current_id,
+                                        id,
                                         title,
-                                        content: content.clone(),
+                                        content_hash: Article::hash_content(&content),
+                                        content,
                                         raw_markup,
                                         categories,
                                         redirect_to: None,
                                         extracted_at: chrono::Utc::now(),
+                                        last_edited: current_edited_at,
                                     };
 
                                     // Write as JSONL
                                     let json = serde_json::to_string(&article)?;
-                                    writeln!(writer, "{}", json)?;
+                                    writer.write_line(&json)?;
 
                                     stats.articles_extracted += 1;
-                                    stats.total_bytes += content.len() as u64;
+                                    stats.total_bytes += article.content.len() as u64;
 
-                                    if stats.articles_extracted % 1000 == 0 {
-                                        pb.set_message(format!("{} articles", stats.articles_extracted));
+                                    if stats.articles_extracted.is_multiple_of(1000) {
+                                        rate.record(stats.articles_extracted);
+                                        let per_second = rate.per_second()
+                                            .unwrap_or_else(|| stats.articles_per_second());
+                                        pb.set_message(extraction_message(
+                                            stats.articles_extracted,
+                                            per_second,
+                                            expected_articles,
+                                        ));
                                     }
 
                                     // Check max articles limit
@@ -328,10 +692,12 @@ impl WikiDownloader {
                                         && stats.articles_extracted >= self.config.max_articles as u64 
                                     {
                                         tracing::info!("Reached max articles limit ({})", self.config.max_articles);
+                                        stats.truncated = true;
                                         break;
                                     }
                                 }
-                                Some(ParsedArticle::Redirect { .. }) => {
+                                Some(ParsedArticle::Redirect { title, target }) => {
+                                    redirects.insert(&title, &target);
                                     stats.redirects += 1;
                                     stats.articles_skipped += 1;
                                 }
@@ -343,13 +709,23 @@ impl WikiDownloader {
                             current_title.clear();
                             current_text.clear();
                             current_id = 0;
+                            current_edited_at = None;
                         }
                         _ => {}
                     }
                 }
                 Ok(Event::Text(e)) => {
-                    let text = e.unescape().unwrap_or_default();
-                    if in_title {
+                    let text = match e.unescape() {
+                        Ok(text) => text,
+                        Err(err) if !path.is_empty() => {
+                            xml_error = Some(err.to_string());
+                            Default::default()
+                        }
+                        Err(_) => Default::default(),
+                    };
+                    if xml_error.is_some() {
+                        // Recovered below
+                    } else if in_title {
                         current_title.push_str(&text);
                     } else if in_text {
                         current_text.push_str(&text);
@@ -357,28 +733,76 @@ impl WikiDownloader {
                         if let Ok(id) = text.parse::<u64>() {
                             current_id = id;
                         }
+                    } else if in_timestamp {
+                        if let Ok(edited_at) = DateTime::parse_from_rfc3339(text.trim()) {
+                            let edited_at = edited_at.with_timezone(&Utc);
+                            current_edited_at = current_edited_at.max(Some(edited_at));
+                        }
                     }
                 }
                 Ok(Event::Eof) => break,
-                Err(e) => {
-                    tracing::warn!("XML parse error at article {}: {}", stats.articles_extracted, e);
-                    current_title.clear();
-                    current_text.clear();
-                }
+                Err(e) => xml_error = Some(e.to_string()),
                 _ => {}
             }
 
-            // Update progress (approximate based on buffer position)
-            pb.set_position(xml_reader.buffer_position());
+            if let Some(error) = xml_error.take() {
+                let position = xml_reader.buffer_position();
+                if last_error_position == Some(position) {
+                    tracing::warn!("XML reader made no progress after an error, stopping extraction");
+                    break;
+                }
+                last_error_position = Some(position);
+                if !path.iter().any(|name| name == b"page") {
+                    // Outside any page (e.g. a stray closing tag left by an
+                    // earlier recovery), so no content is affected
+                    tracing::debug!("XML error near byte {} outside a page: {}", position, error);
+                    buf.clear();
+                    continue;
+                }
+                stats.xml_errors_recovered += 1;
+                tracing::warn!(
+                    "XML error near byte {} of the uncompressed dump ({}), skipping to the next page",
+                    position, error
+                );
+                current_title.clear();
+                current_text.clear();
+                current_id = 0;
+                current_redirect = None;
+                in_title = false;
+                in_text = false;
+                in_id = false;
+                in_timestamp = false;
+                current_edited_at = None;
+                resyncing = true;
+            }
+
+            // Update progress from the compressed bytes consumed so far
+            let position = compressed_read.load(Ordering::Relaxed).min(file_size);
+            pb.set_position(position);
+            if position - last_reported >= PROGRESS_REPORT_INTERVAL {
+                self.report_progress(DownloadPhase::Extracting, position, Some(file_size));
+                last_reported = position;
+            }
             buf.clear();
         }
 
-        writer.flush()?;
+        self.report_progress(DownloadPhase::Extracting, file_size, Some(file_size));
+
         pb.finish_with_message(format!("{} articles extracted!", stats.articles_extracted));
 
+        // Swap in the finished files
+        let marker_path = self.config.complete_marker_path();
+        if marker_path.exists() {
+            fs::remove_file(&marker_path)?;
+        }
+        let output_files = writer.commit()?;
+
         // Finalize stats
         stats.finish();
 
+        // Save redirect aliases
+        redirects.save(self.config.redirects_path())?;
+
         // Save stats
         let stats_json = serde_json::to_string_pretty(&stats)?;
         fs::write(self.config.stats_path(), stats_json)?;
@@ -386,6 +810,9 @@ impl WikiDownloader {
         // Save config
         self.config.save()?;
 
+        // Mark the extraction complete last, once everything else is written
+        fs::write(&marker_path, format!("{}\n", stats.articles_extracted))?;
+
         // Optionally clean up dump file
         if !self.config.keep_dump {
             tracing::info!("Cleaning up dump file...");
@@ -396,16 +823,34 @@ impl WikiDownloader {
         tracing::info!("  Articles extracted: {}", stats.articles_extracted);
         tracing::info!("  Articles skipped: {}", stats.articles_skipped);
         tracing::info!("  Redirects: {}", stats.redirects);
+        if stats.skipped_by_category > 0 {
+            tracing::info!("  Skipped by category: {}", stats.skipped_by_category);
+        }
+        if stats.skipped_by_word_count > 0 {
+            tracing::info!("  Skipped by word count: {}", stats.skipped_by_word_count);
+        }
+        if stats.skipped_by_date > 0 {
+            tracing::info!("  Skipped as last edited before --since: {}", stats.skipped_by_date);
+        }
+        if stats.skipped_by_links > 0 {
+            tracing::info!("  Skipped for too few links: {}", stats.skipped_by_links);
+        }
+        if stats.xml_errors_recovered > 0 {
+            tracing::warn!("  Recovered from {} XML error(s); the affected pages were skipped", stats.xml_errors_recovered);
+        }
         tracing::info!("  Total content: {}", format_bytes(stats.total_bytes));
-        tracing::info!("  Output: {:?}", output_path);
+        match output_files.as_slice() {
+            [file] => tracing::info!("  Output: {:?}", file),
+            files => tracing::info!("  Output: {} shards in {:?}", files.len(), self.config.output_dir),
+        }
 
         Ok(stats)
     }
 
     /// Download and extract in one step
-    pub fn run(&self) -> Result<ExtractionStats> {
+    pub fn run(&self) -> Result<ExtractionStats, Error> {
         self.download()?;
-        self.extract()
+        Ok(self.extract()?)
     }
 }
 
@@ -415,20 +860,743 @@ impl Default for WikiDownloader {
     }
 }
 
-/// Format bytes as human-readable string
-fn format_bytes(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-
-    if bytes >= GB {
-        format!("{:.2} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.2} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.2} KB", bytes as f64 / KB as f64)
+/// Reader wrapper that counts the bytes read through it
+struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R> CountingReader<R> {
+    fn new(inner: R, count: Arc<AtomicU64>) -> Self {
+        Self { inner, count }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// Check whether any of an article's categories is in the given (normalized) set
+fn has_category_in(categories: &[String], set: &HashSet<String>) -> bool {
+    !set.is_empty()
+        && categories.iter().any(|c| set.contains(&WikiParser::normalize_category(c)))
+}
+
+/// Disk space needed to download and extract a dump of `download_size` bytes
+/// (1 GB when the size is unknown)
+pub fn required_space(download_size: u64) -> u64 {
+    if download_size > 0 {
+        download_size.saturating_mul(2)
     } else {
-        format!("{} bytes", bytes)
+        1024 * 1024 * 1024
+    }
+}
+
+/// Available space on the filesystem holding `dir`.
+///
+/// `dir` does not need to exist yet; its nearest existing ancestor is used.
+fn available_space_for(dir: &Path) -> Option<u64> {
+    dir.ancestors()
+        .find(|p| p.exists())
+        .or(Some(Path::new(".")))
+        .and_then(|p| available_space(p).ok())
+}
+
+/// Check that the filesystem holding `dir` has room for a dump of
+/// `download_size` bytes. Passes if the available space can't be determined.
+pub fn check_disk_space(dir: &Path, download_size: u64) -> Result<(), DownloadError> {
+    let required = required_space(download_size);
+    if let Some(available) = available_space_for(dir) {
+        if available < required {
+            return Err(DownloadError::InsufficientSpace { available, required });
+        }
     }
+    Ok(())
 }
 
+/// Space extracting a dump of `dump_size` bytes is estimated to need
+pub fn extraction_space(dump_size: u64, multiplier: f64) -> u64 {
+    (dump_size as f64 * multiplier) as u64
+}
+
+/// Check that `available` bytes (if known) cover the [`extraction_space`]
+/// of a dump of `dump_size` bytes
+pub fn check_extraction_space(available: Option<u64>, dump_size: u64, multiplier: f64) -> Result<(), ExtractError> {
+    let required = extraction_space(dump_size, multiplier);
+    match available {
+        Some(available) if available < required => Err(ExtractError::InsufficientSpace { available, required }),
+        _ => Ok(()),
+    }
+}
+
+/// Check that `dir` (created if missing) can be written to and has room
+/// for a file of `size` bytes
+pub fn check_temp_dir(dir: &Path, size: u64) -> Result<(), DownloadError> {
+    let not_writable = |source| DownloadError::TempDirNotWritable { path: dir.to_path_buf(), source };
+    fs::create_dir_all(dir).map_err(not_writable)?;
+    let probe = dir.join(".rustipedia-write-test");
+    File::create(&probe).map_err(not_writable)?;
+    fs::remove_file(&probe).ok();
+
+    if let Some(available) = available_space_for(dir) {
+        if available < size {
+            return Err(DownloadError::InsufficientSpace { available, required: size });
+        }
+    }
+    Ok(())
+}
+
+/// Move a file, copying it when `from` and `to` are on different filesystems.
+///
+/// A copy goes to a `.tmp` sibling of `to` first, so `to` only ever holds
+/// the complete file.
+pub fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    let mut copy_name = to.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    copy_name.push(".tmp");
+    let copy_path = to.with_file_name(copy_name);
+    fs::copy(from, &copy_path)?;
+    fs::rename(&copy_path, to)?;
+    fs::remove_file(from)
+}
+
+/// Check that a dump of `download_size` bytes is within [`MAX_DOWNLOAD_SIZE`]
+pub fn check_download_size(download_size: u64) -> Result<(), DownloadError> {
+    if download_size > MAX_DOWNLOAD_SIZE {
+        return Err(DownloadError::TooLarge { size: download_size, limit: MAX_DOWNLOAD_SIZE });
+    }
+    Ok(())
+}
+
+/// Rolling articles-per-second over the last [`RATE_WINDOW`]
+struct ArticleRate {
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl ArticleRate {
+    fn new() -> Self {
+        Self { samples: VecDeque::new() }
+    }
+
+    /// Record the article count at the current time
+    fn record(&mut self, articles: u64) {
+        let now = Instant::now();
+        self.samples.push_back((now, articles));
+        while self.samples.len() > 2
+            && self.samples.front().is_some_and(|(at, _)| now.duration_since(*at) > RATE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Rate over the window, once there are two samples to compare
+    fn per_second(&self) -> Option<f64> {
+        let (first_at, first) = self.samples.front()?;
+        let (last_at, last) = self.samples.back()?;
+        let elapsed = last_at.duration_since(*first_at).as_secs_f64();
+        (elapsed > 0.0).then(|| (last - first) as f64 / elapsed)
+    }
+}
+
+/// Seconds left to reach `expected` articles at `per_second`, if known
+fn extraction_eta(extracted: u64, per_second: f64, expected: Option<u64>) -> Option<u64> {
+    let remaining = expected?.checked_sub(extracted).filter(|n| *n > 0)?;
+    (per_second > 0.0).then(|| (remaining as f64 / per_second).round() as u64)
+}
+
+/// Progress bar message during extraction: count, throughput and ETA
+fn extraction_message(extracted: u64, per_second: f64, expected: Option<u64>) -> String {
+    let eta = match extraction_eta(extracted, per_second, expected) {
+        Some(secs) => format!("ETA {}", format_eta(Some(secs))),
+        None => "unknown ETA".to_string(),
+    };
+    format!("{} articles, {:.0}/s, {}", extracted, per_second, eta)
+}
+
+/// Format an ETA in seconds as a human-readable string
+pub fn format_eta(eta_seconds: Option<u64>) -> String {
+    if let Some(eta) = eta_seconds {
+        let hours = eta / 3600;
+        let minutes = (eta % 3600) / 60;
+        let seconds = eta % 60;
+
+        if hours > 0 {
+            format!("{}h {}m", hours, minutes)
+        } else if minutes > 0 {
+            format!("{}m {}s", minutes, seconds)
+        } else {
+            format!("{}s", seconds)
+        }
+    } else {
+        "Unknown".to_string()
+    }
+}
+
+/// Format bytes as human-readable string, using English conventions
+pub fn format_bytes(bytes: u64) -> String {
+    NumberFormat::ENGLISH.format_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bzip2::write::BzEncoder;
+    use bzip2::Compression;
+
+    /// Wrap `<page>` elements in a minimal MediaWiki export document
+    fn dump_xml(pages: &str) -> String {
+        format!("<mediawiki><siteinfo><sitename>Test</sitename></siteinfo>{}</mediawiki>", pages)
+    }
+
+    /// Build a `<page>` element with the given id, title and wikitext
+    fn page(id: u64, title: &str, text: &str) -> String {
+        format!(
+            "<page><title>{}</title><ns>0</ns><id>{}</id><revision><id>{}</id><text>{}</text></revision></page>",
+            title, id, id + 1000, text
+        )
+    }
+
+    /// Write an XZ-compressed dump into `config.dump_path()`
+    fn write_xz_dump(config: &Config, xml: &str) {
+        fs::create_dir_all(&config.output_dir).unwrap();
+        let file = File::create(config.dump_path()).unwrap();
+        let mut encoder = xz2::write::XzEncoder::new(file, 1);
+        encoder.write_all(xml.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    /// Write a bz2-compressed dump into `config.dump_path()`
+    fn write_dump(config: &Config, xml: &str) {
+        fs::create_dir_all(&config.output_dir).unwrap();
+        let file = File::create(config.dump_path()).unwrap();
+        let mut encoder = BzEncoder::new(file, Compression::fast());
+        encoder.write_all(xml.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    /// Run extraction and return the extracted articles
+    fn extract_articles(config: Config) -> (ExtractionStats, Vec<Article>) {
+        let data_path = config.data_path();
+        let stats = WikiDownloader::with_config(config).extract().unwrap();
+        let articles = fs::read_to_string(data_path).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        (stats, articles)
+    }
+
+    fn test_config(dir: &std::path::Path) -> Config {
+        Config::new()
+            .with_output_dir(dir)
+            .with_min_length(10)
+    }
+
+    #[test]
+    fn test_interrupted_extraction_keeps_previous_articles() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        write_dump(&config, &dump_xml(&page(1, "First", "The first complete extraction.")));
+        extract_articles(config.clone());
+        assert_eq!(ExtractionState::of(dir.path()), ExtractionState::Complete);
+        assert_eq!(fs::read_to_string(config.complete_marker_path()).unwrap(), "1\n");
+
+        // A killed run leaves its partial output behind
+        fs::write(config.partial_data_path(), "{\"id\":2,\"title\":\"Half").unwrap();
+        assert_eq!(ExtractionState::of(dir.path()), ExtractionState::Interrupted);
+        let previous = fs::read_to_string(config.data_path()).unwrap();
+        assert!(previous.contains("\"First\""));
+
+        // The next run replaces it and marks the directory complete again
+        write_dump(&config, &dump_xml(&page(2, "Second", "The second complete extraction.")));
+        let (_, articles) = extract_articles(config.clone());
+        assert_eq!(articles.len(), 1);
+        assert_eq!(articles[0].title, "Second");
+        assert!(!config.partial_data_path().exists());
+        assert_eq!(ExtractionState::of(dir.path()), ExtractionState::Complete);
+    }
+
+    #[test]
+    fn test_extraction_space_check_runs_before_writing() {
+        assert!(check_extraction_space(Some(400), 100, DEFAULT_EXTRACT_SPACE_MULTIPLIER).is_ok());
+        assert!(check_extraction_space(None, 100, DEFAULT_EXTRACT_SPACE_MULTIPLIER).is_ok());
+        match check_extraction_space(Some(399), 100, DEFAULT_EXTRACT_SPACE_MULTIPLIER) {
+            Err(ExtractError::InsufficientSpace { available: 399, required: 400 }) => {}
+            other => panic!("expected InsufficientSpace, got {:?}", other),
+        }
+
+        // No disk holds a dump times 1e18
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path()).with_extract_space_multiplier(1e18);
+        write_dump(&config, &dump_xml(&page(1, "First", "Never extracted for lack of space.")));
+        let err = WikiDownloader::with_config(config.clone()).extract().unwrap_err();
+        assert!(matches!(err, ExtractError::InsufficientSpace { .. }), "{:?}", err);
+        assert!(err.to_string().contains("Insufficient disk space"), "{}", err);
+        assert!(!config.partial_data_path().exists());
+        assert!(!config.data_path().exists());
+
+        config.extract_space_multiplier = DEFAULT_EXTRACT_SPACE_MULTIPLIER;
+        extract_articles(config);
+    }
+
+    #[test]
+    fn test_extract_word_count_range_boundaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path()).with_word_range(3, 5);
+        write_dump(&config, &dump_xml(&[
+            page(1, "Two", "alpha beta"),
+            page(2, "Three", "alpha beta gamma"),
+            page(3, "Five", "alpha beta gamma delta epsilon"),
+            page(4, "Six", "alpha beta gamma delta epsilon zeta"),
+        ].concat()));
+
+        let (stats, articles) = extract_articles(config);
+
+        let titles: Vec<&str> = articles.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, vec!["Three", "Five"]);
+        assert_eq!(stats.skipped_by_word_count, 2);
+        assert_eq!(stats.articles_extracted, 2);
+    }
+
+    #[test]
+    fn test_extract_min_links_boundary() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path()).with_min_links(2);
+        write_dump(&config, &dump_xml(&[
+            page(1, "None", "No links at all in this article."),
+            page(2, "One", "Links to [[Iron]] and to [[#History|its history]]."),
+            page(3, "Two", "Links to [[Iron]] and [[Copper|copper]]."),
+            page(4, "Three", "Links to [[Iron]], [[Copper]] and [[Missing page#Uses|a section]]."),
+        ].concat()));
+
+        let (stats, articles) = extract_articles(config);
+
+        let titles: Vec<&str> = articles.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, vec!["Two", "Three"]);
+        assert_eq!(stats.skipped_by_links, 2);
+        assert_eq!(stats.articles_skipped, 2);
+    }
+
+    #[test]
+    fn test_extract_since_skips_older_revisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path())
+            .with_since(chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+        let revised = |id: u64, title: &str, timestamps: &[&str]| {
+            let revisions: String = timestamps.iter()
+                .map(|t| format!("<revision><id>{}</id><timestamp>{}</timestamp><text>Article text for {}.</text></revision>", id + 1000, t, title))
+                .collect();
+            format!("<page><title>{}</title><ns>0</ns><id>{}</id>{}</page>", title, id, revisions)
+        };
+        write_dump(&config, &dump_xml(&[
+            revised(1, "Old", &["2023-12-31T23:59:59Z"]),
+            revised(2, "Recent", &["2024-01-01T00:00:00Z"]),
+            revised(3, "Revised", &["2019-05-01T10:00:00Z", "2024-06-15T08:30:00Z"]),
+            page(4, "Undated", "An article without a revision timestamp."),
+        ].concat()));
+
+        let (stats, articles) = extract_articles(config);
+
+        let titles: Vec<&str> = articles.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, vec!["Recent", "Revised", "Undated"]);
+        assert_eq!(stats.skipped_by_date, 1);
+        assert_eq!(articles[1].last_edited.unwrap().to_rfc3339(), "2024-06-15T08:30:00+00:00");
+        assert_eq!(articles[2].last_edited, None);
+    }
+
+    #[test]
+    fn test_extract_compressed_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        write_dump(&config, &dump_xml(&page(1, "Plain", "An earlier uncompressed extraction.")));
+        extract_articles(config.clone());
+
+        let config = config.with_compress_output(true);
+        write_dump(&config, &dump_xml(&[
+            page(1, "Alpha", "The first compressed article."),
+            page(2, "Beta", "The second compressed article."),
+        ].concat()));
+        WikiDownloader::with_config(config.clone()).extract().unwrap();
+
+        // The stale uncompressed file is replaced by the compressed one
+        assert_eq!(config.data_path(), dir.path().join("articles.jsonl.gz"));
+        assert!(!dir.path().join("articles.jsonl").exists());
+        assert_eq!(jsonl::articles_path(dir.path()), config.data_path());
+        assert_eq!(ExtractionState::of(dir.path()), ExtractionState::Complete);
+
+        let articles: Vec<Article> = std::io::BufRead::lines(jsonl::open(&config.data_path()).unwrap())
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect();
+        let titles: Vec<&str> = articles.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, vec!["Alpha", "Beta"]);
+
+        let index = crate::SearchIndex::create(config.index_path()).unwrap();
+        assert_eq!(index.build_from_jsonl(config.data_path()).unwrap(), 2);
+        assert_eq!(index.search("compressed", 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_extract_sharded_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path()).with_shard_size(2);
+        let titles = ["Alpha", "Beta", "Gamma", "Delta", "Epsilon"];
+        let pages: String = titles.iter().enumerate()
+            .map(|(i, title)| page(i as u64 + 1, title, &format!("The sharded article {}.", title)))
+            .collect();
+        write_dump(&config, &dump_xml(&pages));
+        WikiDownloader::with_config(config.clone()).extract().unwrap();
+
+        let files = jsonl::articles_files(dir.path());
+        let names: Vec<String> = files.iter().map(|f| f.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, vec!["articles.000.jsonl", "articles.001.jsonl", "articles.002.jsonl"]);
+        assert_eq!(config.data_path(), files[0]);
+        assert_eq!(ExtractionState::of(dir.path()), ExtractionState::Complete);
+
+        let articles: Vec<Article> = std::io::BufRead::lines(jsonl::open_all(&files).unwrap())
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect();
+        assert_eq!(articles.iter().map(|a| a.title.as_str()).collect::<Vec<_>>(), titles);
+
+        let index = crate::SearchIndex::create(config.index_path()).unwrap();
+        assert_eq!(index.build_from_jsonl_files(&files, 2).unwrap(), 5);
+        assert_eq!(index.search("sharded", 10).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_extract_assigns_fallback_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        write_dump(&config, &dump_xml(
+            "<page><title>No Id</title><ns>0</ns><revision><text>An article without any id element.</text></revision></page>"
+        ));
+
+        let (_, articles) = extract_articles(config);
+
+        assert_eq!(articles.len(), 1);
+        assert_ne!(articles[0].id, 0);
+        assert_eq!(articles[0].id, Article::fallback_id("No Id"));
+        assert_eq!(articles[0].id & (1 << 63), 1 << 63);
+    }
+
+    #[test]
+    fn test_extract_uses_page_id_not_revision_or_contributor_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        // Revision and contributor ids come before the page id here, as some
+        // exporters order elements differently from Wikimedia's dumps.
+        write_dump(&config, &dump_xml(
+            "<page><title>Ids</title><ns>0</ns>\
+             <revision><id>222</id><contributor><username>Someone</username><id>333</id></contributor>\
+             <text>An article with several kinds of ids.</text></revision>\
+             <id>111</id></page>"
+        ));
+
+        let (_, articles) = extract_articles(config);
+
+        assert_eq!(articles.len(), 1);
+        assert_eq!(articles[0].id, 111);
+    }
+
+    #[test]
+    fn test_extract_saves_redirects() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        write_dump(&config, &dump_xml(&[
+            page(1, "United States", "A country in North America."),
+            page(2, "USA", "#REDIRECT [[United States]]"),
+        ].concat()));
+        let redirects_path = config.redirects_path();
+
+        let (stats, articles) = extract_articles(config);
+
+        assert_eq!(articles.len(), 1);
+        assert_eq!(stats.redirects, 1);
+        let redirects = RedirectMap::load(redirects_path).unwrap();
+        assert_eq!(redirects.resolve("USA"), Some("United States".to_string()));
+    }
+
+    #[test]
+    fn test_extract_resyncs_after_corrupt_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        write_dump(&config, &dump_xml(&[
+            page(1, "Before", "The page before the corrupt one."),
+            // <text> is never closed, and the entity is malformed
+            "<page><title>Broken</title><ns>0</ns><id>2</id><revision><id>1002</id>\
+             <text>Broken &bogus text</revision></page>".to_string(),
+            page(3, "After", "The page after the corrupt one."),
+        ].concat()));
+
+        let (stats, articles) = extract_articles(config);
+
+        assert_eq!(stats.xml_errors_recovered, 1);
+        let titles: Vec<&str> = articles.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, vec!["Before", "After"]);
+        assert_eq!(articles[0].content, "The page before the corrupt one.");
+        assert_eq!(articles[1].content, "The page after the corrupt one.");
+        assert_eq!(articles[1].id, 3);
+    }
+
+    #[test]
+    fn test_extract_redirect_element_is_authoritative() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        write_dump(&config, &dump_xml(&[
+            page(1, "Vereinigte Staaten", "Ein Staat in Nordamerika."),
+            // Localized keyword, and the element names the target
+            "<page><title>USA</title><ns>0</ns><id>2</id><redirect title=\"Vereinigte Staaten\" />\
+             <revision><id>1002</id><text>#WEITERLEITUNG [[Vereinigte Staaten]]</text></revision></page>".to_string(),
+            // The element wins over unrecognized text
+            "<page><title>US of A</title><ns>0</ns><id>3</id><redirect title=\"Vereinigte Staaten\" />\
+             <revision><id>1003</id><text>Some text that doesn't look like a redirect.</text></revision></page>".to_string(),
+        ].concat()));
+        let redirects_path = config.redirects_path();
+
+        let (stats, articles) = extract_articles(config);
+
+        assert_eq!(articles.len(), 1);
+        assert_eq!(stats.redirects, 2);
+        let redirects = RedirectMap::load(redirects_path).unwrap();
+        assert_eq!(redirects.resolve("USA"), Some("Vereinigte Staaten".to_string()));
+        assert_eq!(redirects.resolve("US of A"), Some("Vereinigte Staaten".to_string()));
+    }
+
+    #[test]
+    fn test_extract_reports_progress_to_callback() {
+        use std::sync::Mutex;
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        write_dump(&config, &dump_xml(&page(1, "Progress", "Some article text to extract.")));
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let downloader = WikiDownloader::with_config(config)
+            .with_progress_callback(Box::new(move |p| sink.lock().unwrap().push(p)));
+        downloader.extract().unwrap();
+
+        let reports = reports.lock().unwrap();
+        let last = reports.last().expect("no progress reported");
+        assert_eq!(last.phase, DownloadPhase::Extracting);
+        assert_eq!(Some(last.bytes_done), last.total);
+        assert!(reports.iter().all(|p| Some(p.bytes_done) <= p.total));
+    }
+
+    #[test]
+    fn test_extract_excludes_categories() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path())
+            .with_exclude_categories(vec!["Living_people".to_string()]);
+        write_dump(&config, &dump_xml(&[
+            page(1, "Kept", "A perfectly ordinary article. [[Category:Physics]]"),
+            page(2, "Dropped", "An article about a person. [[Category:Living people]]"),
+        ].concat()));
+
+        let (stats, articles) = extract_articles(config);
+
+        assert_eq!(articles.len(), 1);
+        assert_eq!(articles[0].title, "Kept");
+        assert_eq!(stats.skipped_by_category, 1);
+    }
+
+    #[test]
+    fn test_extract_include_categories_with_exclusion_winning() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path())
+            .with_include_categories(vec!["Physics".to_string()])
+            .with_exclude_categories(vec!["Stubs".to_string()]);
+        write_dump(&config, &dump_xml(&[
+            page(1, "Optics", "Light and lenses explained. [[Category:Physics]]"),
+            page(2, "Poetry", "Verses and rhymes explained. [[Category:Literature]]"),
+            page(3, "Muon", "A short particle stub. [[Category:Physics]] [[Category:Stubs]]"),
+        ].concat()));
+
+        let (stats, articles) = extract_articles(config);
+
+        let titles: Vec<_> = articles.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, vec!["Optics"]);
+        assert_eq!(stats.skipped_by_category, 2);
+    }
+
+    #[test]
+    fn test_plan_does_not_touch_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_dir = dir.path().join("not-yet-created");
+        let config = test_config(&output_dir).with_language(WikiLanguage::Simple);
+        let plan = WikiDownloader::with_config(config).plan();
+
+        assert!(plan.url.ends_with("simplewiki-latest-pages-articles.xml.bz2"));
+        assert_eq!(plan.required_space, plan.estimated_size * 2);
+        assert!(plan.available_space.is_some());
+        assert!(!plan.dump_exists);
+        assert!(!output_dir.exists());
+    }
+
+    #[test]
+    fn test_check_disk_space() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("a/b");
+
+        assert!(check_disk_space(&missing, 1024).is_ok());
+        match check_disk_space(&missing, u64::MAX / 4) {
+            Err(DownloadError::InsufficientSpace { available, required }) => {
+                assert_eq!(required, required_space(u64::MAX / 4));
+                assert!(available < required);
+            }
+            other => panic!("expected InsufficientSpace, got {:?}", other),
+        }
+    }
+
+    /// Serve `body` at any path, and its SHA-256 at paths ending in `.sha256`,
+    /// for `requests` requests; returns the base URL
+    fn serve_dump(body: &'static [u8], requests: usize) -> String {
+        use std::io::{BufRead, BufReader};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+                let content = if request_line.contains(".sha256 ") {
+                    format!("{}  dump.xml.bz2\n", hex::encode(Sha256::digest(body))).into_bytes()
+                } else {
+                    body.to_vec()
+                };
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", content.len()).unwrap();
+                stream.write_all(&content).unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_download_goes_through_temp_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_dir = dir.path().join("wiki");
+        let temp_dir = dir.path().join("scratch");
+        let config = Config::new()
+            .with_output_dir(&output_dir)
+            .with_temp_dir(&temp_dir)
+            .with_dump_base_url(serve_dump(b"not really bzip2", 2));
+        let partial_path = config.partial_dump_path();
+        assert_eq!(partial_path, temp_dir.join("simplewiki-latest-pages-articles.xml.bz2.tmp"));
+
+        // Where the dump is while it is being written
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen_in_callback = seen.clone();
+        let (partial, in_output) = (partial_path.clone(), output_dir.join("simplewiki-latest-pages-articles.xml.bz2.tmp"));
+        let downloader = WikiDownloader::with_config(config.clone())
+            .with_progress_callback(Box::new(move |_| {
+                seen_in_callback.lock().unwrap().push((partial.exists(), in_output.exists()));
+            }));
+        downloader.download().unwrap();
+
+        assert_eq!(*seen.lock().unwrap().last().unwrap(), (true, false));
+        assert_eq!(fs::read(config.dump_path()).unwrap(), b"not really bzip2");
+        assert!(!partial_path.exists());
+
+        // A temp dir that can't be created is reported before downloading
+        let blocked = dir.path().join("file");
+        fs::write(&blocked, "").unwrap();
+        assert!(matches!(check_temp_dir(&blocked.join("scratch"), 0), Err(DownloadError::TempDirNotWritable { .. })));
+    }
+
+    #[test]
+    fn test_check_download_size() {
+        assert!(check_download_size(MAX_DOWNLOAD_SIZE).is_ok());
+        assert!(matches!(
+            check_download_size(MAX_DOWNLOAD_SIZE + 1),
+            Err(DownloadError::TooLarge { size, limit }) if size == MAX_DOWNLOAD_SIZE + 1 && limit == MAX_DOWNLOAD_SIZE
+        ));
+        let message = check_download_size(u64::MAX).unwrap_err().to_string();
+        assert!(message.starts_with("Download size"), "{}", message);
+    }
+
+    #[test]
+    fn test_extract_keeps_raw_markup() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = "Some [[Linked page|linked]] text that is long enough.";
+        let config = test_config(dir.path());
+        write_dump(&config, &dump_xml(&page(1, "Plain", text)));
+        let (_, articles) = extract_articles(config.clone());
+        assert_eq!(articles[0].raw_markup, None);
+
+        let config = config.with_raw_markup(true);
+        write_dump(&config, &dump_xml(&page(1, "Raw", text)));
+        let (_, articles) = extract_articles(config);
+        assert_eq!(articles[0].raw_markup.as_deref(), Some(text));
+    }
+
+    #[test]
+    fn test_extract_flags_truncation() {
+        let dir = tempfile::tempdir().unwrap();
+        let pages = format!("{}{}",
+            page(1, "First", "The first article body text."),
+            page(2, "Second", "The second article body text."));
+        let config = test_config(dir.path()).with_max_articles(1);
+        write_dump(&config, &dump_xml(&pages));
+
+        let (stats, articles) = extract_articles(config);
+        assert_eq!(articles.len(), 1);
+        assert!(stats.truncated);
+        assert_eq!(stats.max_articles, 1);
+
+        let config = test_config(dir.path()).with_max_articles(5);
+        write_dump(&config, &dump_xml(&pages));
+        let (stats, _) = extract_articles(config);
+        assert!(!stats.truncated);
+    }
+
+    #[test]
+    fn test_extract_xz_dump() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path()).with_prefer_xz(true);
+        assert!(config.dump_path().to_string_lossy().ends_with(".xml.xz"));
+        write_xz_dump(&config, &dump_xml(&page(7, "Compressed", "Article body stored in an xz dump.")));
+
+        assert_eq!(DumpCompression::detect(&config.dump_path()).unwrap(), DumpCompression::Xz);
+        let (stats, articles) = extract_articles(config);
+        assert_eq!(stats.articles_extracted, 1);
+        assert_eq!(articles[0].id, 7);
+        assert_eq!(articles[0].title, "Compressed");
+    }
+
+    #[test]
+    fn test_extract_plain_xml_dump() {
+        let dir = tempfile::tempdir().unwrap();
+        let dump = dir.path().join("pages-articles.xml");
+        fs::write(&dump, dump_xml(&page(3, "Uncompressed", "Article body from a plain xml dump."))).unwrap();
+        let config = test_config(&dir.path().join("out")).with_dump_file(&dump);
+        fs::create_dir_all(&config.output_dir).unwrap();
+
+        assert_eq!(DumpCompression::detect(&dump).unwrap(), DumpCompression::Uncompressed);
+        let (stats, articles) = extract_articles(config);
+        assert_eq!(stats.articles_extracted, 1);
+        assert_eq!(articles[0].title, "Uncompressed");
+    }
+
+    #[test]
+    fn test_extraction_eta_message() {
+        // 6.7M expected, 1M done at 1000/s
+        assert_eq!(extraction_eta(1_000_000, 1000.0, Some(6_700_000)), Some(5700));
+        assert_eq!(
+            extraction_message(1_000_000, 1000.0, Some(6_700_000)),
+            "1000000 articles, 1000/s, ETA 1h 35m"
+        );
+        // Custom dumps have no estimate, and estimates can be exceeded
+        assert_eq!(extraction_message(5000, 250.0, None), "5000 articles, 250/s, unknown ETA");
+        assert_eq!(extraction_eta(7_000_000, 1000.0, Some(6_700_000)), None);
+        assert_eq!(extraction_eta(10, 0.0, Some(100)), None);
+    }
+}
@@ -0,0 +1,77 @@
+//! Errors from downloading and extracting dumps
+//!
+//! [`WikiDownloader`](crate::WikiDownloader) returns these instead of
+//! `anyhow::Error`, so programs embedding the library can tell a full disk
+//! from a failed checksum or a network problem and react to each. The rest
+//! of the library and the binaries use `anyhow`, which both convert into.
+
+use std::path::PathBuf;
+
+use crate::downloader::format_bytes;
+
+/// Why downloading a dump failed
+#[derive(Debug, thiserror::Error)]
+pub enum DownloadError {
+    /// `dump_base_url` isn't a usable mirror URL
+    #[error("{0}")]
+    InvalidDumpBaseUrl(String),
+    /// The server has no dump at this URL (HTTP 404)
+    #[error("Dump not found at {url}")]
+    DumpNotFound { url: String },
+    /// The server answered with another error status
+    #[error("Download of {url} failed with status: {status}")]
+    HttpStatus { url: String, status: u16 },
+    /// Connecting or reading the response failed
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+    /// The temporary directory can't be created or written to
+    #[error("Temporary directory {path:?} is not writable: {source}")]
+    TempDirNotWritable { path: PathBuf, source: std::io::Error },
+    /// The filesystem doesn't have room for the dump and its extraction
+    #[error("Insufficient disk space. Available: {}, Required: {}", format_bytes(*available), format_bytes(*required))]
+    InsufficientSpace { available: u64, required: u64 },
+    /// The dump is larger than [`MAX_DOWNLOAD_SIZE`](crate::downloader::MAX_DOWNLOAD_SIZE)
+    #[error("Download size {} exceeds limit of {}", format_bytes(*size), format_bytes(*limit))]
+    TooLarge { size: u64, limit: u64 },
+    /// The downloaded file doesn't match the published SHA-256
+    #[error("Checksum mismatch! Expected: {expected}, Calculated: {calculated}")]
+    ChecksumMismatch { expected: String, calculated: String },
+    /// The connection broke while the dump was being read
+    #[error("Download interrupted: {0}")]
+    Interrupted(std::io::Error),
+    /// Writing or reading back the dump file failed
+    #[error("Failed to write {path:?}: {source}")]
+    Io { path: PathBuf, source: std::io::Error },
+}
+
+/// Why extracting articles from a dump failed
+#[derive(Debug, thiserror::Error)]
+pub enum ExtractError {
+    /// There is no dump file to extract
+    #[error("Dump file not found: {0:?}. Run download first.")]
+    DumpNotFound(PathBuf),
+    /// Another extraction, index build or prune holds the data directory
+    #[error("{0}")]
+    Locked(String),
+    /// The filesystem doesn't have room for the estimated output
+    #[error("Insufficient disk space to extract. Available: {}, Required: {} (estimated)", format_bytes(*available), format_bytes(*required))]
+    InsufficientSpace { available: u64, required: u64 },
+    /// Reading the dump or writing the articles failed
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// An article or the stats couldn't be serialized
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Anything else, e.g. saving config.json
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Why [`WikiDownloader::run`](crate::WikiDownloader::run) failed
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Download(#[from] DownloadError),
+    #[error(transparent)]
+    Extract(#[from] ExtractError),
+}
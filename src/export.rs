@@ -0,0 +1,201 @@
+//! Parquet export
+//!
+//! Writes extracted articles as a Parquet file for use with pandas, Polars
+//! and similar tools. Articles are streamed from `articles.jsonl` and written
+//! one row group at a time, so memory use stays bounded.
+
+use std::fs::File;
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use arrow_array::builder::{ListBuilder, StringBuilder, UInt64Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::WriterProperties;
+
+use crate::article::{Article, ExtractionStats};
+use crate::jsonl::{self, LineParser};
+
+/// Rows per Parquet row group (and per in-memory batch)
+pub const ROW_GROUP_SIZE: usize = 10_000;
+
+/// File metadata key for the Wikipedia language code
+pub const LANGUAGE_METADATA_KEY: &str = "rustipedia.language";
+
+/// File metadata key for the extraction date (RFC 3339)
+pub const EXTRACTED_AT_METADATA_KEY: &str = "rustipedia.extracted_at";
+
+/// Schema of exported articles
+fn article_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::UInt64, false),
+        Field::new("title", DataType::Utf8, false),
+        Field::new("content", DataType::Utf8, false),
+        Field::new(
+            "categories",
+            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+            false,
+        ),
+        Field::new("word_count", DataType::UInt64, false),
+    ]))
+}
+
+/// Column builders for one batch of articles
+struct ArticleBatch {
+    ids: UInt64Builder,
+    titles: StringBuilder,
+    contents: StringBuilder,
+    categories: ListBuilder<StringBuilder>,
+    word_counts: UInt64Builder,
+    len: usize,
+}
+
+impl ArticleBatch {
+    fn new() -> Self {
+        Self {
+            ids: UInt64Builder::with_capacity(ROW_GROUP_SIZE),
+            titles: StringBuilder::new(),
+            contents: StringBuilder::new(),
+            categories: ListBuilder::new(StringBuilder::new()),
+            word_counts: UInt64Builder::with_capacity(ROW_GROUP_SIZE),
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, article: &Article) {
+        self.ids.append_value(article.id);
+        self.titles.append_value(&article.title);
+        self.contents.append_value(&article.content);
+        for category in &article.categories {
+            self.categories.values().append_value(category);
+        }
+        self.categories.append(true);
+        self.word_counts.append_value(article.word_count() as u64);
+        self.len += 1;
+    }
+
+    /// Build a record batch, leaving the builders empty for the next one
+    fn finish(&mut self, schema: &SchemaRef) -> Result<RecordBatch> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.ids.finish()),
+            Arc::new(self.titles.finish()),
+            Arc::new(self.contents.finish()),
+            Arc::new(self.categories.finish()),
+            Arc::new(self.word_counts.finish()),
+        ];
+        self.len = 0;
+        Ok(RecordBatch::try_new(schema.clone(), columns)?)
+    }
+}
+
+/// Export `articles.jsonl` from a data directory to a Parquet file.
+///
+/// The language and extraction date from `stats.json` are stored as file
+/// metadata when available. Malformed lines are skipped (see
+/// [`LineParser`]). Returns the number of articles written.
+pub fn export_parquet(data_dir: impl AsRef<Path>, out_path: impl AsRef<Path>) -> Result<u64> {
+    let data_dir = data_dir.as_ref();
+    let articles_files = jsonl::articles_files(data_dir);
+    if articles_files.is_empty() {
+        anyhow::bail!("Articles file not found: {:?}", jsonl::articles_path(data_dir));
+    }
+
+    let mut metadata = Vec::new();
+    let stats_path = data_dir.join("stats.json");
+    if stats_path.exists() {
+        let stats: ExtractionStats = serde_json::from_str(&std::fs::read_to_string(&stats_path)?)
+            .context("Failed to parse stats.json")?;
+        metadata.push(KeyValue::new(LANGUAGE_METADATA_KEY.to_string(), stats.language));
+        let extracted_at = stats.completed_at.unwrap_or(stats.started_at);
+        metadata.push(KeyValue::new(EXTRACTED_AT_METADATA_KEY.to_string(), extracted_at.to_rfc3339()));
+    }
+
+    let props = WriterProperties::builder()
+        .set_max_row_group_row_count(Some(ROW_GROUP_SIZE))
+        .set_compression(Compression::SNAPPY)
+        .set_key_value_metadata(Some(metadata))
+        .build();
+
+    let schema = article_schema();
+    let out = File::create(out_path.as_ref())
+        .with_context(|| format!("Failed to create {:?}", out_path.as_ref()))?;
+    let mut writer = ArrowWriter::try_new(out, schema.clone(), Some(props))?;
+
+    let mut batch = ArticleBatch::new();
+    let mut count = 0u64;
+    for articles_path in &articles_files {
+        let mut parser = LineParser::new(articles_path);
+        for (line_number, line) in jsonl::open(articles_path)?.lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let Some(article) = parser.parse::<Article>(&line, line_number as u64 + 1)? else {
+                continue;
+            };
+            batch.push(&article);
+            count += 1;
+
+            if batch.len >= ROW_GROUP_SIZE {
+                writer.write(&batch.finish(&schema)?)?;
+            }
+        }
+        parser.warn_if_skipped();
+    }
+    if batch.len > 0 {
+        writer.write(&batch.finish(&schema)?)?;
+    }
+    writer.close()?;
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Array, ListArray, StringArray, UInt64Array};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::io::Write;
+
+    #[test]
+    fn test_parquet_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = File::create(dir.path().join("articles.jsonl")).unwrap();
+        let mut rust = Article::new(7, "Rust".to_string(), "A systems programming language".to_string());
+        rust.categories = vec!["Programming languages".to_string(), "Mozilla".to_string()];
+        let iron = Article::new(8, "Iron".to_string(), "A metal".to_string());
+        writeln!(file, "{}", serde_json::to_string(&rust).unwrap()).unwrap();
+        // A truncated line is skipped rather than aborting the export
+        writeln!(file, "{{\"id\":9,\"title\":\"Cop").unwrap();
+        writeln!(file, "{}", serde_json::to_string(&iron).unwrap()).unwrap();
+        let mut stats = ExtractionStats::new("de", "dewiki.xml.bz2", 10);
+        stats.finish();
+        std::fs::write(dir.path().join("stats.json"), serde_json::to_string(&stats).unwrap()).unwrap();
+
+        let out = dir.path().join("articles.parquet");
+        assert_eq!(export_parquet(dir.path(), &out).unwrap(), 2);
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&out).unwrap()).unwrap();
+        let key_values = builder.metadata().file_metadata().key_value_metadata().unwrap().clone();
+        let language = key_values.iter().find(|kv| kv.key == LANGUAGE_METADATA_KEY).unwrap();
+        assert_eq!(language.value.as_deref(), Some("de"));
+        assert!(key_values.iter().any(|kv| kv.key == EXTRACTED_AT_METADATA_KEY));
+
+        let batch = builder.build().unwrap().next().unwrap().unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        let titles = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(titles.value(0), "Rust");
+        let word_counts = batch.column(4).as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!(word_counts.value(0), 4);
+        let categories = batch.column(3).as_any().downcast_ref::<ListArray>().unwrap();
+        let first = categories.value(0);
+        let first = first.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(first.value(1), "Mozilla");
+        assert_eq!(categories.value(1).len(), 0);
+    }
+}
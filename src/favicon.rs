@@ -0,0 +1,361 @@
+//! Uploaded logos and favicons
+//!
+//! Logo uploads must be PNG or JPEG images of at most [`MAX_LOGO_BYTES`].
+//! `/favicon.ico` is an ICO file holding a [`FAVICON_SIZE`] square PNG
+//! image, which every current browser accepts. The bundled logo comes with a
+//! pre-rendered icon; an uploaded logo is scaled down to one when it is
+//! saved ([`CUSTOM_FAVICON_FILE`]).
+//! The upload route raises the server's request body limit to
+//! [`MAX_LOGO_UPLOAD_BYTES`]; larger uploads get a 413.
+
+use std::fs;
+use std::io::{Cursor, ErrorKind};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use axum::extract::Multipart;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+/// Uploaded logo in the data directory (PNG or JPEG despite the name)
+pub const CUSTOM_LOGO_FILE: &str = "custom_logo.png";
+
+/// The uploaded logo scaled down to a favicon
+pub const CUSTOM_FAVICON_FILE: &str = "custom_favicon.png";
+
+/// Width and height of the favicon
+pub const FAVICON_SIZE: u32 = 32;
+
+/// How long browsers may cache the favicon (one week)
+pub const FAVICON_MAX_AGE: u64 = 7 * 24 * 60 * 60;
+
+/// Largest logo upload accepted (5 MB)
+pub const MAX_LOGO_BYTES: usize = 5 * 1024 * 1024;
+
+/// Body limit of the logo upload route: the largest logo plus room for
+/// the multipart framing
+pub const MAX_LOGO_UPLOAD_BYTES: usize = MAX_LOGO_BYTES + 64 * 1024;
+
+/// Largest logo width or height accepted, for PNGs whose size is in the header
+pub const MAX_LOGO_DIMENSION: u32 = 4096;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_SIGNATURE: &[u8] = b"\xff\xd8\xff";
+
+/// Content type of a PNG or JPEG image, by its magic bytes
+pub fn image_content_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(PNG_SIGNATURE) {
+        Some("image/png")
+    } else if bytes.starts_with(JPEG_SIGNATURE) {
+        Some("image/jpeg")
+    } else {
+        None
+    }
+}
+
+/// Check an uploaded logo, returning its content type.
+///
+/// Rejects anything over [`MAX_LOGO_BYTES`], files that aren't PNG or JPEG,
+/// PNGs without a valid header and PNGs larger than [`MAX_LOGO_DIMENSION`].
+pub fn check_logo(bytes: &[u8]) -> Result<&'static str> {
+    if bytes.len() > MAX_LOGO_BYTES {
+        anyhow::bail!("Logo is {} bytes; the limit is {} MB", bytes.len(), MAX_LOGO_BYTES / (1024 * 1024));
+    }
+    let content_type = image_content_type(bytes)
+        .ok_or_else(|| anyhow::anyhow!("Logo must be a PNG or JPEG image"))?;
+    if content_type == "image/png" {
+        let (width, height) = png_dimensions(bytes)
+            .ok_or_else(|| anyhow::anyhow!("Logo is not a valid PNG image"))?;
+        if width == 0 || height == 0 || width.max(height) > MAX_LOGO_DIMENSION {
+            anyhow::bail!("Logo is {}x{} pixels; at most {} per side is accepted", width, height, MAX_LOGO_DIMENSION);
+        }
+    }
+    Ok(content_type)
+}
+
+/// Read the `logo` field of a multipart upload, `None` if there is no
+/// non-empty one.
+///
+/// Bodies over the route's body limit fail with 413 Payload Too Large and
+/// other malformed uploads with 400, instead of being read any further.
+pub async fn read_logo_upload(multipart: &mut Multipart) -> std::result::Result<Option<Bytes>, (StatusCode, String)> {
+    let upload_error = |e: axum::extract::multipart::MultipartError| (
+        e.status(),
+        format!("Failed to read upload (logos can be at most {} MB): {}", MAX_LOGO_BYTES / (1024 * 1024), e.body_text()),
+    );
+    while let Some(field) = multipart.next_field().await.map_err(upload_error)? {
+        if field.name() != Some("logo") {
+            continue;
+        }
+        let data = field.bytes().await.map_err(upload_error)?;
+        if !data.is_empty() {
+            return Ok(Some(data));
+        }
+    }
+    Ok(None)
+}
+
+/// The uploaded logo of `data_dir`, if there is one
+pub fn read_custom_logo(data_dir: &Path) -> Option<Vec<u8>> {
+    match fs::read(data_dir.join(CUSTOM_LOGO_FILE)) {
+        Ok(bytes) => Some(bytes),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => {
+            tracing::error!("Failed to read custom logo: {}", e);
+            None
+        }
+    }
+}
+
+/// The favicon of the uploaded logo of `data_dir`, if there is one.
+///
+/// Logos saved before favicons were generated get theirs on first use; a
+/// logo that can't be decoded gives `None`, so the default icon is served.
+pub fn read_custom_favicon(data_dir: &Path) -> Option<Vec<u8>> {
+    if let Ok(png) = fs::read(data_dir.join(CUSTOM_FAVICON_FILE)) {
+        return Some(png);
+    }
+    let png = match favicon_png(&read_custom_logo(data_dir)?) {
+        Ok(png) => png,
+        Err(e) => {
+            tracing::warn!("Serving the default favicon: {:#}", e);
+            return None;
+        }
+    };
+    if let Err(e) = fs::write(data_dir.join(CUSTOM_FAVICON_FILE), &png) {
+        tracing::warn!("Failed to save the favicon: {}", e);
+    }
+    Some(png)
+}
+
+/// Save an uploaded logo (already [`check_logo`]ed) and its [`favicon_png`] to `data_dir`
+pub fn save_logo(data_dir: &Path, logo: &[u8], favicon: &[u8]) -> Result<()> {
+    fs::write(data_dir.join(CUSTOM_LOGO_FILE), logo).context("Failed to save logo")?;
+    fs::write(data_dir.join(CUSTOM_FAVICON_FILE), favicon).context("Failed to save favicon")?;
+    Ok(())
+}
+
+/// Serve the uploaded logo of `data_dir`, or `default_logo` (a PNG)
+pub fn logo_response(data_dir: &Path, default_logo: &[u8]) -> Response {
+    match read_custom_logo(data_dir) {
+        Some(bytes) => ([(header::CONTENT_TYPE, image_content_type(&bytes).unwrap_or("image/png"))], bytes).into_response(),
+        None => ([(header::CONTENT_TYPE, "image/png")], default_logo.to_vec()).into_response(),
+    }
+}
+
+/// Remove the uploaded logo of `data_dir` and its favicon; fine if there is none
+pub fn reset_logo(data_dir: &Path) -> Result<()> {
+    for file in [CUSTOM_LOGO_FILE, CUSTOM_FAVICON_FILE] {
+        match fs::remove_file(data_dir.join(file)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e).context("Failed to remove the custom logo"),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Width and height from a PNG's IHDR chunk
+pub fn png_dimensions(png: &[u8]) -> Option<(u32, u32)> {
+    if !png.starts_with(PNG_SIGNATURE) || png.len() < 24 || &png[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(png[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(png[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+/// A logo (PNG or JPEG) as a [`FAVICON_SIZE`] square PNG: scaled down to fit
+/// and centered on a transparent background. Logos of the right size are
+/// returned as they are.
+pub fn favicon_png(logo: &[u8]) -> Result<Vec<u8>> {
+    if png_dimensions(logo) == Some((FAVICON_SIZE, FAVICON_SIZE)) {
+        return Ok(logo.to_vec());
+    }
+    let logo = image::load_from_memory(logo).context("Failed to decode the logo")?;
+    let scaled = logo.resize(FAVICON_SIZE, FAVICON_SIZE, image::imageops::FilterType::Lanczos3).to_rgba8();
+    let mut icon = image::RgbaImage::new(FAVICON_SIZE, FAVICON_SIZE);
+    let x = (FAVICON_SIZE - scaled.width()) / 2;
+    let y = (FAVICON_SIZE - scaled.height()) / 2;
+    image::imageops::overlay(&mut icon, &scaled, x.into(), y.into());
+
+    let mut png = Vec::new();
+    icon.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .context("Failed to encode the favicon")?;
+    Ok(png)
+}
+
+/// An ICO file with `png` as its only image, or `None` if it isn't a PNG
+pub fn ico_from_png(png: &[u8]) -> Option<Vec<u8>> {
+    let (width, height) = png_dimensions(png)?;
+    // ICO stores sizes in a byte, with 0 meaning 256 or more
+    let size_byte = |size: u32| if size >= 256 { 0 } else { size as u8 };
+
+    let mut ico = Vec::with_capacity(22 + png.len());
+    ico.extend_from_slice(&[0, 0, 1, 0, 1, 0]); // reserved, type 1 (icon), one image
+    ico.extend_from_slice(&[size_byte(width), size_byte(height), 0, 0]); // no palette
+    ico.extend_from_slice(&1u16.to_le_bytes()); // color planes
+    ico.extend_from_slice(&32u16.to_le_bytes()); // bits per pixel
+    ico.extend_from_slice(&(png.len() as u32).to_le_bytes());
+    ico.extend_from_slice(&22u32.to_le_bytes()); // image data follows the header
+    ico.extend_from_slice(png);
+    Some(ico)
+}
+
+/// Serve `icon`, a PNG from [`favicon_png`], as a favicon wrapped in an ICO
+pub fn favicon_response(icon: Vec<u8>) -> Response {
+    let cache_control = format!("public, max-age={}", FAVICON_MAX_AGE);
+    match ico_from_png(&icon) {
+        Some(ico) => ([(header::CONTENT_TYPE, "image/x-icon".to_string()), (header::CACHE_CONTROL, cache_control)], ico).into_response(),
+        None => ([(header::CONTENT_TYPE, "image/png".to_string()), (header::CACHE_CONTROL, cache_control)], icon).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use axum::routing::{get, post};
+    use axum::Router;
+    use tower::ServiceExt;
+
+    const FAVICON: &[u8] = include_bytes!("bin/favicon.png");
+
+    #[tokio::test]
+    async fn test_favicon_route_returns_an_icon() {
+        let app = Router::new()
+            .route("/favicon.ico", get(|| async { favicon_response(FAVICON.to_vec()) }));
+
+        let response = app
+            .oneshot(Request::builder().uri("/favicon.ico").body(Body::empty()).unwrap())
+            .await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/x-icon");
+        assert!(response.headers()[header::CACHE_CONTROL].to_str().unwrap().contains("max-age=604800"));
+        let body = to_bytes(response.into_body(), 1 << 20).await.unwrap();
+        assert_eq!(&body[..8], &[0, 0, 1, 0, 1, 0, 32, 32]);
+        assert_eq!(&body[22..], FAVICON);
+    }
+
+    #[test]
+    fn test_uploaded_logo_favicon_is_32x32() {
+        let encode = |width, height, format| {
+            let image = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(width, height, image::Rgb([200, 40, 40])));
+            let mut bytes = Vec::new();
+            image.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+            bytes
+        };
+        let dir = tempfile::tempdir().unwrap();
+
+        for logo in [encode(640, 480, image::ImageFormat::Png), encode(50, 300, image::ImageFormat::Jpeg)] {
+            save_logo(dir.path(), &logo, &favicon_png(&logo).unwrap()).unwrap();
+            let ico = ico_from_png(&read_custom_favicon(dir.path()).unwrap()).unwrap();
+            assert_eq!((ico[6], ico[7]), (32, 32));
+            assert_eq!(png_dimensions(&ico[22..]), Some((32, 32)));
+        }
+
+        // A logo saved before favicons were generated gets one on first use
+        fs::remove_file(dir.path().join(CUSTOM_FAVICON_FILE)).unwrap();
+        assert_eq!(png_dimensions(&read_custom_favicon(dir.path()).unwrap()), Some((32, 32)));
+        assert!(dir.path().join(CUSTOM_FAVICON_FILE).exists());
+
+        // One that can't be decoded falls back to the default icon
+        reset_logo(dir.path()).unwrap();
+        fs::write(dir.path().join(CUSTOM_LOGO_FILE), b"\xff\xd8\xff\xe0JFIF").unwrap();
+        assert_eq!(read_custom_favicon(dir.path()), None);
+
+        assert_eq!(favicon_png(FAVICON).unwrap(), FAVICON);
+    }
+
+    #[tokio::test]
+    async fn test_logo_reset_restores_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().to_path_buf();
+        let reset_dir = data_dir.clone();
+        let app = Router::new()
+            .route("/logo", get(move || async move { logo_response(&data_dir, FAVICON) }))
+            .route("/settings/logo/reset", post(move || async move { reset_logo(&reset_dir).unwrap() }));
+        let logo = |app: Router| async move {
+            let response = app.oneshot(Request::builder().uri("/logo").body(Body::empty()).unwrap()).await.unwrap();
+            let content_type = response.headers()[header::CONTENT_TYPE].to_str().unwrap().to_string();
+            (content_type, to_bytes(response.into_body(), 1 << 20).await.unwrap())
+        };
+        let reset = |app: Router| async move {
+            let request = Request::builder().method("POST").uri("/settings/logo/reset").body(Body::empty()).unwrap();
+            app.oneshot(request).await.unwrap().status()
+        };
+
+        fs::write(dir.path().join(CUSTOM_LOGO_FILE), b"\xff\xd8\xff\xe0JFIF").unwrap();
+        assert_eq!(logo(app.clone()).await.0, "image/jpeg");
+
+        assert_eq!(reset(app.clone()).await, StatusCode::OK);
+        assert!(!dir.path().join(CUSTOM_LOGO_FILE).exists());
+        assert_eq!(logo(app.clone()).await, ("image/png".to_string(), FAVICON.into()));
+
+        // Already the default
+        assert_eq!(reset(app).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_upload_is_rejected_with_413() {
+        use axum::extract::DefaultBodyLimit;
+
+        // A small server-wide limit, raised for the upload route like rustipedia-serve does
+        let app = Router::new()
+            .route("/settings/logo", post(|mut multipart: Multipart| async move {
+                match read_logo_upload(&mut multipart).await {
+                    Ok(Some(data)) => data.len().to_string().into_response(),
+                    Ok(None) => StatusCode::BAD_REQUEST.into_response(),
+                    Err(e) => e.into_response(),
+                }
+            }).layer(DefaultBodyLimit::max(4096)))
+            .route("/other", post(|body: Bytes| async move { body.len().to_string() }))
+            .layer(DefaultBodyLimit::max(1024));
+        let upload = |uri: &str, size: usize| {
+            let mut body = b"--b\r\nContent-Disposition: form-data; name=\"logo\"; filename=\"logo.png\"\r\n\r\n".to_vec();
+            body.resize(body.len() + size, b'x');
+            body.extend_from_slice(b"\r\n--b--\r\n");
+            let request = Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "multipart/form-data; boundary=b")
+                .body(Body::from(body))
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        let ok = upload("/settings/logo", 2000).await.unwrap();
+        assert_eq!(ok.status(), StatusCode::OK);
+        assert_eq!(to_bytes(ok.into_body(), 1 << 20).await.unwrap(), "2000");
+        assert_eq!(upload("/settings/logo", 8000).await.unwrap().status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(upload("/other", 2000).await.unwrap().status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_large_png_sizes_are_zero_in_ico() {
+        let mut png = FAVICON.to_vec();
+        png[16..20].copy_from_slice(&1520u32.to_be_bytes());
+        let ico = ico_from_png(&png).unwrap();
+        assert_eq!((ico[6], ico[7]), (0, 32));
+        assert_eq!(png_dimensions(b"GIF89a"), None);
+    }
+
+    #[test]
+    fn test_check_logo() {
+        assert_eq!(check_logo(FAVICON).unwrap(), "image/png");
+        assert_eq!(check_logo(b"\xff\xd8\xff\xe0JFIF").unwrap(), "image/jpeg");
+
+        let mut oversized = FAVICON.to_vec();
+        oversized.resize(MAX_LOGO_BYTES + 1, 0);
+        assert!(check_logo(&oversized).unwrap_err().to_string().contains("limit is 5 MB"));
+
+        let mut huge = FAVICON.to_vec();
+        huge[16..20].copy_from_slice(&100_000u32.to_be_bytes());
+        assert!(check_logo(&huge).is_err());
+
+        assert!(check_logo(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>").is_err());
+        assert!(check_logo(b"\x89PNG\r\n\x1a\ntruncated").is_err());
+        assert!(check_logo(b"").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! Atom feed of recently extracted articles
+//!
+//! The server keeps the newest articles by `extracted_at` while loading
+//! `articles.jsonl`, so `/feed.xml` can list what an update added without
+//! holding timestamps for every article.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use chrono::{DateTime, Utc};
+
+/// Number of articles in the feed
+pub const FEED_SIZE: usize = 50;
+
+/// An article listed in the feed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedEntry {
+    pub id: u64,
+    pub title: String,
+    pub extracted_at: DateTime<Utc>,
+}
+
+/// (extraction time, position in the file, ID, title)
+type Candidate = (DateTime<Utc>, u64, u64, String);
+
+/// The `capacity` most recently extracted articles seen so far
+pub struct RecentArticles {
+    capacity: usize,
+    /// Min-heap on (time, position), so the oldest kept article is on top
+    heap: BinaryHeap<Reverse<Candidate>>,
+    /// Position of the next article, breaking ties between equal timestamps
+    /// in favor of articles written later
+    position: u64,
+}
+
+impl RecentArticles {
+    /// Keep up to `capacity` articles
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, heap: BinaryHeap::with_capacity(capacity + 1), position: 0 }
+    }
+
+    /// Offer an article, in file order
+    pub fn push(&mut self, id: u64, title: &str, extracted_at: DateTime<Utc>) {
+        let position = self.position;
+        self.position += 1;
+        if self.capacity == 0 {
+            return;
+        }
+        if self.heap.len() == self.capacity {
+            match self.heap.peek() {
+                Some(Reverse((oldest, oldest_position, _, _))) if (extracted_at, position) > (*oldest, *oldest_position) => {
+                    self.heap.pop();
+                }
+                _ => return,
+            }
+        }
+        self.heap.push(Reverse((extracted_at, position, id, title.to_string())));
+    }
+
+    /// The kept articles, newest first
+    pub fn into_entries(self) -> Vec<FeedEntry> {
+        let mut entries = self.heap.into_vec();
+        // Ascending on Reverse is newest first
+        entries.sort();
+        entries.into_iter()
+            .map(|Reverse((extracted_at, _, id, title))| FeedEntry { id, title, extracted_at })
+            .collect()
+    }
+}
+
+/// Render an Atom feed of `entries`.
+///
+/// `base_url` (e.g. `http://localhost:3000`) makes the feed and article IDs
+/// and links absolute, as Atom requires.
+pub fn atom_feed(title: &str, base_url: &str, entries: &[FeedEntry]) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let updated = entries.iter().map(|entry| entry.extracted_at).max().unwrap_or(DateTime::UNIX_EPOCH);
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <id>{}/feed.xml</id>\n", xml_escape(base_url)));
+    xml.push_str(&format!("  <title>{}</title>\n", xml_escape(title)));
+    xml.push_str(&format!("  <updated>{}</updated>\n", updated.to_rfc3339()));
+    xml.push_str(&format!("  <link rel=\"self\" href=\"{}/feed.xml\"/>\n", xml_escape(base_url)));
+    xml.push_str(&format!("  <link rel=\"alternate\" type=\"text/html\" href=\"{}/\"/>\n", xml_escape(base_url)));
+    xml.push_str("  <author><name>Rustipedia</name></author>\n");
+    for entry in entries {
+        let link = xml_escape(&format!("{}/article/{}", base_url, entry.id));
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <id>{}</id>\n", link));
+        xml.push_str(&format!("    <title>{}</title>\n", xml_escape(&entry.title)));
+        xml.push_str(&format!("    <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n", link));
+        xml.push_str(&format!("    <updated>{}</updated>\n", entry.extracted_at.to_rfc3339()));
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    /// Check the elements RFC 4287 requires: feed and entry `id`, `title`
+    /// and `updated` (RFC 3339), an author, and a link on every entry
+    fn validate_atom(xml: &str) -> Vec<String> {
+        let mut reader = Reader::from_str(xml);
+        let mut path: Vec<String> = Vec::new();
+        let mut children: Vec<Vec<String>> = Vec::new();
+        let mut entry_titles = Vec::new();
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(e) => {
+                    let name = String::from_utf8(e.name().as_ref().to_vec()).unwrap();
+                    if path.is_empty() {
+                        assert_eq!(name, "feed");
+                        let ns = e.try_get_attribute("xmlns").unwrap().unwrap();
+                        assert_eq!(ns.unescape_value().unwrap(), "http://www.w3.org/2005/Atom");
+                    }
+                    if let Some(siblings) = children.last_mut() {
+                        siblings.push(name.clone());
+                    }
+                    path.push(name);
+                    children.push(Vec::new());
+                }
+                Event::Empty(e) => {
+                    let name = String::from_utf8(e.name().as_ref().to_vec()).unwrap();
+                    if name == "link" {
+                        assert!(e.try_get_attribute("href").unwrap().is_some());
+                    }
+                    children.last_mut().unwrap().push(name);
+                }
+                Event::Text(text) => {
+                    let text = text.unescape().unwrap().to_string();
+                    match path.last().map(String::as_str) {
+                        Some("updated") => {
+                            DateTime::parse_from_rfc3339(&text).expect("updated must be RFC 3339");
+                        }
+                        Some("title") if path.len() == 3 => entry_titles.push(text),
+                        _ => {}
+                    }
+                }
+                Event::End(_) => {
+                    let name = path.pop().unwrap();
+                    let found = children.pop().unwrap();
+                    let required: &[&str] = match name.as_str() {
+                        "feed" => &["id", "title", "updated", "author"],
+                        "entry" => &["id", "title", "updated", "link"],
+                        _ => &[],
+                    };
+                    for element in required {
+                        assert!(found.iter().any(|f| f == element), "<{}> is missing <{}>", name, element);
+                    }
+                    for element in ["id", "title", "updated"] {
+                        assert!(found.iter().filter(|f| *f == element).count() <= 1,
+                            "<{}> has more than one <{}>", name, element);
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        assert!(path.is_empty());
+        entry_titles
+    }
+
+    #[test]
+    fn test_feed_lists_newest_articles_as_valid_atom() {
+        let day = |d: u32| Utc.with_ymd_and_hms(2024, 5, d, 12, 0, 0).unwrap();
+        let mut recent = RecentArticles::new(3);
+        recent.push(1, "Old", day(1));
+        recent.push(2, "Rust & <Iron>", day(5));
+        recent.push(3, "Copper", day(3));
+        recent.push(4, "Same time, later line", day(5));
+        recent.push(5, "Older", day(2));
+
+        let entries = recent.into_entries();
+        let ids: Vec<u64> = entries.iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![4, 2, 3]);
+
+        let xml = atom_feed("Recently added", "http://localhost:3000/", &entries);
+        assert!(xml.contains("<link rel=\"alternate\" type=\"text/html\" href=\"http://localhost:3000/article/2\"/>"));
+        assert_eq!(validate_atom(&xml), vec!["Same time, later line", "Rust & <Iron>", "Copper"]);
+
+        // An empty feed is still valid
+        assert!(validate_atom(&atom_feed("Recently added", "http://localhost", &[])).is_empty());
+    }
+}
@@ -0,0 +1,316 @@
+//! Link graph export
+//!
+//! Writes the links between articles as a directed graph: a CSV edge list
+//! of article IDs, GraphML or Graphviz DOT. Articles are read twice, like
+//! the link validator does: once to map titles to IDs, then to resolve each
+//! article's links, following `redirects.json` when a link points at a
+//! redirect. Only the title map is kept in memory.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::article::{normalize_title, Article};
+use crate::jsonl::{self, LineParser};
+use crate::parser::WikiParser;
+use crate::prune::wiki_link_targets;
+use crate::redirects::RedirectMap;
+
+/// Output format of [`export_graph`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// `source_id,target_id` rows (plus `broken_target` with broken links)
+    Csv,
+    /// GraphML with article titles as node data
+    GraphMl,
+    /// Graphviz DOT with article titles as labels
+    Dot,
+}
+
+impl GraphFormat {
+    /// File extension for this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            GraphFormat::Csv => "csv",
+            GraphFormat::GraphMl => "graphml",
+            GraphFormat::Dot => "dot",
+        }
+    }
+}
+
+/// Counts from a graph export
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GraphReport {
+    /// Articles (nodes) in the graph
+    pub nodes: u64,
+    /// Links from one article to another, each pair counted once
+    pub edges: u64,
+    /// Links to titles that are neither an article nor a redirect to one,
+    /// each target counted once per article
+    pub broken_links: u64,
+}
+
+#[derive(Deserialize)]
+struct TitleAndId {
+    id: u64,
+    title: String,
+}
+
+/// Where a link leads
+enum Target<'a> {
+    Article(u64),
+    Broken(&'a str),
+}
+
+/// Writes nodes and edges in one [`GraphFormat`]
+struct GraphWriter<'w, W: Write> {
+    out: &'w mut W,
+    format: GraphFormat,
+    include_broken: bool,
+    /// Placeholder nodes written for broken targets, by normalized title
+    broken_nodes: HashMap<String, usize>,
+}
+
+impl<W: Write> GraphWriter<'_, W> {
+    fn start(&mut self) -> Result<()> {
+        match self.format {
+            GraphFormat::Csv if self.include_broken => writeln!(self.out, "source_id,target_id,broken_target")?,
+            GraphFormat::Csv => writeln!(self.out, "source_id,target_id")?,
+            GraphFormat::GraphMl => {
+                writeln!(self.out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+                writeln!(self.out, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+                writeln!(self.out, r#"  <key id="title" for="node" attr.name="title" attr.type="string"/>"#)?;
+                writeln!(self.out, r#"  <key id="broken" for="node" attr.name="broken" attr.type="boolean"/>"#)?;
+                writeln!(self.out, r#"  <graph id="wikipedia" edgedefault="directed">"#)?;
+            }
+            GraphFormat::Dot => writeln!(self.out, "digraph wikipedia {{")?,
+        }
+        Ok(())
+    }
+
+    fn node(&mut self, id: u64, title: &str) -> Result<()> {
+        match self.format {
+            GraphFormat::Csv => {}
+            GraphFormat::GraphMl => writeln!(
+                self.out,
+                r#"    <node id="n{}"><data key="title">{}</data></node>"#,
+                id, WikiParser::html_escape(title)
+            )?,
+            GraphFormat::Dot => writeln!(self.out, "  {} [label=\"{}\"];", id, dot_escape(title))?,
+        }
+        Ok(())
+    }
+
+    fn edge(&mut self, source: u64, target: &Target) -> Result<()> {
+        match (self.format, target) {
+            (GraphFormat::Csv, Target::Article(id)) if self.include_broken => writeln!(self.out, "{},{},", source, id)?,
+            (GraphFormat::Csv, Target::Article(id)) => writeln!(self.out, "{},{}", source, id)?,
+            (GraphFormat::Csv, Target::Broken(title)) => writeln!(self.out, "{},,{}", source, csv_escape(title))?,
+            (GraphFormat::GraphMl, Target::Article(id)) => {
+                writeln!(self.out, r#"    <edge source="n{}" target="n{}"/>"#, source, id)?
+            }
+            (GraphFormat::GraphMl, Target::Broken(title)) => {
+                let node = self.broken_node(title)?;
+                writeln!(self.out, r#"    <edge source="n{}" target="missing{}"/>"#, source, node)?
+            }
+            (GraphFormat::Dot, Target::Article(id)) => writeln!(self.out, "  {} -> {};", source, id)?,
+            (GraphFormat::Dot, Target::Broken(title)) => {
+                let node = self.broken_node(title)?;
+                writeln!(self.out, "  {} -> missing{};", source, node)?
+            }
+        }
+        Ok(())
+    }
+
+    /// Placeholder node number of a broken target, written on first use
+    fn broken_node(&mut self, title: &str) -> Result<usize> {
+        let key = normalize_title(title);
+        if let Some(&node) = self.broken_nodes.get(&key) {
+            return Ok(node);
+        }
+        let node = self.broken_nodes.len();
+        self.broken_nodes.insert(key, node);
+        match self.format {
+            GraphFormat::GraphMl => writeln!(
+                self.out,
+                r#"    <node id="missing{}"><data key="title">{}</data><data key="broken">true</data></node>"#,
+                node, WikiParser::html_escape(title)
+            )?,
+            _ => writeln!(self.out, "  missing{} [label=\"{}\", style=dashed];", node, dot_escape(title))?,
+        }
+        Ok(node)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        match self.format {
+            GraphFormat::Csv => {}
+            GraphFormat::GraphMl => {
+                writeln!(self.out, "  </graph>")?;
+                writeln!(self.out, "</graphml>")?;
+            }
+            GraphFormat::Dot => writeln!(self.out, "}}")?,
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn dot_escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The ID of the article `target` names, directly or through redirects
+fn resolve_link(target: &str, ids: &HashMap<String, u64>, redirects: &RedirectMap) -> Option<u64> {
+    if let Some(&id) = ids.get(&normalize_title(target)) {
+        return Some(id);
+    }
+    let resolved = redirects.resolve(target)?;
+    let page = resolved.split('#').next().unwrap_or(&resolved);
+    ids.get(&normalize_title(page)).copied()
+}
+
+/// Write the link graph of the articles in `data_dir` to `out`.
+///
+/// Links to the same article are written once per source; links from an
+/// article to itself are kept. Broken links are left out unless
+/// `include_broken` is set, in which case they are written flagged: with
+/// the target title in `broken_target` (CSV) or as placeholder nodes marked
+/// broken (GraphML) or dashed (DOT). Malformed lines are skipped (see
+/// [`LineParser`]).
+pub fn export_graph<W: Write>(
+    data_dir: &Path,
+    out: &mut W,
+    format: GraphFormat,
+    include_broken: bool,
+) -> Result<GraphReport> {
+    let articles_files = jsonl::articles_files(data_dir);
+    if articles_files.is_empty() {
+        anyhow::bail!("Articles file not found: {:?}", jsonl::articles_path(data_dir));
+    }
+
+    // First pass: title index, without parsing article content
+    let mut ids: HashMap<String, u64> = HashMap::new();
+    for articles_path in &articles_files {
+        let mut parser = LineParser::new(articles_path);
+        for (line_number, line) in jsonl::open(articles_path)?.lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(article) = parser.parse::<TitleAndId>(&line, line_number as u64 + 1)? {
+                ids.insert(normalize_title(&article.title), article.id);
+            }
+        }
+    }
+
+    let redirects = RedirectMap::load_from_dir(data_dir)?;
+
+    let mut writer = GraphWriter { out, format, include_broken, broken_nodes: HashMap::new() };
+    let mut report = GraphReport::default();
+    writer.start()?;
+
+    // Second pass: nodes and edges
+    for articles_path in &articles_files {
+        let mut parser = LineParser::new(articles_path);
+        for (line_number, line) in jsonl::open(articles_path)?.lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let Some(article) = parser.parse::<Article>(&line, line_number as u64 + 1)? else {
+                continue;
+            };
+            report.nodes += 1;
+            writer.node(article.id, &article.title)?;
+
+            let mut linked = HashSet::new();
+            let mut broken = HashSet::new();
+            for target in wiki_link_targets(&article.content) {
+                match resolve_link(&target, &ids, &redirects) {
+                    Some(id) => {
+                        if linked.insert(id) {
+                            report.edges += 1;
+                            writer.edge(article.id, &Target::Article(id))?;
+                        }
+                    }
+                    None => {
+                        if broken.insert(normalize_title(&target)) {
+                            report.broken_links += 1;
+                            if include_broken {
+                                writer.edge(article.id, &Target::Broken(&target))?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        parser.warn_if_skipped();
+    }
+
+    writer.finish()?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write_corpus(dir: &Path) {
+        let articles = [
+            Article::new(1, "Rust".to_string(), WikiParser::clean_wiki_markup("[[Iron]], [[iron oxide|oxide]] and [[Ferrum]]. [[Rust#History|Again]]")),
+            Article::new(2, "Iron".to_string(), WikiParser::clean_wiki_markup("See [[Rust]] and [[Steel]].")),
+            Article::new(3, "Iron oxide".to_string(), WikiParser::clean_wiki_markup("Found on [[Iron]] with [[Steel|\"steel\", mostly]].")),
+        ];
+        let lines: Vec<String> = articles.iter().map(|a| serde_json::to_string(a).unwrap()).collect();
+        fs::write(dir.join("articles.jsonl"), lines.join("\n") + "\n").unwrap();
+
+        let mut redirects = RedirectMap::new();
+        redirects.insert("Ferrum", "Iron");
+        redirects.save(dir.join("redirects.json")).unwrap();
+    }
+
+    fn export(dir: &Path, format: GraphFormat, include_broken: bool) -> (String, GraphReport) {
+        let mut out = Vec::new();
+        let report = export_graph(dir, &mut out, format, include_broken).unwrap();
+        (String::from_utf8(out).unwrap(), report)
+    }
+
+    #[test]
+    fn test_export_graph_edges_of_tiny_corpus() {
+        let dir = tempfile::tempdir().unwrap();
+        write_corpus(dir.path());
+
+        // "Ferrum" redirects to Iron, which is already linked; "Rust#History" is a self-link
+        let (csv, report) = export(dir.path(), GraphFormat::Csv, false);
+        assert_eq!(csv, "source_id,target_id\n1,2\n1,3\n1,1\n2,1\n3,2\n");
+        assert_eq!(report, GraphReport { nodes: 3, edges: 5, broken_links: 2 });
+
+        let (csv, report) = export(dir.path(), GraphFormat::Csv, true);
+        assert_eq!(csv, "source_id,target_id,broken_target\n1,2,\n1,3,\n1,1,\n2,1,\n2,,Steel\n3,2,\n3,,Steel\n");
+        assert_eq!(report.broken_links, 2);
+
+        let (graphml, _) = export(dir.path(), GraphFormat::GraphMl, true);
+        assert!(graphml.contains(r#"<node id="n3"><data key="title">Iron oxide</data></node>"#));
+        assert!(graphml.contains(r#"<edge source="n1" target="n2"/>"#));
+        assert_eq!(graphml.matches(r#"<data key="broken">true</data>"#).count(), 1);
+        assert!(graphml.contains(r#"<edge source="n3" target="missing0"/>"#));
+        assert!(graphml.trim_end().ends_with("</graphml>"));
+
+        let (dot, _) = export(dir.path(), GraphFormat::Dot, false);
+        assert!(dot.starts_with("digraph wikipedia {\n  1 [label=\"Rust\"];\n"));
+        assert!(dot.contains("  3 -> 2;\n"));
+        assert!(!dot.contains("missing"));
+    }
+}
@@ -0,0 +1,226 @@
+//! Importing articles cleaned by other tools
+//!
+//! Reads a JSON Lines file of articles (e.g. converted from wikiextractor
+//! output), checks each record against the [`Article`] schema and writes the
+//! accepted ones as a canonical `articles.jsonl`, so the data directory can
+//! be indexed and served like one produced by extraction. ZIM files are
+//! read by [`crate::zim`] and written the same way.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::article::{Article, ExtractionStats};
+use crate::downloader::{COMPLETE_MARKER_FILE, PARTIAL_ARTICLES_FILE};
+use crate::jsonl;
+use crate::redirects::RedirectMap;
+
+/// Rejected records listed individually in an [`ImportReport`]
+pub const MAX_LISTED_REJECTIONS: usize = 20;
+
+/// A record that wasn't imported
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Rejection {
+    /// Line of a JSONL file (1-based), or entry index of a ZIM file
+    pub record: u64,
+    pub reason: String,
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub accepted: u64,
+    pub rejected: u64,
+    /// Redirects saved to redirects.json
+    pub redirects: u64,
+    /// Rejected records by reason, with schema errors grouped as "invalid record"
+    pub reasons: BTreeMap<String, u64>,
+    /// The first [`MAX_LISTED_REJECTIONS`] rejected records
+    pub rejections: Vec<Rejection>,
+}
+
+/// Writes imported articles to a data directory the way extraction does:
+/// into `articles.jsonl.tmp`, swapped in by [`Self::finish`].
+pub struct ImportWriter {
+    data_dir: PathBuf,
+    language: Option<String>,
+    writer: BufWriter<File>,
+    ids: HashSet<u64>,
+    stats: ExtractionStats,
+    report: ImportReport,
+}
+
+impl ImportWriter {
+    /// Start importing into `data_dir` from the file named `source_file`.
+    ///
+    /// `language`, if given, is recorded in config.json.
+    pub fn create(data_dir: &Path, source_file: &str, language: Option<&str>) -> Result<Self> {
+        fs::create_dir_all(data_dir)?;
+        let writer = BufWriter::new(File::create(data_dir.join(PARTIAL_ARTICLES_FILE))?);
+        Ok(Self {
+            data_dir: data_dir.to_path_buf(),
+            language: language.map(str::to_string),
+            writer,
+            ids: HashSet::new(),
+            stats: ExtractionStats::new(language.unwrap_or("custom"), source_file, 0),
+            report: ImportReport::default(),
+        })
+    }
+
+    /// Check and write an article.
+    ///
+    /// Articles with an empty title or content, or an ID already written,
+    /// are rejected. Titles are trimmed and content hashes filled in.
+    pub fn add(&mut self, record: u64, mut article: Article) -> Result<()> {
+        article.title = article.title.trim().to_string();
+        if article.title.is_empty() {
+            self.reject(record, "empty title", "title is empty".to_string());
+            return Ok(());
+        }
+        if article.content.trim().is_empty() {
+            self.reject(record, "empty content", format!("\"{}\" has no content", article.title));
+            return Ok(());
+        }
+        if !self.ids.insert(article.id) {
+            self.reject(record, "duplicate id", format!("id {} was already imported", article.id));
+            return Ok(());
+        }
+        article.update_content_hash();
+
+        writeln!(self.writer, "{}", serde_json::to_string(&article)?)?;
+        self.report.accepted += 1;
+        self.stats.total_bytes += article.content.len() as u64;
+        Ok(())
+    }
+
+    /// Record a rejected record; `category` groups it in [`ImportReport::reasons`]
+    pub fn reject(&mut self, record: u64, category: &str, reason: String) {
+        self.report.rejected += 1;
+        *self.report.reasons.entry(category.to_string()).or_default() += 1;
+        if self.report.rejections.len() < MAX_LISTED_REJECTIONS {
+            self.report.rejections.push(Rejection { record, reason });
+        }
+    }
+
+    /// Swap in the new `articles.jsonl` and write stats, config and, if
+    /// given, the redirects; the completion marker comes last
+    pub fn finish(mut self, redirects: Option<&RedirectMap>) -> Result<ImportReport> {
+        self.writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+
+        let marker_path = self.data_dir.join(COMPLETE_MARKER_FILE);
+        if marker_path.exists() {
+            fs::remove_file(&marker_path)?;
+        }
+        let articles_path = self.data_dir.join("articles.jsonl");
+        fs::rename(self.data_dir.join(PARTIAL_ARTICLES_FILE), &articles_path)?;
+        jsonl::remove_other_articles_files(&self.data_dir, std::slice::from_ref(&articles_path))?;
+
+        if let Some(redirects) = redirects {
+            redirects.save(self.data_dir.join("redirects.json"))?;
+            self.report.redirects = redirects.len() as u64;
+            self.stats.redirects = self.report.redirects;
+        }
+
+        self.stats.articles_extracted = self.report.accepted;
+        self.stats.articles_skipped = self.report.rejected;
+        self.stats.finish();
+        fs::write(self.data_dir.join("stats.json"), serde_json::to_string_pretty(&self.stats)?)?;
+
+        if let Some(ref language) = self.language {
+            set_config_language(&self.data_dir.join("config.json"), language)?;
+        }
+
+        fs::write(&marker_path, format!("{}\n", self.report.accepted))?;
+        Ok(self.report)
+    }
+}
+
+/// Import the articles in `input` into `data_dir`, replacing its `articles.jsonl`.
+///
+/// Records need the `id`, `title` and `content` of an [`Article`]; its
+/// optional fields are kept and unknown fields are dropped. See
+/// [`ImportWriter::add`] for the other checks.
+pub fn import_jsonl(input: &Path, data_dir: &Path, language: Option<&str>) -> Result<ImportReport> {
+    let reader = BufReader::new(File::open(input).with_context(|| format!("Failed to open {:?}", input))?);
+    let source_file = input.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut writer = ImportWriter::create(data_dir, &source_file, language)?;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.with_context(|| format!("Failed to read {:?}", input))?;
+        let line_number = index as u64 + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Article>(&line) {
+            Ok(article) => writer.add(line_number, article)?,
+            Err(e) => writer.reject(line_number, "invalid record", e.to_string()),
+        }
+    }
+
+    writer.finish(None)
+}
+
+/// Set `language` in config.json, keeping its other settings
+fn set_config_language(path: &Path, language: &str) -> Result<()> {
+    let mut config = match fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&content)
+            .with_context(|| format!("{:?} is not a JSON object", path))?,
+        Err(_) => serde_json::Map::new(),
+    };
+    config.insert("language".to_string(), language.into());
+    fs::write(path, serde_json::to_string_pretty(&config)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloader::ExtractionState;
+    use crate::DataDirConfig;
+
+    #[test]
+    fn test_import_mixed_validity_jsonl() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("cleaned.jsonl");
+        fs::write(&input, concat!(
+            "{\"id\": 1, \"title\": \"Rust\", \"content\": \"A language.\", \"url\": \"https://example.org/?curid=1\"}\n",
+            "{\"id\": 2, \"title\": \"Iron\"}\n",
+            "not json\n",
+            "\n",
+            "{\"id\": 3, \"title\": \"  \", \"content\": \"Untitled\"}\n",
+            "{\"id\": 1, \"title\": \"Rust again\", \"content\": \"Duplicate\"}\n",
+            "{\"id\": \"4\", \"title\": \"Copper\", \"content\": \"Metal\"}\n",
+            "{\"id\": 5, \"title\": \" Zinc \", \"content\": \"Metal\", \"categories\": [\"Metals\"]}\n",
+        )).unwrap();
+
+        let data_dir = dir.path().join("wiki");
+        let report = import_jsonl(&input, &data_dir, Some("en")).unwrap();
+        assert_eq!(report.accepted, 2);
+        assert_eq!(report.rejected, 5);
+        assert_eq!(report.reasons["invalid record"], 3);
+        assert_eq!(report.reasons["empty title"], 1);
+        assert_eq!(report.reasons["duplicate id"], 1);
+        let lines: Vec<u64> = report.rejections.iter().map(|r| r.record).collect();
+        assert_eq!(lines, vec![2, 3, 5, 6, 7]);
+        assert!(report.rejections[0].reason.contains("missing field `content`"), "{}", report.rejections[0].reason);
+
+        // Canonical records: extra fields dropped, titles trimmed, hashes filled in
+        let articles: Vec<Article> = fs::read_to_string(data_dir.join("articles.jsonl")).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(articles.len(), 2);
+        assert_eq!(articles[1].title, "Zinc");
+        assert_eq!(articles[1].categories, vec!["Metals"]);
+        assert_eq!(articles[0].content_hash, Article::hash_content("A language."));
+        assert!(!fs::read_to_string(data_dir.join("articles.jsonl")).unwrap().contains("curid"));
+
+        assert_eq!(ExtractionState::of(&data_dir), ExtractionState::Complete);
+        let config = DataDirConfig::load(&data_dir.join("config.json"), true).unwrap();
+        assert_eq!(config.language.as_deref(), Some("en"));
+    }
+}
@@ -0,0 +1,460 @@
+//! Tolerant JSONL parsing and compressed JSONL files
+//!
+//! A truncated or corrupt line in `articles.jsonl` (e.g. from an interrupted
+//! write) is skipped with a warning instead of aborting the whole load.
+//! Strict mode restores fail-fast behavior for validation scenarios.
+//!
+//! Extraction can write `articles.jsonl.gz` instead of `articles.jsonl`
+//! ([`crate::Config::compress_output`]); readers open either through
+//! [`articles_path`] and [`open`]. Very large extractions can be split into
+//! `articles.000.jsonl`, `articles.001.jsonl`, ... ([`crate::Config::shard_size`]);
+//! [`articles_files`] lists whichever files a data directory has and
+//! [`open_all`] reads them as one stream.
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+
+use crate::downloader::PARTIAL_ARTICLES_FILE;
+
+/// Suffix of gzip-compressed JSONL files
+pub const COMPRESSED_SUFFIX: &str = ".gz";
+
+/// gzip level for compressed output: most of the size reduction of higher
+/// levels, at a speed that keeps up with extraction
+const COMPRESSION_LEVEL: u32 = 6;
+
+/// Whether malformed lines are an error for the whole process
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Make malformed JSONL lines an error instead of a warning
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+/// Check if malformed JSONL lines are an error
+pub fn is_strict() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+/// Parses the lines of one JSONL file, counting the malformed ones
+pub struct LineParser {
+    path: PathBuf,
+    strict: bool,
+    skipped: u64,
+}
+
+impl LineParser {
+    /// Create a parser for `path`, using the process-wide strict setting
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self { path: path.as_ref().to_path_buf(), strict: is_strict(), skipped: 0 }
+    }
+
+    /// Override the strict setting for this file
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Parse line `line_number` (1-based).
+    ///
+    /// Returns `Ok(None)` for a malformed line, or an error in strict mode.
+    pub fn parse<T: DeserializeOwned>(&mut self, line: &str, line_number: u64) -> Result<Option<T>> {
+        match serde_json::from_str(line) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if self.strict => {
+                anyhow::bail!("Invalid JSON at {:?} line {}: {}", self.path, line_number, e)
+            }
+            Err(e) => {
+                tracing::warn!("Skipping malformed line {} of {:?}: {}", line_number, self.path, e);
+                self.skipped += 1;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Number of malformed lines skipped so far
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Log a summary if any lines were skipped
+    pub fn warn_if_skipped(&self) {
+        if self.skipped > 0 {
+            tracing::warn!("Skipped {} malformed line(s) in {:?}", self.skipped, self.path);
+        }
+    }
+}
+
+/// `path` with [`COMPRESSED_SUFFIX`] appended
+pub fn compressed_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(COMPRESSED_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Whether `path` names a compressed JSONL file
+pub fn is_compressed(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(COMPRESSED_SUFFIX)
+}
+
+/// The articles file in `data_dir`: `articles.jsonl`, or `articles.jsonl.gz`
+/// if only the compressed form exists
+pub fn articles_path(data_dir: &Path) -> PathBuf {
+    let plain = data_dir.join("articles.jsonl");
+    let compressed = compressed_path(&plain);
+    if !plain.exists() && compressed.exists() {
+        compressed
+    } else {
+        plain
+    }
+}
+
+/// Shard `index` of a sharded data directory: `articles.NNN.jsonl`, or
+/// `articles.NNN.jsonl.gz` if `compress`
+pub fn shard_path(data_dir: &Path, index: usize, compress: bool) -> PathBuf {
+    let path = data_dir.join(format!("articles.{:03}.jsonl", index));
+    if compress { compressed_path(&path) } else { path }
+}
+
+/// Shard number of an `articles.NNN.jsonl[.gz]` file name
+fn shard_index(file_name: &str) -> Option<usize> {
+    let (number, extension) = file_name.strip_prefix("articles.")?.split_once('.')?;
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) || !matches!(extension, "jsonl" | "jsonl.gz") {
+        return None;
+    }
+    number.parse().ok()
+}
+
+/// The shards in `data_dir`, in order
+pub fn shard_paths(data_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(data_dir) else {
+        return Vec::new();
+    };
+    let mut shards: Vec<(usize, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Some((shard_index(entry.file_name().to_str()?)?, entry.path())))
+        .collect();
+    shards.sort();
+    shards.into_iter().map(|(_, path)| path).collect()
+}
+
+/// The files holding the articles of `data_dir`, in order: its shards, or
+/// else the single [`articles_path`]. Empty if there are no articles.
+pub fn articles_files(data_dir: &Path) -> Vec<PathBuf> {
+    let shards = shard_paths(data_dir);
+    if !shards.is_empty() {
+        return shards;
+    }
+    let path = articles_path(data_dir);
+    if path.exists() { vec![path] } else { Vec::new() }
+}
+
+/// After writing `current` (the articles of `data_dir`, in either layout),
+/// delete every other articles file so it can't be read instead
+pub fn remove_other_articles_files(data_dir: &Path, current: &[PathBuf]) -> Result<()> {
+    let plain = data_dir.join("articles.jsonl");
+    let mut others = vec![compressed_path(&plain), plain];
+    others.extend(shard_paths(data_dir));
+    for other in others {
+        if !current.contains(&other) && other.exists() {
+            fs::remove_file(&other).with_context(|| format!("Failed to remove {:?}", other))?;
+        }
+    }
+    Ok(())
+}
+
+/// Check for articles files left by an unfinished extraction
+pub fn has_partial_files(data_dir: &Path) -> bool {
+    data_dir.join(PARTIAL_ARTICLES_FILE).exists() || !partial_shards(data_dir).is_empty()
+}
+
+/// Unfinished shards (`articles.NNN.jsonl.tmp`) in `data_dir`
+fn partial_shards(data_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(data_dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.file_name().to_str()
+                .and_then(|name| name.strip_suffix(".tmp"))
+                .is_some_and(|name| shard_index(name).is_some())
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// Open a JSONL file for reading, decompressing it if [`is_compressed`]
+pub fn open(path: &Path) -> Result<Box<dyn BufRead + Send>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    if is_compressed(path) {
+        Ok(Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Open JSONL files (e.g. [`articles_files`]) as one stream of lines, in order.
+/// Each file must end with a newline, as written by [`ArticlesWriter`].
+pub fn open_all(paths: &[PathBuf]) -> Result<Box<dyn BufRead + Send>> {
+    let readers = paths.iter().map(|path| open(path)).collect::<Result<_>>()?;
+    Ok(Box::new(Concat { readers }))
+}
+
+/// Readers read one after the other
+struct Concat {
+    readers: VecDeque<Box<dyn BufRead + Send>>,
+}
+
+impl io::Read for Concat {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for Concat {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while let Some(reader) = self.readers.front_mut() {
+            if !reader.fill_buf()?.is_empty() {
+                break;
+            }
+            self.readers.pop_front();
+        }
+        match self.readers.front_mut() {
+            Some(reader) => reader.fill_buf(),
+            None => Ok(&[]),
+        }
+    }
+
+    fn consume(&mut self, amount: usize) {
+        if let Some(reader) = self.readers.front_mut() {
+            reader.consume(amount);
+        }
+    }
+}
+
+/// Writes the articles of a data directory as extraction does: under `.tmp`
+/// names until [`Self::commit`], into a single `articles.jsonl` or, with a
+/// shard size, into `articles.000.jsonl`, `articles.001.jsonl`, ... of at
+/// most that many articles each
+pub struct ArticlesWriter {
+    data_dir: PathBuf,
+    compress: bool,
+    shard_size: usize,
+    writer: JsonlWriter,
+    /// Lines in the current file
+    lines: usize,
+    /// Final paths of the files written so far, the current one last
+    files: Vec<PathBuf>,
+}
+
+impl ArticlesWriter {
+    /// Start writing into `data_dir`; `shard_size` 0 writes a single file
+    pub fn create(data_dir: &Path, compress: bool, shard_size: usize) -> Result<Self> {
+        // Shards of an earlier interrupted run
+        for partial in partial_shards(data_dir) {
+            fs::remove_file(&partial).with_context(|| format!("Failed to remove {:?}", partial))?;
+        }
+        let first = if shard_size == 0 {
+            let path = data_dir.join("articles.jsonl");
+            if compress { compressed_path(&path) } else { path }
+        } else {
+            shard_path(data_dir, 0, compress)
+        };
+        let writer = create_partial(&partial_path(data_dir, &first), compress)?;
+        Ok(Self { data_dir: data_dir.to_path_buf(), compress, shard_size, writer, lines: 0, files: vec![first] })
+    }
+
+    /// Write one article's JSON line, moving on to the next shard if the
+    /// current one is full
+    pub fn write_line(&mut self, line: &str) -> Result<()> {
+        if self.shard_size > 0 && self.lines == self.shard_size {
+            let next = shard_path(&self.data_dir, self.files.len(), self.compress);
+            let next_writer = create_partial(&partial_path(&self.data_dir, &next), self.compress)?;
+            std::mem::replace(&mut self.writer, next_writer).finish()?;
+            self.files.push(next);
+            self.lines = 0;
+        }
+        writeln!(self.writer, "{}", line)?;
+        self.lines += 1;
+        Ok(())
+    }
+
+    /// Finish writing, move every file into place and remove articles files
+    /// of an earlier run. Returns the files written, in order.
+    pub fn commit(self) -> Result<Vec<PathBuf>> {
+        self.writer.finish()?;
+        for file in &self.files {
+            fs::rename(partial_path(&self.data_dir, file), file)
+                .with_context(|| format!("Failed to move {:?} into place", file))?;
+        }
+        remove_other_articles_files(&self.data_dir, &self.files)?;
+        Ok(self.files)
+    }
+}
+
+/// Name `path` is written under until it is complete: `path.tmp` for a
+/// shard, [`PARTIAL_ARTICLES_FILE`] for the single articles file
+fn partial_path(data_dir: &Path, path: &Path) -> PathBuf {
+    let is_shard = path.file_name().and_then(|name| name.to_str()).and_then(shard_index).is_some();
+    if is_shard {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(".tmp");
+        PathBuf::from(name)
+    } else {
+        data_dir.join(PARTIAL_ARTICLES_FILE)
+    }
+}
+
+/// Create a file readable by everyone (a server may run as another user)
+fn create_partial(path: &Path, compress: bool) -> Result<JsonlWriter> {
+    let file = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o644))?;
+    }
+    JsonlWriter::new(file, compress)
+}
+
+/// Streaming writer for a JSONL file, optionally gzip-compressed
+pub enum JsonlWriter {
+    Plain(BufWriter<File>),
+    Compressed(flate2::write::GzEncoder<BufWriter<File>>),
+}
+
+impl JsonlWriter {
+    /// Create (or truncate) `path`. The caller picks the file name; see
+    /// [`compressed_path`].
+    pub fn create(path: &Path, compress: bool) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+        Self::new(file, compress)
+    }
+
+    /// Write JSONL to an open `file`
+    pub fn new(file: File, compress: bool) -> Result<Self> {
+        let file = BufWriter::new(file);
+        if compress {
+            Ok(Self::Compressed(flate2::write::GzEncoder::new(file, flate2::Compression::new(COMPRESSION_LEVEL))))
+        } else {
+            Ok(Self::Plain(file))
+        }
+    }
+
+    /// Flush everything (ending the compressed stream) and sync to disk
+    pub fn finish(self) -> Result<()> {
+        let writer = match self {
+            Self::Plain(writer) => writer,
+            Self::Compressed(encoder) => encoder.finish()?,
+        };
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        Ok(())
+    }
+}
+
+impl Write for JsonlWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Plain(writer) => writer.write(buf),
+            Self::Compressed(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            Self::Compressed(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_malformed_lines_unless_strict() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Entry {
+            id: u64,
+        }
+
+        let mut parser = LineParser::new("articles.jsonl").with_strict(false);
+        assert_eq!(parser.parse::<Entry>("{\"id\":1}", 1).unwrap().unwrap().id, 1);
+        assert!(parser.parse::<Entry>("{\"id\":2,\"tit", 2).unwrap().is_none());
+        assert_eq!(parser.skipped(), 1);
+
+        let mut strict = LineParser::new("articles.jsonl").with_strict(true);
+        let err = strict.parse::<Entry>("{\"id\":2,\"tit", 7).unwrap_err();
+        assert!(err.to_string().contains("line 7"), "{}", err);
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(articles_path(dir.path()), dir.path().join("articles.jsonl"));
+
+        let path = compressed_path(&dir.path().join("articles.jsonl"));
+        let mut writer = JsonlWriter::create(&path, true).unwrap();
+        for id in 1..=3 {
+            writeln!(writer, "{{\"id\":{},\"title\":\"Article {}\"}}", id, id).unwrap();
+        }
+        writer.finish().unwrap();
+
+        assert_eq!(articles_path(dir.path()), path);
+        assert!(is_compressed(&path));
+        let raw = std::fs::read(&path).unwrap();
+        assert!(raw.starts_with(&[0x1f, 0x8b]), "file is not gzip-compressed");
+
+        let lines: Vec<String> = open(&path).unwrap().lines().map(|line| line.unwrap()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[2], "{\"id\":3,\"title\":\"Article 3\"}");
+
+        // The plain form wins when both exist
+        std::fs::write(dir.path().join("articles.jsonl"), "").unwrap();
+        assert_eq!(articles_path(dir.path()), dir.path().join("articles.jsonl"));
+    }
+
+    #[test]
+    fn test_sharded_articles_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("articles.jsonl"), "{\"id\":99}\n").unwrap();
+
+        let mut writer = ArticlesWriter::create(dir.path(), false, 2).unwrap();
+        for id in 1..=5 {
+            writer.write_line(&format!("{{\"id\":{}}}", id)).unwrap();
+        }
+        assert!(has_partial_files(dir.path()));
+        let files = writer.commit().unwrap();
+
+        let expected: Vec<PathBuf> = (0..3).map(|i| dir.path().join(format!("articles.{:03}.jsonl", i))).collect();
+        assert_eq!(files, expected);
+        assert_eq!(articles_files(dir.path()), expected);
+        assert!(!dir.path().join("articles.jsonl").exists());
+        assert!(!has_partial_files(dir.path()));
+        assert_eq!(std::fs::read_to_string(&expected[2]).unwrap(), "{\"id\":5}\n");
+
+        let ids: Vec<u64> = open_all(&files).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(&line.unwrap()).unwrap()["id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+
+        // Going back to a single file removes the shards
+        let mut writer = ArticlesWriter::create(dir.path(), true, 0).unwrap();
+        writer.write_line("{\"id\":1}").unwrap();
+        assert_eq!(writer.commit().unwrap(), vec![compressed_path(&dir.path().join("articles.jsonl"))]);
+        assert_eq!(articles_files(dir.path()), vec![compressed_path(&dir.path().join("articles.jsonl"))]);
+        assert!(shard_paths(dir.path()).is_empty());
+    }
+}
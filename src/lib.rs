@@ -2,21 +2,57 @@
 //! 
 //! Download and host Wikipedia locally with full-text search.
 
+pub mod access_log;
+pub mod analyzer;
 pub mod article;
+pub mod article_cache;
+pub mod corpus;
+pub mod diff;
+pub mod disk_usage;
 pub mod downloader;
+pub mod error;
+#[cfg(feature = "parquet")]
+pub mod export;
+pub mod favicon;
+pub mod feed;
+pub mod graph;
+pub mod import;
+pub mod jsonl;
+pub mod locale;
+pub mod lock;
+pub mod metrics;
+pub mod offsets;
+pub mod onboarding;
 pub mod parser;
+pub mod print;
+pub mod progress;
+pub mod prune;
+pub mod redirects;
 pub mod search;
+pub mod server;
+pub mod templates;
 pub mod config;
 pub mod update_config;
 pub mod update_manager;
+pub mod zim;
 
 pub use article::Article;
-pub use downloader::WikiDownloader;
+pub use downloader::{WikiDownloader, DownloadProgress, DownloadPhase, DownloadPlan, DumpCompression, ExtractionState};
+pub use error::{DownloadError, ExtractError};
 pub use parser::WikiParser;
+pub use redirects::RedirectMap;
 pub use search::SearchIndex;
-pub use config::Config;
-pub use update_config::{UpdateConfig, UpdateSchedule, UpdateMode, Weekday};
-pub use update_manager::{UpdateManager, UpdateStatus, Status, UpdateProgress};
+pub use config::{Config, DataDirConfig};
+pub use locale::NumberFormat;
+pub use update_config::{parse_pause_until, UpdateConfig, UpdateSchedule, UpdateMode, Weekday};
+pub use update_manager::{UpdateManager, UpdateStatus, Status, UpdateProgress, UpdateHistoryEntry};
+
+/// Minimum article length in characters for editions without their own
+/// default (see [`WikiLanguage::default_min_length`])
+pub const DEFAULT_MIN_LENGTH: usize = 200;
+
+/// Where dumps are downloaded from unless [`Config::dump_base_url`] names a mirror
+pub const DEFAULT_DUMP_BASE_URL: &str = "https://dumps.wikimedia.org";
 
 /// Supported Wikipedia languages/editions
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -63,10 +99,21 @@ impl WikiLanguage {
 
     /// Get the dump URL
     pub fn dump_url(&self) -> String {
+        self.dump_url_at(DEFAULT_DUMP_BASE_URL, false)
+    }
+
+    /// Get the URL of the XZ-compressed dump
+    pub fn dump_url_xz(&self) -> String {
+        self.dump_url_at(DEFAULT_DUMP_BASE_URL, true)
+    }
+
+    /// Get the dump URL on a server laid out like dumps.wikimedia.org,
+    /// e.g. a mirror (XZ-compressed with `xz`)
+    pub fn dump_url_at(&self, base_url: &str, xz: bool) -> String {
         let code = self.code();
         format!(
-            "https://dumps.wikimedia.org/{}wiki/latest/{}wiki-latest-pages-articles.xml.bz2",
-            code, code
+            "{}/{}wiki/latest/{}wiki-latest-pages-articles.xml.{}",
+            base_url.trim_end_matches('/'), code, code, if xz { "xz" } else { "bz2" }
         )
     }
 
@@ -86,6 +133,24 @@ impl WikiLanguage {
         }
     }
 
+    /// Get estimated compressed dump size in bytes (matches [`Self::estimated_size`])
+    pub fn estimated_size_bytes(&self) -> u64 {
+        const MB: u64 = 1024 * 1024;
+        const GB: u64 = 1024 * MB;
+        match self {
+            WikiLanguage::Simple => 300 * MB,
+            WikiLanguage::English => 22 * GB,
+            WikiLanguage::German => 7 * GB,
+            WikiLanguage::French => 5 * GB,
+            WikiLanguage::Spanish => 4 * GB,
+            WikiLanguage::Japanese => 4 * GB,
+            WikiLanguage::Russian => 5 * GB,
+            WikiLanguage::Chinese => 3 * GB,
+            WikiLanguage::Italian => 4 * GB,
+            WikiLanguage::Portuguese => 2 * GB,
+        }
+    }
+
     /// Get estimated article count
     pub fn estimated_articles(&self) -> &'static str {
         match self {
@@ -102,6 +167,22 @@ impl WikiLanguage {
         }
     }
 
+    /// Get estimated article count (matches [`Self::estimated_articles`])
+    pub fn estimated_article_count(&self) -> u64 {
+        match self {
+            WikiLanguage::Simple => 200_000,
+            WikiLanguage::English => 6_700_000,
+            WikiLanguage::German => 2_800_000,
+            WikiLanguage::French => 2_500_000,
+            WikiLanguage::Spanish => 1_900_000,
+            WikiLanguage::Japanese => 1_400_000,
+            WikiLanguage::Russian => 1_900_000,
+            WikiLanguage::Chinese => 1_300_000,
+            WikiLanguage::Italian => 1_800_000,
+            WikiLanguage::Portuguese => 1_100_000,
+        }
+    }
+
     /// Get display name
     pub fn display_name(&self) -> &'static str {
         match self {
@@ -118,6 +199,23 @@ impl WikiLanguage {
         }
     }
 
+    /// Default minimum article length in characters.
+    ///
+    /// Short articles are valid on Simple English, and a CJK character
+    /// carries about as much as three or four Latin letters.
+    pub fn default_min_length(&self) -> usize {
+        match self {
+            WikiLanguage::Simple => 100,
+            WikiLanguage::Japanese | WikiLanguage::Chinese => 60,
+            _ => DEFAULT_MIN_LENGTH,
+        }
+    }
+
+    /// Get the number and size formatting conventions
+    pub fn number_format(&self) -> NumberFormat {
+        NumberFormat::for_language(Some(*self))
+    }
+
     /// Parse from string
     pub fn from_code(code: &str) -> Option<WikiLanguage> {
         match code.to_lowercase().as_str() {
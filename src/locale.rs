@@ -0,0 +1,112 @@
+//! Locale-aware number formatting
+//!
+//! Article counts and sizes are shown using the conventions of the wiki's
+//! language: `1.234.567` for German, `1,234,567` for English. Languages
+//! without specific rules fall back to English.
+
+use crate::WikiLanguage;
+
+/// Digit grouping, decimal separator and size units of a language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    group_separator: &'static str,
+    decimal_separator: &'static str,
+    /// Units for bytes, KB, MB and GB
+    units: [&'static str; 4],
+}
+
+impl NumberFormat {
+    /// `1,234,567` and `1.50 MB`
+    pub const ENGLISH: NumberFormat = NumberFormat {
+        group_separator: ",",
+        decimal_separator: ".",
+        units: ["bytes", "KB", "MB", "GB"],
+    };
+
+    /// Format for `language`, or English if unknown
+    pub fn for_language(language: Option<WikiLanguage>) -> Self {
+        let Some(language) = language else {
+            return Self::ENGLISH;
+        };
+        match language {
+            WikiLanguage::Simple
+            | WikiLanguage::English
+            | WikiLanguage::Japanese
+            | WikiLanguage::Chinese => Self::ENGLISH,
+            WikiLanguage::German => Self::european(".", ["Bytes", "KB", "MB", "GB"]),
+            WikiLanguage::Spanish | WikiLanguage::Portuguese => Self::european(".", ["bytes", "KB", "MB", "GB"]),
+            WikiLanguage::Italian => Self::european(".", ["byte", "KB", "MB", "GB"]),
+            // Narrow no-break space
+            WikiLanguage::French => Self::european("\u{202F}", ["octets", "Ko", "Mo", "Go"]),
+            // No-break space
+            WikiLanguage::Russian => Self::european("\u{A0}", ["байт", "КБ", "МБ", "ГБ"]),
+        }
+    }
+
+    /// Format for a language code such as `de`, or English if unknown
+    pub fn for_code(code: &str) -> Self {
+        Self::for_language(WikiLanguage::from_code(code))
+    }
+
+    /// Decimal comma with the given grouping separator
+    const fn european(group_separator: &'static str, units: [&'static str; 4]) -> Self {
+        Self { group_separator, decimal_separator: ",", units }
+    }
+
+    /// Format an integer with digit grouping
+    pub fn format_number(&self, n: u64) -> String {
+        let digits = n.to_string();
+        let mut result = String::with_capacity(digits.len() + digits.len() / 3 * self.group_separator.len());
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                result.push_str(self.group_separator);
+            }
+            result.push(c);
+        }
+        result
+    }
+
+    /// Format a byte count using binary units, e.g. `1.50 MB`
+    pub fn format_bytes(&self, bytes: u64) -> String {
+        const KB: u64 = 1024;
+        const MB: u64 = KB * 1024;
+        const GB: u64 = MB * 1024;
+
+        let (value, unit) = if bytes >= GB {
+            (bytes as f64 / GB as f64, self.units[3])
+        } else if bytes >= MB {
+            (bytes as f64 / MB as f64, self.units[2])
+        } else if bytes >= KB {
+            (bytes as f64 / KB as f64, self.units[1])
+        } else {
+            return format!("{} {}", self.format_number(bytes), self.units[0]);
+        };
+        format!("{} {}", format!("{:.2}", value).replace('.', self.decimal_separator), unit)
+    }
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self::ENGLISH
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grouping_by_language() {
+        let english = NumberFormat::for_language(Some(WikiLanguage::English));
+        let german = NumberFormat::for_language(Some(WikiLanguage::German));
+        assert_eq!(english.format_number(1234567), "1,234,567");
+        assert_eq!(german.format_number(1234567), "1.234.567");
+        assert_eq!(german.format_number(123), "123");
+        assert_eq!(german.format_bytes(1536 * 1024), "1,50 MB");
+        assert_eq!(NumberFormat::for_code("fr").format_bytes(1024), "1,00 Ko");
+
+        // Unknown languages use English conventions
+        assert_eq!(NumberFormat::for_code("xx").format_number(1234567), "1,234,567");
+        assert_eq!(NumberFormat::default().format_bytes(500), "500 bytes");
+    }
+}
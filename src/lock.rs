@@ -0,0 +1,148 @@
+//! Data directory locking
+//!
+//! Extraction, indexing and pruning take an exclusive advisory lock on
+//! `.lock` in the data directory, so a second writer fails fast instead of
+//! corrupting `articles.jsonl` or the index. The server takes a shared lock
+//! on `.readers`, which lets writers tell that a server is using the
+//! directory without blocking updates (the server keeps serving the previous
+//! data until restarted). `update_status.json` has a lock of its own, so
+//! the server and the update daemon don't interleave writes to it.
+
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+use fs2::FileExt;
+
+/// Lock file held exclusively by writers
+pub const WRITE_LOCK_FILE: &str = ".lock";
+
+/// Lock file held shared by servers reading the directory
+pub const READ_LOCK_FILE: &str = ".readers";
+
+/// Lock file guarding `update_status.json`
+pub const STATUS_LOCK_FILE: &str = ".update_status.lock";
+
+/// Whether locks are taken at all (some network filesystems don't support them)
+static LOCKING: AtomicBool = AtomicBool::new(true);
+
+/// Enable or disable data directory locking for the whole process
+pub fn set_locking(enabled: bool) {
+    LOCKING.store(enabled, Ordering::Relaxed);
+}
+
+/// An advisory lock on a data directory, released when dropped
+pub struct DataDirLock {
+    /// `None` when locking is disabled
+    file: Option<File>,
+    path: PathBuf,
+}
+
+impl DataDirLock {
+    /// Take the exclusive writer lock, failing if another operation holds it.
+    ///
+    /// `operation` names what is about to run, for log messages.
+    pub fn exclusive(data_dir: &Path, operation: &str) -> Result<Self> {
+        let path = data_dir.join(WRITE_LOCK_FILE);
+        if !LOCKING.load(Ordering::Relaxed) {
+            return Ok(Self { file: None, path });
+        }
+        let file = open_lock_file(&path)?;
+        if file.try_lock_exclusive().is_err() {
+            anyhow::bail!(
+                "Another operation is in progress on {:?} (lock held on {}). Try again once it has finished.",
+                data_dir, WRITE_LOCK_FILE
+            );
+        }
+        tracing::debug!("Locked {:?} for {}", data_dir, operation);
+        if readers_active(data_dir) {
+            tracing::info!("A server is reading {:?}; restart it after the {} to serve the new data", data_dir, operation);
+        }
+        Ok(Self { file: Some(file), path })
+    }
+
+    /// Take a shared reader lock, failing only if the lock file can't be opened
+    pub fn shared(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(READ_LOCK_FILE);
+        if !LOCKING.load(Ordering::Relaxed) {
+            return Ok(Self { file: None, path });
+        }
+        let file = open_lock_file(&path)?;
+        file.try_lock_shared()
+            .with_context(|| format!("Failed to lock {:?}", path))?;
+        Ok(Self { file: Some(file), path })
+    }
+
+    /// Wait for the update status lock: exclusive to write the status file,
+    /// shared to read it
+    pub fn status(data_dir: &Path, exclusive: bool) -> Result<Self> {
+        let path = data_dir.join(STATUS_LOCK_FILE);
+        if !LOCKING.load(Ordering::Relaxed) {
+            return Ok(Self { file: None, path });
+        }
+        let file = open_lock_file(&path)?;
+        let locked = if exclusive { file.lock_exclusive() } else { file.lock_shared() };
+        locked.with_context(|| format!("Failed to lock {:?}", path))?;
+        Ok(Self { file: Some(file), path })
+    }
+}
+
+impl Drop for DataDirLock {
+    fn drop(&mut self) {
+        if let Some(ref file) = self.file {
+            if let Err(e) = FileExt::unlock(file) {
+                tracing::warn!("Failed to release lock {:?}: {}", self.path, e);
+            }
+        }
+    }
+}
+
+/// Check if a server holds a reader lock on `data_dir`
+pub fn readers_active(data_dir: &Path) -> bool {
+    let Ok(file) = open_lock_file(&data_dir.join(READ_LOCK_FILE)) else {
+        return false;
+    };
+    match file.try_lock_exclusive() {
+        Ok(()) => {
+            let _ = FileExt::unlock(&file);
+            false
+        }
+        Err(_) => true,
+    }
+}
+
+fn open_lock_file(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to open lock file {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_exclusive_lock_fails() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let lock = DataDirLock::exclusive(dir.path(), "extraction").unwrap();
+        let err = DataDirLock::exclusive(dir.path(), "indexing").err().unwrap();
+        assert!(err.to_string().contains("Another operation is in progress"), "{}", err);
+
+        // Released on drop
+        drop(lock);
+        DataDirLock::exclusive(dir.path(), "indexing").unwrap();
+
+        // Readers don't block writers, but are visible to them
+        assert!(!readers_active(dir.path()));
+        let reader = DataDirLock::shared(dir.path()).unwrap();
+        assert!(readers_active(dir.path()));
+        let _writer = DataDirLock::exclusive(dir.path(), "update").unwrap();
+        drop(reader);
+        assert!(!readers_active(dir.path()));
+    }
+}
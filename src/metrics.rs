@@ -0,0 +1,204 @@
+//! Prometheus metrics for the server
+//!
+//! A small hand-rolled registry: request counts by route and status, request
+//! latency histograms by route, and article hit/miss counters. Rendered in
+//! the Prometheus text exposition format.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+/// Latency histogram bucket upper bounds, in seconds
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Routes that serve a single article, possibly nested under `/{code}` when
+/// serving several languages
+const ARTICLE_ROUTES: &[&str] = &["/article/:id", "/wiki/:title"];
+
+/// Route label for requests that matched no route
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Cumulative latency histogram
+#[derive(Default)]
+struct Histogram {
+    /// Count per bucket of [`LATENCY_BUCKETS`] (not cumulative)
+    buckets: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; LATENCY_BUCKETS.len()];
+        }
+        if let Some(i) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[i] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    /// (route, status) -> count
+    requests: BTreeMap<(String, u16), u64>,
+    /// route -> latency
+    latencies: BTreeMap<String, Histogram>,
+    articles_served: u64,
+    articles_not_found: u64,
+}
+
+/// Server metrics registry
+#[derive(Default)]
+pub struct Metrics {
+    registry: Mutex<Registry>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a finished request
+    pub fn record_request(&self, route: &str, status: u16, duration: Duration) {
+        let mut registry = self.registry.lock().unwrap();
+        *registry.requests.entry((route.to_string(), status)).or_insert(0) += 1;
+        registry.latencies.entry(route.to_string()).or_default().observe(duration.as_secs_f64());
+
+        if is_article_route(route) {
+            match status {
+                200 => registry.articles_served += 1,
+                404 => registry.articles_not_found += 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Render all metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let registry = self.registry.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP rustipedia_http_requests_total HTTP requests by route and status.\n");
+        out.push_str("# TYPE rustipedia_http_requests_total counter\n");
+        for ((route, status), count) in &registry.requests {
+            let _ = writeln!(out, "rustipedia_http_requests_total{{route=\"{}\",status=\"{}\"}} {}",
+                escape_label(route), status, count);
+        }
+
+        out.push_str("# HELP rustipedia_http_request_duration_seconds HTTP request latency by route.\n");
+        out.push_str("# TYPE rustipedia_http_request_duration_seconds histogram\n");
+        for (route, histogram) in &registry.latencies {
+            let route = escape_label(route);
+            let mut cumulative = 0;
+            for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+                cumulative += histogram.buckets.get(i).copied().unwrap_or(0);
+                let _ = writeln!(out, "rustipedia_http_request_duration_seconds_bucket{{route=\"{}\",le=\"{}\"}} {}",
+                    route, bound, cumulative);
+            }
+            let _ = writeln!(out, "rustipedia_http_request_duration_seconds_bucket{{route=\"{}\",le=\"+Inf\"}} {}",
+                route, histogram.count);
+            let _ = writeln!(out, "rustipedia_http_request_duration_seconds_sum{{route=\"{}\"}} {}", route, histogram.sum);
+            let _ = writeln!(out, "rustipedia_http_request_duration_seconds_count{{route=\"{}\"}} {}", route, histogram.count);
+        }
+
+        out.push_str("# HELP rustipedia_articles_served_total Articles served successfully.\n");
+        out.push_str("# TYPE rustipedia_articles_served_total counter\n");
+        let _ = writeln!(out, "rustipedia_articles_served_total {}", registry.articles_served);
+        out.push_str("# HELP rustipedia_articles_not_found_total Article requests that found no article.\n");
+        out.push_str("# TYPE rustipedia_articles_not_found_total counter\n");
+        let _ = writeln!(out, "rustipedia_articles_not_found_total {}", registry.articles_not_found);
+
+        out
+    }
+}
+
+/// Whether `route` is one of [`ARTICLE_ROUTES`], at the root or under a language prefix
+fn is_article_route(route: &str) -> bool {
+    ARTICLE_ROUTES.iter().any(|article_route| route.ends_with(article_route))
+}
+
+/// Escape a Prometheus label value
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Middleware recording every request, labelled by its route pattern
+pub async fn track_requests(State(metrics): State<Arc<Metrics>>, request: Request, next: Next) -> Response {
+    let route = request.extensions().get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let start = Instant::now();
+    let response = next.run(request).await;
+    metrics.record_request(&route, response.status().as_u16(), start.elapsed());
+    response
+}
+
+/// `GET /metrics` handler
+pub async fn metrics_handler(State(metrics): State<Arc<Metrics>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_request_counter_increments() {
+        let metrics = Arc::new(Metrics::new());
+        let app = Router::new()
+            .route("/wiki/:title", get(|| async { "article" }))
+            .layer(axum::middleware::from_fn_with_state(metrics.clone(), track_requests));
+
+        let counter = "rustipedia_http_requests_total{route=\"/wiki/:title\",status=\"200\"}";
+        assert!(!metrics.render().contains(counter));
+
+        for _ in 0..2 {
+            let request = Request::builder().uri("/wiki/Rust").body(Body::empty()).unwrap();
+            app.clone().oneshot(request).await.unwrap();
+        }
+
+        let rendered = metrics.render();
+        assert!(rendered.contains(&format!("{} 2", counter)), "{}", rendered);
+        assert!(rendered.contains("rustipedia_http_request_duration_seconds_count{route=\"/wiki/:title\"} 2"));
+        assert!(rendered.contains("rustipedia_articles_served_total 2"));
+    }
+
+    #[tokio::test]
+    async fn test_article_counters_with_language_prefixes() {
+        let metrics = Arc::new(Metrics::new());
+        let language_routes = || Router::new()
+            .route("/article/:id", get(|| async { "article" }))
+            .route("/wiki/:title", get(|| async { axum::http::StatusCode::NOT_FOUND }))
+            .route("/search", get(|| async { "results" }));
+        let app = Router::new()
+            .nest("/simple", language_routes())
+            .nest("/de", language_routes())
+            .layer(axum::middleware::from_fn_with_state(metrics.clone(), track_requests));
+
+        for uri in ["/simple/article/1", "/de/article/2", "/de/wiki/Missing", "/simple/search"] {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            app.clone().oneshot(request).await.unwrap();
+        }
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("rustipedia_http_requests_total{route=\"/de/article/:id\",status=\"200\"} 1"), "{}", rendered);
+        assert!(rendered.contains("rustipedia_articles_served_total 2"), "{}", rendered);
+        assert!(rendered.contains("rustipedia_articles_not_found_total 1"), "{}", rendered);
+    }
+}
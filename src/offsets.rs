@@ -0,0 +1,176 @@
+//! Byte-offset index of `articles.jsonl`
+//!
+//! Maps article IDs to the byte offset of their line so a single article can
+//! be read on demand, without the search index and without holding every
+//! article in memory. The map is rebuilt when the file is rewritten (prune,
+//! update), detected by a change in its size or modification time.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::article::Article;
+use crate::jsonl::LineParser;
+
+/// Size and modification time, used to notice when the file was rewritten
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("Failed to read metadata of {:?}", path))?;
+        Ok(Self { len: metadata.len(), modified: metadata.modified().ok() })
+    }
+}
+
+struct OffsetMap {
+    offsets: HashMap<u64, u64>,
+    stamp: FileStamp,
+}
+
+/// ID to byte-offset index of an `articles.jsonl` file
+pub struct ArticleOffsets {
+    path: PathBuf,
+    map: RwLock<OffsetMap>,
+}
+
+impl ArticleOffsets {
+    /// Scan `path` and record the offset of every article
+    pub fn build(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let map = scan(&path)?;
+        Ok(Self { path, map: RwLock::new(map) })
+    }
+
+    /// Number of indexed articles
+    pub fn len(&self) -> usize {
+        self.map.read().unwrap().offsets.len()
+    }
+
+    /// Check if no articles are indexed
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read the article with `id`, rebuilding the index first if the file changed
+    pub fn get(&self, id: u64) -> Result<Option<Article>> {
+        if FileStamp::of(&self.path)? != self.map.read().unwrap().stamp {
+            self.rebuild()?;
+        }
+        let Some(offset) = self.map.read().unwrap().offsets.get(&id).copied() else {
+            return Ok(None);
+        };
+        let article = read_at(&self.path, offset)?;
+        if article.as_ref().is_some_and(|article| article.id == id) {
+            return Ok(article);
+        }
+
+        // Rewritten without a visible change in size or mtime; rescan once
+        self.rebuild()?;
+        let Some(offset) = self.map.read().unwrap().offsets.get(&id).copied() else {
+            return Ok(None);
+        };
+        Ok(read_at(&self.path, offset)?.filter(|article| article.id == id))
+    }
+
+    fn rebuild(&self) -> Result<()> {
+        tracing::info!("{:?} changed, rebuilding article offsets", self.path);
+        let map = scan(&self.path)?;
+        *self.map.write().unwrap() = map;
+        Ok(())
+    }
+}
+
+/// Record the offset of each line's article ID. Later duplicates win.
+///
+/// Malformed lines are skipped (see [`LineParser`]).
+fn scan(path: &Path) -> Result<OffsetMap> {
+    #[derive(Deserialize)]
+    struct ArticleId {
+        id: u64,
+    }
+
+    let stamp = FileStamp::of(path)?;
+    let file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut reader = BufReader::new(file);
+    let mut parser = LineParser::new(path);
+    let mut offsets = HashMap::new();
+    let mut offset = 0u64;
+    let mut line_number = 0u64;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line)?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+        let trimmed = line.trim_end();
+        if !trimmed.is_empty() {
+            if let Some(article) = parser.parse::<ArticleId>(trimmed, line_number)? {
+                offsets.insert(article.id, offset);
+            }
+        }
+        offset += read as u64;
+    }
+    parser.warn_if_skipped();
+    Ok(OffsetMap { offsets, stamp })
+}
+
+/// Parse the article on the line starting at `offset`
+fn read_at(path: &Path, offset: u64) -> Result<Option<Article>> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut line = String::new();
+    BufReader::new(file).read_line(&mut line)?;
+    Ok(serde_json::from_str(line.trim_end()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_jsonl(path: &Path, articles: &[Article]) {
+        let mut file = File::create(path).unwrap();
+        for article in articles {
+            writeln!(file, "{}", serde_json::to_string(article).unwrap()).unwrap();
+        }
+    }
+
+    fn article(id: u64, title: &str, content: &str) -> Article {
+        Article::new(id, title.to_string(), content.to_string())
+    }
+
+    #[test]
+    fn test_seek_article_and_rebuild_after_rewrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("articles.jsonl");
+        write_jsonl(&path, &[article(1, "Rust", "A language"), article(2, "Iron", "A metal")]);
+
+        let offsets = ArticleOffsets::build(&path).unwrap();
+        assert_eq!(offsets.len(), 2);
+        assert_eq!(offsets.get(2).unwrap().unwrap().title, "Iron");
+        assert!(offsets.get(3).unwrap().is_none());
+
+        // Rewritten in a different order with changed content, as after pruning
+        write_jsonl(&path, &[
+            article(3, "Copper", "Another metal"),
+            article(2, "Iron", "A metal, element 26"),
+            article(1, "Rust", "A language"),
+        ]);
+        assert_eq!(offsets.get(2).unwrap().unwrap().content, "A metal, element 26");
+        assert_eq!(offsets.get(3).unwrap().unwrap().title, "Copper");
+        assert_eq!(offsets.len(), 3);
+    }
+}
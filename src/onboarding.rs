@@ -0,0 +1,221 @@
+//! First-run page
+//!
+//! `rustipedia-serve` started before anything was downloaded doesn't exit
+//! with an error: it answers every page with the commands to download a
+//! Wikipedia (and the API with a 503) until it is restarted with data.
+//! When only some of several data directories are missing, the others are
+//! served and the page only answers under the missing languages' prefixes.
+
+use std::path::{Path, PathBuf};
+
+use axum::http::{StatusCode, Uri};
+use axum::response::{Html, IntoResponse, Response};
+use anyhow::Result;
+use axum::{Json, Router};
+
+use crate::config::DataDirConfig;
+use crate::downloader::ExtractionState;
+use crate::jsonl;
+use crate::parser::WikiParser;
+
+/// Data directories without an articles file
+pub fn missing_data_dirs(data_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    data_dirs.iter()
+        .filter(|data_dir| jsonl::articles_files(data_dir).is_empty())
+        .cloned()
+        .collect()
+}
+
+/// Prefix a data directory without articles is reserved under: the
+/// language in its config.json, or else the directory's name (`--data de`)
+pub fn missing_language(data_dir: &Path) -> String {
+    DataDirConfig::load(&data_dir.join("config.json"), false)
+        .ok()
+        .and_then(|config| config.language)
+        .or_else(|| data_dir.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .unwrap_or_default()
+}
+
+/// The app for `data_dirs`.
+///
+/// With no articles anywhere this is the first-run [`router`]. Otherwise
+/// `serve` builds the routes for the directories that have articles, given
+/// the language prefixes of the missing ones (so it prefixes its own routes
+/// and rejects clashes), and the first-run page is nested under each of
+/// those prefixes.
+pub fn app<F>(data_dirs: &[PathBuf], serve: F) -> Result<Router>
+where
+    F: FnOnce(Vec<PathBuf>, &[String]) -> Result<Router>,
+{
+    let missing = missing_data_dirs(data_dirs);
+    if missing.len() == data_dirs.len() {
+        return Ok(router(missing));
+    }
+
+    let present = data_dirs.iter().filter(|data_dir| !missing.contains(data_dir)).cloned().collect();
+    let codes: Vec<String> = missing.iter().map(|data_dir| missing_language(data_dir)).collect();
+    let mut app = serve(present, &codes)?;
+    for (code, data_dir) in codes.iter().zip(missing) {
+        app = app.nest(&format!("/{}", code), router(vec![data_dir]));
+    }
+    Ok(app)
+}
+
+/// The page telling how to get data into `missing`
+pub fn onboarding_html(missing: &[PathBuf]) -> String {
+    let steps: String = missing.iter().map(|data_dir| dir_steps(data_dir)).collect();
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>No data yet - Rustipedia</title>
+    <style>
+        body {{
+            max-width: 44em;
+            margin: 0 auto;
+            padding: 48px 24px;
+            background: #0f1115;
+            color: #e6e6e6;
+            font-family: system-ui, -apple-system, "Segoe UI", sans-serif;
+            line-height: 1.6;
+        }}
+        h1 {{ font-size: 2rem; margin-bottom: 0.2em; }}
+        h2 {{ font-size: 1.1rem; margin-top: 2em; }}
+        .muted {{ color: #9aa0a6; }}
+        pre {{ background: #1b1e24; border: 1px solid #2c313a; border-radius: 8px; padding: 12px 16px; overflow-x: auto; }}
+        code {{ font-family: ui-monospace, "SF Mono", Consolas, monospace; font-size: 0.95em; }}
+    </style>
+</head>
+<body>
+    <h1>📚 No data yet</h1>
+    <p class="muted">Rustipedia is running, but there are no articles to serve. Download a Wikipedia, then restart <code>rustipedia-serve</code> to pick it up.</p>
+    {steps}
+    <h2>Other editions</h2>
+    <p>Replace <code>simple</code> (Simple English, about 300 MB) with <code>en</code>, <code>de</code>, <code>fr</code>, <code>es</code> and others; <code>rustipedia-download list</code> shows them all. Full English Wikipedia is a 22 GB download.</p>
+</body>
+</html>"#,
+        steps = steps,
+    )
+}
+
+/// Instructions for one data directory
+fn dir_steps(data_dir: &Path) -> String {
+    let dir = WikiParser::html_escape(&data_dir.to_string_lossy());
+    let status = if ExtractionState::of(data_dir) == ExtractionState::Interrupted {
+        "An extraction into this directory is running or was interrupted. Wait for it to finish, or run the download again:"
+    } else {
+        "Download and index Simple English Wikipedia into it:"
+    };
+    format!(r#"
+    <h2>{dir}</h2>
+    <p>{status}</p>
+    <pre><code>rustipedia-download --lang simple --output {dir}</code></pre>
+    <p>Then restart the server:</p>
+    <pre><code>rustipedia-serve --data {dir}</code></pre>"#,
+        dir = dir,
+        status = status,
+    )
+}
+
+/// A router answering every request with the first-run page (JSON 503s under `/api/`)
+pub fn router(missing: Vec<PathBuf>) -> Router {
+    let html = onboarding_html(&missing);
+    Router::new().fallback(move |uri: Uri| {
+        let html = html.clone();
+        async move { onboarding_response(&uri, html) }
+    })
+}
+
+fn onboarding_response(uri: &Uri, html: String) -> Response {
+    if uri.path().starts_with("/api/") {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": "No articles yet. Run rustipedia-download, then restart the server." })),
+        ).into_response();
+    }
+    Html(html).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_empty_data_dir_serves_onboarding_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().join("wiki");
+        let missing = missing_data_dirs(std::slice::from_ref(&data_dir));
+        assert_eq!(missing, vec![data_dir.clone()]);
+        let app = router(missing);
+
+        let page = app.clone()
+            .oneshot(Request::builder().uri("/wiki/Rust").body(Body::empty()).unwrap())
+            .await.unwrap();
+        assert_eq!(page.status(), StatusCode::OK);
+        let body = to_bytes(page.into_body(), 1 << 20).await.unwrap();
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains("No data yet"));
+        assert!(body.contains(&format!("rustipedia-download --lang simple --output {}", data_dir.display())));
+
+        let api = app
+            .oneshot(Request::builder().uri("/api/search?q=rust").body(Body::empty()).unwrap())
+            .await.unwrap();
+        assert_eq!(api.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::write(data_dir.join("articles.jsonl"), "").unwrap();
+        assert!(missing_data_dirs(&[data_dir]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_missing_language_is_nested_next_to_loaded_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let en = dir.path().join("en");
+        std::fs::create_dir_all(&en).unwrap();
+        std::fs::write(en.join("articles.jsonl"), "").unwrap();
+        let de = dir.path().join("de");
+        let data_dirs = vec![en.clone(), de.clone()];
+
+        let served = app(&data_dirs, |present, missing| {
+            assert_eq!(present, vec![en.clone()]);
+            assert_eq!(missing, ["de".to_string()]);
+            Ok(Router::new().route("/en/wiki/:title", get(|| async { "article" })))
+        }).unwrap();
+
+        let article = served.clone()
+            .oneshot(Request::builder().uri("/en/wiki/Rust").body(Body::empty()).unwrap())
+            .await.unwrap();
+        assert_eq!(article.status(), StatusCode::OK);
+        assert_eq!(to_bytes(article.into_body(), 1024).await.unwrap(), "article");
+
+        let page = served.clone()
+            .oneshot(Request::builder().uri("/de/wiki/Rust").body(Body::empty()).unwrap())
+            .await.unwrap();
+        assert_eq!(page.status(), StatusCode::OK);
+        let body = to_bytes(page.into_body(), 1 << 20).await.unwrap();
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains("No data yet"));
+        assert!(body.contains(&format!("--output {}", de.display())));
+        assert!(!body.contains(&format!("--output {}", en.display())));
+
+        let api = served
+            .oneshot(Request::builder().uri("/de/api/search?q=rust").body(Body::empty()).unwrap())
+            .await.unwrap();
+        assert_eq!(api.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // A config.json language wins over the directory name
+        std::fs::create_dir_all(&de).unwrap();
+        std::fs::write(de.join("config.json"), r#"{"language": "fr"}"#).unwrap();
+        assert_eq!(missing_language(&de), "fr");
+        let all_missing = app(std::slice::from_ref(&de), |_, _| panic!("nothing to serve")).unwrap();
+        let page = all_missing
+            .oneshot(Request::builder().uri("/wiki/Rust").body(Body::empty()).unwrap())
+            .await.unwrap();
+        assert_eq!(page.status(), StatusCode::OK);
+    }
+}
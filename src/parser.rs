@@ -3,6 +3,11 @@
 use regex::Regex;
 use once_cell::sync::Lazy;
 
+use crate::article::{normalize_title, slugify};
+use crate::prune::TitleSet;
+use crate::templates::TemplateHandlers;
+use crate::WikiLanguage;
+
 /// Regex patterns for wiki markup cleaning (compiled once)
 static REF_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<ref[^>]*>.*?</ref>").unwrap());
 static REF_SELF_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<ref[^/]*/\s*>").unwrap());
@@ -16,8 +21,18 @@ static BULLET_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^\s*[\*#:]+\s*").u
 static HTML_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]+>").unwrap());
 static MULTI_SPACE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"[ \t]+").unwrap());
 static MULTI_NEWLINE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n{3,}").unwrap());
+/// Tags allowed in stored content: the ones the cleaner and renderer emit
+static ALLOWED_TAG_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^(?:<a href="(?:/wiki/|#)[^"<>]*">|</?(?:a|h[2-6]|ul|ol|li|mark)>)"#).unwrap()
+});
+
+/// Character and numeric entity references, left alone when escaping `&`
+static ENTITY_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^&(?:[a-zA-Z][a-zA-Z0-9]*|#[0-9]+|#[xX][0-9a-fA-F]+);").unwrap());
+
+/// Built-in template handlers, used when no custom set is given
+static DEFAULT_TEMPLATES: Lazy<TemplateHandlers> = Lazy::new(TemplateHandlers::default);
 
-/// Special page namespace prefixes to skip
+/// Special page namespace prefixes to skip (canonical names, valid on every edition)
 const SKIP_PREFIXES: &[&str] = &[
     "Wikipedia:", "Template:", "Category:", "File:", "Image:",
     "Help:", "Portal:", "Draft:", "MediaWiki:", "Module:",
@@ -26,26 +41,125 @@ const SKIP_PREFIXES: &[&str] = &[
     "Help talk:", "Portal talk:", "Draft talk:",
 ];
 
+/// Localized namespace prefixes, in addition to [`SKIP_PREFIXES`]
+const GERMAN_PREFIXES: &[&str] = &[
+    "Benutzer:", "Benutzerin:", "Diskussion:", "Benutzer Diskussion:", "Benutzerin Diskussion:",
+    "Wikipedia Diskussion:", "Datei:", "Datei Diskussion:", "Bild:", "MediaWiki Diskussion:",
+    "Vorlage:", "Vorlage Diskussion:", "Hilfe:", "Hilfe Diskussion:", "Kategorie:",
+    "Kategorie Diskussion:", "Portal Diskussion:", "Modul:", "Modul Diskussion:",
+];
+
+const FRENCH_PREFIXES: &[&str] = &[
+    "Utilisateur:", "Utilisatrice:", "Discussion:", "Discussion utilisateur:", "Wikipédia:",
+    "Discussion Wikipédia:", "Fichier:", "Discussion fichier:", "Modèle:", "Discussion modèle:",
+    "Aide:", "Discussion aide:", "Catégorie:", "Discussion catégorie:", "Portail:",
+    "Discussion Portail:", "Projet:", "Discussion Projet:", "Discussion module:", "Référence:",
+];
+
+const SPANISH_PREFIXES: &[&str] = &[
+    "Usuario:", "Usuaria:", "Discusión:", "Usuario discusión:", "Wikipedia discusión:",
+    "Archivo:", "Archivo discusión:", "Plantilla:", "Plantilla discusión:", "Ayuda:",
+    "Ayuda discusión:", "Categoría:", "Categoría discusión:", "Portal discusión:",
+    "Wikiproyecto:", "Anexo:", "Anexo discusión:", "Módulo:", "Módulo discusión:",
+];
+
+const JAPANESE_PREFIXES: &[&str] = &[
+    "利用者:", "ノート:", "利用者‐会話:", "Wikipedia‐ノート:", "ファイル:", "画像:",
+    "ファイル‐ノート:", "MediaWiki‐ノート:", "Template‐ノート:", "Help‐ノート:",
+    "Category‐ノート:", "Portal‐ノート:", "プロジェクト:", "モジュール:",
+];
+
+const RUSSIAN_PREFIXES: &[&str] = &[
+    "Участник:", "Участница:", "Обсуждение:", "Обсуждение участника:", "Википедия:",
+    "Обсуждение Википедии:", "Файл:", "Обсуждение файла:", "Шаблон:", "Обсуждение шаблона:",
+    "Справка:", "Обсуждение справки:", "Категория:", "Обсуждение категории:", "Портал:",
+    "Обсуждение портала:", "Проект:", "Модуль:", "Инкубатор:",
+];
+
+const CHINESE_PREFIXES: &[&str] = &[
+    "用户:", "用戶:", "讨论:", "討論:", "用户讨论:", "用戶討論:", "Wikipedia讨论:", "文件:",
+    "檔案:", "模板:", "帮助:", "幫助:", "分类:", "分類:", "主题:", "主題:", "模块:", "模組:",
+    "草稿:",
+];
+
+const ITALIAN_PREFIXES: &[&str] = &[
+    "Utente:", "Discussione:", "Discussioni utente:", "Discussioni Wikipedia:", "Immagine:",
+    "Discussioni file:", "Discussioni template:", "Aiuto:", "Discussioni aiuto:", "Categoria:",
+    "Discussioni categoria:", "Portale:", "Discussioni portale:", "Progetto:", "Modulo:",
+];
+
+const PORTUGUESE_PREFIXES: &[&str] = &[
+    "Usuário(a):", "Usuário:", "Utilizador:", "Discussão:", "Usuário(a) Discussão:",
+    "Wikipédia:", "Wikipédia Discussão:", "Ficheiro:", "Arquivo:", "Predefinição:",
+    "Predefinição Discussão:", "Ajuda:", "Categoria:", "Categoria Discussão:",
+    "Wikiprojeto:", "Anexo:", "Módulo:",
+];
+
+/// Redirect magic words (lowercase, without `#`): English plus the localized
+/// keywords of the supported editions. MediaWiki only accepts a localized
+/// keyword on its own edition, but article text never starts with one, so
+/// all are checked regardless of language.
+const REDIRECT_KEYWORDS: &[&str] = &[
+    "redirect", "weiterleitung", "redirection", "redirección", "redireccion",
+    "転送", "リダイレクト", "перенаправление", "перенапр", "重定向",
+    "rinvia", "rinvio", "redirecionamento",
+];
+
+/// Namespace prefixes of special pages on a Wikipedia edition
+pub fn namespace_prefixes(language: WikiLanguage) -> impl Iterator<Item = &'static str> {
+    let localized: &[&str] = match language {
+        WikiLanguage::Simple | WikiLanguage::English => &[],
+        WikiLanguage::German => GERMAN_PREFIXES,
+        WikiLanguage::French => FRENCH_PREFIXES,
+        WikiLanguage::Spanish => SPANISH_PREFIXES,
+        WikiLanguage::Japanese => JAPANESE_PREFIXES,
+        WikiLanguage::Russian => RUSSIAN_PREFIXES,
+        WikiLanguage::Chinese => CHINESE_PREFIXES,
+        WikiLanguage::Italian => ITALIAN_PREFIXES,
+        WikiLanguage::Portuguese => PORTUGUESE_PREFIXES,
+    };
+    SKIP_PREFIXES.iter().chain(localized).copied()
+}
+
 /// Wikipedia XML dump parser
 pub struct WikiParser {
-    /// Minimum article length to include
-    min_length: usize,
+    /// Minimum article length in characters, if not the language's default
+    min_length: Option<usize>,
     /// Keep raw markup in articles
     keep_raw: bool,
+    /// Edition being parsed, for its localized namespace names
+    language: WikiLanguage,
+    /// Templates rendered to text instead of being stripped
+    templates: TemplateHandlers,
 }
 
 impl WikiParser {
     /// Create a new parser with default settings
     pub fn new() -> Self {
         Self {
-            min_length: 200,
+            min_length: None,
             keep_raw: false,
+            language: WikiLanguage::default(),
+            templates: TemplateHandlers::default(),
         }
     }
 
-    /// Set minimum article length
+    /// Replace the template handlers (see [`TemplateHandlers`])
+    pub fn with_templates(mut self, templates: TemplateHandlers) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Set the Wikipedia edition being parsed
+    pub fn with_language(mut self, language: WikiLanguage) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Set minimum article length, in characters of cleaned text (not
+    /// bytes, which would let short multibyte articles through)
     pub fn with_min_length(mut self, min: usize) -> Self {
-        self.min_length = min;
+        self.min_length = Some(min);
         self
     }
 
@@ -55,10 +169,13 @@ impl WikiParser {
         self
     }
 
-    /// Check if text is a redirect page
+    /// Check if text is a redirect page (`#REDIRECT` or a localized keyword)
     pub fn is_redirect(text: &str) -> bool {
-        let lower = text.trim().to_lowercase();
-        lower.starts_with("#redirect") || lower.starts_with("# redirect")
+        let Some(rest) = text.trim_start().strip_prefix('#') else {
+            return false;
+        };
+        let lower = rest.trim_start().to_lowercase();
+        REDIRECT_KEYWORDS.iter().any(|keyword| lower.starts_with(keyword))
     }
 
     /// Extract redirect target from redirect text
@@ -80,14 +197,9 @@ impl WikiParser {
         })
     }
 
-    /// Check if this is a content article (not a special page)
-    pub fn is_content_article(title: &str) -> bool {
-        for prefix in SKIP_PREFIXES {
-            if title.starts_with(prefix) {
-                return false;
-            }
-        }
-        true
+    /// Check if this is a content article (not a special page) on `language`'s edition
+    pub fn is_content_article(title: &str, language: WikiLanguage) -> bool {
+        !namespace_prefixes(language).any(|prefix| title.starts_with(prefix))
     }
 
     /// Extract categories from wiki markup
@@ -99,9 +211,22 @@ impl WikiParser {
     }
 
 
+    /// Normalize a category name for comparison.
+    ///
+    /// Strips an optional `Category:` prefix, treats underscores as spaces and
+    /// lowercases the result, so `Category:Living_people` and `living people`
+    /// compare equal.
+    pub fn normalize_category(name: &str) -> String {
+        let name = name.trim();
+        let name = match name.get(..9) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("category:") => &name[9..],
+            _ => name,
+        };
+        name.trim().replace('_', " ").to_lowercase()
+    }
 
     /// Escape HTML special characters
-    fn html_escape(s: &str) -> String {
+    pub fn html_escape(s: &str) -> String {
         s.replace('&', "&amp;")
             .replace('<', "&lt;")
             .replace('>', "&gt;")
@@ -115,53 +240,18 @@ impl WikiParser {
     }
 
     /// Clean Wikipedia markup to plain text, optionally filtering links
-    pub fn clean_wiki_markup_with_filter(text: &str, valid_titles: Option<&std::collections::HashSet<String>>) -> String {
-        let mut result = text.to_string();
+    pub fn clean_wiki_markup_with_filter(text: &str, valid_titles: Option<&dyn TitleSet>) -> String {
+        Self::clean_wiki_markup_with_templates(text, valid_titles, &DEFAULT_TEMPLATES)
+    }
 
-        // Remove templates {{...}} and tables {|...|} using a stack to handle nesting
-        let mut clean_buffer = String::with_capacity(result.len());
-        let mut stack: Vec<&str> = Vec::new();
-        let mut chars = result.chars().peekable();
-        
-        while let Some(c) = chars.next() {
-            let next_char = chars.peek().copied();
-            
-            // Check for starts
-            if c == '{' && next_char == Some('{') {
-                chars.next(); // consume second {
-                stack.push("}}");
-                continue;
-            }
-            if c == '{' && next_char == Some('|') {
-                chars.next(); // consume |
-                stack.push("|}");
-                continue;
-            }
-            
-            // Check for ends
-            if let Some(&expected_close) = stack.last() {
-                if expected_close == "}}" {
-                    if c == '}' && next_char == Some('}') {
-                        chars.next(); // consume second }
-                        stack.pop();
-                        continue;
-                    }
-                } else if expected_close == "|}" {
-                    if c == '|' && next_char == Some('}') {
-                        chars.next(); // consume }
-                        stack.pop();
-                        continue;
-                    }
-                }
-                
-                // Inside a structure, ignore content
-                continue;
-            }
-            
-            // Not inside a structure, keep character
-            clean_buffer.push(c);
-        }
-        result = clean_buffer;
+    /// Clean Wikipedia markup to plain text, rendering templates with `templates`
+    pub fn clean_wiki_markup_with_templates(
+        text: &str,
+        valid_titles: Option<&dyn TitleSet>,
+        templates: &TemplateHandlers,
+    ) -> String {
+        // Render known templates; remove other templates {{...}} and tables {|...|}
+        let mut result = templates.expand(text);
 
         // Remove references <ref>...</ref>
         result = REF_RE.replace_all(&result, "").to_string();
@@ -246,51 +336,116 @@ impl WikiParser {
 
         // Convert wiki links [[target|display]] to HTML
         result = LINK_PIPE_RE.replace_all(&result, |caps: &regex::Captures| {
-            let target = &caps[1];
-            let text = &caps[2];
-            
-            if let Some(valid) = valid_titles {
-                let normalized = target.to_lowercase().replace('_', " ");
-                if valid.contains(&normalized) {
-                    format!("<a href=\"/wiki/{}\">{}</a>", urlencoding::encode(target), Self::html_escape(text))
-                } else {
-                    Self::html_escape(text)
-                }
-            } else {
-                format!("<a href=\"/wiki/{}\">{}</a>", urlencoding::encode(target), Self::html_escape(text))
-            }
+            Self::link_html(&caps[1], &caps[2], valid_titles)
         }).to_string();
 
         // Convert wiki links [[target]] to HTML
         result = LINK_RE.replace_all(&result, |caps: &regex::Captures| {
-            let target = &caps[1];
-            
-            if let Some(valid) = valid_titles {
-                let normalized = target.to_lowercase().replace('_', " ");
-                if valid.contains(&normalized) {
-                    format!("<a href=\"/wiki/{}\">{}</a>", urlencoding::encode(target), Self::html_escape(target))
-                } else {
-                    Self::html_escape(target)
-                }
-            } else {
-                format!("<a href=\"/wiki/{}\">{}</a>", urlencoding::encode(target), Self::html_escape(target))
-            }
+            Self::link_html(&caps[1], &caps[1], valid_titles)
         }).to_string();
 
         // Clean up whitespace
         result = MULTI_SPACE_RE.replace_all(&result, " ").to_string();
         result = MULTI_NEWLINE_RE.replace_all(&result, "\n\n").to_string();
 
-        result.trim().to_string()
+        Self::sanitize_html(result.trim())
+    }
+
+    /// A wiki link to `target` showing `text`, or just the text if the target
+    /// page isn't in `valid_titles`.
+    ///
+    /// A `#Section` in the target links to the section's heading anchor
+    /// (the [`slugify`]d name, as in [`crate::Article::headings`]);
+    /// `[[#Section]]` links within the page.
+    fn link_html(target: &str, text: &str, valid_titles: Option<&dyn TitleSet>) -> String {
+        let (page, section) = match target.split_once('#') {
+            Some((page, section)) => (page.trim(), Some(section)),
+            None => (target, None),
+        };
+        let anchor = section.map(|section| format!("#{}", urlencoding::encode(&slugify(section))));
+        if page.is_empty() {
+            if let Some(anchor) = anchor {
+                return format!("<a href=\"{}\">{}</a>", anchor, Self::html_escape(text));
+            }
+        }
+        if let Some(valid) = valid_titles {
+            if !valid.contains_title(&normalize_title(page)) {
+                return Self::html_escape(text);
+            }
+        }
+        format!("<a href=\"/wiki/{}{}\">{}</a>", urlencoding::encode(page), anchor.unwrap_or_default(), Self::html_escape(text))
+    }
+
+    /// Escape every `<` and `>` that isn't part of an allowed tag.
+    ///
+    /// Allowed are wiki and section links (`<a href="/wiki/...">`,
+    /// `<a href="#...">`) and attribute-free
+    /// heading, list, `<a>` and `<mark>` tags, so stray angle brackets (e.g.
+    /// from entities or malformed markup) can't form markup when content is
+    /// rendered.
+    pub fn sanitize_html(content: &str) -> String {
+        Self::escape_outside_tags(content, &['<', '>'])
+    }
+
+    /// HTML-escape content for rendering, keeping the allowed tags of
+    /// [`sanitize_html`](Self::sanitize_html) and existing entity references
+    pub fn escape_content(content: &str) -> String {
+        Self::escape_outside_tags(content, &['<', '>', '&', '"', '\''])
+    }
+
+    /// Escape `special` characters that aren't part of an allowed tag or entity
+    fn escape_outside_tags(content: &str, special: &[char]) -> String {
+        let mut out = String::with_capacity(content.len());
+        let mut rest = content;
+        while let Some(pos) = rest.find(special) {
+            out.push_str(&rest[..pos]);
+            rest = &rest[pos..];
+            let keep = match rest.as_bytes()[0] {
+                b'<' | b'>' => ALLOWED_TAG_RE.find(rest),
+                b'&' => ENTITY_RE.find(rest),
+                _ => None,
+            };
+            if let Some(keep) = keep {
+                out.push_str(keep.as_str());
+                rest = &rest[keep.end()..];
+                continue;
+            }
+            out.push_str(match rest.as_bytes()[0] {
+                b'<' => "&lt;",
+                b'>' => "&gt;",
+                b'&' => "&amp;",
+                b'"' => "&quot;",
+                _ => "&#x27;",
+            });
+            rest = &rest[1..];
+        }
+        out.push_str(rest);
+        out
     }
 
     /// Parse article content, return None if it should be skipped
     pub fn parse_article(&self, title: &str, text: &str) -> Option<ParsedArticle> {
+        self.parse_page(title, text, None)
+    }
+
+    /// Parse a page, given the target of its `<redirect title="..."/>`
+    /// element if it had one.
+    ///
+    /// The element is authoritative; without it, redirects are detected
+    /// from the text.
+    pub fn parse_page(&self, title: &str, text: &str, redirect: Option<&str>) -> Option<ParsedArticle> {
         // Skip non-content pages
-        if !Self::is_content_article(title) {
+        if !Self::is_content_article(title, self.language) {
             return None;
         }
 
+        if let Some(target) = redirect.map(str::trim).filter(|target| !target.is_empty()) {
+            return Some(ParsedArticle::Redirect {
+                title: title.to_string(),
+                target: target.to_string(),
+            });
+        }
+
         // Handle redirects
         if Self::is_redirect(text) {
             if let Some(target) = Self::extract_redirect_target(text) {
@@ -306,10 +461,11 @@ impl WikiParser {
         let categories = Self::extract_categories(text);
 
         // Clean the markup
-        let content = Self::clean_wiki_markup(text);
+        let content = Self::clean_wiki_markup_with_templates(text, None, &self.templates);
 
-        // Check minimum length
-        if content.len() < self.min_length {
+        // Check minimum length, in characters so multibyte scripts aren't favored
+        let min_length = self.min_length.unwrap_or_else(|| self.language.default_min_length());
+        if content.chars().count() < min_length {
             return None;
         }
 
@@ -356,11 +512,37 @@ mod tests {
         assert_eq!(result, "This is a <a href=\"/wiki/link\">link</a> and <a href=\"/wiki/target\">displayed text</a>.");
     }
 
+    #[test]
+    fn test_section_links() {
+        let result = WikiParser::clean_wiki_markup("See [[Albert Einstein#Early life]] and [[Relativity#Special relativity|special relativity]].");
+        assert_eq!(result, concat!(
+            "See <a href=\"/wiki/Albert%20Einstein#early-life\">Albert Einstein#Early life</a> and ",
+            "<a href=\"/wiki/Relativity#special-relativity\">special relativity</a>.",
+        ));
+
+        let result = WikiParser::clean_wiki_markup("As noted [[#Early life|above]], see [[#History]].");
+        assert_eq!(result, "As noted <a href=\"#early-life\">above</a>, see <a href=\"#history\">#History</a>.");
+
+        // The anchors match the ids given to headings
+        let content = format!("<h2>Early life</h2>\n\n{}", result);
+        let html = crate::Article::new(1, "Einstein".to_string(), content).content_html();
+        assert!(html.contains("<h2 id=\"early-life\">"), "{}", html);
+        assert!(html.contains("<a href=\"#early-life\">above</a>"), "{}", html);
+
+        // Only the page is checked against the valid titles
+        let valid: std::collections::HashSet<String> = ["Albert Einstein".to_string()].into_iter().collect();
+        let result = WikiParser::clean_wiki_markup_with_filter("[[Albert Einstein#Death]] [[Missing#Death]] [[#Death]]", Some(&valid));
+        assert_eq!(result, "<a href=\"/wiki/Albert%20Einstein#death\">Albert Einstein#Death</a> Missing#Death <a href=\"#death\">#Death</a>");
+    }
+
     #[test]
     fn test_is_redirect() {
         assert!(WikiParser::is_redirect("#REDIRECT [[Target]]"));
         assert!(WikiParser::is_redirect("# redirect [[Target]]"));
+        assert!(WikiParser::is_redirect("#WEITERLEITUNG [[Ziel]]"));
+        assert!(WikiParser::is_redirect("#перенаправление [[Цель]]"));
         assert!(!WikiParser::is_redirect("Normal article text"));
+        assert!(!WikiParser::is_redirect("#1 hit single"));
     }
 
     #[test]
@@ -371,9 +553,47 @@ mod tests {
 
     #[test]
     fn test_is_content_article() {
-        assert!(WikiParser::is_content_article("Albert Einstein"));
-        assert!(!WikiParser::is_content_article("Wikipedia:About"));
-        assert!(!WikiParser::is_content_article("Category:Science"));
+        assert!(WikiParser::is_content_article("Albert Einstein", WikiLanguage::English));
+        assert!(!WikiParser::is_content_article("Wikipedia:About", WikiLanguage::English));
+        assert!(!WikiParser::is_content_article("Category:Science", WikiLanguage::English));
+    }
+
+    #[test]
+    fn test_localized_namespaces() {
+        assert!(!WikiParser::is_content_article("Kategorie:Physik", WikiLanguage::German));
+        assert!(!WikiParser::is_content_article("Category:Physik", WikiLanguage::German));
+        assert!(WikiParser::is_content_article("Albert Einstein", WikiLanguage::German));
+        assert!(!WikiParser::is_content_article("Modèle:Infobox Pays", WikiLanguage::French));
+        assert!(WikiParser::is_content_article("Modèle:Infobox Pays", WikiLanguage::German));
+
+        let parser = WikiParser::new().with_min_length(0).with_language(WikiLanguage::French);
+        assert!(parser.parse_article("Modèle:Palette", "Du texte de modèle.").is_none());
+        assert!(parser.parse_article("Paris", "Paris est la capitale de la France.").is_some());
+    }
+
+    #[test]
+    fn test_min_length_counts_characters() {
+        // 60 CJK characters are 180 bytes
+        let text = "東京は日本の首都であり、世界有数の大都市である。".repeat(3);
+        let text = &text[..text.char_indices().nth(60).map_or(text.len(), |(i, _)| i)];
+        assert_eq!(text.chars().count(), 60);
+        assert_eq!(text.len(), 180);
+
+        let parser = WikiParser::new().with_language(WikiLanguage::Japanese);
+        assert!(parser.parse_article("東京", text).is_some());
+        let shorter: String = text.chars().take(59).collect();
+        assert!(parser.parse_article("東京", &shorter).is_none());
+
+        // A byte count would have let 180 bytes pass a 100-character minimum
+        let parser = parser.with_min_length(100);
+        assert!(parser.parse_article("東京", text).is_none());
+        assert!(WikiParser::new().with_min_length(60).parse_article("Tokyo", &"a".repeat(60)).is_some());
+
+        // Cyrillic letters are two bytes each
+        let moscow = "Москва — столица России.";
+        assert!(moscow.len() > 40 && moscow.chars().count() < 40);
+        let parser = WikiParser::new().with_language(WikiLanguage::Russian).with_min_length(40);
+        assert!(parser.parse_article("Москва", moscow).is_none());
     }
 
     #[test]
@@ -383,6 +603,13 @@ mod tests {
         assert_eq!(cats, vec!["Science", "Physics"]);
     }
 
+    #[test]
+    fn test_normalize_category() {
+        assert_eq!(WikiParser::normalize_category("Category:Living_people"), "living people");
+        assert_eq!(WikiParser::normalize_category("category: Living people"), "living people");
+        assert_eq!(WikiParser::normalize_category("Living People"), "living people");
+    }
+
     #[test]
     fn test_clean_wiki_markup_xss() {
         // Test 1: HTML tags should be stripped by HTML_RE
@@ -397,5 +624,22 @@ mod tests {
         let result = WikiParser::clean_wiki_markup(input);
         assert!(result.contains("Text &quot; with quotes"));
     }
+
+    #[test]
+    fn test_sanitize_unclosed_and_nested_tags() {
+        // An unclosed tag survives tag stripping and would be closed by the
+        // renderer's own markup
+        let result = WikiParser::clean_wiki_markup("Intro <img src=x onerror=alert(1)// and [[Link]]");
+        assert_eq!(result, "Intro &lt;img src=x onerror=alert(1)// and <a href=\"/wiki/Link\">Link</a>");
+
+        // Nested brackets reassemble a tag once the inner one is stripped
+        let result = WikiParser::clean_wiki_markup("<scr<b>ipt>alert(1)</scr</b>ipt>");
+        assert!(!result.contains("<scr") && !result.contains("</scr"), "{}", result);
+
+        assert_eq!(
+            WikiParser::sanitize_html("<mark>hit</mark> <a href=\"/x\" onclick=\"y\">z</a>"),
+            "<mark>hit</mark> &lt;a href=\"/x\" onclick=\"y\"&gt;z</a>"
+        );
+    }
 }
 
@@ -0,0 +1,95 @@
+//! Print-friendly article pages
+//!
+//! `/article/:id/print` renders an article on its own, without the site's
+//! header, navigation and footer, styled for the browser's print-to-PDF:
+//! serif black-on-white text with link URLs written out after the links.
+
+use crate::article::Article;
+use crate::parser::WikiParser;
+use crate::server::prefix_wiki_links;
+
+/// Render `article` as a standalone page for printing.
+///
+/// `base_url` (e.g. `http://localhost:8080/en`) makes article links
+/// absolute, so the URLs shown on paper can be typed in.
+pub fn print_html(article: &Article, base_url: &str) -> String {
+    let title = WikiParser::html_escape(&article.title);
+    let content = prefix_wiki_links(&article.content_html(), base_url.trim_end_matches('/'));
+    let categories = if article.categories.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<p class=\"categories\">Categories: {}</p>",
+            article.categories.iter().map(|c| WikiParser::html_escape(c)).collect::<Vec<_>>().join(", ")
+        )
+    };
+
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <meta name="robots" content="noindex">
+    <title>{title}</title>
+    <style>
+        @page {{ margin: 2cm; }}
+        body {{
+            max-width: 42em;
+            margin: 0 auto;
+            padding: 1em;
+            background: #fff;
+            color: #000;
+            font-family: Georgia, "Times New Roman", serif;
+            font-size: 12pt;
+            line-height: 1.5;
+        }}
+        h1 {{ font-size: 22pt; margin: 0 0 0.2em; }}
+        h2 {{ font-size: 16pt; margin: 1.2em 0 0.4em; page-break-after: avoid; }}
+        h3 {{ font-size: 13pt; margin: 1em 0 0.3em; page-break-after: avoid; }}
+        p {{ margin: 0 0 0.8em; orphans: 3; widows: 3; }}
+        a {{ color: #000; text-decoration: none; }}
+        a[href]::after {{ content: " (" attr(href) ")"; font-size: 9pt; color: #444; word-break: break-all; }}
+        .source, .categories {{ font-size: 9pt; color: #444; }}
+        .source {{ border-bottom: 1px solid #000; padding-bottom: 0.5em; margin-bottom: 1.5em; }}
+    </style>
+</head>
+<body>
+    <h1>{title}</h1>
+    <p class="source">From Rustipedia • {base_url}/article/{id} • {words} words</p>
+    {content}
+    {categories}
+</body>
+</html>"#,
+        title = title,
+        base_url = WikiParser::html_escape(base_url.trim_end_matches('/')),
+        id = article.id,
+        words = article.word_count(),
+        content = content,
+        categories = categories,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_view_omits_navigation() {
+        let mut article = Article::new(
+            7,
+            "Rust & Iron".to_string(),
+            "Rust forms on <a href=\"/wiki/Iron\">iron</a>.\n\n<h2>Uses</h2>\n\nPigments.".to_string(),
+        );
+        article.categories = vec!["Chemistry".to_string()];
+        let html = print_html(&article, "http://localhost:8080/en/");
+
+        for tag in ["<header", "<nav", "<footer", "<form", "<script"] {
+            assert!(!html.contains(tag), "print view contains {}", tag);
+        }
+        assert!(html.contains("<h1>Rust &amp; Iron</h1>"));
+        assert!(html.contains("<a href=\"http://localhost:8080/en/wiki/Iron\">iron</a>"));
+        assert!(html.contains("<h2 id=\"uses\">Uses</h2>"));
+        assert!(html.contains("http://localhost:8080/en/article/7"));
+        assert!(html.contains("Categories: Chemistry"));
+    }
+}
@@ -0,0 +1,185 @@
+//! Progress reporting
+//!
+//! Draws indicatif progress bars on a terminal. When bars are disabled (or
+//! stdout isn't a terminal, e.g. under cron or when piped to a log file),
+//! progress is printed as a plain line every few seconds instead.
+
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use once_cell::sync::Lazy;
+
+use crate::downloader::format_bytes;
+
+/// Minimum time between plain-text progress lines
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Whether progress bars are drawn; defaults to whether stdout is a terminal
+static PROGRESS_BARS: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(std::io::stdout().is_terminal()));
+
+/// Enable or disable progress bars for the whole process
+pub fn set_progress_bars(enabled: bool) {
+    PROGRESS_BARS.store(enabled, Ordering::Relaxed);
+}
+
+/// Check if progress bars are drawn
+pub fn progress_bars_enabled() -> bool {
+    PROGRESS_BARS.load(Ordering::Relaxed)
+}
+
+/// What a progress position counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressUnit {
+    Bytes,
+    Items,
+}
+
+/// A progress bar, or periodic log lines when bars are disabled
+pub struct Progress {
+    bar: ProgressBar,
+    plain: Option<Mutex<PlainLog>>,
+}
+
+/// State of a plain-text progress reporter
+struct PlainLog {
+    label: String,
+    unit: ProgressUnit,
+    len: Option<u64>,
+    position: u64,
+    message: String,
+    last_log: Instant,
+}
+
+impl PlainLog {
+    fn format_amount(&self, amount: u64) -> String {
+        match self.unit {
+            ProgressUnit::Bytes => format_bytes(amount),
+            ProgressUnit::Items => amount.to_string(),
+        }
+    }
+
+    fn line(&self) -> String {
+        let mut line = format!("{}: {}", self.label, self.format_amount(self.position));
+        if let Some(len) = self.len.filter(|len| *len > 0) {
+            line.push_str(&format!(
+                " / {} ({:.0}%)",
+                self.format_amount(len),
+                self.position as f64 / len as f64 * 100.0
+            ));
+        }
+        if !self.message.is_empty() {
+            line.push_str(&format!(" - {}", self.message));
+        }
+        line
+    }
+
+    fn log(&mut self) {
+        println!("{}", self.line());
+        self.last_log = Instant::now();
+    }
+
+    fn maybe_log(&mut self) {
+        if self.last_log.elapsed() >= LOG_INTERVAL {
+            self.log();
+        }
+    }
+}
+
+impl Progress {
+    /// Create a progress reporter; `len` of `None` shows a spinner.
+    ///
+    /// `label` prefixes the plain-text lines used when bars are disabled.
+    pub fn new(len: Option<u64>, unit: ProgressUnit, label: &str) -> Self {
+        if progress_bars_enabled() {
+            let bar = match len {
+                Some(len) => ProgressBar::new(len),
+                None => ProgressBar::new_spinner(),
+            };
+            return Self { bar, plain: None };
+        }
+        Self {
+            bar: ProgressBar::hidden(),
+            plain: Some(Mutex::new(PlainLog {
+                label: label.to_string(),
+                unit,
+                len,
+                position: 0,
+                message: String::new(),
+                last_log: Instant::now(),
+            })),
+        }
+    }
+
+    /// A reporter that shows nothing
+    pub fn hidden() -> Self {
+        Self { bar: ProgressBar::hidden(), plain: None }
+    }
+
+    /// Set the bar style (ignored for plain-text output)
+    pub fn set_style(&self, style: ProgressStyle) {
+        self.bar.set_style(style);
+    }
+
+    pub fn set_message(&self, message: impl Into<String>) {
+        let message = message.into();
+        if let Some(ref plain) = self.plain {
+            plain.lock().unwrap().message = message.clone();
+        }
+        self.bar.set_message(message);
+    }
+
+    pub fn set_position(&self, position: u64) {
+        if let Some(ref plain) = self.plain {
+            let mut plain = plain.lock().unwrap();
+            plain.position = position;
+            plain.maybe_log();
+        }
+        self.bar.set_position(position);
+    }
+
+    pub fn inc(&self, delta: u64) {
+        if let Some(ref plain) = self.plain {
+            let mut plain = plain.lock().unwrap();
+            plain.position += delta;
+            plain.maybe_log();
+        }
+        self.bar.inc(delta);
+    }
+
+    /// Finish, printing a final line in plain-text mode
+    pub fn finish_with_message(&self, message: impl Into<String>) {
+        let message = message.into();
+        if let Some(ref plain) = self.plain {
+            let plain = plain.lock().unwrap();
+            println!("{}: {}", plain.label, message);
+        }
+        self.bar.finish_with_message(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_progress_line() {
+        let mut log = PlainLog {
+            label: "Extracting".to_string(),
+            unit: ProgressUnit::Bytes,
+            len: Some(4 * 1024 * 1024),
+            position: 1024 * 1024,
+            message: "12 articles".to_string(),
+            last_log: Instant::now(),
+        };
+        assert_eq!(log.line(), "Extracting: 1.00 MB / 4.00 MB (25%) - 12 articles");
+
+        log.unit = ProgressUnit::Items;
+        log.len = None;
+        log.position = 7;
+        log.message.clear();
+        assert_eq!(log.line(), "Extracting: 7");
+    }
+}
@@ -0,0 +1,604 @@
+//! Broken link pruning
+//!
+//! Removes links to articles that aren't part of the extracted dump, keeping
+//! the link text. `rustipedia-download prune` runs it after extraction;
+//! [`prune_data_dir`] runs it on a served data directory and rebuilds the
+//! search index, for the server's `/api/v1/prune`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::LN_2;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::article::{normalize_title, Article};
+use crate::config::DataDirConfig;
+use crate::downloader::{check_temp_dir, move_file};
+use crate::jsonl::{self, JsonlWriter, LineParser};
+use crate::parser::WikiParser;
+use crate::redirects::RedirectMap;
+use crate::search::SearchIndex;
+use crate::WikiLanguage;
+
+/// Links as rendered by [`WikiParser::clean_wiki_markup`]
+static HTML_LINK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r##"<a href="/wiki/([^"#]+)(?:#[^"]*)?">([^<]+)</a>"##).unwrap());
+
+/// Number of links to other articles in rendered HTML (section links
+/// within the same article don't count)
+pub fn count_wiki_links(html: &str) -> usize {
+    HTML_LINK_RE.find_iter(html).count()
+}
+
+/// Titles linked to from rendered HTML, in order, with the URL encoding of
+/// their `href` undone (section links within the article are left out)
+pub fn wiki_link_targets(html: &str) -> impl Iterator<Item = String> + '_ {
+    HTML_LINK_RE.captures_iter(html).map(|caps| {
+        urlencoding::decode(&caps[1]).map(|title| title.into_owned()).unwrap_or_else(|_| caps[1].to_string())
+    })
+}
+
+/// Target false-positive rate of the low-memory title filter
+pub const BLOOM_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Lookup of valid titles, keyed by [`normalize_title`]
+pub trait TitleSet {
+    /// Check if a normalized title is (probably) valid
+    fn contains_title(&self, normalized: &str) -> bool;
+}
+
+impl TitleSet for HashSet<String> {
+    fn contains_title(&self, normalized: &str) -> bool {
+        self.contains(normalized)
+    }
+}
+
+/// Titles mapped to something, e.g. their article IDs
+impl<V> TitleSet for HashMap<String, V> {
+    fn contains_title(&self, normalized: &str) -> bool {
+        self.contains_key(normalized)
+    }
+}
+
+/// Valid titles plus the redirects to them, so links to redirects count as
+/// valid (see [`title_exists`])
+pub struct TitlesWithRedirects<'a> {
+    pub titles: &'a dyn TitleSet,
+    pub redirects: &'a RedirectMap,
+}
+
+impl TitleSet for TitlesWithRedirects<'_> {
+    fn contains_title(&self, normalized: &str) -> bool {
+        title_exists(normalized, self.titles, self.redirects)
+    }
+}
+
+/// Compact probabilistic set of titles.
+///
+/// Never reports a present title as missing, but reports roughly
+/// [`BLOOM_FALSE_POSITIVE_RATE`] of missing titles as present.
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u64,
+}
+
+impl BloomFilter {
+    /// Create a filter sized for `expected_items` at the given false-positive rate
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let n = expected_items.max(1) as f64;
+        let num_bits = (-(n * false_positive_rate.ln()) / (LN_2 * LN_2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * LN_2).round().clamp(1.0, 16.0) as u64;
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    /// Add an item
+    pub fn insert(&mut self, item: &str) {
+        for bit in self.bit_positions(item) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Check if an item may have been added
+    pub fn contains(&self, item: &str) -> bool {
+        self.bit_positions(item)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Size of the bit array in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    /// Bit positions for an item (double hashing)
+    fn bit_positions(&self, item: &str) -> impl Iterator<Item = u64> {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        let h1 = hasher.finish();
+        0x9e37_79b9_7f4a_7c15u64.hash(&mut hasher);
+        let h2 = hasher.finish() | 1;
+
+        let num_bits = self.num_bits;
+        (0..self.num_hashes).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+impl TitleSet for BloomFilter {
+    fn contains_title(&self, normalized: &str) -> bool {
+        self.contains(normalized)
+    }
+}
+
+/// Valid titles collected for pruning, either exact or low-memory
+pub enum ValidTitles {
+    /// Exact set of titles
+    Exact(HashSet<String>),
+    /// Bloom filter; may keep a few broken links
+    Bloom(BloomFilter),
+}
+
+impl ValidTitles {
+    /// Exact title set
+    pub fn exact() -> Self {
+        ValidTitles::Exact(HashSet::new())
+    }
+
+    /// Low-memory title set sized for `expected_titles`
+    pub fn low_memory(expected_titles: usize) -> Self {
+        ValidTitles::Bloom(BloomFilter::new(expected_titles, BLOOM_FALSE_POSITIVE_RATE))
+    }
+
+    /// Add a title (normalized internally)
+    pub fn insert(&mut self, title: &str) {
+        let normalized = normalize_title(title);
+        match self {
+            ValidTitles::Exact(set) => {
+                set.insert(normalized);
+            }
+            ValidTitles::Bloom(filter) => filter.insert(&normalized),
+        }
+    }
+}
+
+impl TitleSet for ValidTitles {
+    fn contains_title(&self, normalized: &str) -> bool {
+        match self {
+            ValidTitles::Exact(set) => set.contains_title(normalized),
+            ValidTitles::Bloom(filter) => filter.contains_title(normalized),
+        }
+    }
+}
+
+/// How a link target resolves against the extracted articles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkStatus {
+    /// Target is an article
+    Valid,
+    /// Target is a redirect to an article
+    Redirect,
+    /// Target is neither an article nor a redirect to one
+    Broken,
+}
+
+/// Classify a link target as written (any `#Section` is ignored),
+/// following redirects when it isn't an article
+pub fn classify_link(target: &str, valid_titles: &dyn TitleSet, redirects: &RedirectMap) -> LinkStatus {
+    let target = target.split('#').next().unwrap_or(target);
+    if valid_titles.contains_title(&normalize_title(target)) {
+        return LinkStatus::Valid;
+    }
+    match redirects.resolve(target) {
+        Some(resolved) => {
+            let page = resolved.split('#').next().unwrap_or(&resolved);
+            if valid_titles.contains_title(&normalize_title(page)) {
+                LinkStatus::Redirect
+            } else {
+                LinkStatus::Broken
+            }
+        }
+        None => LinkStatus::Broken,
+    }
+}
+
+/// Whether a link to `raw_target` (as written, e.g. `united_States#History`)
+/// leads to an article, directly or through redirects.
+///
+/// This is the one check of link validity: the server marks links with it,
+/// and pruning and the link validator go through [`classify_link`] too.
+pub fn title_exists(raw_target: &str, valid_titles: &dyn TitleSet, redirects: &RedirectMap) -> bool {
+    classify_link(raw_target, valid_titles, redirects) != LinkStatus::Broken
+}
+
+/// Remove links to titles missing from `valid_titles` from an article.
+///
+/// When the article has raw markup, its content is regenerated from the
+/// markup with only valid links kept. Otherwise links are stripped from the
+/// rendered HTML, which can miss some broken links.
+pub fn prune_article_links(article: &mut Article, valid_titles: &dyn TitleSet) {
+    article.content = match article.raw_markup {
+        Some(ref markup) => WikiParser::clean_wiki_markup_with_filter(markup, Some(valid_titles)),
+        None => prune_html_links(&article.content, valid_titles),
+    };
+    article.update_content_hash();
+}
+
+/// Strip `<a>` tags whose target is not a valid title, keeping their text
+fn prune_html_links(html: &str, valid_titles: &dyn TitleSet) -> String {
+    HTML_LINK_RE.replace_all(html, |caps: &regex::Captures| {
+        let target = urlencoding::decode(&caps[1]).unwrap_or(std::borrow::Cow::Borrowed(&caps[1]));
+        if valid_titles.contains_title(&normalize_title(&target)) {
+            caps[0].to_string()
+        } else {
+            caps[2].to_string()
+        }
+    }).to_string()
+}
+
+/// File in the temporary directory each articles file is pruned into
+/// before it replaces the original
+pub const PRUNED_ARTICLES_FILE: &str = "articles_pruned.jsonl";
+
+/// Directory to prune `data_dir` in: `temp_dir` if given, else `temp_dir`
+/// from its config.json, else the data directory itself.
+///
+/// A separate directory is checked to be writable and to have room for the
+/// largest articles file.
+pub fn prune_temp_dir(data_dir: &Path, temp_dir: Option<&Path>, articles_files: &[PathBuf]) -> Result<PathBuf> {
+    let temp_dir = match temp_dir {
+        Some(temp_dir) => temp_dir.to_path_buf(),
+        None => match DataDirConfig::load(&data_dir.join("config.json"), false)?.temp_dir {
+            Some(temp_dir) => temp_dir,
+            None => return Ok(data_dir.to_path_buf()),
+        },
+    };
+    let largest = articles_files.iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .max()
+        .unwrap_or(0);
+    check_temp_dir(&temp_dir, largest)?;
+    Ok(temp_dir)
+}
+
+/// Outcome of pruning a data directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PruneReport {
+    /// Articles rewritten
+    pub articles: u64,
+    /// Links removed from them
+    pub links_removed: u64,
+    /// Malformed lines dropped from the articles files
+    pub dropped_lines: u64,
+}
+
+/// Add the titles of the articles in `articles_files` to `valid_titles`,
+/// returning how many were added (malformed lines are skipped)
+pub fn collect_titles(articles_files: &[PathBuf], valid_titles: &mut ValidTitles) -> Result<u64> {
+    #[derive(Deserialize)]
+    struct TitleOnly {
+        title: String,
+    }
+
+    let mut titles = 0;
+    for line in jsonl::open_all(articles_files)?.lines() {
+        let line = line?;
+        if let Ok(article) = serde_json::from_str::<TitleOnly>(&line) {
+            valid_titles.insert(&article.title);
+            titles += 1;
+        }
+    }
+    Ok(titles)
+}
+
+/// Rewrite each articles file with the links to titles missing from
+/// `valid_titles` removed (see [`prune_article_links`]).
+///
+/// A file is written to [`PRUNED_ARTICLES_FILE`] in `temp_dir`, compressed
+/// if the original was, then moved over it. Malformed lines are dropped.
+/// `on_article` is called after each line.
+pub fn prune_articles_files(
+    temp_dir: &Path,
+    articles_files: &[PathBuf],
+    valid_titles: &dyn TitleSet,
+    mut on_article: impl FnMut(),
+) -> Result<PruneReport> {
+    let temp_path = temp_dir.join(PRUNED_ARTICLES_FILE);
+    let mut report = PruneReport::default();
+    for articles_path in articles_files {
+        let reader = jsonl::open(articles_path)?;
+        let mut writer = JsonlWriter::create(&temp_path, jsonl::is_compressed(articles_path))?;
+
+        let mut parser = LineParser::new(articles_path);
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+
+            // Re-renders from raw markup when the article has it (--keep-raw-markup)
+            let Some(mut article) = parser.parse::<Article>(&line, line_number as u64 + 1)? else {
+                on_article();
+                continue;
+            };
+            let links = count_wiki_links(&article.content);
+            prune_article_links(&mut article, valid_titles);
+            report.articles += 1;
+            report.links_removed += links.saturating_sub(count_wiki_links(&article.content)) as u64;
+
+            serde_json::to_writer(&mut writer, &article)?;
+            writer.write_all(b"\n")?;
+            on_article();
+        }
+        writer.finish()?;
+        report.dropped_lines += parser.skipped();
+
+        move_file(&temp_path, articles_path)?;
+    }
+    Ok(report)
+}
+
+/// What a prune started from the server is doing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PruneState {
+    #[default]
+    Idle,
+    Running,
+    Success,
+    Failed,
+}
+
+/// Status of the last prune started from the server, for `/api/v1/prune/status`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PruneStatus {
+    pub state: PruneState,
+    /// Step the running prune is at, e.g. "Pruning links"
+    pub phase: Option<String>,
+    /// Articles pruned so far, out of `articles_total`
+    pub articles_done: u64,
+    pub articles_total: u64,
+    pub percent: f64,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Outcome of the last successful prune
+    pub report: Option<PruneReport>,
+    pub error: Option<String>,
+}
+
+/// Articles between two progress updates of a [`PruneStatus`]
+const STATUS_UPDATE_INTERVAL: u64 = 1000;
+
+impl PruneStatus {
+    /// Mark a prune as started
+    pub fn start(&mut self) {
+        *self = Self {
+            state: PruneState::Running,
+            phase: Some("Starting".to_string()),
+            started_at: Some(Utc::now()),
+            ..Self::default()
+        };
+    }
+
+    /// Move on to the next step
+    pub fn set_phase(&mut self, phase: &str) {
+        self.phase = Some(phase.to_string());
+    }
+
+    /// Record how the prune ended
+    pub fn finish(&mut self, result: &Result<PruneReport>) {
+        self.finished_at = Some(Utc::now());
+        self.phase = None;
+        match result {
+            Ok(report) => {
+                self.state = PruneState::Success;
+                self.percent = 100.0;
+                self.report = Some(*report);
+            }
+            Err(e) => {
+                self.state = PruneState::Failed;
+                self.error = Some(format!("{:#}", e));
+            }
+        }
+    }
+
+    fn set_progress(&mut self, done: u64) {
+        self.articles_done = done;
+        if self.articles_total > 0 {
+            self.percent = (done as f64 / self.articles_total as f64 * 100.0).min(100.0);
+        }
+    }
+}
+
+/// Prune broken links in a served data directory and rebuild its search
+/// index (if it has one), so the server can reload the pruned articles.
+///
+/// The caller holds the data directory's writer lock. `status` is updated
+/// as the prune goes; setting its start and end is left to the caller.
+pub fn prune_data_dir(data_dir: &Path, status: &Mutex<PruneStatus>) -> Result<PruneReport> {
+    let update = |f: &dyn Fn(&mut PruneStatus)| f(&mut status.lock().unwrap_or_else(|e| e.into_inner()));
+    let articles_files = jsonl::articles_files(data_dir);
+    if articles_files.is_empty() {
+        anyhow::bail!("Articles file not found: {:?}", jsonl::articles_path(data_dir));
+    }
+
+    let temp_dir = prune_temp_dir(data_dir, None, &articles_files)?;
+
+    update(&|status| status.set_phase("Scanning titles"));
+    let mut valid_titles = ValidTitles::exact();
+    let titles = collect_titles(&articles_files, &mut valid_titles)?;
+    let redirects = RedirectMap::load_from_dir(data_dir)?;
+    let valid_titles = TitlesWithRedirects { titles: &valid_titles, redirects: &redirects };
+
+    update(&|status| {
+        status.set_phase("Pruning links");
+        status.articles_total = titles;
+    });
+    let mut done = 0;
+    let report = prune_articles_files(&temp_dir, &articles_files, &valid_titles, || {
+        done += 1;
+        if done % STATUS_UPDATE_INTERVAL == 0 {
+            update(&|status| status.set_progress(done));
+        }
+    })?;
+    update(&|status| status.set_progress(done));
+
+    let index_path = data_dir.join("search_index");
+    if index_path.exists() {
+        update(&|status| status.set_phase("Rebuilding the search index"));
+        let language = DataDirConfig::load(&data_dir.join("config.json"), false)
+            .ok()
+            .and_then(|config| config.language)
+            .and_then(|code| WikiLanguage::from_code(&code))
+            .unwrap_or_default();
+        SearchIndex::rebuild(&index_path, &jsonl::articles_files(data_dir), language, SearchIndex::default_threads())?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_data_dir_removes_broken_link_from_served_html() {
+        let dir = tempfile::tempdir().unwrap();
+        let articles_path = dir.path().join("articles.jsonl");
+        let lines: Vec<String> = [
+            Article::new(1, "Rust".to_string(), WikiParser::clean_wiki_markup("Forms on [[Iron oxide|iron]], not [[Gone page|gone]].")),
+            Article::new(2, "Iron oxide".to_string(), "A compound.".to_string()),
+        ].iter().map(|article| serde_json::to_string(article).unwrap()).collect();
+        fs::write(&articles_path, lines.join("\n") + "\n").unwrap();
+        let index_path = dir.path().join("search_index");
+        SearchIndex::rebuild(&index_path, std::slice::from_ref(&articles_path), WikiLanguage::default(), 1).unwrap();
+        let served = || SearchIndex::open(&index_path).unwrap().get_article(1).unwrap().unwrap().content_html();
+        assert!(served().contains(r#"<a href="/wiki/Gone%20page">gone</a>"#));
+
+        let status = Mutex::new(PruneStatus::default());
+        status.lock().unwrap().start();
+        let report = prune_data_dir(dir.path(), &status).unwrap();
+        assert_eq!(report, PruneReport { articles: 2, links_removed: 1, dropped_lines: 0 });
+        let status = status.into_inner().unwrap();
+        assert_eq!((status.articles_done, status.articles_total), (2, 2));
+
+        // Gone from the rebuilt index the server reloads; the valid link stays
+        let html = served();
+        assert!(!html.contains("Gone%20page"), "{}", html);
+        assert!(html.contains("not gone."), "{}", html);
+        assert!(html.contains(r#"<a href="/wiki/Iron%20oxide">iron</a>"#), "{}", html);
+        assert!(!dir.path().join(PRUNED_ARTICLES_FILE).exists());
+    }
+
+    #[test]
+    fn test_raw_markup_and_html_paths_diverge() {
+        let markup = "See [[Rust]] and [[Missing page|]] here.";
+        let valid: HashSet<String> = ["Rust".to_string()].into_iter().collect();
+
+        let mut from_html = Article::new(1, "Test".to_string(), WikiParser::clean_wiki_markup(markup));
+        let mut from_raw = from_html.clone();
+        from_raw.raw_markup = Some(markup.to_string());
+
+        prune_article_links(&mut from_html, &valid);
+        prune_article_links(&mut from_raw, &valid);
+
+        // The empty-text link to a missing page escapes the HTML regex
+        assert!(from_html.content.contains(r#"<a href="/wiki/Missing%20page"></a>"#));
+        assert!(!from_raw.content.contains("Missing"));
+        // Valid links survive both paths
+        assert!(from_html.content.contains(r#"<a href="/wiki/Rust">Rust</a>"#));
+        assert!(from_raw.content.contains(r#"<a href="/wiki/Rust">Rust</a>"#));
+    }
+
+    #[test]
+    fn test_html_path_strips_broken_link() {
+        let mut article = Article::new(1, "Test".to_string(), WikiParser::clean_wiki_markup("[[Gone|old link]] text"));
+        prune_article_links(&mut article, &HashSet::new());
+        assert_eq!(article.content, "old link text");
+    }
+
+    #[test]
+    fn test_exact_and_bloom_pruning_agree_on_valid_links() {
+        let titles: Vec<String> = (0..2000).map(|i| format!("Topic {}", i)).collect();
+        let mut exact = ValidTitles::exact();
+        let mut bloom = ValidTitles::low_memory(titles.len());
+        for title in &titles {
+            exact.insert(title);
+            bloom.insert(title);
+        }
+
+        // Links to 2000 valid and 2000 missing pages
+        let markup: String = (0..4000).map(|i| format!("[[Topic {}]] ", i)).collect();
+        let mut from_exact = Article::new(1, "Index".to_string(), String::new());
+        from_exact.raw_markup = Some(markup);
+        let mut from_bloom = from_exact.clone();
+        prune_article_links(&mut from_exact, &exact);
+        prune_article_links(&mut from_bloom, &bloom);
+
+        let exact_links = from_exact.content.matches("<a href").count();
+        let bloom_links = from_bloom.content.matches("<a href").count();
+        assert_eq!(exact_links, 2000);
+        // The bloom filter keeps every valid link plus a few false positives
+        assert!(bloom_links >= exact_links);
+        assert!(bloom_links - exact_links < 60, "{} false positives", bloom_links - exact_links);
+        for i in 0..2000 {
+            assert!(from_bloom.content.contains(&format!(r#"<a href="/wiki/Topic%20{}">"#, i)));
+        }
+    }
+
+    #[test]
+    fn test_title_exists_normalization_edge_cases() {
+        let titles: HashMap<String, u64> = ["United States", "IPhone", "C++", "Rust (programming language)"]
+            .iter()
+            .enumerate()
+            .map(|(id, title)| (normalize_title(title), id as u64))
+            .collect();
+        let mut redirects = RedirectMap::new();
+        redirects.insert("USA", "United States#History");
+        redirects.insert("Atlantis", "Lost city");
+        let exists = |target: &str| title_exists(target, &titles, &redirects);
+
+        assert!(exists("United States"));
+        assert!(exists("United_States"));
+        assert!(exists("  united__States_ "));
+        assert!(!exists("United states"));
+        assert!(exists("iPhone"));
+        assert!(exists("IPhone#Models"));
+        assert!(exists("C++"));
+        assert!(exists("Rust_(programming_language)"));
+        // Redirects count, with the same first-letter rule
+        assert!(exists("uSA"));
+        assert!(!exists("usa"));
+        assert!(!exists("Atlantis"));
+        assert!(!exists(""));
+        assert!(!exists("#History"));
+
+        // Pruning keeps links to redirects, as the server follows them
+        let mut article = Article::new(1, "Test".to_string(), WikiParser::clean_wiki_markup("[[USA]] and [[Atlantis]]"));
+        prune_article_links(&mut article, &TitlesWithRedirects { titles: &titles, redirects: &redirects });
+        assert_eq!(article.content, r#"<a href="/wiki/USA">USA</a> and Atlantis"#);
+    }
+
+    #[test]
+    fn test_classify_link_follows_redirects() {
+        let valid: HashSet<String> = ["United States".to_string()].into_iter().collect();
+        let mut redirects = RedirectMap::new();
+        redirects.insert("USA", "United States");
+        redirects.insert("Atlantis", "Lost city");
+
+        assert_eq!(classify_link("United_States", &valid, &redirects), LinkStatus::Valid);
+        assert_eq!(classify_link("USA", &valid, &redirects), LinkStatus::Redirect);
+        assert_eq!(classify_link("Atlantis", &valid, &redirects), LinkStatus::Broken);
+        assert_eq!(classify_link("Nowhere", &valid, &redirects), LinkStatus::Broken);
+    }
+}
@@ -0,0 +1,166 @@
+//! Redirect aliases
+//!
+//! Maps redirect page titles (e.g. "USA") to the title they point at
+//! (e.g. "United States"), so aliases stay navigable after extraction.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::article::normalize_title;
+
+/// Maximum number of redirects followed before giving up on a chain
+const MAX_REDIRECT_HOPS: usize = 8;
+
+/// Mapping of redirect alias to target title
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RedirectMap {
+    /// Normalized alias -> target title as written in the redirect page
+    map: HashMap<String, String>,
+    /// Keys are lowercased, as in redirects.json written before titles were
+    /// normalized like MediaWiki's; lookups are then lowercased too
+    #[serde(skip)]
+    lowercase_keys: bool,
+}
+
+impl RedirectMap {
+    /// Create an empty redirect map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Key of a title in the map (see [`normalize_title`])
+    fn key(&self, title: &str) -> String {
+        let key = normalize_title(title);
+        if self.lowercase_keys { key.to_lowercase() } else { key }
+    }
+
+    /// Record that `alias` redirects to `target`
+    pub fn insert(&mut self, alias: &str, target: &str) {
+        self.map.insert(self.key(alias), target.trim().to_string());
+    }
+
+    /// Get the direct target of a redirect, if `title` is one
+    pub fn get(&self, title: &str) -> Option<&str> {
+        self.map.get(&self.key(title)).map(|s| s.as_str())
+    }
+
+    /// Follow a redirect chain to its final target.
+    ///
+    /// Returns `None` if `title` is not a redirect. Any `#Section` fragment
+    /// on an intermediate target is ignored for lookup; the fragment of the
+    /// last hop is kept. Cycles and overly long chains stop at the last
+    /// target reached.
+    pub fn resolve(&self, title: &str) -> Option<String> {
+        let mut current = self.get(title)?.to_string();
+        let mut seen = HashSet::new();
+        seen.insert(normalize_title(title));
+
+        for _ in 0..MAX_REDIRECT_HOPS {
+            let page = current.split('#').next().unwrap_or(&current);
+            if !seen.insert(normalize_title(page)) {
+                break;
+            }
+            match self.get(page) {
+                Some(next) => current = next.to_string(),
+                None => break,
+            }
+        }
+
+        Some(current)
+    }
+
+    /// Number of redirects in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Check if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Load a redirect map from file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let mut map: RedirectMap = serde_json::from_str(&content)?;
+        map.lowercase_keys = !map.map.is_empty() && map.map.keys().all(|key| *key == key.to_lowercase());
+        Ok(map)
+    }
+
+    /// Load `redirects.json` from a data directory, or an empty map if it has none
+    pub fn load_from_dir(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join("redirects.json");
+        if path.exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::new())
+        }
+    }
+
+    /// Save the redirect map to file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let content = serde_json::to_string(self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_two_hop_chain() {
+        let mut redirects = RedirectMap::new();
+        redirects.insert("USA", "United States of America");
+        redirects.insert("United States of America", "United States");
+
+        assert_eq!(redirects.resolve("USA"), Some("United States".to_string()));
+        assert_eq!(redirects.resolve("uSA"), Some("United States".to_string()));
+        assert_eq!(redirects.resolve("usa"), None);
+        assert_eq!(redirects.resolve("United States"), None);
+    }
+
+    #[test]
+    fn test_load_lowercased_redirects() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("redirects.json");
+        std::fs::write(&path, r#"{"usa": "United States", "iphone": "IPhone"}"#).unwrap();
+        let legacy = RedirectMap::load(&path).unwrap();
+        assert_eq!(legacy.get("USA"), Some("United States"));
+        assert_eq!(legacy.get("i_Phone"), None);
+        assert_eq!(legacy.get("iPhone"), Some("IPhone"));
+
+        let mut redirects = RedirectMap::new();
+        redirects.insert("iPhone 1", "IPhone (1st generation)");
+        redirects.save(&path).unwrap();
+        let reloaded = RedirectMap::load(&path).unwrap();
+        assert_eq!(reloaded.get("IPhone_1"), Some("IPhone (1st generation)"));
+        assert_eq!(reloaded.get("Iphone 1"), None);
+    }
+
+    #[test]
+    fn test_resolve_stops_on_cycle() {
+        let mut redirects = RedirectMap::new();
+        redirects.insert("A", "B");
+        redirects.insert("B", "A");
+
+        assert!(redirects.resolve("A").is_some());
+    }
+
+    #[test]
+    fn test_resolve_keeps_section_fragment() {
+        let mut redirects = RedirectMap::new();
+        redirects.insert("Relativity theory", "Relativity");
+        redirects.insert("Relativity", "Theory of relativity#Special relativity");
+
+        assert_eq!(
+            redirects.resolve("Relativity_theory"),
+            Some("Theory of relativity#Special relativity".to_string())
+        );
+    }
+}
@@ -1,21 +1,56 @@
 //! Full-text search index for Wikipedia articles
 
-use std::path::Path;
-use std::fs::{self, File};
-use std::io::{BufRead, BufReader};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::io::BufRead;
 
 use regex::Regex;
 use once_cell::sync::Lazy;
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use tantivy::collector::TopDocs;
-use tantivy::query::QueryParser;
+use tantivy::query::{BooleanQuery, FuzzyTermQuery, Occur, Query, QueryParser};
 use tantivy::schema::*;
-use tantivy::{Index, IndexWriter, ReloadPolicy, TantivyDocument};
+use tantivy::tokenizer::TokenStream;
+use tantivy::{Index, IndexWriter, ReloadPolicy, TantivyDocument, Term};
 
-use crate::article::Article;
+use crate::analyzer;
+use crate::article::{truncate_at_word, Article};
+use crate::jsonl::{self, LineParser};
+use crate::progress::{Progress, ProgressUnit};
+use crate::WikiLanguage;
 
 static HTML_TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]+>").unwrap());
 
+/// `category:` at the start of a query term, accepted for `categories:`
+static CATEGORY_FIELD_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(^|[\s(+\-])category:").unwrap());
+
+/// Maximum number of indexing threads supported by Tantivy
+const MAX_INDEX_THREADS: usize = 8;
+
+/// Index writer heap per indexing thread
+const HEAP_PER_THREAD: usize = 100_000_000; // 100MB
+
+/// Articles indexed between commits (and checkpoints) of a build
+const COMMIT_INTERVAL: u64 = 10_000;
+
+/// Build progress saved in the index directory by a resumable build
+pub const INDEX_CHECKPOINT_FILE: &str = "index_checkpoint.json";
+
+/// Longest preview callers can request when a search index is present.
+/// Previews of this many characters are stored with each document.
+pub const MAX_PREVIEW_LENGTH: usize = 500;
+
+/// Minimum title similarity (0.0-1.0) for a "did you mean" suggestion
+pub const SUGGESTION_MIN_SIMILARITY: f64 = 0.7;
+
+/// Number of fuzzy matches considered when picking a suggestion
+const SUGGESTION_CANDIDATES: usize = 20;
+
+/// Minimum similarity for titles listed by [`similar_titles`]
+pub const SIMILAR_TITLE_MIN_SIMILARITY: f64 = 0.5;
+
 /// Search result
 #[derive(Debug, Clone)]
 pub struct SearchResult {
@@ -23,10 +58,68 @@ pub struct SearchResult {
     pub id: u64,
     /// Article title
     pub title: String,
-    /// Preview/snippet of the content
+    /// Preview/snippet of the content (up to [`MAX_PREVIEW_LENGTH`] characters)
     pub preview: String,
     /// Search score
     pub score: f32,
+    /// Article word count (missing in indexes built by older versions)
+    pub word_count: Option<u64>,
+    /// Article categories
+    pub categories: Vec<String>,
+}
+
+/// Most categories listed by [`category_facets`]
+pub const MAX_FACETS: usize = 20;
+
+/// The `max` categories most common among `results`, with the number of
+/// results in each, most common first (ties alphabetically)
+pub fn category_facets(results: &[SearchResult], max: usize) -> Vec<(String, u64)> {
+    let mut counts: HashMap<&str, u64> = HashMap::new();
+    for result in results {
+        for category in &result.categories {
+            *counts.entry(category.as_str()).or_default() += 1;
+        }
+    }
+    let mut facets: Vec<(String, u64)> = counts.into_iter()
+        .map(|(category, count)| (category.to_string(), count))
+        .collect();
+    facets.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    facets.truncate(max);
+    facets
+}
+
+/// Ordering of search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSort {
+    /// Best match first (the index's order)
+    #[default]
+    Relevance,
+    /// Alphabetical by title
+    Title,
+    /// Longest article first
+    Length,
+}
+
+impl SearchSort {
+    /// Query parameter value
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchSort::Relevance => "relevance",
+            SearchSort::Title => "title",
+            SearchSort::Length => "length",
+        }
+    }
+}
+
+/// Re-sort results in place. Sorting is stable, so ties keep relevance order;
+/// results without a word count go last when sorting by length.
+pub fn sort_results(results: &mut [SearchResult], sort: SearchSort) {
+    match sort {
+        SearchSort::Relevance => {}
+        SearchSort::Title => results.sort_by_cached_key(|r| r.title.to_lowercase()),
+        SearchSort::Length => results.sort_by_key(|r| std::cmp::Reverse(r.word_count)),
+    }
 }
 
 /// Full-text search index for Wikipedia articles
@@ -37,12 +130,78 @@ pub struct SearchIndex {
     content_field: Field,
     raw_content_field: Field,
     categories_field: Field,
+    /// Stored plain-text preview (missing in indexes built by older versions)
+    preview_field: Option<Field>,
+    /// Stored word count (missing in indexes built by older versions)
+    word_count_field: Option<Field>,
+    /// Stored revision timestamp in seconds (missing in indexes built by older versions)
+    last_edited_field: Option<Field>,
     id_field: Field,
+    /// Where builds checkpoint their progress, if they do
+    checkpoint_path: Option<PathBuf>,
+    commit_interval: u64,
+}
+
+/// How far an interrupted index build got
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexCheckpoint {
+    /// Articles committed to the index
+    pub articles: u64,
+    /// Opstamp of the commit this checkpoint describes
+    pub opstamp: u64,
+    /// Progress per articles file, by file name
+    pub files: BTreeMap<String, FileCheckpoint>,
+}
+
+/// How far an interrupted index build got through one articles file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileCheckpoint {
+    /// Size of the file when the build started, to notice it being replaced
+    pub size: u64,
+    /// Lines already indexed
+    pub lines: u64,
+}
+
+impl IndexCheckpoint {
+    /// Load a checkpoint, `None` if there is none
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)
+                .with_context(|| format!("Invalid index checkpoint {:?}", path))?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {:?}", path)),
+        }
+    }
+
+    /// Save the checkpoint, replacing the file in one step
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Lines of `path` already indexed
+    fn lines_done(&self, path: &Path) -> u64 {
+        self.files.get(&checkpoint_key(path)).map_or(0, |file| file.lines)
+    }
+}
+
+/// Key of an articles file in [`IndexCheckpoint::files`]
+fn checkpoint_key(path: &Path) -> String {
+    path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
 }
 
 impl SearchIndex {
     /// Create a new search index in the given directory
     pub fn create(index_path: impl AsRef<Path>) -> Result<Self> {
+        Self::create_with_language(index_path, WikiLanguage::default())
+    }
+
+    /// Create a new search index using the text analysis for `language`.
+    ///
+    /// The tokenizer choice is recorded in the index schema and picked up again by [`Self::open`].
+    pub fn create_with_language(index_path: impl AsRef<Path>, language: WikiLanguage) -> Result<Self> {
         let index_path = index_path.as_ref();
         
         // Create directory if needed
@@ -51,21 +210,28 @@ impl SearchIndex {
         }
 
         // Build schema
+        let indexing = TextFieldIndexing::default()
+            .set_tokenizer(analyzer::tokenizer_name(language))
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+        let text = TextOptions::default().set_indexing_options(indexing);
+
         let mut schema_builder = Schema::builder();
         let id_field = schema_builder.add_u64_field("id", STORED | INDEXED);
-        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
-        let content_field = schema_builder.add_text_field("content", TEXT);
+        let title_field = schema_builder.add_text_field("title", text.clone().set_stored());
+        let content_field = schema_builder.add_text_field("content", text.clone());
         let raw_content_field = schema_builder.add_text_field("raw_content", STORED);
-        let categories_field = schema_builder.add_text_field("categories", TEXT | STORED);
+        let categories_field = schema_builder.add_text_field("categories", text.set_stored());
+        let preview_field = schema_builder.add_text_field("preview", STORED);
+        let word_count_field = schema_builder.add_u64_field("word_count", STORED);
+        let last_edited_field = schema_builder.add_i64_field("last_edited", STORED);
         let schema = schema_builder.build();
 
         // Create index
         let index = Index::create_in_dir(index_path, schema.clone())
             .context("Failed to create search index")?;
+        analyzer::register_analyzers(&index);
 
-        let mut query_parser = QueryParser::for_index(&index, vec![title_field, content_field]);
-        query_parser.set_field_boost(title_field, 5.0);
-        query_parser.set_conjunction_by_default();
+        let query_parser = query_parser(&index, title_field, content_field, categories_field);
 
         Ok(Self {
             index,
@@ -74,7 +240,12 @@ impl SearchIndex {
             content_field,
             raw_content_field,
             categories_field,
+            preview_field: Some(preview_field),
+            word_count_field: Some(word_count_field),
+            last_edited_field: Some(last_edited_field),
             id_field,
+            checkpoint_path: None,
+            commit_interval: COMMIT_INTERVAL,
         })
     }
 
@@ -84,6 +255,7 @@ impl SearchIndex {
         
         let index = Index::open_in_dir(index_path)
             .context("Failed to open search index")?;
+        analyzer::register_analyzers(&index);
 
         let schema = index.schema();
         let id_field = schema.get_field("id").context("Missing id field")?;
@@ -91,10 +263,11 @@ impl SearchIndex {
         let content_field = schema.get_field("content").context("Missing content field")?;
         let raw_content_field = schema.get_field("raw_content").context("Missing raw_content field")?;
         let categories_field = schema.get_field("categories").context("Missing categories field")?;
+        let preview_field = schema.get_field("preview").ok();
+        let word_count_field = schema.get_field("word_count").ok();
+        let last_edited_field = schema.get_field("last_edited").ok();
 
-        let mut query_parser = QueryParser::for_index(&index, vec![title_field, content_field]);
-        query_parser.set_field_boost(title_field, 5.0);
-        query_parser.set_conjunction_by_default();
+        let query_parser = query_parser(&index, title_field, content_field, categories_field);
 
         Ok(Self {
             index,
@@ -103,20 +276,127 @@ impl SearchIndex {
             content_field,
             raw_content_field,
             categories_field,
+            preview_field,
+            word_count_field,
+            last_edited_field,
             id_field,
+            checkpoint_path: None,
+            commit_interval: COMMIT_INTERVAL,
         })
     }
 
+    /// Checkpoint build progress to `path` on every commit, and resume from
+    /// it if it exists (see [`Self::build_from_jsonl_files`])
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint_path = Some(path.into());
+        self
+    }
+
+    /// Create an index whose build can be resumed after an interruption,
+    /// or reopen the index of an interrupted build to resume it.
+    ///
+    /// Progress is checkpointed to [`INDEX_CHECKPOINT_FILE`] in the index
+    /// directory. With `resume` false nothing is checkpointed and the index
+    /// of an interrupted resumable build is discarded, so the build starts
+    /// over.
+    pub fn create_resumable(index_path: impl AsRef<Path>, language: WikiLanguage, resume: bool) -> Result<Self> {
+        let index_path = index_path.as_ref();
+        let checkpoint_path = index_path.join(INDEX_CHECKPOINT_FILE);
+        let interrupted = checkpoint_path.exists();
+        if !resume {
+            if interrupted {
+                fs::remove_dir_all(index_path).context("Failed to remove the partial index")?;
+            }
+            return Self::create_with_language(index_path, language);
+        }
+        let index = if interrupted {
+            Self::open(index_path)?
+        } else {
+            Self::create_with_language(index_path, language)?
+        };
+        Ok(index.with_checkpoint(checkpoint_path))
+    }
+
+    /// Default number of indexing threads (available CPUs, capped at Tantivy's maximum)
+    pub fn default_threads() -> usize {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .clamp(1, MAX_INDEX_THREADS)
+    }
+
     /// Build index from JSONL file
     pub fn build_from_jsonl(&self, jsonl_path: impl AsRef<Path>) -> Result<u64> {
-        use indicatif::{ProgressBar, ProgressStyle};
-        
-        let file = File::open(jsonl_path.as_ref())?;
-        let file_size = file.metadata()?.len();
-        let reader = BufReader::new(file);
+        self.build_from_jsonl_parallel(jsonl_path, 1)
+    }
+
+    /// Build index from JSONL file using multiple indexing threads.
+    ///
+    /// Lines are read and parsed on a separate producer thread so JSON
+    /// decoding doesn't starve the index writer. Each indexing thread gets
+    /// its own 100MB heap. `num_threads` is clamped to 1..=8.
+    pub fn build_from_jsonl_parallel(&self, jsonl_path: impl AsRef<Path>, num_threads: usize) -> Result<u64> {
+        self.build_from_jsonl_files(&[jsonl_path.as_ref().to_path_buf()], num_threads)
+    }
+
+    /// Build index from several JSONL files, such as the shards of a data
+    /// directory ([`jsonl::articles_files`]).
+    ///
+    /// Like [`Self::build_from_jsonl_parallel`], with up to `num_threads`
+    /// files read and parsed in parallel, one producer thread each.
+    ///
+    /// With a checkpoint (see [`Self::with_checkpoint`]), the lines indexed
+    /// so far are saved after every commit, lines a previous build already
+    /// committed are skipped, and the checkpoint is removed once the build
+    /// completes. The returned count includes resumed articles. Resuming
+    /// fails if the articles files changed size or the index was committed
+    /// to after the checkpoint was saved.
+    pub fn build_from_jsonl_files(&self, jsonl_paths: &[PathBuf], num_threads: usize) -> Result<u64> {
+        use indicatif::ProgressStyle;
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::mpsc::sync_channel;
+        use std::sync::Arc;
+
+        let mut checkpoint = match &self.checkpoint_path {
+            Some(path) => IndexCheckpoint::load(path)?,
+            None => None,
+        };
+        let opstamp = self.index.load_metas()?.opstamp;
+        if let Some(checkpoint) = &checkpoint {
+            if opstamp != checkpoint.opstamp {
+                anyhow::bail!("The index changed after its checkpoint was saved; rebuild it from scratch");
+            }
+            for path in jsonl_paths {
+                if let Some(file) = checkpoint.files.get(&checkpoint_key(path)) {
+                    if fs::metadata(path)?.len() != file.size {
+                        anyhow::bail!("{:?} changed since the interrupted build; rebuild the index from scratch", path);
+                    }
+                }
+            }
+        }
+        let resumed = checkpoint.as_ref().map_or(0, |checkpoint| checkpoint.articles);
+        if let Some(path) = &self.checkpoint_path {
+            let mut files = BTreeMap::new();
+            for jsonl_path in jsonl_paths {
+                let lines = checkpoint.as_ref().map_or(0, |checkpoint| checkpoint.lines_done(jsonl_path));
+                files.insert(checkpoint_key(jsonl_path), FileCheckpoint { size: fs::metadata(jsonl_path)?.len(), lines });
+            }
+            // Saved before the first commit too, so an interrupted build is
+            // always recognized as one
+            let started = IndexCheckpoint { articles: resumed, opstamp, files };
+            started.save(path)?;
+            checkpoint = Some(started);
+        }
+
+        // Progress counts uncompressed bytes, so it has no total for compressed files
+        let file_size = if jsonl_paths.iter().any(|path| jsonl::is_compressed(path)) {
+            None
+        } else {
+            Some(jsonl_paths.iter().map(|path| fs::metadata(path).map(|m| m.len())).sum::<std::io::Result<u64>>()?)
+        };
 
         // Create progress bar
-        let pb = ProgressBar::new(file_size);
+        let pb = Progress::new(file_size, ProgressUnit::Bytes, "Indexing");
         pb.set_style(
             ProgressStyle::default_bar()
                 .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta}) {msg}")
@@ -125,40 +405,177 @@ impl SearchIndex {
         );
         pb.set_message("Building search index...");
 
-        let mut writer = self.index.writer(100_000_000)?; // 100MB heap
-        let mut count = 0u64;
-        let mut bytes_read = 0u64;
+        let num_threads = num_threads.clamp(1, MAX_INDEX_THREADS);
+        let mut writer = self.index.writer_with_num_threads(num_threads, num_threads * HEAP_PER_THREAD)?;
+        let mut count = resumed;
+        if resumed > 0 {
+            pb.set_message(format!("Resuming after {} articles...", resumed));
+        }
 
-        for line in reader.lines() {
-            let line = line?;
-            bytes_read += line.len() as u64 + 1; // +1 for newline
-            
-            if line.is_empty() {
-                continue;
-            }
+        // Producers: read and parse lines, sending (article, bytes read so far,
+        // file index, line number). Files are dealt out round-robin to at
+        // most `num_threads` readers.
+        let (tx, rx) = sync_channel::<(Article, u64, usize, u64)>(1024);
+        let bytes_read = Arc::new(AtomicU64::new(0));
+        let num_producers = num_threads.min(jsonl_paths.len()).max(1);
+        let mut producers = Vec::with_capacity(num_producers);
+        for producer in 0..num_producers {
+            let files: Vec<(usize, PathBuf, u64)> = jsonl_paths.iter()
+                .enumerate()
+                .skip(producer)
+                .step_by(num_producers)
+                .map(|(file, path)| {
+                    let skip = checkpoint.as_ref().map_or(0, |checkpoint| checkpoint.lines_done(path));
+                    (file, path.clone(), skip)
+                })
+                .collect();
+            let tx = tx.clone();
+            let bytes_read = bytes_read.clone();
+            producers.push(std::thread::spawn(move || -> Result<()> {
+                for (file, path, skip) in files {
+                    let reader = jsonl::open(&path)?;
+                    let mut parser = LineParser::new(&path);
+                    for (line_number, line) in reader.lines().enumerate() {
+                        let line = line?;
+                        let line_number = line_number as u64 + 1;
+                        let total = bytes_read.fetch_add(line.len() as u64 + 1, Ordering::Relaxed) // +1 for newline
+                            + line.len() as u64 + 1;
 
-            let article: Article = serde_json::from_str(&line)
-                .context("Failed to parse article JSON")?;
+                        if line.is_empty() || line_number <= skip {
+                            continue;
+                        }
 
+                        let Some(article) = parser.parse::<Article>(&line, line_number)? else {
+                            continue;
+                        };
+
+                        if tx.send((article, total, file, line_number)).is_err() {
+                            // Consumer stopped early
+                            return Ok(());
+                        }
+                    }
+                    parser.warn_if_skipped();
+                }
+                Ok(())
+            }));
+        }
+        drop(tx);
+
+        // Lines of each file sent to the writer; every line before them was
+        // too, since each file is read in order by a single producer
+        let mut lines_sent: Vec<Option<u64>> = vec![None; jsonl_paths.len()];
+        for (article, bytes_read, file, line_number) in rx {
             self.add_article_to_writer(&mut writer, &article)?;
             count += 1;
+            lines_sent[file] = Some(line_number);
 
-            if count % 1000 == 0 {
+            if count.is_multiple_of(1000) {
                 pb.set_position(bytes_read);
                 pb.set_message(format!("Indexed {} articles", count));
             }
 
-            if count % 10000 == 0 {
-                writer.commit()?;
+            if count.is_multiple_of(self.commit_interval) {
+                let opstamp = writer.commit()?;
+                self.save_checkpoint(checkpoint.as_mut(), jsonl_paths, &lines_sent, count, opstamp)?;
             }
         }
 
+        for producer in producers {
+            producer.join()
+                .map_err(|_| anyhow::anyhow!("Article reader thread panicked"))??;
+        }
+
         writer.commit()?;
+        if let Some(path) = &self.checkpoint_path {
+            if path.exists() {
+                fs::remove_file(path).context("Failed to remove the index checkpoint")?;
+            }
+        }
         pb.finish_with_message(format!("✓ Indexed {} articles", count));
 
         Ok(count)
     }
 
+    /// Record a commit of `count` articles with opstamp `opstamp` in the
+    /// checkpoint, if there is one
+    fn save_checkpoint(
+        &self,
+        checkpoint: Option<&mut IndexCheckpoint>,
+        jsonl_paths: &[PathBuf],
+        lines_sent: &[Option<u64>],
+        count: u64,
+        opstamp: u64,
+    ) -> Result<()> {
+        let (Some(checkpoint), Some(path)) = (checkpoint, &self.checkpoint_path) else {
+            return Ok(());
+        };
+        checkpoint.articles = count;
+        checkpoint.opstamp = opstamp;
+        for (jsonl_path, lines) in jsonl_paths.iter().zip(lines_sent) {
+            if let (Some(lines), Some(file)) = (lines, checkpoint.files.get_mut(&checkpoint_key(jsonl_path))) {
+                file.lines = *lines;
+            }
+        }
+        checkpoint.save(path)
+    }
+
+    /// Rebuild the index at `index_path` from JSONL files, in place.
+    ///
+    /// The new index is built in a sibling `.tmp` directory and only swapped
+    /// in once it has been fully committed, so an interrupted or failed
+    /// rebuild leaves the existing index untouched. A running server keeps
+    /// using the old index until it is restarted.
+    pub fn rebuild(
+        index_path: impl AsRef<Path>,
+        jsonl_paths: &[PathBuf],
+        language: WikiLanguage,
+        num_threads: usize,
+    ) -> Result<u64> {
+        let index_path = index_path.as_ref();
+        let file_name = index_path.file_name()
+            .context("Index path has no directory name")?
+            .to_string_lossy()
+            .to_string();
+        let tmp_path = index_path.with_file_name(format!("{}.tmp", file_name));
+        let old_path = index_path.with_file_name(format!("{}.old", file_name));
+
+        // Leftovers from a previously interrupted rebuild
+        if tmp_path.exists() {
+            fs::remove_dir_all(&tmp_path).context("Failed to remove stale temporary index")?;
+        }
+        if old_path.exists() {
+            fs::remove_dir_all(&old_path).context("Failed to remove stale previous index")?;
+        }
+
+        // Build into the temporary directory; the index (and its writer lock)
+        // is dropped before the swap
+        let built = SearchIndex::create_with_language(&tmp_path, language)
+            .and_then(|index| index.build_from_jsonl_files(jsonl_paths, num_threads));
+        let count = match built {
+            Ok(count) => count,
+            Err(e) => {
+                fs::remove_dir_all(&tmp_path).ok();
+                return Err(e);
+            }
+        };
+
+        // Swap: keep the old index aside until the new one is in place
+        if index_path.exists() {
+            fs::rename(index_path, &old_path).context("Failed to move existing index aside")?;
+        }
+        if let Err(e) = fs::rename(&tmp_path, index_path) {
+            if old_path.exists() {
+                fs::rename(&old_path, index_path).ok();
+            }
+            return Err(e).context("Failed to move new index into place");
+        }
+        if old_path.exists() {
+            fs::remove_dir_all(&old_path).ok();
+        }
+
+        Ok(count)
+    }
+
     /// Add a single article to the index
     fn add_article_to_writer(&self, writer: &mut IndexWriter, article: &Article) -> Result<()> {
         let mut doc = TantivyDocument::default();
@@ -171,6 +588,15 @@ impl SearchIndex {
         // Strip HTML tags for search indexing
         let content_text = HTML_TAG_RE.replace_all(&article.content, " ");
         doc.add_text(self.content_field, &content_text);
+        if let Some(preview_field) = self.preview_field {
+            doc.add_text(preview_field, make_preview(&content_text));
+        }
+        if let Some(word_count_field) = self.word_count_field {
+            doc.add_u64(word_count_field, content_text.split_whitespace().count() as u64);
+        }
+        if let (Some(last_edited_field), Some(last_edited)) = (self.last_edited_field, article.last_edited) {
+            doc.add_i64(last_edited_field, last_edited.timestamp());
+        }
         
         for cat in &article.categories {
             doc.add_text(self.categories_field, cat);
@@ -180,18 +606,84 @@ impl SearchIndex {
         Ok(())
     }
 
-    /// Search for articles
+    /// Search for articles.
+    ///
+    /// Terms must all match in the title, content or categories; the query
+    /// syntax also takes `"quoted phrases"`, `title:`/`content:`/`category:`
+    /// scoped terms, `-excluded` terms and `OR`.
     pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        let query = CATEGORY_FIELD_RE.replace_all(query, "${1}categories:");
+        let query = self.query_parser.parse_query(&query)
+            .context("Failed to parse search query")?;
+        self.run_query(&query, limit)
+    }
+
+    /// Search titles, tolerating small typos in each query term
+    pub fn fuzzy_search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        let mut tokenizer = self.index.tokenizer_for_field(self.title_field)?;
+        let mut stream = tokenizer.token_stream(query);
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        while let Some(token) = stream.next() {
+            // Allow two edits only for longer words to keep short ones precise
+            let distance = if token.text.chars().count() >= 6 { 2 } else { 1 };
+            let term = Term::from_field_text(self.title_field, &token.text);
+            clauses.push((Occur::Should, Box::new(FuzzyTermQuery::new(term, distance, true))));
+        }
+
+        if clauses.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.run_query(&BooleanQuery::new(clauses), limit)
+    }
+
+    /// Suggest the title closest to a query ("did you mean").
+    ///
+    /// Returns the single best fuzzy match, or `None` if no title is at
+    /// least [`SUGGESTION_MIN_SIMILARITY`] similar to the query.
+    pub fn suggest(&self, query: &str) -> Result<Option<SearchResult>> {
+        let candidates = self.fuzzy_search(query, SUGGESTION_CANDIDATES)?;
+        let best = closest_title(query, candidates.iter().map(|r| r.title.as_str()))
+            .map(|title| title.to_string());
+        Ok(best.and_then(|title| candidates.into_iter().find(|r| r.title == title)))
+    }
+
+    /// Titles similar to `query`, best first: titles starting with it, then
+    /// fuzzy matches. Used for "did you mean" links on missing pages.
+    pub fn similar_titles(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        let mut candidates = self.fuzzy_search(query, SUGGESTION_CANDIDATES)?;
+
+        let mut tokenizer = self.index.tokenizer_for_field(self.title_field)?;
+        let mut stream = tokenizer.token_stream(query);
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        while let Some(token) = stream.next() {
+            let term = Term::from_field_text(self.title_field, &token.text);
+            clauses.push((Occur::Must, Box::new(FuzzyTermQuery::new_prefix(term, 0, true))));
+        }
+        if !clauses.is_empty() {
+            candidates.extend(self.run_query(&BooleanQuery::new(clauses), SUGGESTION_CANDIDATES)?);
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        candidates.retain(|r| seen.insert(r.id));
+        let ranked: Vec<String> = similar_titles(query, candidates.iter().map(|r| r.title.as_str()), limit)
+            .into_iter()
+            .map(|title| title.to_string())
+            .collect();
+        Ok(ranked.iter()
+            .filter_map(|title| candidates.iter().position(|r| &r.title == title))
+            .map(|i| candidates[i].clone())
+            .collect())
+    }
+
+    /// Run a query and collect the top results
+    fn run_query(&self, query: &dyn Query, limit: usize) -> Result<Vec<SearchResult>> {
         let reader = self.index
             .reader_builder()
             .reload_policy(ReloadPolicy::OnCommitWithDelay)
             .try_into()?;
         let searcher = reader.searcher();
 
-        let query = self.query_parser.parse_query(query)
-            .context("Failed to parse search query")?;
-
-        let top_docs = searcher.search(&query, &TopDocs::with_limit(limit))?;
+        let top_docs = searcher.search(query, &TopDocs::with_limit(limit))?;
 
         let mut results = Vec::new();
         for (score, doc_address) in top_docs {
@@ -206,28 +698,45 @@ impl SearchIndex {
                 .unwrap_or("")
                 .to_string();
             
-            let content = doc.get_first(self.content_field)
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
-            
-            // Create a preview (first 200 chars)
-            let preview = if content.chars().count() > 200 {
-                content.chars().take(200).collect::<String>() + "..."
-            } else {
-                content.to_string()
+            let preview = match self.preview_field.and_then(|f| doc.get_first(f)).and_then(|v| v.as_str()) {
+                Some(preview) => preview.to_string(),
+                None => {
+                    // Older index without stored previews: derive one from the HTML
+                    let html = doc.get_first(self.raw_content_field)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    make_preview(&HTML_TAG_RE.replace_all(html, " "))
+                }
             };
 
+            let word_count = self.word_count_field
+                .and_then(|f| doc.get_first(f))
+                .and_then(|v| v.as_u64());
+
+            let categories = doc.get_all(self.categories_field)
+                .filter_map(|v| v.as_str())
+                .map(|s| s.to_string())
+                .collect();
+
             results.push(SearchResult {
                 id,
                 title,
                 preview,
                 score,
+                word_count,
+                categories,
             });
         }
 
         Ok(results)
     }
 
+    /// Number of documents in the index
+    pub fn num_docs(&self) -> Result<u64> {
+        let reader = self.index.reader()?;
+        Ok(reader.searcher().num_docs())
+    }
+
     /// Get article by ID
     pub fn get_by_id(&self, article_id: u64) -> Result<Option<SearchResult>> {
         let results = self.search(&format!("id:{}", article_id), 1)?;
@@ -272,6 +781,10 @@ impl SearchIndex {
             // so we return a basic Article object.
             let mut article = Article::new(id, title, content);
             article.categories = categories;
+            article.last_edited = self.last_edited_field
+                .and_then(|field| doc.get_first(field))
+                .and_then(|v| v.as_i64())
+                .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0));
             Ok(Some(article))
         } else {
             Ok(None)
@@ -279,3 +792,444 @@ impl SearchIndex {
     }
 }
 
+
+/// Build a stored preview: whitespace-collapsed text cut to [`MAX_PREVIEW_LENGTH`]
+/// characters at a word boundary
+fn make_preview(text: &str) -> String {
+    let mut preview = String::new();
+    let mut chars = 0;
+    for word in text.split_whitespace() {
+        if chars > MAX_PREVIEW_LENGTH {
+            break;
+        }
+        if !preview.is_empty() {
+            preview.push(' ');
+            chars += 1;
+        }
+        preview.push_str(word);
+        chars += word.chars().count();
+    }
+    truncate_at_word(&preview, MAX_PREVIEW_LENGTH).to_string()
+}
+
+/// Query parser over titles (boosted), content and categories, requiring
+/// every term to match
+fn query_parser(index: &Index, title_field: Field, content_field: Field, categories_field: Field) -> QueryParser {
+    let mut query_parser = QueryParser::for_index(index, vec![title_field, content_field, categories_field]);
+    query_parser.set_field_boost(title_field, 5.0);
+    query_parser.set_conjunction_by_default();
+    query_parser
+}
+
+/// Fraction by which an index's document count may differ from the number
+/// of articles and still count as in sync (e.g. after skipped malformed lines)
+pub const INDEX_COUNT_TOLERANCE: f64 = 0.01;
+
+/// Whether an index of `indexed` documents roughly matches `articles`
+/// articles, within [`INDEX_COUNT_TOLERANCE`]
+pub fn index_count_matches(indexed: u64, articles: u64) -> bool {
+    indexed.abs_diff(articles) as f64 <= articles as f64 * INDEX_COUNT_TOLERANCE
+}
+
+/// Similarity of two titles from 0.0 to 1.0 (case-insensitive), based on
+/// edit distance relative to the longer title
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 1.0;
+    }
+
+    // Levenshtein distance, two rows at a time
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    1.0 - prev[b.len()] as f64 / max_len as f64
+}
+
+/// Pick the title most similar to `query`, if any reaches [`SUGGESTION_MIN_SIMILARITY`]
+pub fn closest_title<'a>(query: &str, titles: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let query_len = query.chars().count();
+    titles.into_iter()
+        .filter(|title| {
+            // Skip titles whose length alone rules out a close match
+            let title_len = title.chars().count();
+            let max_len = query_len.max(title_len).max(1);
+            1.0 - query_len.abs_diff(title_len) as f64 / max_len as f64 >= SUGGESTION_MIN_SIMILARITY
+        })
+        .map(|title| (title_similarity(query, title), title))
+        .filter(|(similarity, _)| *similarity >= SUGGESTION_MIN_SIMILARITY)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, title)| title)
+}
+
+/// Up to `limit` titles similar to `query`, best first.
+///
+/// Titles starting with the query (case-insensitive) rank first, then
+/// titles at least [`SIMILAR_TITLE_MIN_SIMILARITY`] similar. Ties go to
+/// the shorter title.
+pub fn similar_titles<'a>(query: &str, titles: impl IntoIterator<Item = &'a str>, limit: usize) -> Vec<&'a str> {
+    let query_lower = query.to_lowercase();
+    let query_len = query.chars().count();
+    if query_len == 0 {
+        return Vec::new();
+    }
+
+    let mut scored: Vec<(f64, &str)> = titles.into_iter()
+        .filter_map(|title| {
+            if title.to_lowercase().starts_with(&query_lower) {
+                return Some((1.0, title));
+            }
+            let title_len = title.chars().count();
+            let max_len = query_len.max(title_len);
+            let length_similarity = 1.0 - query_len.abs_diff(title_len) as f64 / max_len as f64;
+            if length_similarity < SIMILAR_TITLE_MIN_SIMILARITY {
+                return None;
+            }
+            let similarity = title_similarity(query, title);
+            (similarity >= SIMILAR_TITLE_MIN_SIMILARITY).then_some((similarity, title))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.len().cmp(&b.1.len())));
+    scored.into_iter().take(limit).map(|(_, title)| title).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::io::Write;
+
+    /// Write articles to a JSONL file
+    fn write_jsonl(path: &Path, articles: &[Article]) {
+        let mut file = fs::File::create(path).unwrap();
+        for article in articles {
+            writeln!(file, "{}", serde_json::to_string(article).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_index_count_matches_within_tolerance() {
+        assert!(index_count_matches(10_000, 10_000));
+        assert!(index_count_matches(9_900, 10_000));
+        assert!(index_count_matches(10_100, 10_000));
+        assert!(!index_count_matches(9_899, 10_000));
+        assert!(!index_count_matches(10_101, 10_000));
+        assert!(index_count_matches(0, 0));
+        assert!(!index_count_matches(1, 0));
+    }
+
+    #[test]
+    fn test_build_from_jsonl_parallel() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        let articles: Vec<Article> = (1..=50)
+            .map(|i| Article::new(i, format!("Article {}", i), format!("Shared body text number {}", i)))
+            .collect();
+        write_jsonl(&jsonl, &articles);
+
+        let index = SearchIndex::create(dir.path().join("search_index")).unwrap();
+        let count = index.build_from_jsonl_parallel(&jsonl, 2).unwrap();
+
+        assert_eq!(count, 50);
+        assert_eq!(index.num_docs().unwrap(), 50);
+        assert_eq!(index.search("shared", 100).unwrap().len(), 50);
+        assert_eq!(index.get_article(7).unwrap().unwrap().title, "Article 7");
+    }
+
+    #[test]
+    fn test_interrupted_build_resumes_from_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        let index_path = dir.path().join("search_index");
+        let line = |i: u64| serde_json::to_string(&Article::new(i, format!("Article {}", i), "Shared body".to_string())).unwrap();
+        // An unreadable line after article 25 stops the first build, after
+        // two commits of 10, like a crash
+        let mut content: Vec<u8> = (1..=25).flat_map(|i| format!("{}\n", line(i)).into_bytes()).collect();
+        content.extend_from_slice(b"\xff\n");
+        content.extend((26..=30).flat_map(|i| format!("{}\n", line(i)).into_bytes()));
+        fs::write(&jsonl, &content).unwrap();
+
+        let mut index = SearchIndex::create_resumable(&index_path, WikiLanguage::default(), true).unwrap();
+        index.commit_interval = 10;
+        assert!(index.build_from_jsonl(&jsonl).is_err());
+        drop(index);
+        let checkpoint = IndexCheckpoint::load(&index_path.join(INDEX_CHECKPOINT_FILE)).unwrap().unwrap();
+        assert_eq!(checkpoint.articles, 20);
+        assert_eq!(checkpoint.files["articles.jsonl"].lines, 20);
+
+        // Same size, now readable
+        let bad = content.iter().position(|&b| b == 0xff).unwrap();
+        content[bad] = b'\n';
+        fs::write(&jsonl, &content).unwrap();
+
+        let mut index = SearchIndex::create_resumable(&index_path, WikiLanguage::default(), true).unwrap();
+        index.commit_interval = 10;
+        assert_eq!(index.build_from_jsonl(&jsonl).unwrap(), 30);
+        assert_eq!(index.num_docs().unwrap(), 30);
+        let ids: HashSet<u64> = index.search("shared", 100).unwrap().iter().map(|r| r.id).collect();
+        assert_eq!(ids, (1..=30).collect());
+        assert!(!index_path.join(INDEX_CHECKPOINT_FILE).exists());
+    }
+
+    #[test]
+    fn test_build_skips_corrupt_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        write_jsonl(&jsonl, &[Article::new(1, "First".to_string(), "Intact body".to_string())]);
+        let mut file = fs::OpenOptions::new().append(true).open(&jsonl).unwrap();
+        writeln!(file, "{{\"id\":2,\"title\":\"Trunc").unwrap();
+        writeln!(file, "{}", serde_json::to_string(&Article::new(3, "Third".to_string(), "Intact body".to_string())).unwrap()).unwrap();
+
+        let index = SearchIndex::create(dir.path().join("search_index")).unwrap();
+        assert_eq!(index.build_from_jsonl(&jsonl).unwrap(), 2);
+        assert_eq!(index.get_article(3).unwrap().unwrap().title, "Third");
+    }
+
+    #[test]
+    fn test_rebuild_swaps_in_new_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        let index_path = dir.path().join("search_index");
+
+        write_jsonl(&jsonl, &[Article::new(1, "Old".to_string(), "Original content".to_string())]);
+        SearchIndex::rebuild(&index_path, std::slice::from_ref(&jsonl), WikiLanguage::default(), 1).unwrap();
+
+        write_jsonl(&jsonl, &[Article::new(2, "New".to_string(), "Replacement content".to_string())]);
+        let count = SearchIndex::rebuild(&index_path, std::slice::from_ref(&jsonl), WikiLanguage::default(), 1).unwrap();
+
+        assert_eq!(count, 1);
+        let index = SearchIndex::open(&index_path).unwrap();
+        assert!(index.search("original", 10).unwrap().is_empty());
+        assert_eq!(index.search("replacement", 10).unwrap().len(), 1);
+        assert!(!dir.path().join("search_index.tmp").exists());
+        assert!(!dir.path().join("search_index.old").exists());
+    }
+
+    #[test]
+    fn test_failed_rebuild_keeps_previous_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        let index_path = dir.path().join("search_index");
+
+        write_jsonl(&jsonl, &[Article::new(1, "Old".to_string(), "Original content".to_string())]);
+        SearchIndex::rebuild(&index_path, std::slice::from_ref(&jsonl), WikiLanguage::default(), 1).unwrap();
+
+        // An unreadable (non-UTF-8) line makes the rebuild fail part-way through
+        let good = serde_json::to_string(&Article::new(2, "New".to_string(), "Replacement".to_string())).unwrap();
+        let mut content = format!("{}\n", good).into_bytes();
+        content.extend_from_slice(b"{\"id\": 3, \"title\": \"\xff\xfe\"}\n");
+        fs::write(&jsonl, content).unwrap();
+        assert!(SearchIndex::rebuild(&index_path, std::slice::from_ref(&jsonl), WikiLanguage::default(), 1).is_err());
+
+        let index = SearchIndex::open(&index_path).unwrap();
+        assert_eq!(index.search("original", 10).unwrap().len(), 1);
+        assert!(!dir.path().join("search_index.tmp").exists());
+    }
+
+    #[test]
+    fn test_suggest_one_character_typo() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        write_jsonl(&jsonl, &[
+            Article::new(1, "Photosynthesis".to_string(), "Plants convert light".to_string()),
+            Article::new(2, "Volcano".to_string(), "Molten rock".to_string()),
+        ]);
+        let index = SearchIndex::create(dir.path().join("search_index")).unwrap();
+        index.build_from_jsonl(&jsonl).unwrap();
+
+        assert!(index.search("photosinthesis", 10).unwrap().is_empty());
+        let suggestion = index.suggest("photosinthesis").unwrap().unwrap();
+        assert_eq!(suggestion.title, "Photosynthesis");
+        assert_eq!(suggestion.id, 1);
+        assert!(index.suggest("xylophone").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_similar_titles_for_missing_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        write_jsonl(&jsonl, &[
+            Article::new(1, "Photosynthesis".to_string(), "Plants convert light".to_string()),
+            Article::new(2, "Photosynthetic efficiency".to_string(), "How much light".to_string()),
+            Article::new(3, "Volcano".to_string(), "Molten rock".to_string()),
+        ]);
+        let index = SearchIndex::create(dir.path().join("search_index")).unwrap();
+        index.build_from_jsonl(&jsonl).unwrap();
+
+        let titles: Vec<String> = index.similar_titles("Photosynthesys", 5).unwrap()
+            .into_iter().map(|r| r.title).collect();
+        assert_eq!(titles[0], "Photosynthesis");
+        assert!(!titles.contains(&"Volcano".to_string()));
+
+        let all = ["Volcano", "Volcanic ash", "Volleyball", "Rust"];
+        assert_eq!(similar_titles("volcan", all, 5), vec!["Volcano", "Volcanic ash"]);
+        assert_eq!(similar_titles("Vulcano", all, 5), vec!["Volcano"]);
+        assert!(similar_titles("", all, 5).is_empty());
+    }
+
+    #[test]
+    fn test_field_scoped_and_phrase_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        let mut einstein = Article::new(1, "Albert Einstein".to_string(), "Developed the theory of relativity".to_string());
+        einstein.categories = vec!["Physicists".to_string()];
+        let mut relativity = Article::new(2, "Relativity".to_string(), "A theory by Albert Einstein of space and time".to_string());
+        relativity.categories = vec!["Physics".to_string()];
+        let time = Article::new(3, "Time".to_string(), "Space and relativity, as Einstein saw it".to_string());
+        write_jsonl(&jsonl, &[einstein, relativity, time]);
+        let index = SearchIndex::create(dir.path().join("search_index")).unwrap();
+        index.build_from_jsonl(&jsonl).unwrap();
+
+        let ids = |query: &str| -> Vec<u64> {
+            let mut ids: Vec<u64> = index.search(query, 10).unwrap().into_iter().map(|r| r.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids("einstein"), vec![1, 2, 3]);
+        assert_eq!(ids("title:einstein"), vec![1]);
+        assert_eq!(ids("\"space and time\""), vec![2]);
+        assert_eq!(ids("\"theory of relativity\""), vec![1]);
+        assert_eq!(ids("category:physics"), vec![2]);
+        assert_eq!(ids("einstein category:physicists"), vec![1]);
+        assert_eq!(ids("physics"), vec![2]);
+    }
+
+    #[test]
+    fn test_category_facets_count_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        let article = |id: u64, title: &str, categories: &[&str]| {
+            let mut article = Article::new(id, title.to_string(), "A river".to_string());
+            article.categories = categories.iter().map(|c| c.to_string()).collect();
+            article
+        };
+        write_jsonl(&jsonl, &[
+            article(1, "Danube", &["Rivers", "Europe"]),
+            article(2, "Rhine", &["Rivers", "Europe", "Germany"]),
+            article(3, "Nile", &["Rivers", "Africa"]),
+            article(4, "Amazon", &[]),
+            Article::new(5, "Alps".to_string(), "Mountains".to_string()),
+        ]);
+        let index = SearchIndex::create(dir.path().join("search_index")).unwrap();
+        index.build_from_jsonl(&jsonl).unwrap();
+
+        let results = index.search("river", 10).unwrap();
+        assert_eq!(results.len(), 4);
+        let facets = category_facets(&results, MAX_FACETS);
+        assert_eq!(facets, vec![
+            ("Rivers".to_string(), 3),
+            ("Europe".to_string(), 2),
+            ("Africa".to_string(), 1),
+            ("Germany".to_string(), 1),
+        ]);
+        let memberships: usize = results.iter().map(|r| r.categories.len()).sum();
+        assert_eq!(facets.iter().map(|(_, count)| *count as usize).sum::<usize>(), memberships);
+
+        assert_eq!(category_facets(&results, 2).len(), 2);
+    }
+
+    #[test]
+    fn test_sort_results_by_title_and_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        write_jsonl(&jsonl, &[
+            Article::new(1, "Volcano eruption".to_string(), "Lava from a volcano flows downhill".to_string()),
+            Article::new(2, "Active volcano".to_string(), "A volcano".to_string()),
+            Article::new(3, "ash".to_string(), "Volcano ash".to_string()),
+        ]);
+        let index = SearchIndex::create(dir.path().join("search_index")).unwrap();
+        index.build_from_jsonl(&jsonl).unwrap();
+
+        let mut results = index.search("volcano", 10).unwrap();
+        assert_eq!(results.len(), 3);
+
+        sort_results(&mut results, SearchSort::Title);
+        let titles: Vec<&str> = results.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(titles, vec!["Active volcano", "ash", "Volcano eruption"]);
+
+        sort_results(&mut results, SearchSort::Length);
+        assert_eq!(results[0].title, "Volcano eruption");
+        assert_eq!(results[0].word_count, Some(6));
+    }
+
+    #[test]
+    fn test_closest_title_threshold() {
+        let titles = ["Volcano", "Volleyball"];
+        assert_eq!(closest_title("volcanp", titles), Some("Volcano"));
+        assert_eq!(closest_title("banana", titles), None);
+    }
+
+    #[test]
+    fn test_search_results_include_stored_preview() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        let body = format!("<p>Große   Wörter</p> {}", "wort ".repeat(200));
+        write_jsonl(&jsonl, &[Article::new(1, "Deutsch".to_string(), body)]);
+        let index = SearchIndex::create(dir.path().join("search_index")).unwrap();
+        index.build_from_jsonl(&jsonl).unwrap();
+
+        let preview = index.get_by_id(1).unwrap().unwrap().preview;
+        assert!(preview.starts_with("Große Wörter wort wort"));
+        assert!(preview.chars().count() <= MAX_PREVIEW_LENGTH);
+        assert!(preview.chars().count() > 200);
+        assert!(preview.ends_with("wort"));
+    }
+
+    #[test]
+    fn test_get_article_keeps_last_edited() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        let mut edited = Article::new(1, "Edited".to_string(), "Has a revision timestamp".to_string());
+        edited.last_edited = Some("2023-05-01T12:00:00Z".parse().unwrap());
+        let old = Article::new(2, "Old".to_string(), "Extracted before timestamps were kept".to_string());
+        write_jsonl(&jsonl, &[edited, old]);
+        let index = SearchIndex::create(dir.path().join("search_index")).unwrap();
+        index.build_from_jsonl(&jsonl).unwrap();
+
+        let article = index.get_article(1).unwrap().unwrap();
+        assert_eq!(article.last_edited.unwrap().to_rfc3339(), "2023-05-01T12:00:00+00:00");
+        assert_eq!(index.get_article(2).unwrap().unwrap().last_edited, None);
+    }
+
+    #[test]
+    fn test_japanese_two_character_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        write_jsonl(&jsonl, &[
+            Article::new(1, "東京都".to_string(), "東京都は日本の首都である。".to_string()),
+            Article::new(2, "大阪府".to_string(), "大阪府は近畿地方にある。".to_string()),
+        ]);
+        let index_path = dir.path().join("search_index");
+        let index = SearchIndex::create_with_language(&index_path, WikiLanguage::Japanese).unwrap();
+        index.build_from_jsonl(&jsonl).unwrap();
+        drop(index);
+
+        // Reopening must reconstruct the Japanese tokenizer from the index meta
+        let index = SearchIndex::open(&index_path).unwrap();
+        let results = index.search("首都", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, 1);
+        assert_eq!(index.search("日本の首都", 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_german_stemming() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        write_jsonl(&jsonl, &[Article::new(1, "Häuser".to_string(), "Die Häuser der Stadt".to_string())]);
+        let index = SearchIndex::create_with_language(dir.path().join("search_index"), WikiLanguage::German).unwrap();
+        index.build_from_jsonl(&jsonl).unwrap();
+
+        assert_eq!(index.search("haus", 10).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,493 @@
+//! Server runtime tuning
+//!
+//! Worker threads, listen backlog, connection limit, listing page sizes,
+//! article truncation, multi-language routing, CORS, data reloading, the
+//! article JSON API (and its versioning), broken link marking and the
+//! optional fallback to live Wikipedia for `rustipedia-serve`.
+//! Everything defaults to the previous behavior when unset.
+
+use std::net::SocketAddr;
+
+use anyhow::{Context, Result};
+use axum::extract::{OriginalUri, Request};
+use axum::http::{header, HeaderMap, HeaderValue, Method};
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::Router;
+use tokio::net::{TcpListener, TcpSocket};
+use tokio::runtime::Runtime;
+use tokio::sync::RwLock;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tower_http::cors::{AllowHeaders, AllowOrigin, Any, CorsLayer};
+
+use crate::article::{normalize_title, Article};
+use crate::WikiLanguage;
+
+/// Opening tags of `/wiki/` links in rendered article HTML (before [`prefix_wiki_links`])
+static WIKI_LINK_TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r##"<a href="/wiki/([^"#]*)((?:#[^"]*)?)">"##).unwrap());
+
+/// Class of links to articles that aren't in the data directory
+pub const BROKEN_LINK_CLASS: &str = "broken-link";
+
+/// Default articles per page on `/browse` and `/api/v1/articles`
+pub const DEFAULT_BROWSE_PER_PAGE: usize = 50;
+
+/// Default results per page on `/search`
+pub const DEFAULT_SEARCH_PER_PAGE: usize = 20;
+
+/// Default cap on results fetched from the index for a `/search` query
+pub const DEFAULT_MAX_SEARCH_RESULTS: usize = 100;
+
+/// Results returned by `/api/v1/search`, within `max_search_results`
+pub const API_SEARCH_RESULTS: usize = 50;
+
+/// Default characters of article HTML rendered before the rest is loaded on
+/// demand; long enough that only huge list articles are affected
+pub const DEFAULT_MAX_RENDER_CHARS: usize = 200_000;
+
+/// Largest page size a request can ask for
+pub const MAX_PER_PAGE: usize = 100;
+
+/// Page size for a request: `requested` (`?per_page=`) or `default`,
+/// clamped to `1..=MAX_PER_PAGE`
+pub fn page_size(requested: Option<usize>, default: usize) -> usize {
+    requested.unwrap_or(default).clamp(1, MAX_PER_PAGE)
+}
+
+/// Results to fetch for a search that wants `wanted`, capped at the
+/// configured `max_search_results` (at least 1)
+pub fn search_limit(wanted: usize, max_results: usize) -> usize {
+    wanted.min(max_results).max(1)
+}
+
+/// Items on the 1-based `page`
+pub fn page_slice<T>(items: &[T], page: usize, per_page: usize) -> &[T] {
+    let start = page.max(1).saturating_sub(1).saturating_mul(per_page).min(items.len());
+    let end = start.saturating_add(per_page).min(items.len());
+    &items[start..end]
+}
+
+/// Largest request body accepted by default (4 MB); the logo upload route
+/// raises it to [`crate::favicon::MAX_LOGO_UPLOAD_BYTES`]
+pub const DEFAULT_MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// Most articles one `/api/v1/articles/batch` request can fetch
+pub const MAX_BATCH_ARTICLES: usize = 100;
+
+/// An article as returned by the JSON API
+pub fn article_json(article: &Article) -> serde_json::Value {
+    serde_json::json!({
+        "id": article.id,
+        "title": article.title,
+        "content": article.content,
+        "categories": article.categories,
+        "word_count": article.word_count(),
+        "last_edited": article.last_edited,
+    })
+}
+
+/// Fetch the articles for `ids` in order, with `null` for IDs that don't exist.
+///
+/// Fails if more than [`MAX_BATCH_ARTICLES`] are requested.
+pub fn batch_articles(ids: &[u64], get_article: impl Fn(u64) -> Option<Article>) -> Result<Vec<serde_json::Value>> {
+    if ids.len() > MAX_BATCH_ARTICLES {
+        anyhow::bail!("Too many IDs ({}); at most {} articles can be fetched at once", ids.len(), MAX_BATCH_ARTICLES);
+    }
+    Ok(ids.iter()
+        .map(|id| get_article(*id).map(|article| article_json(&article)).unwrap_or(serde_json::Value::Null))
+        .collect())
+}
+
+/// Check the languages of several data directories served together.
+///
+/// Each is routed under `/{code}`, so every data directory needs a known,
+/// distinct language in its config.json.
+pub fn check_language_codes(codes: &[&str]) -> Result<()> {
+    let mut seen = std::collections::HashSet::new();
+    for code in codes {
+        if code.is_empty() || *code == "unknown" || *code == "static" {
+            anyhow::bail!("Serving several data directories needs a language in each config.json (found {:?})", code);
+        }
+        if !seen.insert(*code) {
+            anyhow::bail!("Two data directories have the language {:?}", code);
+        }
+    }
+    Ok(())
+}
+
+/// Point the `/wiki/` links in rendered article HTML at `base_path`
+/// (e.g. `/simple`), for languages served under a prefix
+pub fn prefix_wiki_links(html: &str, base_path: &str) -> String {
+    if base_path.is_empty() {
+        return html.to_string();
+    }
+    html.replace("href=\"/wiki/", &format!("href=\"{}/wiki/", base_path))
+}
+
+/// The page for `title` on the live Wikipedia of `language` (e.g.
+/// `https://de.wikipedia.org/wiki/IPhone`), or `None` for a language that
+/// isn't a known edition
+pub fn wikipedia_url(language: &str, title: &str) -> Option<String> {
+    let language = WikiLanguage::from_code(language)?;
+    let title = normalize_title(title).replace(' ', "_");
+    Some(format!("https://{}.wikipedia.org/wiki/{}", language.code(), urlencoding::encode(&title)))
+}
+
+/// Give `/wiki/` links whose (decoded) title `exists` rejects the
+/// [`BROKEN_LINK_CLASS`], so they don't look like working links
+pub fn mark_broken_links(html: &str, exists: impl Fn(&str) -> bool) -> String {
+    WIKI_LINK_TAG_RE.replace_all(html, |caps: &regex::Captures| {
+        let title = urlencoding::decode(&caps[1]).unwrap_or(std::borrow::Cow::Borrowed(&caps[1]));
+        if exists(&title) {
+            caps[0].to_string()
+        } else {
+            format!(r#"<a class="{}" href="/wiki/{}{}">"#, BROKEN_LINK_CLASS, &caps[1], &caps[2])
+        }
+    }).into_owned()
+}
+
+/// CORS for the server: any origin when `origins` is empty (the local
+/// default), otherwise only the listed origins, with credentials allowed.
+pub fn cors_layer(origins: &[String]) -> Result<CorsLayer> {
+    if origins.is_empty() {
+        return Ok(CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any));
+    }
+    let origins = origins.iter()
+        .map(|origin| HeaderValue::from_str(origin.trim_end_matches('/'))
+            .with_context(|| format!("Invalid CORS origin {:?}", origin)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_headers(AllowHeaders::mirror_request())
+        .allow_credentials(true))
+}
+
+/// Replace the state in `shared` with a freshly loaded one.
+///
+/// `load` runs on a blocking thread while the old state keeps serving, and
+/// the swap happens at once. If loading fails the old state is kept.
+pub async fn reload_state<T: Send + Sync + 'static>(
+    shared: &RwLock<T>,
+    load: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<()> {
+    let state = tokio::task::spawn_blocking(load).await.context("Reloading panicked")??;
+    *shared.write().await = state;
+    Ok(())
+}
+
+/// Whether a request may run admin actions such as `/api/v1/reload`.
+///
+/// With an `admin_token` configured it must be sent as
+/// `Authorization: Bearer <token>`; without one only clients on this
+/// machine are allowed.
+pub fn admin_authorized(headers: &HeaderMap, peer: Option<SocketAddr>, admin_token: Option<&str>) -> bool {
+    match admin_token {
+        Some(token) => headers.get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|sent| sent.trim() == token),
+        None => peer.is_some_and(|peer| peer.ip().is_loopback()),
+    }
+}
+
+/// API versions served under `/api/{version}`, oldest first
+pub const API_VERSIONS: &[&str] = &["v1"];
+
+/// Version the unversioned `/api/...` paths are aliases of
+pub const CURRENT_API_VERSION: &str = "v1";
+
+/// Serve `api` (routes relative to the API root, such as `/search`) under
+/// `/api/v1`, and under `/api` as deprecated aliases.
+///
+/// Alias responses carry `Deprecation: true` and a `Link` to the same path
+/// under `/api/v1` as the successor version.
+pub fn versioned_api<S: Clone + Send + Sync + 'static>(api: Router<S>) -> Router<S> {
+    Router::new()
+        .nest(&format!("/api/{}", CURRENT_API_VERSION), api.clone())
+        .nest("/api", api.layer(middleware::from_fn(deprecated_api)))
+}
+
+/// Mark a response to an unversioned API path as deprecated
+async fn deprecated_api(request: Request, next: Next) -> Response {
+    // The nested router sees the path without its prefix (`/search` for
+    // `/en/api/search`); the successor puts the version between the two
+    let path = request.uri().path().to_string();
+    let successor = request.extensions().get::<OriginalUri>()
+        .and_then(|original| original.path().strip_suffix(path.as_str()).map(str::to_string))
+        .map(|prefix| format!("<{}/{}{}>; rel=\"successor-version\"", prefix, CURRENT_API_VERSION, path));
+
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    if let Some(link) = successor.and_then(|link| HeaderValue::from_str(&link).ok()) {
+        headers.insert(header::LINK, link);
+    }
+    response
+}
+
+/// Body of `/api/version`: the server version and the API versions it serves
+pub fn api_version_json() -> serde_json::Value {
+    serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "api_versions": API_VERSIONS,
+        "current_api_version": CURRENT_API_VERSION,
+    })
+}
+
+/// Build the server's Tokio runtime.
+///
+/// `None` uses one worker thread per CPU core, like `Runtime::new()`.
+pub fn build_runtime(worker_threads: Option<usize>) -> Result<Runtime> {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder.enable_all();
+    if let Some(threads) = worker_threads {
+        if threads == 0 {
+            anyhow::bail!("worker_threads must be at least 1");
+        }
+        builder.worker_threads(threads);
+    }
+    builder.build().context("Failed to start the Tokio runtime")
+}
+
+/// Bind a TCP listener on `addr` (`host:port`).
+///
+/// `None` keeps Tokio's default listen backlog (1024).
+pub async fn bind_listener(addr: &str, backlog: Option<u32>) -> Result<TcpListener> {
+    let Some(backlog) = backlog else {
+        return TcpListener::bind(addr).await
+            .with_context(|| format!("Failed to bind {}", addr));
+    };
+
+    let socket_addr = tokio::net::lookup_host(addr).await
+        .with_context(|| format!("Failed to resolve {}", addr))?
+        .next()
+        .with_context(|| format!("No address found for {}", addr))?;
+    let socket = if socket_addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+    #[cfg(not(windows))]
+    socket.set_reuseaddr(true)?;
+    socket.bind(socket_addr).with_context(|| format!("Failed to bind {}", addr))?;
+    Ok(socket.listen(backlog)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use crate::offsets::ArticleOffsets;
+    use crate::{DataDirConfig, SearchIndex};
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::get;
+    use axum::Router;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tower::limit::GlobalConcurrencyLimitLayer;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_server_starts_with_one_worker() {
+        let rt = build_runtime(Some(1)).unwrap();
+        let response = rt.block_on(async {
+            let listener = bind_listener("127.0.0.1:0", Some(16)).await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let app = Router::new()
+                .route("/", get(|| async { "ok" }))
+                .layer(GlobalConcurrencyLimitLayer::new(4));
+            tokio::spawn(async move { axum::serve(listener, app).await });
+
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        });
+
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("ok"));
+        assert!(build_runtime(Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_wikipedia_fallback_uses_language_host() {
+        assert_eq!(wikipedia_url("de", "iPhone").unwrap(), "https://de.wikipedia.org/wiki/IPhone");
+        assert_eq!(
+            wikipedia_url("simple", "Rust (programming language)").unwrap(),
+            "https://simple.wikipedia.org/wiki/Rust_%28programming_language%29"
+        );
+        assert_eq!(wikipedia_url("ja", "東京").unwrap(), "https://ja.wikipedia.org/wiki/%E6%9D%B1%E4%BA%AC");
+        assert_eq!(wikipedia_url("unknown", "Rust"), None);
+    }
+
+    #[test]
+    fn test_link_to_missing_title_gets_broken_class() {
+        let html = concat!(
+            r#"<a href="/wiki/Rust">Rust</a> and <a href="/wiki/Iron%20oxide#Uses">rust</a>, "#,
+            r##"see <a href="#history">History</a>"##,
+        );
+        let marked = mark_broken_links(html, |title| title == "Rust");
+        assert_eq!(marked, concat!(
+            r#"<a href="/wiki/Rust">Rust</a> and <a class="broken-link" href="/wiki/Iron%20oxide#Uses">rust</a>, "#,
+            r##"see <a href="#history">History</a>"##,
+        ));
+        assert_eq!(prefix_wiki_links(&marked, "/en"), marked.replace("/wiki/", "/en/wiki/"));
+    }
+
+    #[test]
+    fn test_per_page_is_honored_and_capped() {
+        let items: Vec<u32> = (0..500).collect();
+
+        let per_page = page_size(Some(5), DEFAULT_BROWSE_PER_PAGE);
+        assert_eq!(page_slice(&items, 1, per_page), &[0, 1, 2, 3, 4]);
+        assert_eq!(page_slice(&items, 3, per_page), &[10, 11, 12, 13, 14]);
+
+        let per_page = page_size(Some(10_000), DEFAULT_BROWSE_PER_PAGE);
+        assert_eq!(per_page, MAX_PER_PAGE);
+        assert_eq!(page_slice(&items, 1, per_page).len(), MAX_PER_PAGE);
+
+        assert_eq!(page_size(None, DEFAULT_SEARCH_PER_PAGE), 20);
+        assert_eq!(page_size(Some(0), DEFAULT_SEARCH_PER_PAGE), 1);
+        assert!(page_slice(&items, 99, 50).is_empty());
+    }
+
+    #[test]
+    fn test_max_search_results_caps_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("articles.jsonl");
+        let mut file = std::fs::File::create(&jsonl).unwrap();
+        for i in 1..=50 {
+            let article = Article::new(i, format!("Article {}", i), format!("Shared body text number {}", i));
+            writeln!(file, "{}", serde_json::to_string(&article).unwrap()).unwrap();
+        }
+        let index = SearchIndex::create(dir.path().join("search_index")).unwrap();
+        index.build_from_jsonl(&jsonl).unwrap();
+
+        let (config, _) = DataDirConfig::parse(r#"{"max_search_results": 10}"#).unwrap();
+        let max_results = config.max_search_results.unwrap_or(DEFAULT_MAX_SEARCH_RESULTS);
+        assert_eq!(index.search("shared", search_limit(DEFAULT_MAX_SEARCH_RESULTS, max_results)).unwrap().len(), 10);
+        assert_eq!(index.search("shared", search_limit(API_SEARCH_RESULTS, max_results)).unwrap().len(), 10);
+
+        // Without a cap the usual limits apply
+        assert_eq!(index.search("shared", search_limit(API_SEARCH_RESULTS, DEFAULT_MAX_SEARCH_RESULTS)).unwrap().len(), 50);
+        assert_eq!(search_limit(API_SEARCH_RESULTS, 0), 1);
+    }
+
+    #[test]
+    fn test_batch_articles_with_a_missing_id() {
+        let articles: std::collections::HashMap<u64, Article> = [1, 2]
+            .map(|id| (id, Article::new(id, format!("Article {}", id), "Some text".to_string())))
+            .into();
+        let get_article = |id| articles.get(&id).cloned();
+
+        let batch = batch_articles(&[2, 99, 1], get_article).unwrap();
+        assert_eq!(batch.len(), 3);
+        assert_eq!(batch[0]["title"], "Article 2");
+        assert!(batch[1].is_null());
+        assert_eq!(batch[2]["id"], 1);
+        assert_eq!(batch[2]["word_count"], 2);
+
+        let too_many: Vec<u64> = (0..=MAX_BATCH_ARTICLES as u64).collect();
+        assert!(batch_articles(&too_many, get_article).is_err());
+        assert!(batch_articles(&[], get_article).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unversioned_api_paths_are_deprecated_aliases() {
+        let api = Router::new().route("/article/:id", get(|axum::extract::Path(id): axum::extract::Path<u64>| async move {
+            format!("{{\"id\":{}}}", id)
+        }));
+        let app = Router::new().nest("/en", versioned_api(api));
+        let get_path = |path: &str| app.clone().oneshot(Request::builder().uri(path).body(Body::empty()).unwrap());
+
+        let versioned = get_path("/en/api/v1/article/7").await.unwrap();
+        assert!(versioned.headers().get("deprecation").is_none());
+        let versioned_body = axum::body::to_bytes(versioned.into_body(), 1 << 20).await.unwrap();
+
+        let alias = get_path("/en/api/article/7").await.unwrap();
+        assert_eq!(alias.headers()["deprecation"], "true");
+        assert_eq!(alias.headers()[header::LINK], "</en/api/v1/article/7>; rel=\"successor-version\"");
+        let alias_body = axum::body::to_bytes(alias.into_body(), 1 << 20).await.unwrap();
+        assert_eq!(alias_body, versioned_body);
+        assert_eq!(versioned_body, "{\"id\":7}");
+
+        let version = api_version_json();
+        assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(version["api_versions"], serde_json::json!(["v1"]));
+    }
+
+    #[tokio::test]
+    async fn test_cors_allowlist_rejects_other_origins() {
+        let app = Router::new()
+            .route("/api/search", get(|| async { "[]" }))
+            .layer(cors_layer(&["https://wiki.example.org/".to_string()]).unwrap());
+        let request = |origin: &str| Request::builder()
+            .uri("/api/search")
+            .header(header::ORIGIN, origin)
+            .body(Body::empty())
+            .unwrap();
+
+        let allowed = app.clone().oneshot(request("https://wiki.example.org")).await.unwrap();
+        assert_eq!(allowed.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://wiki.example.org");
+        assert_eq!(allowed.headers()[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+        let other = app.clone().oneshot(request("https://evil.example.com")).await.unwrap();
+        assert!(other.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+        let open = Router::new()
+            .route("/api/search", get(|| async { "[]" }))
+            .layer(cors_layer(&[]).unwrap());
+        let any = open.oneshot(request("https://evil.example.com")).await.unwrap();
+        assert_eq!(any.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(cors_layer(&["bad\norigin".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reload_state_picks_up_new_article() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("articles.jsonl");
+        let line = |id: u64| serde_json::to_string(&Article::new(id, format!("Article {}", id), "Text".to_string())).unwrap();
+        std::fs::write(&path, format!("{}\n", line(1))).unwrap();
+        let shared = RwLock::new(ArticleOffsets::build(&path).unwrap());
+        assert!(shared.read().await.get(2).unwrap().is_none());
+
+        std::fs::write(&path, format!("{}\n{}\n", line(1), line(2))).unwrap();
+        let reload_path = path.clone();
+        reload_state(&shared, move || ArticleOffsets::build(reload_path)).await.unwrap();
+        assert_eq!(shared.read().await.get(2).unwrap().unwrap().title, "Article 2");
+
+        // A failed load keeps what was being served
+        assert!(reload_state(&shared, || anyhow::bail!("articles.jsonl is missing")).await.is_err());
+        assert_eq!(shared.read().await.len(), 2);
+    }
+
+    #[test]
+    fn test_admin_authorization() {
+        let local: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+        let remote: SocketAddr = "192.0.2.7:50000".parse().unwrap();
+        let mut bearer = HeaderMap::new();
+        bearer.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer s3cret"));
+
+        assert!(admin_authorized(&HeaderMap::new(), Some(local), None));
+        assert!(!admin_authorized(&HeaderMap::new(), Some(remote), None));
+        assert!(admin_authorized(&bearer, Some(remote), Some("s3cret")));
+        assert!(!admin_authorized(&bearer, Some(local), Some("other")));
+        assert!(!admin_authorized(&HeaderMap::new(), Some(local), Some("s3cret")));
+    }
+
+    #[test]
+    fn test_language_prefixes() {
+        assert!(check_language_codes(&["en", "simple"]).is_ok());
+        assert!(check_language_codes(&["en", "en"]).is_err());
+        assert!(check_language_codes(&["en", "unknown"]).is_err());
+
+        let html = r#"<p>See <a href="/wiki/Rust">Rust</a> and <a href="https://example.com/wiki/X">X</a>.</p>"#;
+        assert_eq!(prefix_wiki_links(html, ""), html);
+        assert_eq!(
+            prefix_wiki_links(html, "/simple"),
+            r#"<p>See <a href="/simple/wiki/Rust">Rust</a> and <a href="https://example.com/wiki/X">X</a>.</p>"#
+        );
+    }
+}
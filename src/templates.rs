@@ -0,0 +1,246 @@
+//! Rendering of common inline templates
+//!
+//! Most templates (infoboxes, navboxes, citation needed, ...) are stripped
+//! during cleaning, but a few inline ones carry article text, e.g.
+//! `{{convert|5|km}}`. Those are rendered by a handler; templates without a
+//! handler are removed.
+
+use std::collections::HashMap;
+
+/// Renders a template from its arguments (excluding the name).
+///
+/// Nested templates in the arguments have already been expanded. Named
+/// arguments are passed as `key=value`.
+pub type TemplateHandler = fn(&[&str]) -> String;
+
+/// Template handlers by normalized template name
+#[derive(Clone)]
+pub struct TemplateHandlers {
+    handlers: HashMap<String, TemplateHandler>,
+}
+
+impl TemplateHandlers {
+    /// No handlers: every template is removed
+    pub fn empty() -> Self {
+        Self { handlers: HashMap::new() }
+    }
+
+    /// Add or replace the handler for `name` (case-insensitive)
+    pub fn with_handler(mut self, name: &str, handler: TemplateHandler) -> Self {
+        self.insert(name, handler);
+        self
+    }
+
+    /// Add or replace the handler for `name` (case-insensitive)
+    pub fn insert(&mut self, name: &str, handler: TemplateHandler) {
+        self.handlers.insert(normalize_name(name), handler);
+    }
+
+    /// Remove the handler for `name`, so the template is stripped
+    pub fn remove(&mut self, name: &str) {
+        self.handlers.remove(&normalize_name(name));
+    }
+
+    /// Replace templates in `text` with their rendering, removing those
+    /// without a handler and all tables (`{| ... |}`)
+    pub fn expand(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        // Closing delimiters of the open templates and tables
+        let mut stack: Vec<&str> = Vec::new();
+        // Content of the outermost open template or table
+        let mut body = String::new();
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let next_char = chars.peek().copied();
+
+            let open = match (c, next_char) {
+                ('{', Some('{')) => Some(("{{", "}}")),
+                ('{', Some('|')) => Some(("{|", "|}")),
+                _ => None,
+            };
+            if let Some((open, close)) = open {
+                chars.next();
+                if !stack.is_empty() {
+                    body.push_str(open);
+                }
+                stack.push(close);
+                continue;
+            }
+
+            if let Some(&close) = stack.last() {
+                let closes = match close {
+                    "}}" => c == '}' && next_char == Some('}'),
+                    _ => c == '|' && next_char == Some('}'),
+                };
+                if closes {
+                    chars.next();
+                    stack.pop();
+                    if !stack.is_empty() {
+                        body.push_str(close);
+                    } else {
+                        if close == "}}" {
+                            out.push_str(&self.render(&body));
+                        }
+                        body.clear();
+                    }
+                } else {
+                    body.push(c);
+                }
+                continue;
+            }
+
+            out.push(c);
+        }
+        out
+    }
+
+    /// Render one template from the text between its braces
+    fn render(&self, body: &str) -> String {
+        let mut parts = split_arguments(body);
+        let name = normalize_name(parts.remove(0));
+        let Some(handler) = self.handlers.get(&name) else {
+            return String::new();
+        };
+        let args: Vec<String> = parts.iter().map(|arg| self.expand(arg)).collect();
+        let args: Vec<&str> = args.iter().map(|arg| arg.trim()).collect();
+        handler(&args)
+    }
+}
+
+impl Default for TemplateHandlers {
+    /// Handlers for `convert`, `nowrap`, `lang` and `as of`
+    fn default() -> Self {
+        Self::empty()
+            .with_handler("convert", render_convert)
+            .with_handler("cvt", render_convert)
+            .with_handler("nowrap", render_nowrap)
+            .with_handler("nobr", render_nowrap)
+            .with_handler("lang", render_lang)
+            .with_handler("as of", render_as_of)
+    }
+}
+
+/// Lowercase, strip a `Template:` prefix and treat underscores as spaces
+fn normalize_name(name: &str) -> String {
+    let name = name.trim();
+    let name = match name.get(..9) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("template:") => &name[9..],
+        _ => name,
+    };
+    name.trim().replace('_', " ").to_lowercase()
+}
+
+/// Split a template body on `|`, ignoring pipes inside nested templates and links
+fn split_arguments(body: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let bytes = body.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'{', Some(b'{')) | (b'[', Some(b'[')) => {
+                depth += 1;
+                i += 2;
+                continue;
+            }
+            (b'}', Some(b'}')) | (b']', Some(b']')) => {
+                depth = depth.saturating_sub(1);
+                i += 2;
+                continue;
+            }
+            (b'|', _) if depth == 0 => {
+                parts.push(&body[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    parts.push(&body[start..]);
+    parts
+}
+
+/// Arguments without a `key=` name
+fn positional<'a>(args: &[&'a str]) -> Vec<&'a str> {
+    args.iter().copied().filter(|arg| !arg.contains('=')).collect()
+}
+
+/// `{{convert|5|km}}` -> "5 km", `{{convert|5|-|10|km}}` -> "5–10 km"
+fn render_convert(args: &[&str]) -> String {
+    let args = positional(args);
+    let separator = match args.get(1).copied() {
+        Some("-") | Some("–") => Some("–"),
+        Some("to") => Some(" to "),
+        Some("and") => Some(" and "),
+        Some("or") => Some(" or "),
+        Some("x") | Some("by") => Some(" × "),
+        _ => None,
+    };
+    match (separator, args.as_slice()) {
+        (Some(separator), [from, _, to, unit, ..]) => format!("{}{}{} {}", from, separator, to, unit),
+        (None, [value, unit, ..]) => format!("{} {}", value, unit),
+        (_, [value, ..]) => value.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// `{{nowrap|text}}` -> "text"
+fn render_nowrap(args: &[&str]) -> String {
+    positional(args).first().map(|text| text.to_string()).unwrap_or_default()
+}
+
+/// `{{lang|fr|texte}}` -> "texte"
+fn render_lang(args: &[&str]) -> String {
+    positional(args).get(1).map(|text| text.to_string()).unwrap_or_default()
+}
+
+/// `{{as of|2020|5}}` -> "As of May 2020"
+fn render_as_of(args: &[&str]) -> String {
+    const MONTHS: [&str; 12] = [
+        "January", "February", "March", "April", "May", "June",
+        "July", "August", "September", "October", "November", "December",
+    ];
+    let lowercase = args.iter().any(|arg| arg.replace(' ', "") == "lc=y");
+    let args = positional(args);
+    let month = args.get(1)
+        .and_then(|month| month.parse::<usize>().ok())
+        .and_then(|month| MONTHS.get(month.wrapping_sub(1)));
+
+    let mut date = String::new();
+    if let (Some(day), Some(_)) = (args.get(2), month) {
+        date.push_str(day);
+        date.push(' ');
+    }
+    if let Some(month) = month {
+        date.push_str(month);
+        date.push(' ');
+    }
+    date.push_str(args.first().copied().unwrap_or_default());
+    format!("{} {}", if lowercase { "as of" } else { "As of" }, date.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_and_unknown_templates() {
+        let templates = TemplateHandlers::default();
+        assert_eq!(templates.expand("A {{convert|5|km}} walk."), "A 5 km walk.");
+        assert_eq!(templates.expand("{{Convert|5|-|10|km|mi|abbr=on}}"), "5–10 km");
+        assert_eq!(templates.expand("Text{{Infobox person|name=X|born={{birth date|1900|1|1}}}} here."), "Text here.");
+        assert_eq!(templates.expand("{{nowrap|{{convert|2|m}} tall}}"), "2 m tall");
+        assert_eq!(templates.expand("{{lang|fr|Bonjour}}, {{as of|2020|5}}"), "Bonjour, As of May 2020");
+        assert_eq!(templates.expand("Table: {| class=wikitable\n| {{convert|1|m}}\n|} done"), "Table:  done");
+    }
+
+    #[test]
+    fn test_custom_handlers() {
+        let mut templates = TemplateHandlers::default()
+            .with_handler("Template:Small", |args| args.first().map(|a| a.to_string()).unwrap_or_default());
+        templates.remove("convert");
+        assert_eq!(templates.expand("{{small|fine print}} {{convert|5|km}}"), "fine print ");
+    }
+}
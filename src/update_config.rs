@@ -4,7 +4,11 @@
 
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc, Timelike};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Timelike, Utc};
+
+/// Minutes a scheduled time stays due beyond the check interval, so a check
+/// that runs a little late still catches it
+pub const SCHEDULE_GRACE_MINUTES: i64 = 5;
 
 /// Main auto-update configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +39,10 @@ pub struct UpdateConfig {
     
     /// Notification settings
     pub notifications: NotificationConfig,
+
+    /// Skip scheduled updates until this time, keeping the schedule
+    #[serde(default)]
+    pub paused_until: Option<DateTime<Utc>>,
 }
 
 impl Default for UpdateConfig {
@@ -53,6 +61,7 @@ impl Default for UpdateConfig {
             update_window: None,
             retry_config: RetryConfig::default(),
             notifications: NotificationConfig::default(),
+            paused_until: None,
         }
     }
 }
@@ -94,6 +103,41 @@ impl UpdateConfig {
         data_dir.join("update.log")
     }
 
+    /// Check if scheduled updates are paused at `now`
+    pub fn is_paused_at(&self, now: &DateTime<Utc>) -> bool {
+        self.paused_until.is_some_and(|until| *now < until)
+    }
+
+    /// Determine if a scheduled update should run at `now` for a checker
+    /// that runs every `check_interval`.
+    ///
+    /// Each scheduled time triggers once: `last_check` (when the schedule
+    /// last triggered an update check) must be before it. `last_success`
+    /// is the time of the last successful update.
+    pub fn should_update_at(
+        &self,
+        now: &DateTime<Utc>,
+        last_check: Option<DateTime<Utc>>,
+        last_success: Option<DateTime<Utc>>,
+        check_interval: Duration,
+    ) -> bool {
+        if self.is_paused_at(now) {
+            return false;
+        }
+
+        // Don't update more than once per day
+        if let Some(last_success) = last_success {
+            if (*now - last_success).num_hours() < 23 {
+                return false;
+            }
+        }
+
+        match self.schedule.due_occurrence_at(now, check_interval) {
+            Some(occurrence) => last_check.is_none_or(|checked| checked < occurrence),
+            None => false,
+        }
+    }
+
     /// Validate the configuration
     pub fn validate(&self) -> anyhow::Result<()> {
         // Validate schedule
@@ -171,6 +215,53 @@ impl UpdateSchedule {
         Ok(())
     }
 
+    /// Scheduled hour and minute
+    fn time_of_day(&self) -> (u8, u8) {
+        match self {
+            UpdateSchedule::Daily { hour, minute }
+            | UpdateSchedule::Weekly { hour, minute, .. }
+            | UpdateSchedule::Monthly { hour, minute, .. } => (*hour, *minute),
+            // TODO: Implement cron expression parsing
+            // For now, just update once per day
+            #[cfg(unix)]
+            UpdateSchedule::Custom { cron_expression: _ } => (3, 0),
+        }
+    }
+
+    /// Whether the schedule runs on `date`
+    fn runs_on(&self, date: NaiveDate) -> bool {
+        match self {
+            UpdateSchedule::Daily { .. } => true,
+            UpdateSchedule::Weekly { day, .. } => date.weekday().num_days_from_sunday() == day.num_days_from_sunday(),
+            UpdateSchedule::Monthly { day, .. } => date.day() == *day as u32,
+            #[cfg(unix)]
+            UpdateSchedule::Custom { cron_expression: _ } => true,
+        }
+    }
+
+    /// The latest scheduled time at or before `now`
+    pub fn last_occurrence_at(&self, now: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let (hour, minute) = self.time_of_day();
+        let today = now.date_naive();
+        // A year back covers every schedule, including day 31 of a month
+        (0..=366)
+            .filter_map(|days_back| today.checked_sub_signed(Duration::days(days_back)))
+            .filter(|date| self.runs_on(*date))
+            .filter_map(|date| date.and_hms_opt(hour as u32, minute as u32, 0))
+            .map(|time| time.and_utc())
+            .find(|time| time <= now)
+    }
+
+    /// The scheduled time that is due at `now`, if any.
+    ///
+    /// A scheduled time stays due for `check_interval` plus
+    /// [`SCHEDULE_GRACE_MINUTES`], so a checker running every
+    /// `check_interval` sees it however its checks line up with the schedule.
+    pub fn due_occurrence_at(&self, now: &DateTime<Utc>, check_interval: Duration) -> Option<DateTime<Utc>> {
+        let window = check_interval.max(Duration::zero()) + Duration::minutes(SCHEDULE_GRACE_MINUTES);
+        self.last_occurrence_at(now).filter(|occurrence| *now - *occurrence < window)
+    }
+
     /// Convert to a human-readable string
     pub fn to_human_string(&self) -> String {
         match self {
@@ -217,6 +308,19 @@ impl Weekday {
         }
     }
 
+    /// Number of days since Sunday (0-6)
+    pub fn num_days_from_sunday(&self) -> u32 {
+        match self {
+            Weekday::Sunday => 0,
+            Weekday::Monday => 1,
+            Weekday::Tuesday => 2,
+            Weekday::Wednesday => 3,
+            Weekday::Thursday => 4,
+            Weekday::Friday => 5,
+            Weekday::Saturday => 6,
+        }
+    }
+
     /// Convert to Windows Task Scheduler day code
     #[cfg(windows)]
     pub fn to_windows_code(&self) -> &'static str {
@@ -303,6 +407,24 @@ impl TimeWindow {
     }
 }
 
+/// Parse the end of an update pause.
+///
+/// Accepts RFC 3339 (`2024-08-01T09:00:00Z`), a `datetime-local` form value
+/// (`2024-08-01T09:00`, taken as UTC) or a date (`2024-08-01`, midnight UTC).
+pub fn parse_pause_until(value: &str) -> anyhow::Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M") {
+        return Ok(time.and_utc());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    anyhow::bail!("Invalid pause end {:?}: expected a date (YYYY-MM-DD) or RFC 3339 time", value)
+}
+
 /// Retry configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RetryConfig {
@@ -385,6 +507,69 @@ mod tests {
         assert!(!window.is_within_window(&time_7am));
     }
 
+    #[test]
+    fn test_pause_suppresses_updates() {
+        let config = UpdateConfig {
+            schedule: UpdateSchedule::Daily { hour: 3, minute: 0 },
+            ..Default::default()
+        };
+        let interval = Duration::minutes(5);
+        let scheduled = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap().and_hms_opt(3, 2, 0).unwrap().and_utc();
+        assert!(config.should_update_at(&scheduled, None, None, interval));
+
+        let paused = UpdateConfig {
+            paused_until: Some(parse_pause_until("2024-07-15").unwrap()),
+            ..config.clone()
+        };
+        assert!(paused.is_paused_at(&scheduled));
+        assert!(!paused.should_update_at(&scheduled, None, None, interval));
+
+        // The schedule applies again once the pause has passed
+        let after = NaiveDate::from_ymd_opt(2024, 7, 15).unwrap().and_hms_opt(3, 2, 0).unwrap().and_utc();
+        assert!(paused.should_update_at(&after, None, None, interval));
+
+        assert_eq!(parse_pause_until("2024-07-15T08:30").unwrap().hour(), 8);
+        assert!(parse_pause_until("next week").is_err());
+    }
+
+    #[test]
+    fn test_hourly_checker_hits_daily_schedule() {
+        let config = UpdateConfig {
+            schedule: UpdateSchedule::Daily { hour: 3, minute: 0 },
+            ..Default::default()
+        };
+        let hourly = Duration::minutes(60);
+        let at = |day: u32, hour: u32, minute: u32| {
+            NaiveDate::from_ymd_opt(2024, 7, day).unwrap().and_hms_opt(hour, minute, 0).unwrap().and_utc()
+        };
+
+        // Checks at 37 past every hour: 03:37 is the first one after 03:00
+        let mut last_check = None;
+        let mut fired = Vec::new();
+        for hour in 0..48 {
+            let now = at(1 + hour / 24, hour % 24, 37);
+            if config.should_update_at(&now, last_check, None, hourly) {
+                last_check = Some(now);
+                fired.push(now);
+            }
+        }
+        assert_eq!(fired, vec![at(1, 3, 37), at(2, 3, 37)]);
+
+        // A check running a little late still counts, a retry right after doesn't
+        assert!(config.should_update_at(&at(1, 4, 3), None, None, hourly));
+        assert!(!config.should_update_at(&at(1, 4, 3), Some(at(1, 3, 1)), None, hourly));
+        assert!(!config.should_update_at(&at(1, 4, 6), None, None, hourly));
+
+        // The old fixed 5 minute window still applies to frequent checks
+        assert!(!config.should_update_at(&at(1, 3, 12), None, None, Duration::minutes(1)));
+
+        let weekly = UpdateSchedule::Weekly { day: Weekday::Monday, hour: 3, minute: 0 };
+        assert_eq!(weekly.last_occurrence_at(&at(3, 12, 0)), Some(at(1, 3, 0)));
+        let monthly = UpdateSchedule::Monthly { day: 31, hour: 3, minute: 0 };
+        let may_31 = NaiveDate::from_ymd_opt(2024, 5, 31).unwrap().and_hms_opt(3, 0, 0).unwrap().and_utc();
+        assert_eq!(monthly.last_occurrence_at(&at(15, 0, 0)), Some(may_31));
+    }
+
     #[test]
     fn test_schedule_validation() {
         let valid = UpdateSchedule::Daily { hour: 12, minute: 30 };
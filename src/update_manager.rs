@@ -2,49 +2,61 @@
 //!
 //! Manages automatic Wikipedia updates, including scheduling, execution, and status tracking.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
 use tokio::sync::RwLock;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use anyhow::{Result, Context};
-use std::process::Command;
 
+use crate::lock::DataDirLock;
 use crate::update_config::{UpdateConfig, UpdateMode};
 
+/// Error message recorded when an update is cancelled
+const CANCELLED_MESSAGE: &str = "Update cancelled by user";
+
+/// How often a running update checks whether it has been cancelled
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Update manager handles the update process
 pub struct UpdateManager {
     config: UpdateConfig,
     status: Arc<RwLock<UpdateStatus>>,
+    /// Set when the running update was stopped by `cancel_update`
+    cancelled: AtomicBool,
 }
 
 impl UpdateManager {
     /// Create a new update manager
     pub fn new(config: UpdateConfig) -> Self {
         let status = Arc::new(RwLock::new(UpdateStatus::default()));
-        Self { config, status }
+        Self { config, status, cancelled: AtomicBool::new(false) }
     }
 
-    /// Load update manager from config file
-    pub fn load(data_dir: &PathBuf) -> Result<Self> {
+    /// Load update manager from config file, with the last saved status.
+    ///
+    /// Reads files and may wait for the status file lock, so async code
+    /// should use [`Self::load_async`].
+    pub fn load(data_dir: &Path) -> Result<Self> {
         let config_path = UpdateConfig::config_path(data_dir);
         let config = if config_path.exists() {
             UpdateConfig::load(&config_path)?
         } else {
             UpdateConfig::default()
         };
+        let status = load_saved_status(data_dir).unwrap_or_default();
+        Ok(Self { config, status: Arc::new(RwLock::new(status)), cancelled: AtomicBool::new(false) })
+    }
 
-        let manager = Self::new(config);
-        
-        // Try to load existing status
-        let status_path = UpdateConfig::status_path(data_dir);
-        if status_path.exists() {
-            if let Ok(status) = UpdateStatus::load(&status_path) {
-                *manager.status.blocking_write() = status;
-            }
-        }
-
-        Ok(manager)
+    /// [`Self::load`] on a blocking thread, for the server and the daemon
+    pub async fn load_async(data_dir: &Path) -> Result<Self> {
+        let data_dir = data_dir.to_path_buf();
+        tokio::task::spawn_blocking(move || Self::load(&data_dir)).await?
     }
 
     /// Save the current configuration
@@ -53,20 +65,27 @@ impl UpdateManager {
         self.config.save(&config_path)
     }
 
-    /// Get the current status
+    /// Get the current status.
+    ///
+    /// Unless this manager is running an update itself, a status saved
+    /// since by another process (the daemon, or an update started from
+    /// another request) takes precedence.
     pub async fn get_status(&self) -> UpdateStatus {
         let mut current = self.status.write().await;
-        
-        // If we are idle, check if another process is doing something
-        if current.current_status == Status::Idle {
-            let status_path = UpdateConfig::status_path(&self.config.data_dir);
-            if status_path.exists() {
-                if let Ok(disk_status) = UpdateStatus::load(&status_path) {
-                    *current = disk_status;
-                }
+        if current.current_status.is_in_progress() {
+            return current.clone();
+        }
+
+        let data_dir = self.config.data_dir.clone();
+        let saved = tokio::task::spawn_blocking(move || load_saved_status(&data_dir))
+            .await
+            .ok()
+            .flatten();
+        if let Some(saved) = saved {
+            if saved.updated_at >= current.updated_at {
+                *current = saved;
             }
         }
-        
         current.clone()
     }
 
@@ -122,6 +141,9 @@ impl UpdateManager {
             }
         }
 
+        self.cancelled.store(false, Ordering::SeqCst);
+        let started_at = Utc::now();
+
         // Update status
         {
             let mut status = self.status.write().await;
@@ -148,6 +170,12 @@ impl UpdateManager {
         {
             let mut status = self.status.write().await;
             match result {
+                Err(_) if self.cancelled.load(Ordering::SeqCst) => {
+                    status.current_status = Status::Idle;
+                    status.error_message = Some(CANCELLED_MESSAGE.to_string());
+
+                    tracing::info!("Wikipedia update cancelled");
+                }
                 Ok(_) => {
                     status.current_status = Status::Success;
                     status.last_success = Some(Utc::now());
@@ -158,7 +186,8 @@ impl UpdateManager {
                     
                     // Log success if configured
                     if self.config.notifications.on_success {
-                        self.log_update_result(true, None).await?;
+                        let entry = self.history_entry(Status::Success, started_at, None);
+                        self.log_update_result(&entry).await?;
                     }
                 }
                 Err(ref e) => {
@@ -170,7 +199,8 @@ impl UpdateManager {
                     
                     // Log failure if configured
                     if self.config.notifications.on_failure {
-                        self.log_update_result(false, Some(e.to_string())).await?;
+                        let entry = self.history_entry(Status::Failed, started_at, Some(e.to_string()));
+                        self.log_update_result(&entry).await?;
                     }
                 }
             }
@@ -207,6 +237,7 @@ impl UpdateManager {
         cmd.arg("--lang").arg(&self.config.language);
         cmd.arg("--output").arg(&self.config.data_dir);
         cmd.arg("--skip-download"); // Skip if already downloaded
+        cmd.arg("--no-progress"); // Output goes to a pipe, not a terminal
         
         // Update status
         {
@@ -223,13 +254,7 @@ impl UpdateManager {
 
         // Execute the download command
         tracing::info!("Executing: {:?}", cmd);
-        let output = cmd.output()
-            .context("Failed to execute rustipedia-download")?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("Download failed: {}", stderr);
-        }
+        self.run_cancellable(cmd).await?;
 
         // Update status - extraction
         {
@@ -262,6 +287,61 @@ impl UpdateManager {
         Ok(())
     }
 
+    /// Run a command to completion, killing it if the update gets cancelled.
+    ///
+    /// Cancellation is picked up from the status file, so it works even when
+    /// `cancel_update` is called from another process or manager instance.
+    async fn run_cancellable(&self, mut cmd: Command) -> Result<()> {
+        cmd.stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut child = cmd.spawn()
+            .context("Failed to execute rustipedia-download")?;
+
+        // Collect stderr in the background so the child never blocks on a full pipe
+        let mut stderr = child.stderr.take();
+        let stderr_task = tokio::spawn(async move {
+            let mut output = String::new();
+            if let Some(ref mut stderr) = stderr {
+                let _ = stderr.read_to_string(&mut output).await;
+            }
+            output
+        });
+
+        loop {
+            tokio::select! {
+                exit = child.wait() => {
+                    let exit = exit.context("Failed to wait for rustipedia-download")?;
+                    if !exit.success() {
+                        let stderr = stderr_task.await.unwrap_or_default();
+                        anyhow::bail!("Download failed: {}", stderr);
+                    }
+                    return Ok(());
+                }
+                _ = tokio::time::sleep(CANCEL_POLL_INTERVAL) => {
+                    if self.cancel_requested() {
+                        tracing::info!("Cancellation requested, stopping rustipedia-download");
+                        child.kill().await.ok();
+                        self.cancelled.store(true, Ordering::SeqCst);
+                        anyhow::bail!(CANCELLED_MESSAGE);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check whether the status on disk records a cancellation
+    fn cancel_requested(&self) -> bool {
+        let status_path = UpdateConfig::status_path(&self.config.data_dir);
+        UpdateStatus::load(&status_path)
+            .map(|disk| {
+                disk.current_status == Status::Idle
+                    && disk.error_message.as_deref() == Some(CANCELLED_MESSAGE)
+            })
+            .unwrap_or(false)
+    }
+
     /// Retry a failed update
     pub async fn retry_failed_update(&self) -> Result<()> {
         let status = self.status.read().await;
@@ -274,41 +354,67 @@ impl UpdateManager {
         self.perform_update().await
     }
 
-    /// Cancel an ongoing update
+    /// Cancel an ongoing update.
+    ///
+    /// The cancelled status is saved to disk, where the process running the
+    /// update picks it up and stops its download/extraction.
     pub async fn cancel_update(&self) -> Result<()> {
-        let mut status = self.status.write().await;
-        
-        match status.current_status {
-            Status::Downloading | Status::Extracting | Status::Indexing | Status::Checking => {
-                status.current_status = Status::Idle;
-                status.progress = None;
-                status.error_message = Some("Update cancelled by user".to_string());
-                Ok(())
-            }
-            _ => {
-                anyhow::bail!("No update in progress to cancel")
+        {
+            let mut status = self.status.write().await;
+
+            match status.current_status {
+                Status::Downloading | Status::Extracting | Status::Indexing | Status::Checking => {
+                    status.current_status = Status::Idle;
+                    status.progress = None;
+                    status.error_message = Some(CANCELLED_MESSAGE.to_string());
+                }
+                _ => {
+                    anyhow::bail!("No update in progress to cancel")
+                }
             }
         }
+
+        self.save_status().await
     }
 
     /// Save the current status to disk
     async fn save_status(&self) -> Result<()> {
-        let status = self.status.read().await;
+        let status = {
+            let mut status = self.status.write().await;
+            status.updated_at = Some(Utc::now());
+            status.clone()
+        };
         let status_path = UpdateConfig::status_path(&self.config.data_dir);
-        status.save(&status_path)
+        tokio::task::spawn_blocking(move || status.save(&status_path)).await?
+    }
+
+    /// Build a history entry for an update that started at `started_at`
+    fn history_entry(&self, status: Status, started_at: DateTime<Utc>, error: Option<String>) -> UpdateHistoryEntry {
+        let now = Utc::now();
+
+        // Content size of the extraction, if it produced stats
+        let bytes = std::fs::read_to_string(self.config.data_dir.join("stats.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<crate::article::ExtractionStats>(&content).ok())
+            .filter(|_| status == Status::Success)
+            .map(|stats| stats.total_bytes);
+
+        UpdateHistoryEntry {
+            timestamp: now,
+            status,
+            language: self.config.language.clone(),
+            duration_secs: Some((now - started_at).num_milliseconds() as f64 / 1000.0),
+            bytes,
+            error,
+        }
     }
 
-    /// Log update result
-    async fn log_update_result(&self, success: bool, error: Option<String>) -> Result<()> {
+    /// Log update result as a JSON line
+    async fn log_update_result(&self, entry: &UpdateHistoryEntry) -> Result<()> {
         let log_path = &self.config.notifications.log_file;
-        
-        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC");
-        let result = if success { "SUCCESS" } else { "FAILED" };
-        let message = if let Some(err) = error {
-            format!("[{}] Update {}: {}\n", timestamp, result, err)
-        } else {
-            format!("[{}] Update {}\n", timestamp, result)
-        };
+
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
 
         // Append to log file
         use std::fs::OpenOptions;
@@ -319,28 +425,183 @@ impl UpdateManager {
             .append(true)
             .open(log_path)?;
         
-        file.write_all(message.as_bytes())?;
+        file.write_all(line.as_bytes())?;
         Ok(())
     }
 
-    /// Get update history (last N lines of log)
-    pub async fn get_history(&self, lines: usize) -> Result<Vec<String>> {
+    /// Get update history (last N entries of the log, newest first)
+    pub async fn get_history(&self, lines: usize) -> Result<Vec<UpdateHistoryEntry>> {
         let log_path = &self.config.notifications.log_file;
         
         if !log_path.exists() {
             return Ok(Vec::new());
         }
 
-        // Simple implementation: read whole file and take last N lines.
-        let content = tokio::fs::read_to_string(log_path).await?;
-        let log_lines: Vec<String> = content
-            .lines()
-            .rev()
-            .take(lines)
-            .map(String::from)
-            .collect();
-            
-        Ok(log_lines)
+        // Only read the tail of the file; the log can grow indefinitely.
+        // Unparseable lines are skipped without using up the `lines` budget.
+        let log_path = log_path.clone();
+        let entries = tokio::task::spawn_blocking(move || -> std::io::Result<Vec<UpdateHistoryEntry>> {
+            let mut file = std::fs::File::open(log_path)?;
+            read_last_entries(&mut file, lines, UpdateHistoryEntry::parse_line)
+        })
+        .await??;
+
+        Ok(entries)
+    }
+}
+
+/// Ask a running server to load updated data, by POSTing to its
+/// `/api/v1/reload` URL (with `token` as a bearer token if given)
+pub async fn request_reload(url: &str, token: Option<&str>) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(600))
+        .build()?;
+    let mut request = client.post(url);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.with_context(|| format!("Failed to reach {}", url))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("{} returned {}: {}", url, status, body.trim());
+    }
+    Ok(())
+}
+
+/// The status saved in `data_dir`, if there is a readable one
+fn load_saved_status(data_dir: &Path) -> Option<UpdateStatus> {
+    let status_path = UpdateConfig::status_path(data_dir);
+    if !status_path.exists() {
+        return None;
+    }
+    UpdateStatus::load(&status_path)
+        .map_err(|e| tracing::warn!("Ignoring unreadable {:?}: {:#}", status_path, e))
+        .ok()
+}
+
+/// Parse lines from the end of a file until `n` of them parse, newest first.
+/// Lines `parse` rejects (empty, corrupt or unrecognized) don't count.
+///
+/// Reads backwards in fixed-size chunks, so only the tail of the file is
+/// touched no matter how large it is.
+fn read_last_entries<R, T>(reader: &mut R, n: usize, mut parse: impl FnMut(&str) -> Option<T>) -> std::io::Result<Vec<T>>
+where
+    R: std::io::Read + std::io::Seek,
+{
+    use std::io::SeekFrom;
+
+    const CHUNK_SIZE: u64 = 8192;
+
+    let mut lines = Vec::new();
+    let mut pos = reader.seek(SeekFrom::End(0))?;
+    // Bytes between `pos` and the start of the last line taken
+    let mut buf: Vec<u8> = Vec::new();
+
+    let mut push_line = |lines: &mut Vec<T>, bytes: &[u8]| {
+        let line = String::from_utf8_lossy(bytes);
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            return;
+        }
+        if let Some(entry) = parse(line) {
+            lines.push(entry);
+        }
+    };
+
+    loop {
+        // Everything after the last newline in the buffer is a complete line
+        while lines.len() < n {
+            match buf.iter().rposition(|&b| b == b'\n') {
+                Some(i) => {
+                    push_line(&mut lines, &buf[i + 1..]);
+                    buf.truncate(i);
+                }
+                None => break,
+            }
+        }
+
+        if lines.len() >= n || pos == 0 {
+            break;
+        }
+
+        let read_size = CHUNK_SIZE.min(pos);
+        pos -= read_size;
+        reader.seek(SeekFrom::Start(pos))?;
+        let mut chunk = vec![0u8; read_size as usize];
+        reader.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&buf);
+        buf = chunk;
+    }
+
+    // The first line of the file has no newline before it
+    if pos == 0 && lines.len() < n && !buf.is_empty() {
+        push_line(&mut lines, &buf);
+    }
+
+    Ok(lines)
+}
+
+/// A single completed update, as recorded in the update log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateHistoryEntry {
+    /// When the update finished
+    pub timestamp: DateTime<Utc>,
+
+    /// Outcome (`Success` or `Failed`)
+    pub status: Status,
+
+    /// Language that was updated
+    #[serde(default)]
+    pub language: String,
+
+    /// How long the update took
+    #[serde(default)]
+    pub duration_secs: Option<f64>,
+
+    /// Extracted content size in bytes
+    #[serde(default)]
+    pub bytes: Option<u64>,
+
+    /// Error message (if failed)
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl UpdateHistoryEntry {
+    /// Parse a log line, accepting both JSON records and the older
+    /// `[2025-01-01 03:00:00 UTC] Update FAILED: reason` text format
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        if line.starts_with('{') {
+            return serde_json::from_str(line).ok();
+        }
+
+        let (timestamp, rest) = line.strip_prefix('[')?.split_once("] Update ")?;
+        let timestamp = chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S UTC")
+            .ok()?
+            .and_utc();
+        let (result, error) = match rest.split_once(": ") {
+            Some((result, error)) => (result, Some(error.to_string())),
+            None => (rest, None),
+        };
+        let status = match result {
+            "SUCCESS" => Status::Success,
+            "FAILED" => Status::Failed,
+            _ => return None,
+        };
+
+        Some(Self {
+            timestamp,
+            status,
+            language: String::new(),
+            duration_secs: None,
+            bytes: None,
+            error,
+        })
     }
 }
 
@@ -367,6 +628,10 @@ pub struct UpdateStatus {
     
     /// Error message (if failed)
     pub error_message: Option<String>,
+
+    /// When this status was last saved
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 impl Default for UpdateStatus {
@@ -379,26 +644,42 @@ impl Default for UpdateStatus {
             current_status: Status::Idle,
             progress: None,
             error_message: None,
+            updated_at: None,
         }
     }
 }
 
 impl UpdateStatus {
-    /// Load status from file
+    /// Load status from file, waiting for a writer in another process to finish
     pub fn load(path: &PathBuf) -> Result<Self> {
+        let _lock = DataDirLock::status(status_dir(path), false)?;
         let content = std::fs::read_to_string(path)?;
         let status: UpdateStatus = serde_json::from_str(&content)?;
         Ok(status)
     }
 
-    /// Save status to file
+    /// Save status to file.
+    ///
+    /// Writers in other processes are waited for, and the file is replaced
+    /// in one step so readers never see partial JSON.
     pub fn save(&self, path: &PathBuf) -> Result<()> {
         let content = serde_json::to_string_pretty(self)?;
-        std::fs::write(path, content)?;
+        let _lock = DataDirLock::status(status_dir(path), true)?;
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, content)?;
+        std::fs::rename(&tmp_path, path)?;
         Ok(())
     }
 }
 
+/// Directory holding the status file at `path`, for its lock
+fn status_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
 /// Update status enum
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Status {
@@ -425,6 +706,11 @@ pub enum Status {
 }
 
 impl Status {
+    /// Whether an update is running
+    pub fn is_in_progress(&self) -> bool {
+        matches!(self, Status::Checking | Status::Downloading | Status::Extracting | Status::Indexing)
+    }
+
     /// Convert to human-readable string
     pub fn to_string(&self) -> &'static str {
         match self {
@@ -461,38 +747,12 @@ pub struct UpdateProgress {
 impl UpdateProgress {
     /// Format bytes as human-readable string
     pub fn format_bytes(bytes: u64) -> String {
-        const KB: u64 = 1024;
-        const MB: u64 = KB * 1024;
-        const GB: u64 = MB * 1024;
-
-        if bytes >= GB {
-            format!("{:.2} GB", bytes as f64 / GB as f64)
-        } else if bytes >= MB {
-            format!("{:.2} MB", bytes as f64 / MB as f64)
-        } else if bytes >= KB {
-            format!("{:.2} KB", bytes as f64 / KB as f64)
-        } else {
-            format!("{} bytes", bytes)
-        }
+        crate::downloader::format_bytes(bytes)
     }
 
     /// Format ETA as human-readable string
     pub fn format_eta(&self) -> String {
-        if let Some(eta) = self.eta_seconds {
-            let hours = eta / 3600;
-            let minutes = (eta % 3600) / 60;
-            let seconds = eta % 60;
-
-            if hours > 0 {
-                format!("{}h {}m", hours, minutes)
-            } else if minutes > 0 {
-                format!("{}m {}s", minutes, seconds)
-            } else {
-                format!("{}s", seconds)
-            }
-        } else {
-            "Unknown".to_string()
-        }
+        crate::downloader::format_eta(self.eta_seconds)
     }
 }
 
@@ -510,6 +770,7 @@ mod tests {
             current_status: Status::Idle,
             progress: None,
             error_message: None,
+            updated_at: None,
         };
 
         let json = serde_json::to_string(&status).unwrap();
@@ -518,6 +779,168 @@ mod tests {
         assert_eq!(status.current_status, deserialized.current_status);
     }
 
+    #[tokio::test]
+    async fn test_cancel_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = UpdateConfig {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let manager = UpdateManager::new(config);
+
+        // Nothing running yet
+        assert!(manager.cancel_update().await.is_err());
+
+        manager.status.write().await.current_status = Status::Downloading;
+        manager.cancel_update().await.unwrap();
+
+        let saved = UpdateStatus::load(&UpdateConfig::status_path(dir.path())).unwrap();
+        assert_eq!(saved.current_status, Status::Idle);
+        assert!(manager.cancel_requested());
+    }
+
+    #[tokio::test]
+    async fn test_async_load_and_status_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = UpdateConfig {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        config.save(UpdateConfig::config_path(dir.path())).unwrap();
+
+        // Nothing saved yet
+        let manager = UpdateManager::load_async(dir.path()).await.unwrap();
+        assert_eq!(manager.get_status().await.current_status, Status::Idle);
+
+        // Another process saves a failure; loading (inside the runtime) picks it up
+        let other = UpdateManager::new(config.clone());
+        other.status.write().await.current_status = Status::Failed;
+        other.save_status().await.unwrap();
+        let loaded = UpdateManager::load_async(dir.path()).await.unwrap();
+        assert_eq!(loaded.status.read().await.current_status, Status::Failed);
+
+        // A running update isn't replaced by the older status on disk
+        let running = UpdateManager::new(config.clone());
+        running.status.write().await.current_status = Status::Downloading;
+        assert_eq!(running.get_status().await.current_status, Status::Downloading);
+
+        // An idle manager adopts a newer saved status, but not an older one
+        other.status.write().await.current_status = Status::Success;
+        other.save_status().await.unwrap();
+        assert_eq!(loaded.get_status().await.current_status, Status::Success);
+        loaded.status.write().await.current_status = Status::Idle;
+        loaded.save_status().await.unwrap();
+        UpdateStatus { current_status: Status::Failed, ..UpdateStatus::default() }
+            .save(&UpdateConfig::status_path(dir.path())).unwrap();
+        assert_eq!(loaded.get_status().await.current_status, Status::Idle);
+        assert!(!dir.path().join("update_status.json.tmp").exists());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_load_inside_runtime_does_not_block() {
+        let dir = tempfile::tempdir().unwrap();
+        UpdateStatus { current_status: Status::Failed, ..UpdateStatus::default() }
+            .save(&UpdateConfig::status_path(dir.path())).unwrap();
+
+        // A single-threaded runtime would hang (or panic) if loading blocked on the status lock
+        let loaded = tokio::time::timeout(Duration::from_secs(5), UpdateManager::load_async(dir.path()))
+            .await
+            .expect("load_async timed out")
+            .unwrap();
+        assert_eq!(loaded.get_status().await.current_status, Status::Failed);
+        assert_eq!(UpdateManager::load(dir.path()).unwrap().status.read().await.current_status, Status::Failed);
+    }
+
+    #[test]
+    fn test_history_entry_parse_line() {
+        let entry = UpdateHistoryEntry {
+            timestamp: Utc::now(),
+            status: Status::Success,
+            language: "simple".to_string(),
+            duration_secs: Some(12.5),
+            bytes: Some(1024),
+            error: None,
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        let parsed = UpdateHistoryEntry::parse_line(&json).unwrap();
+        assert_eq!(parsed.status, Status::Success);
+        assert_eq!(parsed.bytes, Some(1024));
+
+        // Legacy plain-text lines
+        let legacy = UpdateHistoryEntry::parse_line("[2025-11-28 03:00:00 UTC] Update FAILED: Download failed").unwrap();
+        assert_eq!(legacy.status, Status::Failed);
+        assert_eq!(legacy.error.as_deref(), Some("Download failed"));
+        assert!(UpdateHistoryEntry::parse_line("[2025-11-28 03:00:00 UTC] Update SUCCESS").is_some());
+        assert!(UpdateHistoryEntry::parse_line("garbage").is_none());
+    }
+
+    /// Cursor that counts how many bytes were actually read
+    struct CountingReader {
+        inner: std::io::Cursor<Vec<u8>>,
+        bytes_read: usize,
+    }
+
+    impl std::io::Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = std::io::Read::read(&mut self.inner, buf)?;
+            self.bytes_read += n;
+            Ok(n)
+        }
+    }
+
+    impl std::io::Seek for CountingReader {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_read_last_entries() {
+        let content: String = (0..5000).map(|i| format!("line {}\n", i)).collect();
+        let total = content.len();
+        let mut reader = CountingReader {
+            inner: std::io::Cursor::new(content.into_bytes()),
+            bytes_read: 0,
+        };
+
+        let lines = read_last_entries(&mut reader, 50, |line| Some(line.to_string())).unwrap();
+
+        let expected: Vec<String> = (4950..5000).rev().map(|i| format!("line {}", i)).collect();
+        assert_eq!(lines, expected);
+        assert!(reader.bytes_read < total / 4, "read {} of {} bytes", reader.bytes_read, total);
+    }
+
+    #[test]
+    fn test_read_last_entries_short_file() {
+        let mut reader = std::io::Cursor::new(b"first\n\nsecond\r\nthird".to_vec());
+        let lines = read_last_entries(&mut reader, 10, |line| Some(line.to_string())).unwrap();
+        assert_eq!(lines, vec!["third", "second", "first"]);
+    }
+
+    #[tokio::test]
+    async fn test_history_skips_corrupt_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = UpdateConfig {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        config.notifications.log_file = dir.path().join("updates.log");
+        let entry = |language: &str| serde_json::to_string(&UpdateHistoryEntry {
+            timestamp: Utc::now(),
+            status: Status::Success,
+            language: language.to_string(),
+            duration_secs: None,
+            bytes: None,
+            error: None,
+        }).unwrap();
+        let log = [entry("de"), entry("fr"), "{\"timestamp\":\"2025-11-".to_string(), entry("simple"), "garbage".to_string()];
+        std::fs::write(&config.notifications.log_file, log.join("\n") + "\n").unwrap();
+
+        let history = UpdateManager::new(config).get_history(2).await.unwrap();
+        let languages: Vec<&str> = history.iter().map(|entry| entry.language.as_str()).collect();
+        assert_eq!(languages, vec!["simple", "fr"]);
+    }
+
     #[test]
     fn test_format_bytes() {
         assert_eq!(UpdateProgress::format_bytes(500), "500 bytes");
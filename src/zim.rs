@@ -0,0 +1,816 @@
+//! ZIM file import
+//!
+//! Reads Kiwix ZIM archives (format versions 5 and 6) directly: the
+//! directory entries, and clusters that are uncompressed, xz or zstd
+//! compressed. HTML articles are converted to the content format the
+//! parser produces (paragraphs, `<h2>`-style headings and `/wiki/` links)
+//! and redirects become redirect aliases.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::article::Article;
+use crate::import::{ImportReport, ImportWriter};
+use crate::parser::WikiParser;
+use crate::redirects::RedirectMap;
+
+/// First four bytes of every ZIM file
+const ZIM_MAGIC: u32 = 72_173_914;
+const HEADER_SIZE: usize = 80;
+/// Mime type index of redirect entries
+const REDIRECT_MIME: u16 = 0xffff;
+/// Mime type indexes of link target and deleted entries, which have no content
+const LINK_TARGET_MIME: u16 = 0xfffe;
+const DELETED_MIME: u16 = 0xfffd;
+/// Largest decompressed cluster accepted; real clusters are a few megabytes
+const MAX_CLUSTER_SIZE: u64 = 512 * 1024 * 1024;
+
+static TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<(/?)([a-zA-Z][a-zA-Z0-9]*)([^>]*)>|(?s:<!--.*?-->)|<![^>]*>").unwrap());
+static HREF_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"href\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap());
+static ENTITY_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap());
+static WHITESPACE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+").unwrap());
+static HEADING_SPACE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<(h[2-6])> | </(h[2-6])>").unwrap());
+
+/// Elements left out of the content entirely
+const DROPPED_ELEMENTS: &[&str] = &[
+    "head", "script", "style", "table", "sup", "figure", "noscript", "math", "svg",
+    "audio", "video", "iframe", "h1",
+];
+/// Elements that start a new paragraph
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p", "div", "section", "article", "main", "ul", "ol", "dl", "blockquote", "pre", "center", "details",
+];
+/// Elements that start a new line (on their opening tag)
+const LINE_ELEMENTS: &[&str] = &["br", "li", "dd", "dt", "tr"];
+
+/// Fixed-size header at the start of a ZIM file
+#[derive(Debug, Clone)]
+struct Header {
+    major_version: u16,
+    entry_count: u32,
+    cluster_count: u32,
+    url_ptr_pos: u64,
+    cluster_ptr_pos: u64,
+    mime_list_pos: u64,
+    checksum_pos: u64,
+}
+
+/// What a directory entry points at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    /// A blob in a cluster, with the index of its mime type
+    Content { mime_type: u16, cluster: u32, blob: u32 },
+    /// Another entry, by index
+    Redirect { target: u32 },
+    /// Link targets and deleted entries
+    Other,
+}
+
+/// A directory entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    /// Namespace: `A`/`C` for articles, `M` for metadata, ...
+    pub namespace: char,
+    pub url: String,
+    /// Title, falling back to the URL when the entry has none
+    pub title: String,
+    pub kind: EntryKind,
+}
+
+/// A ZIM archive
+pub struct ZimArchive<R> {
+    reader: R,
+    /// File size, which every offset read from the file must stay within
+    len: u64,
+    header: Header,
+    mime_types: Vec<String>,
+}
+
+impl ZimArchive<BufReader<File>> {
+    /// Open the ZIM file at `path`
+    pub fn open_file(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+        Self::open(BufReader::new(file)).with_context(|| format!("Failed to read {:?}", path))
+    }
+}
+
+impl<R: Read + Seek> ZimArchive<R> {
+    /// Read the header and mime type list
+    pub fn open(mut reader: R) -> Result<Self> {
+        let mut bytes = [0u8; HEADER_SIZE];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut bytes).context("File is too short to be a ZIM file")?;
+        let u32_at = |pos: usize| u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap());
+        let u64_at = |pos: usize| u64::from_le_bytes(bytes[pos..pos + 8].try_into().unwrap());
+        if u32_at(0) != ZIM_MAGIC {
+            anyhow::bail!("Not a ZIM file (bad magic number)");
+        }
+        let header = Header {
+            major_version: u16::from_le_bytes([bytes[4], bytes[5]]),
+            entry_count: u32_at(24),
+            cluster_count: u32_at(28),
+            url_ptr_pos: u64_at(32),
+            cluster_ptr_pos: u64_at(48),
+            mime_list_pos: u64_at(56),
+            checksum_pos: u64_at(72),
+        };
+        if header.major_version != 5 && header.major_version != 6 {
+            anyhow::bail!("Unsupported ZIM version {}", header.major_version);
+        }
+        let len = reader.seek(SeekFrom::End(0))?;
+        let table_fits = |pos: u64, count: u32| {
+            pos.checked_add(8 * count as u64).is_some_and(|end| end <= len)
+        };
+        if !table_fits(header.url_ptr_pos, header.entry_count)
+            || !table_fits(header.cluster_ptr_pos, header.cluster_count)
+            || header.mime_list_pos >= len
+            || header.checksum_pos > len
+        {
+            anyhow::bail!("ZIM file is truncated or its header is corrupt");
+        }
+
+        reader.seek(SeekFrom::Start(header.mime_list_pos))?;
+        let mut mime_types = Vec::new();
+        loop {
+            let mime_type = read_cstring(&mut reader)?;
+            if mime_type.is_empty() {
+                break;
+            }
+            mime_types.push(mime_type);
+        }
+
+        Ok(Self { reader, len, header, mime_types })
+    }
+
+    /// Number of directory entries
+    pub fn entry_count(&self) -> u32 {
+        self.header.entry_count
+    }
+
+    /// Mime type of a content entry
+    pub fn mime_type(&self, index: u16) -> Option<&str> {
+        self.mime_types.get(index as usize).map(String::as_str)
+    }
+
+    /// Directory entry `index`, in URL order
+    pub fn entry(&mut self, index: u32) -> Result<DirEntry> {
+        if index >= self.header.entry_count {
+            anyhow::bail!("Entry {} out of range ({} entries)", index, self.header.entry_count);
+        }
+        let position = self.read_u64_at(self.header.url_ptr_pos + 8 * index as u64)?;
+        self.reader.seek(SeekFrom::Start(position))?;
+
+        let mut fixed = [0u8; 8];
+        self.reader.read_exact(&mut fixed)?;
+        let mime_type = u16::from_le_bytes([fixed[0], fixed[1]]);
+        let parameter_len = fixed[2] as usize;
+        let namespace = fixed[3] as char;
+        let kind = match mime_type {
+            REDIRECT_MIME => EntryKind::Redirect { target: self.read_u32()? },
+            LINK_TARGET_MIME | DELETED_MIME => EntryKind::Other,
+            _ => EntryKind::Content { mime_type, cluster: self.read_u32()?, blob: self.read_u32()? },
+        };
+        let url = read_cstring(&mut self.reader)?;
+        let title = read_cstring(&mut self.reader)?;
+        let mut parameter = vec![0u8; parameter_len];
+        self.reader.read_exact(&mut parameter)?;
+
+        let title = if title.is_empty() { url.clone() } else { title };
+        Ok(DirEntry { namespace, url, title, kind })
+    }
+
+    /// Find an entry by namespace and URL
+    pub fn find(&mut self, namespace: char, url: &str) -> Result<Option<(u32, DirEntry)>> {
+        let (mut low, mut high) = (0, self.header.entry_count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let entry = self.entry(middle)?;
+            match (entry.namespace, entry.url.as_str()).cmp(&(namespace, url)) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => return Ok(Some((middle, entry))),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Decompressed blobs of cluster `index`
+    pub fn cluster(&mut self, index: u32) -> Result<Vec<Vec<u8>>> {
+        if index >= self.header.cluster_count {
+            anyhow::bail!("Cluster {} out of range ({} clusters)", index, self.header.cluster_count);
+        }
+        let start = self.read_u64_at(self.header.cluster_ptr_pos + 8 * index as u64)?;
+        let end = if index + 1 < self.header.cluster_count {
+            self.read_u64_at(self.header.cluster_ptr_pos + 8 * (index as u64 + 1))?
+        } else {
+            self.header.checksum_pos
+        };
+        if end <= start || end > self.len || end - start > MAX_CLUSTER_SIZE {
+            anyhow::bail!("Cluster {} has an invalid size", index);
+        }
+
+        let mut raw = vec![0u8; (end - start) as usize];
+        self.reader.seek(SeekFrom::Start(start))?;
+        self.reader.read_exact(&mut raw)?;
+        let info = raw[0];
+        let mut data = Vec::new();
+        let decompressed = match info & 0x0f {
+            0 | 1 => (&raw[1..]).take(MAX_CLUSTER_SIZE + 1).read_to_end(&mut data),
+            4 => xz2::read::XzDecoder::new(&raw[1..]).take(MAX_CLUSTER_SIZE + 1).read_to_end(&mut data),
+            5 => zstd::stream::Decoder::new(&raw[1..])
+                .and_then(|decoder| decoder.take(MAX_CLUSTER_SIZE + 1).read_to_end(&mut data)),
+            compression => anyhow::bail!("Cluster {} uses unsupported compression {}", index, compression),
+        };
+        decompressed.with_context(|| format!("Failed to decompress cluster {}", index))?;
+        if data.len() as u64 > MAX_CLUSTER_SIZE {
+            anyhow::bail!("Cluster {} decompresses to more than {} bytes", index, MAX_CLUSTER_SIZE);
+        }
+
+        // Blob offsets, 8 bytes wide in extended clusters
+        let width = if info & 0x10 != 0 { 8 } else { 4 };
+        let offset_at = |i: usize| -> Result<usize> {
+            let bytes = data.get(i * width..(i + 1) * width)
+                .with_context(|| format!("Cluster {} is truncated", index))?;
+            Ok(if width == 8 {
+                u64::from_le_bytes(bytes.try_into().unwrap()) as usize
+            } else {
+                u32::from_le_bytes(bytes.try_into().unwrap()) as usize
+            })
+        };
+        // The first offset is where the offset table ends, so it also gives the blob count
+        let table_len = offset_at(0)?;
+        let blob_count = table_len.checked_div(width)
+            .and_then(|count| count.checked_sub(1))
+            .filter(|_| table_len % width == 0 && table_len <= data.len())
+            .with_context(|| format!("Cluster {} has a corrupt offset table", index))?;
+        let mut blobs = Vec::with_capacity(blob_count);
+        for i in 0..blob_count {
+            let (blob_start, blob_end) = (offset_at(i)?, offset_at(i + 1)?);
+            let blob = data.get(blob_start..blob_end)
+                .with_context(|| format!("Cluster {} has an invalid blob offset", index))?;
+            blobs.push(blob.to_vec());
+        }
+        Ok(blobs)
+    }
+
+    /// Content of a metadata entry such as `Language` or `Title`
+    pub fn metadata(&mut self, name: &str) -> Result<Option<String>> {
+        let Some((_, entry)) = self.find('M', name)? else {
+            return Ok(None);
+        };
+        let EntryKind::Content { cluster, blob, .. } = entry.kind else {
+            return Ok(None);
+        };
+        let blobs = self.cluster(cluster)?;
+        Ok(blobs.get(blob as usize).map(|bytes| String::from_utf8_lossy(bytes).into_owned()))
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        let mut bytes = [0u8; 4];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn read_u64_at(&mut self, position: u64) -> Result<u64> {
+        if position.checked_add(8).is_none_or(|end| end > self.len) {
+            anyhow::bail!("Offset {} is past the end of the file", position);
+        }
+        let mut bytes = [0u8; 8];
+        self.reader.seek(SeekFrom::Start(position))?;
+        self.reader.read_exact(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+fn read_cstring(reader: &mut impl Read) -> Result<String> {
+    let mut bytes = Vec::new();
+    let mut byte = [0u8; 1];
+    loop {
+        reader.read_exact(&mut byte)?;
+        if byte[0] == 0 {
+            break;
+        }
+        bytes.push(byte[0]);
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Whether entries in `namespace` are articles (`A` before ZIM 6.1, `C` after)
+fn is_article_namespace(namespace: char) -> bool {
+    namespace == 'A' || namespace == 'C'
+}
+
+/// Wikipedia language code for a ZIM `Language` (ISO 639-3, possibly a
+/// comma-separated list), if it is one Rustipedia knows
+fn language_code(iso639_3: &str) -> Option<&'static str> {
+    let code = match iso639_3.split(',').next()?.trim() {
+        "eng" => "en",
+        "deu" | "ger" => "de",
+        "fra" | "fre" => "fr",
+        "spa" => "es",
+        "jpn" => "ja",
+        "rus" => "ru",
+        "zho" | "chi" => "zh",
+        "ita" => "it",
+        "por" => "pt",
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Import the HTML articles and redirects of a ZIM file into `data_dir`,
+/// replacing its `articles.jsonl`.
+///
+/// Articles get title-derived IDs ([`Article::fallback_id`]), as ZIM files
+/// carry no page IDs. Without `language`, the ZIM's `Language` metadata is
+/// used when it names a known language.
+pub fn import_zim(path: &Path, data_dir: &Path, language: Option<&str>) -> Result<ImportReport> {
+    let mut archive = ZimArchive::open_file(path)?;
+    let zim_language = archive.metadata("Language")?;
+    let language = language.or_else(|| zim_language.as_deref().and_then(language_code));
+
+    // Collect article blobs and redirects, then read articles cluster by
+    // cluster so each is decompressed once
+    let mut articles = Vec::new();
+    let mut redirect_entries = Vec::new();
+    for index in 0..archive.entry_count() {
+        let entry = archive.entry(index)?;
+        if !is_article_namespace(entry.namespace) {
+            continue;
+        }
+        match entry.kind {
+            EntryKind::Content { mime_type, cluster, blob } => {
+                if archive.mime_type(mime_type).is_some_and(|mime| mime.starts_with("text/html")) {
+                    articles.push((cluster, blob, index));
+                }
+            }
+            EntryKind::Redirect { target } => redirect_entries.push((index, target)),
+            EntryKind::Other => {}
+        }
+    }
+    articles.sort_unstable();
+
+    let source_file = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut writer = ImportWriter::create(data_dir, &source_file, language)?;
+    let mut loaded: Option<(u32, Vec<Vec<u8>>)> = None;
+    for (cluster, blob, index) in articles {
+        if loaded.as_ref().map(|(loaded_cluster, _)| *loaded_cluster) != Some(cluster) {
+            loaded = Some((cluster, archive.cluster(cluster)?));
+        }
+        let blobs = &loaded.as_ref().unwrap().1;
+        let entry = archive.entry(index)?;
+        let Some(html) = blobs.get(blob as usize) else {
+            writer.reject(index as u64, "invalid record", format!("\"{}\" points at a missing blob", entry.title));
+            continue;
+        };
+        let content = html_to_content(&String::from_utf8_lossy(html));
+        writer.add(index as u64, Article::new(Article::fallback_id(&entry.title), entry.title, content))?;
+    }
+
+    let mut redirects = RedirectMap::new();
+    for (index, target) in redirect_entries {
+        let alias = archive.entry(index)?;
+        let target = archive.entry(target)?;
+        if is_article_namespace(target.namespace) {
+            redirects.insert(&alias.title, &target.title);
+        }
+    }
+
+    writer.finish(Some(&redirects))
+}
+
+/// Convert article HTML to stored content: paragraphs separated by blank
+/// lines, `<h2>`-`<h6>` headings, and links to other articles as
+/// `<a href="/wiki/Title">`. Tables, references, media and scripts are dropped.
+pub fn html_to_content(html: &str) -> String {
+    let body = match (html.find("<body"), html.rfind("</body>")) {
+        (Some(start), Some(end)) if start < end => &html[start..end],
+        _ => html,
+    };
+
+    let mut out = String::with_capacity(body.len() / 2);
+    // Dropped element being skipped, and its nesting depth
+    let mut skipping: Option<(String, usize)> = None;
+    // Whether each open <a> was written as a link
+    let mut links: Vec<bool> = Vec::new();
+    let mut last = 0;
+
+    for tag in TAG_RE.captures_iter(body) {
+        let whole = tag.get(0).unwrap();
+        if skipping.is_none() {
+            push_text(&mut out, &body[last..whole.start()]);
+        }
+        last = whole.end();
+
+        let Some(name) = tag.get(2) else {
+            continue; // Comment or doctype
+        };
+        let name = name.as_str().to_ascii_lowercase();
+        let closing = !tag[1].is_empty();
+        let self_closing = tag[3].trim_end().ends_with('/');
+
+        if let Some((ref skipped, ref mut depth)) = skipping {
+            if name == *skipped && !self_closing {
+                if closing {
+                    *depth -= 1;
+                } else {
+                    *depth += 1;
+                }
+                if *depth == 0 {
+                    skipping = None;
+                }
+            }
+            continue;
+        }
+
+        if DROPPED_ELEMENTS.contains(&name.as_str()) {
+            if !closing && !self_closing {
+                skipping = Some((name, 1));
+            }
+        } else if matches!(name.as_str(), "h2" | "h3" | "h4" | "h5" | "h6") {
+            if closing {
+                out.push_str(&format!("</{}>\n\n", name));
+            } else {
+                out.push_str(&format!("\n\n<{}>", name));
+            }
+        } else if name == "a" {
+            if closing {
+                if links.pop() == Some(true) {
+                    out.push_str("</a>");
+                }
+            } else {
+                let target = HREF_RE.captures(&tag[3])
+                    .and_then(|href| href.get(1).or_else(|| href.get(2)))
+                    .and_then(|href| link_target(href.as_str()));
+                if let Some(ref target) = target {
+                    out.push_str(&format!("<a href=\"/wiki/{}\">", urlencoding::encode(target)));
+                }
+                links.push(target.is_some());
+            }
+        } else if BLOCK_ELEMENTS.contains(&name.as_str()) {
+            out.push_str("\n\n");
+        } else if LINE_ELEMENTS.contains(&name.as_str()) && !closing {
+            out.push('\n');
+        }
+    }
+    if skipping.is_none() {
+        push_text(&mut out, &body[last..]);
+    }
+    for open in links {
+        if open {
+            out.push_str("</a>");
+        }
+    }
+
+    let out = HEADING_SPACE_RE.replace_all(&out, |caps: &regex::Captures| match caps.get(1) {
+        Some(open) => format!("<{}>", open.as_str()),
+        None => format!("</{}>", &caps[2]),
+    });
+    let paragraphs: Vec<String> = out.split("\n\n")
+        .map(|paragraph| {
+            paragraph.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .filter(|paragraph| {
+            // Headings whose text was all dropped leave empty tags behind
+            let text = TAG_RE.replace_all(paragraph, "");
+            !text.trim().is_empty()
+        })
+        .collect();
+    WikiParser::sanitize_html(&paragraphs.join("\n\n"))
+}
+
+/// Append HTML text with whitespace collapsed and numeric and common entities
+/// decoded. `&amp;`, `&lt;` and `&gt;` stay encoded to keep the text free of
+/// markup; other named entities are kept as they are and render as usual.
+fn push_text(out: &mut String, text: &str) {
+    let text = WHITESPACE_RE.replace_all(text, " ");
+    let text = ENTITY_RE.replace_all(&text, |caps: &regex::Captures| {
+        let entity = &caps[1];
+        let decoded = match entity {
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{A0}'),
+            _ => {
+                let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                };
+                code.and_then(char::from_u32).filter(|c| !matches!(c, '&' | '<' | '>'))
+            }
+        };
+        decoded.map(String::from).unwrap_or_else(|| caps[0].to_string())
+    });
+    if out.is_empty() || out.ends_with('\n') {
+        out.push_str(text.trim_start());
+    } else {
+        out.push_str(&text);
+    }
+}
+
+/// Title of the article a ZIM-relative `href` points at, or `None` for
+/// external links, in-page anchors and other namespaces
+fn link_target(href: &str) -> Option<String> {
+    let href = href.split(['#', '?']).next().unwrap_or("");
+    if href.is_empty() || href.contains("://") || href.starts_with("mailto:") || href.starts_with('/') {
+        return None;
+    }
+    let mut path = href;
+    while let Some(rest) = path.strip_prefix("./") {
+        path = rest;
+    }
+    if let Some(rest) = path.strip_prefix("../") {
+        // Across namespaces (old layout): only article namespaces are articles
+        path = rest.strip_prefix("A/").or_else(|| rest.strip_prefix("C/"))?;
+    }
+    if path.is_empty() {
+        return None;
+    }
+    let title = urlencoding::decode(path).ok()?.replace('_', " ");
+    Some(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    use crate::downloader::ExtractionState;
+
+    /// An entry of a test archive: namespace, URL, title, and either a mime
+    /// type with content or the URL of a redirect target
+    struct TestEntry {
+        namespace: char,
+        url: &'static str,
+        title: &'static str,
+        content: Result<(&'static str, &'static str), &'static str>,
+    }
+
+    /// Build a ZIM 6.0 file: metadata in an uncompressed cluster, articles
+    /// in an xz cluster with the last one in a zstd cluster
+    fn build_zim(mut entries: Vec<TestEntry>) -> Vec<u8> {
+        entries.sort_by(|a, b| (a.namespace, a.url).cmp(&(b.namespace, b.url)));
+        let mut mime_types: Vec<&str> = Vec::new();
+        for entry in &entries {
+            if let Ok((mime, _)) = entry.content {
+                if !mime_types.contains(&mime) {
+                    mime_types.push(mime);
+                }
+            }
+        }
+
+        // Assign blobs to clusters
+        let mut clusters: Vec<(u8, Vec<&str>)> = vec![(1, Vec::new()), (4, Vec::new()), (5, Vec::new())];
+        let content_count = entries.iter().filter(|e| e.content.is_ok() && e.namespace != 'M').count();
+        let mut seen_articles = 0;
+        let mut locations = Vec::new();
+        for entry in &entries {
+            locations.push(match entry.content {
+                Ok((_, content)) => {
+                    let cluster = if entry.namespace == 'M' {
+                        0
+                    } else {
+                        seen_articles += 1;
+                        if seen_articles == content_count { 2 } else { 1 }
+                    };
+                    clusters[cluster].1.push(content);
+                    Some((cluster as u32, clusters[cluster].1.len() as u32 - 1))
+                }
+                Err(_) => None,
+            });
+        }
+
+        let mut mime_list = Vec::new();
+        for mime in &mime_types {
+            mime_list.extend_from_slice(mime.as_bytes());
+            mime_list.push(0);
+        }
+        mime_list.push(0);
+
+        let mut dirents = Vec::new();
+        let mut dirent_offsets = Vec::new();
+        for (entry, location) in entries.iter().zip(&locations) {
+            dirent_offsets.push(dirents.len() as u64);
+            match (entry.content, location) {
+                (Ok((mime, _)), Some((cluster, blob))) => {
+                    let mime_index = mime_types.iter().position(|m| *m == mime).unwrap() as u16;
+                    dirents.extend_from_slice(&mime_index.to_le_bytes());
+                    dirents.extend_from_slice(&[0, entry.namespace as u8, 0, 0, 0, 0]);
+                    dirents.extend_from_slice(&cluster.to_le_bytes());
+                    dirents.extend_from_slice(&blob.to_le_bytes());
+                }
+                (Err(target), _) => {
+                    let target_index = entries.iter().position(|e| e.url == target).unwrap() as u32;
+                    dirents.extend_from_slice(&REDIRECT_MIME.to_le_bytes());
+                    dirents.extend_from_slice(&[0, entry.namespace as u8, 0, 0, 0, 0]);
+                    dirents.extend_from_slice(&target_index.to_le_bytes());
+                }
+                _ => unreachable!(),
+            }
+            dirents.extend_from_slice(entry.url.as_bytes());
+            dirents.push(0);
+            dirents.extend_from_slice(entry.title.as_bytes());
+            dirents.push(0);
+        }
+
+        let cluster_bytes: Vec<Vec<u8>> = clusters.iter()
+            .map(|(compression, blobs)| {
+                let mut data = Vec::new();
+                let mut offset = 4 * (blobs.len() as u32 + 1);
+                data.extend_from_slice(&offset.to_le_bytes());
+                for blob in blobs {
+                    offset += blob.len() as u32;
+                    data.extend_from_slice(&offset.to_le_bytes());
+                }
+                for blob in blobs {
+                    data.extend_from_slice(blob.as_bytes());
+                }
+                let mut cluster = vec![*compression];
+                match compression {
+                    4 => {
+                        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+                        encoder.write_all(&data).unwrap();
+                        cluster.extend(encoder.finish().unwrap());
+                    }
+                    5 => cluster.extend(zstd::stream::encode_all(&data[..], 3).unwrap()),
+                    _ => cluster.extend(data),
+                }
+                cluster
+            })
+            .collect();
+
+        let entry_count = entries.len() as u64;
+        let mime_list_pos = HEADER_SIZE as u64;
+        let url_ptr_pos = mime_list_pos + mime_list.len() as u64;
+        let title_ptr_pos = url_ptr_pos + 8 * entry_count;
+        let dirents_pos = title_ptr_pos + 4 * entry_count;
+        let cluster_ptr_pos = dirents_pos + dirents.len() as u64;
+        let mut cluster_pos = cluster_ptr_pos + 8 * cluster_bytes.len() as u64;
+        let mut cluster_ptrs = Vec::new();
+        for cluster in &cluster_bytes {
+            cluster_ptrs.push(cluster_pos);
+            cluster_pos += cluster.len() as u64;
+        }
+        let checksum_pos = cluster_pos;
+
+        let mut file = Vec::new();
+        file.extend_from_slice(&ZIM_MAGIC.to_le_bytes());
+        file.extend_from_slice(&6u16.to_le_bytes());
+        file.extend_from_slice(&0u16.to_le_bytes());
+        file.extend_from_slice(&[7; 16]);
+        file.extend_from_slice(&(entry_count as u32).to_le_bytes());
+        file.extend_from_slice(&(cluster_bytes.len() as u32).to_le_bytes());
+        file.extend_from_slice(&url_ptr_pos.to_le_bytes());
+        file.extend_from_slice(&title_ptr_pos.to_le_bytes());
+        file.extend_from_slice(&cluster_ptr_pos.to_le_bytes());
+        file.extend_from_slice(&mime_list_pos.to_le_bytes());
+        file.extend_from_slice(&u32::MAX.to_le_bytes());
+        file.extend_from_slice(&u32::MAX.to_le_bytes());
+        file.extend_from_slice(&checksum_pos.to_le_bytes());
+        assert_eq!(file.len(), HEADER_SIZE);
+        file.extend(mime_list);
+        for offset in dirent_offsets {
+            file.extend_from_slice(&(dirents_pos + offset).to_le_bytes());
+        }
+        for index in 0..entry_count as u32 {
+            file.extend_from_slice(&index.to_le_bytes());
+        }
+        file.extend(dirents);
+        for ptr in cluster_ptrs {
+            file.extend_from_slice(&ptr.to_le_bytes());
+        }
+        for cluster in cluster_bytes {
+            file.extend(cluster);
+        }
+        file.extend_from_slice(&[0; 16]);
+        file
+    }
+
+    fn tiny_zim() -> Vec<u8> {
+        let article = |url, title, html| TestEntry { namespace: 'A', url, title, content: Ok(("text/html", html)) };
+        build_zim(vec![
+            article("Rust", "Rust", concat!(
+                "<html><head><title>Rust</title><style>p { color: red }</style></head><body>",
+                "<h1>Rust</h1><p><b>Rust</b> is an <a href=\"Iron_oxide\">iron oxide</a> &amp; a ",
+                "<a href=\"https://example.com\">word</a>.<sup><a href=\"#cite-1\">[1]</a></sup></p>",
+                "<table><tr><td>Infobox</td></tr></table>",
+                "<h2>Uses  </h2><ul><li>Paint</li><li><a href=\"../A/Bridge\">Bridges</a></li></ul>",
+                "<p>Café &#8211; 3&nbsp;&lt;&nbsp;4 &hellip;</p></body></html>",
+            )),
+            article("Iron_oxide", "Iron oxide", "<p>A compound of <i>iron</i>.</p>"),
+            article("Empty", "Empty", "<table><tr><td>Only a table</td></tr></table>"),
+            TestEntry { namespace: 'A', url: "Rusting", title: "Rusting", content: Err("Rust") },
+            TestEntry { namespace: 'I', url: "logo.png", title: "", content: Ok(("image/png", "PNG")) },
+            TestEntry { namespace: 'M', url: "Language", title: "", content: Ok(("text/plain", "eng")) },
+        ])
+    }
+
+    #[test]
+    fn test_read_tiny_zim() {
+        let mut archive = ZimArchive::open(Cursor::new(tiny_zim())).unwrap();
+        assert_eq!(archive.entry_count(), 6);
+        assert_eq!(archive.metadata("Language").unwrap().as_deref(), Some("eng"));
+        assert!(archive.metadata("Title").unwrap().is_none());
+
+        let (_, entry) = archive.find('A', "Iron_oxide").unwrap().unwrap();
+        assert_eq!(entry.title, "Iron oxide");
+        let EntryKind::Content { cluster, blob, .. } = entry.kind else { panic!("not content") };
+        assert_eq!(archive.cluster(cluster).unwrap()[blob as usize], b"<p>A compound of <i>iron</i>.</p>");
+
+        let (_, redirect) = archive.find('A', "Rusting").unwrap().unwrap();
+        let EntryKind::Redirect { target } = redirect.kind else { panic!("not a redirect") };
+        assert_eq!(archive.entry(target).unwrap().url, "Rust");
+
+        assert!(ZimArchive::open(Cursor::new(b"not a zim file".to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_truncated_zim_is_an_error() {
+        let zim = tiny_zim();
+        // The checksum comes last, so any cut loses part of it
+        for len in 0..zim.len() - 16 {
+            assert!(ZimArchive::open(Cursor::new(zim[..len].to_vec())).is_err(), "{} bytes", len);
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let zim_path = dir.path().join("truncated.zim");
+        std::fs::write(&zim_path, &zim[..zim.len() / 2]).unwrap();
+        assert!(import_zim(&zim_path, &dir.path().join("wiki"), None).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_cluster_is_an_error() {
+        let zim = tiny_zim();
+        let u64_at = |zim: &[u8], pos: usize| u64::from_le_bytes(zim[pos..pos + 8].try_into().unwrap()) as usize;
+        let cluster_ptr_pos = u64_at(&zim, 48);
+        // Cluster 0 holds the metadata uncompressed: an info byte, then the offsets
+        let offsets_pos = u64_at(&zim, cluster_ptr_pos) + 1;
+
+        for first_offset in [0u32, 2, 3, u32::MAX] {
+            let mut corrupt = zim.clone();
+            corrupt[offsets_pos..offsets_pos + 4].copy_from_slice(&first_offset.to_le_bytes());
+            let mut archive = ZimArchive::open(Cursor::new(corrupt)).unwrap();
+            assert!(archive.cluster(0).is_err(), "first offset {}", first_offset);
+        }
+
+        // A blob ending past the cluster
+        let mut corrupt = zim.clone();
+        corrupt[offsets_pos + 4..offsets_pos + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(ZimArchive::open(Cursor::new(corrupt)).unwrap().cluster(0).is_err());
+
+        // A cluster pointer past the end of the file is refused before allocating
+        let mut corrupt = zim.clone();
+        corrupt[cluster_ptr_pos + 8..cluster_ptr_pos + 16].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+        let mut archive = ZimArchive::open(Cursor::new(corrupt)).unwrap();
+        assert!(archive.cluster(0).is_err());
+        assert!(archive.cluster(1).is_err());
+    }
+
+    #[test]
+    fn test_html_to_content() {
+        let html = "<body><p>See <a href=\"./Iron_oxide#Uses\">rust <i>itself</i></a>.</p><p><a href=\"../I/x.png\">image</a></p></body>";
+        assert_eq!(html_to_content(html), "See <a href=\"/wiki/Iron%20oxide\">rust itself</a>.\n\nimage");
+        assert_eq!(html_to_content("<p>Kept<!-- a comment\nover <b>two</b> lines --> text</p>"), "Kept text");
+    }
+
+    #[test]
+    fn test_import_tiny_zim() {
+        let dir = tempfile::tempdir().unwrap();
+        let zim_path = dir.path().join("tiny.zim");
+        std::fs::write(&zim_path, tiny_zim()).unwrap();
+        let data_dir = dir.path().join("wiki");
+
+        let report = import_zim(&zim_path, &data_dir, None).unwrap();
+        assert_eq!(report.accepted, 2);
+        assert_eq!(report.reasons["empty content"], 1);
+        assert_eq!(report.redirects, 1);
+        assert_eq!(ExtractionState::of(&data_dir), ExtractionState::Complete);
+
+        let articles: Vec<Article> = std::fs::read_to_string(data_dir.join("articles.jsonl")).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let rust = articles.iter().find(|a| a.title == "Rust").unwrap();
+        assert_eq!(rust.id, Article::fallback_id("Rust"));
+        assert_eq!(rust.content, concat!(
+            "Rust is an <a href=\"/wiki/Iron%20oxide\">iron oxide</a> &amp; a word.\n\n",
+            "<h2>Uses</h2>\n\n",
+            "Paint\n<a href=\"/wiki/Bridge\">Bridges</a>\n\n",
+            "Café – 3\u{A0}&lt;\u{A0}4 &hellip;",
+        ));
+        assert_eq!(rust.headings()[0].text, "Uses");
+
+        let redirects = RedirectMap::load(data_dir.join("redirects.json")).unwrap();
+        assert_eq!(redirects.resolve("Rusting").as_deref(), Some("Rust"));
+        let config = crate::DataDirConfig::load(&data_dir.join("config.json"), true).unwrap();
+        assert_eq!(config.language.as_deref(), Some("en"));
+    }
+}
@@ -0,0 +1,119 @@
+//! `rustipedia-download quickstart` end to end: a tiny dump served from a
+//! local mirror is downloaded, extracted and indexed, and the article is
+//! then served by `rustipedia-serve` on a real port.
+
+#![cfg(unix)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use bzip2::write::BzEncoder;
+use bzip2::Compression;
+use sha2::{Digest, Sha256};
+
+const DUMP_XML: &str = r#"<mediawiki>
+  <page>
+    <title>Rust (programming language)</title>
+    <id>1</id>
+    <revision>
+      <timestamp>2024-01-01T00:00:00Z</timestamp>
+      <text>'''Rust''' is a programming language that focuses on memory safety and speed. It was first designed by Graydon Hoare and is used to write fast, reliable software such as web servers and browsers.</text>
+    </revision>
+  </page>
+</mediawiki>
+"#;
+
+/// Answer every request with the compressed dump, or its SHA-256 for `.sha256` paths
+fn serve_mirror(dump: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut header = String::new();
+            while reader.read_line(&mut header).unwrap() > 2 {
+                header.clear();
+            }
+            let body = if request_line.contains(".sha256 ") {
+                format!("{}  dump.xml.bz2\n", hex::encode(Sha256::digest(&dump))).into_bytes()
+            } else {
+                dump.clone()
+            };
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()).unwrap();
+            stream.write_all(&body).unwrap();
+        }
+    });
+    url
+}
+
+/// GET `path` from the server, or `None` while nothing is listening yet
+fn get(port: u16, path: &str) -> Option<String> {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).ok()?;
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok()?;
+    Some(response)
+}
+
+/// Kills the server when the test ends, passing or not
+struct KillOnDrop(Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[test]
+fn test_quickstart_serves_downloaded_article() {
+    let mut encoder = BzEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(DUMP_XML.as_bytes()).unwrap();
+    let mirror = serve_mirror(encoder.finish().unwrap());
+
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let dir = tempfile::tempdir().unwrap();
+    let data_dir = dir.path().join("wiki");
+    let mut quickstart = KillOnDrop(Command::new(env!("CARGO_BIN_EXE_rustipedia-download"))
+        .arg("--output").arg(&data_dir)
+        .arg("--dump-base-url").arg(&mirror)
+        .arg("--no-progress")
+        .args(["quickstart", "--lang", "simple", "--port", &port.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap());
+
+    let deadline = Instant::now() + Duration::from_secs(120);
+    let page = loop {
+        if let Some(status) = quickstart.0.try_wait().unwrap() {
+            panic!("quickstart exited with {} before serving", status);
+        }
+        if let Some(response) = get(port, "/wiki/Rust_(programming_language)") {
+            break response;
+        }
+        assert!(Instant::now() < deadline, "server didn't start on port {}", port);
+        std::thread::sleep(Duration::from_millis(200));
+    };
+
+    assert!(page.starts_with("HTTP/1.1 200"), "{}", page);
+    assert!(page.contains("memory safety"), "{}", page);
+    assert!(data_dir.join("search_index").exists());
+    let search = get(port, "/api/v1/search?q=graydon").unwrap();
+    assert!(search.contains("Rust (programming language)"), "{}", search);
+
+    // The directory is complete now, but holds another language
+    let other_language = Command::new(env!("CARGO_BIN_EXE_rustipedia-download"))
+        .arg("--output").arg(&data_dir)
+        .args(["quickstart", "--lang", "de", "--port", &port.to_string()])
+        .output()
+        .unwrap();
+    assert!(!other_language.status.success());
+    let stderr = String::from_utf8_lossy(&other_language.stderr);
+    assert!(stderr.contains("already holds the 'simple' Wikipedia, not 'de'"), "{}", stderr);
+}